@@ -4,8 +4,9 @@ use dashmap::DashMap;
 use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::{Request, Response, Status};
-use tracing::info;
+use tracing::{info, warn};
 
+use loom_core::compression::{self, PayloadCompression, ACCEPT_ENCODING_KEY};
 use loom_core::{ActionBroker, EventBus};
 use loom_proto::{
     bridge_server::{Bridge, BridgeServer},
@@ -42,6 +43,10 @@ pub struct BridgeState {
     pub forwarding_tasks: Arc<DashMap<String, Vec<JoinHandle<()>>>>,
     // agent_id -> list of action_result ids for cleanup
     pub action_result_index: Arc<DashMap<String, Vec<String>>>,
+    // agent_id -> accept-encoding from registration metadata
+    pub accept_encodings: Arc<DashMap<String, String>>,
+    // compression for payloads sent to agents that accept its codec (None = never compress)
+    pub compression: Option<PayloadCompression>,
 }
 
 impl BridgeState {
@@ -56,8 +61,23 @@ impl BridgeState {
             subscription_ids: Arc::new(DashMap::new()),
             forwarding_tasks: Arc::new(DashMap::new()),
             action_result_index: Arc::new(DashMap::new()),
+            accept_encodings: Arc::new(DashMap::new()),
+            compression: None,
         }
     }
+
+    /// Compress action calls, deliveries and forwarded results for agents that accept the codec
+    pub fn with_compression(mut self, compression: PayloadCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Compression to use towards `agent_id`, if it registered an `accept-encoding` with the codec
+    fn compression_for(&self, agent_id: &str) -> Option<PayloadCompression> {
+        let compression = self.compression?;
+        let accept = self.accept_encodings.get(agent_id)?;
+        compression::accepts(&accept, compression.codec).then_some(compression)
+    }
 }
 
 #[derive(Clone)]
@@ -71,8 +91,14 @@ impl BridgeService {
     }
 
     /// Push an ActionCall to an agent's active stream; returns Ok(true) if delivered.
-    pub async fn push_action_call(&self, agent_id: &str, call: ActionCall) -> Result<bool> {
+    pub async fn push_action_call(&self, agent_id: &str, mut call: ActionCall) -> Result<bool> {
         if let Some(sender) = self.state.streams.get(agent_id) {
+            if let Some(compression) = self.state.compression_for(agent_id) {
+                // On failure the call goes out uncompressed
+                if let Err(e) = compression.compress_call(&mut call) {
+                    warn!(agent_id=%agent_id, call_id=%call.id, error=%e, "Failed to compress action_call");
+                }
+            }
             let server_event = ServerEvent {
                 msg: Some(server_event::Msg::ActionCall(call)),
             };
@@ -114,6 +140,16 @@ impl Bridge for BridgeService {
         self.state
             .capabilities
             .insert(agent_id.clone(), req.capabilities.clone());
+        match req.metadata.get(ACCEPT_ENCODING_KEY) {
+            Some(accept) => {
+                self.state
+                    .accept_encodings
+                    .insert(agent_id.clone(), accept.clone());
+            }
+            None => {
+                self.state.accept_encodings.remove(&agent_id);
+            }
+        }
         info!(agent_id=%agent_id, topics=?req.subscribed_topics, caps=req.capabilities.len(), "Agent registered via Bridge");
         Ok(Response::new(AgentRegisterResponse {
             success: true,
@@ -150,6 +186,7 @@ impl Bridge for BridgeService {
         let agent_id_for_inbound = agent_id.clone();
 
         // For each subscribed topic, subscribe and spawn a forwarding task, tracking ids and handles
+        let compression = self.state.compression_for(&agent_id);
         if let Some(topics) = self.state.subscriptions.get(&agent_id).map(|v| v.clone()) {
             let mut sub_ids: Vec<String> = Vec::new();
             let mut handles: Vec<JoinHandle<()>> = Vec::new();
@@ -168,7 +205,12 @@ impl Bridge for BridgeService {
                     sub_ids.push(sub_id.clone());
                     let tx_clone = tx.clone();
                     let handle: JoinHandle<()> = tokio::spawn(async move {
-                        while let Some(ev) = rx_bus.recv().await {
+                        while let Some(mut ev) = rx_bus.recv().await {
+                            if let Some(compression) = compression {
+                                if let Err(e) = compression.compress_event(&mut ev) {
+                                    warn!(event_id=%ev.id, error=%e, "Failed to compress delivery");
+                                }
+                            }
                             if tx_clone
                                 .send(ServerEvent {
                                     msg: Some(server_event::Msg::Delivery(Delivery {
@@ -209,8 +251,15 @@ impl Bridge for BridgeService {
             while let Some(Ok(msg)) = inbound.message().await.transpose() {
                 match msg.msg {
                     Some(client_event::Msg::Publish(p)) => {
-                        if let (Some(ev), topic) = (p.event, p.topic) {
-                            let _ = event_bus.publish(&topic, ev).await;
+                        if let (Some(mut ev), topic) = (p.event, p.topic) {
+                            match compression::decompress_event(&mut ev) {
+                                Ok(_) => {
+                                    let _ = event_bus.publish(&topic, ev).await;
+                                }
+                                Err(e) => {
+                                    warn!(agent_id=%agent_id_for_inbound, event_id=%ev.id, error=%e, "Dropping published event that failed to decompress");
+                                }
+                            }
                         }
                     }
                     Some(client_event::Msg::Ping(_hb)) => {
@@ -223,8 +272,11 @@ impl Bridge for BridgeService {
                             })
                             .await;
                     }
-                    Some(client_event::Msg::ActionResult(ar)) => {
+                    Some(client_event::Msg::ActionResult(mut ar)) => {
                         info!(action_id=%ar.id, "Received action result from agent");
+                        if let Err(e) = compression::decompress_result(&mut ar) {
+                            warn!(action_id=%ar.id, error=%e, "Action result failed to decompress; storing it as received");
+                        }
                         action_results.insert(ar.id.clone(), ar.clone());
                         // index this result under agent for cleanup
                         action_result_index
@@ -269,10 +321,25 @@ impl Bridge for BridgeService {
         &self,
         request: Request<ActionCall>,
     ) -> std::result::Result<Response<ActionResult>, Status> {
-        let call = request.into_inner();
+        let mut call = request.into_inner();
+        compression::decompress_call(&mut call)
+            .map_err(|e| Status::invalid_argument(format!("undecodable payload: {e}")))?;
         let broker = Arc::clone(&self.state.action_broker);
         match broker.invoke(call.clone()).await {
-            Ok(res) => Ok(Response::new(res)),
+            Ok(mut res) => {
+                // The caller names what it can decode in the call's own headers
+                let accepted = self.state.compression.filter(|c| {
+                    call.headers
+                        .get(ACCEPT_ENCODING_KEY)
+                        .is_some_and(|accept| compression::accepts(accept, c.codec))
+                });
+                if let Some(compression) = accepted {
+                    if let Err(e) = compression.compress_result(&mut res) {
+                        warn!(call_id=%call.id, error=%e, "Failed to compress forwarded result");
+                    }
+                }
+                Ok(Response::new(res))
+            }
             Err(e) => Ok(Response::new(ActionResult {
                 id: call.id,
                 status: loom_proto::ActionStatus::ActionError as i32,
@@ -282,6 +349,7 @@ impl Bridge for BridgeService {
                     message: e.to_string(),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             })),
         }
     }
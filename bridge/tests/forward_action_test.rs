@@ -15,6 +15,8 @@ impl CapabilityProvider for TestEchoProvider {
             version: "1.0".into(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -24,6 +26,7 @@ impl CapabilityProvider for TestEchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -43,7 +46,7 @@ async fn test_forward_action_success() {
         headers: Default::default(),
         timeout_ms: 1000,
         correlation_id: "c1".into(),
-        qos: 0,
+        qos: None,
     };
 
     let res = svc
@@ -69,7 +72,7 @@ async fn test_forward_action_missing_capability() {
         headers: Default::default(),
         timeout_ms: 10,
         correlation_id: "c2".into(),
-        qos: 0,
+        qos: None,
     };
 
     let res = svc
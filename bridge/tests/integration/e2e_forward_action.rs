@@ -13,6 +13,8 @@ impl CapabilityProvider for TestEchoProvider {
             version: "1.0".into(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -22,6 +24,7 @@ impl CapabilityProvider for TestEchoProvider {
             status: super::ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -49,7 +52,7 @@ async fn test_forward_action_echo() {
             headers: Default::default(),
             timeout_ms: 1000,
             correlation_id: "c1".into(),
-            qos: 0,
+            qos: None,
         })
         .await
         .unwrap()
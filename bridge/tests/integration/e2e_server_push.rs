@@ -53,6 +53,7 @@ async fn test_server_push_action_and_client_reply() {
                                 status: ActionStatus::ActionOk as i32,
                                 output: b"done".to_vec(),
                                 error: None,
+                                metadata: Default::default(),
                             })),
                         })
                         .await;
@@ -75,7 +76,7 @@ async fn test_server_push_action_and_client_reply() {
                 headers: Default::default(),
                 timeout_ms: 1000,
                 correlation_id: "c1".into(),
-                qos: 0,
+                qos: None,
             },
         )
         .await
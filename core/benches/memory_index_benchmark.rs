@@ -0,0 +1,244 @@
+/// InMemoryMemory benchmarks: indexed retrieval and batched ingestion
+///
+/// Run with: cargo bench --bench memory_index_benchmark
+///
+/// Benchmarks cover:
+/// - Linear scan: text query matched against every stored event
+/// - Indexed: `type` / `tags` filters resolved through the secondary indexes
+/// - Streaming: pulling the top 4 of `retrieve_stream` vs ranking every match
+/// - Ingestion: `append_events` batch vs looped `append_event`
+/// - Cold start: rebuilding an `EmbeddingIndex` by re-embedding vs `load` from a saved file
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use loom_core::context::memory::InMemoryMemory;
+use loom_core::context::{Embedder, EmbeddingIndex, MemoryReader, MemoryWriter};
+use loom_core::proto::Event;
+use std::sync::Arc;
+
+fn make_event(id: u64) -> Event {
+    // 1% of events are "rare" and tagged "alert"; the rest are common
+    let rare = id.is_multiple_of(100);
+    Event {
+        id: format!("evt_{}", id),
+        r#type: if rare { "rare_type" } else { "common" }.to_string(),
+        timestamp_ms: id as i64 + 1,
+        source: format!("sensor.{}", id % 8),
+        metadata: Default::default(),
+        payload: vec![],
+        confidence: 1.0,
+        tags: if rare { vec!["alert".into()] } else { vec![] },
+        priority: 0,
+    }
+}
+
+fn populated(rt: &tokio::runtime::Runtime, count: u64) -> Arc<InMemoryMemory> {
+    rt.block_on(async {
+        let mem = InMemoryMemory::new();
+        for i in 0..count {
+            mem.append_event(&format!("s{}", i % 16), make_event(i))
+                .await
+                .unwrap();
+        }
+        mem
+    })
+}
+
+/// Benchmark: find the rare events by linear text scan vs type/tag index
+fn bench_filtered_retrieval(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memory_filtered_retrieval");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    for event_count in [10_000u64, 100_000].iter() {
+        let mem = populated(&rt, *event_count);
+        group.throughput(Throughput::Elements(*event_count));
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("linear_scan/{}", event_count)),
+            event_count,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        black_box(mem.retrieve("rare_type", 50, None).await.unwrap());
+                    })
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("type_index/{}", event_count)),
+            event_count,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let filter = serde_json::json!({"type": "rare_type"});
+                        black_box(mem.retrieve("", 50, Some(filter)).await.unwrap());
+                    })
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("tag_and_source_index/{}", event_count)),
+            event_count,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let filter = serde_json::json!({"tag": "alert", "source": "sensor.0"});
+                        black_box(mem.retrieve("", 50, Some(filter)).await.unwrap());
+                    })
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Benchmark: stop a streamed retrieval after the top 4 docs vs drain the fully ranked stream
+fn bench_streamed_retrieval(c: &mut Criterion) {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    let mut group = c.benchmark_group("memory_streamed_retrieval");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    for event_count in [10_000u64, 100_000].iter() {
+        // 99% of events match "common"
+        let mem = populated(&rt, *event_count);
+        group.throughput(Throughput::Elements(*event_count));
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("early_stop_top4/{}", event_count)),
+            event_count,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let top: Vec<_> = mem
+                            .retrieve_stream("common", None)
+                            .take(4)
+                            .try_collect()
+                            .await
+                            .unwrap();
+                        black_box(top);
+                    })
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("rank_all/{}", event_count)),
+            event_count,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let all: Vec<_> = mem
+                            .retrieve_stream("common", None)
+                            .try_collect()
+                            .await
+                            .unwrap();
+                        black_box(all);
+                    })
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Benchmark: ingest one session's events as a batch vs one append at a time
+fn bench_batched_append(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memory_batched_append");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let event_count = 10_000u64;
+    let events: Vec<Event> = (0..event_count).map(make_event).collect();
+    group.throughput(Throughput::Elements(event_count));
+
+    group.bench_function(format!("looped/{}", event_count), |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mem = InMemoryMemory::new();
+                for e in events.iter().cloned() {
+                    mem.append_event("s0", e).await.unwrap();
+                }
+                black_box(mem);
+            })
+        });
+    });
+
+    group.bench_function(format!("batched/{}", event_count), |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mem = InMemoryMemory::new();
+                mem.append_events("s0", events.clone()).await.unwrap();
+                black_box(mem);
+            })
+        });
+    });
+    group.finish();
+}
+
+/// Local stand-in for an embedding model: hashes character trigrams into 384 buckets.
+/// Real (remote) embedders are far slower, so this understates the gap.
+struct TrigramEmbedder;
+
+#[async_trait::async_trait]
+impl Embedder for TrigramEmbedder {
+    async fn embed(&self, text: &str) -> loom_core::Result<Vec<f32>> {
+        use std::hash::{Hash, Hasher};
+        let mut v = vec![0.0f32; 384];
+        let chars: Vec<char> = text.chars().collect();
+        for w in chars.windows(3) {
+            let mut h = std::collections::hash_map::DefaultHasher::new();
+            w.hash(&mut h);
+            v[(h.finish() % 384) as usize] += 1.0;
+        }
+        Ok(v)
+    }
+}
+
+/// Benchmark: make a 10k-document vector index searchable after a restart
+fn bench_vector_cold_start(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vector_index_cold_start");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let doc_count = 10_000usize;
+    let docs: Vec<String> = (0..doc_count)
+        .map(|i| format!("document {} about sensor {} reading {}", i, i % 8, i * 7))
+        .collect();
+    let path = std::env::temp_dir().join(format!("loom-bench-vectors-{}.bin", std::process::id()));
+    rt.block_on(async {
+        let index = EmbeddingIndex::new(Arc::new(TrigramEmbedder));
+        for (i, text) in docs.iter().enumerate() {
+            index.add(format!("doc{}", i), text).await.unwrap();
+        }
+        index.save(&path).unwrap();
+    });
+    group.throughput(Throughput::Elements(doc_count as u64));
+
+    group.bench_function(format!("re_embed/{}", doc_count), |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let index = EmbeddingIndex::new(Arc::new(TrigramEmbedder));
+                for (i, text) in docs.iter().enumerate() {
+                    index.add(format!("doc{}", i), text).await.unwrap();
+                }
+                black_box(index);
+            })
+        });
+    });
+
+    group.bench_function(format!("load/{}", doc_count), |b| {
+        b.iter(|| {
+            let index = EmbeddingIndex::new(Arc::new(TrigramEmbedder));
+            index.load(&path).unwrap();
+            black_box(index);
+        });
+    });
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(
+    benches,
+    bench_filtered_retrieval,
+    bench_streamed_retrieval,
+    bench_batched_append,
+    bench_vector_cold_start
+);
+criterion_main!(benches);
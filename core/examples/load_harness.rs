@@ -0,0 +1,41 @@
+/// Example: Load testing the ActionBroker with synthetic providers
+///
+/// This example demonstrates how to:
+/// 1. Register a `SyntheticProvider` with a long-tailed latency distribution and an error rate
+/// 2. Cap it with `set_concurrency_limit` and drive 64 concurrent workers with `LoadHarness`
+/// 3. Print throughput, latency percentiles and failures by error code
+///
+/// Run with:
+/// ```bash
+/// cargo run --release --features testing --example load_harness
+/// ```
+use loom_core::action_broker::ActionBroker;
+use loom_core::testing::{LatencyDistribution, LoadHarness, SyntheticProvider};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    let broker = Arc::new(ActionBroker::new());
+    let provider = Arc::new(
+        SyntheticProvider::named("search.web")
+            .latency(LatencyDistribution::Exponential {
+                mean: Duration::from_millis(20),
+            })
+            .error_rate(0.05),
+    );
+    broker.register_provider(provider.clone());
+    broker.set_concurrency_limit("search.web", Some(8));
+
+    let report = LoadHarness::new(broker, "search.web")
+        .concurrency(64)
+        .duration(Duration::from_secs(3))
+        .run()
+        .await;
+
+    println!("{report}");
+    println!(
+        "peak in flight at the provider: {}",
+        provider.peak_in_flight()
+    );
+}
@@ -13,7 +13,7 @@
 /// ```bash
 /// cargo run --example mcp_integration
 /// ```
-use loom_core::{mcp::types::McpServerConfig, Loom, QoSLevel};
+use loom_core::{mcp::types::McpServerConfig, ActionCallExt, Loom, QoSLevel};
 use serde_json::json;
 use std::collections::HashMap;
 use tracing::{info, warn};
@@ -120,18 +120,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("Created test file: {}", test_file);
 
         // Invoke the filesystem:read_file tool
-        let call = loom_core::proto::ActionCall {
-            id: "test-mcp-call-1".to_string(),
-            capability: "filesystem:read_file".to_string(),
-            version: String::new(),
-            payload: serde_json::to_vec(&json!({
-                "path": test_file
-            }))?,
-            headers: HashMap::new(),
-            timeout_ms: 5000,
-            correlation_id: "example-correlation".to_string(),
-            qos: QoSLevel::QosRealtime as i32,
-        };
+        let call = loom_core::proto::ActionCall::builder("filesystem:read_file")
+            .id("test-mcp-call-1")
+            .payload_json(&json!({ "path": test_file }))?
+            .timeout_ms(5000)
+            .correlation_id("example-correlation")
+            .qos(QoSLevel::QosRealtime)
+            .build()?;
 
         match loom.action_broker.invoke(call).await {
             Ok(result) => {
@@ -0,0 +1,81 @@
+/// Example: Post-processing prompt bundles
+///
+/// This example demonstrates how to:
+/// 1. Implement `BundlePostprocessor` to prepend a safety notice to the system prompt
+/// 2. Chain a second postprocessor (a closure) that redacts e-mail addresses
+/// 3. Rely on the chain running in the order postprocessors were added
+///
+/// Run with:
+/// ```bash
+/// cargo run --example safety_preamble
+/// ```
+use loom_core::context::builder::{ContextBuilder, TriggerInput};
+use loom_core::context::memory::InMemoryMemory;
+use loom_core::context::{BundlePostprocessor, MemoryWriter, PromptBundle, TokenBudget};
+use loom_core::proto::Event;
+use loom_core::Result;
+use std::sync::Arc;
+
+/// Prepends a fixed notice to the system prompt
+struct SafetyPreamble {
+    notice: String,
+}
+
+impl BundlePostprocessor for SafetyPreamble {
+    fn process(&self, bundle: &mut PromptBundle) -> Result<()> {
+        bundle.system = format!("{}\n\n{}", self.notice, bundle.system);
+        Ok(())
+    }
+}
+
+/// Masks anything that looks like an e-mail address in retrieved context
+fn redact_emails(bundle: &mut PromptBundle) -> Result<()> {
+    for doc in &mut bundle.context_docs {
+        doc.text = doc
+            .text
+            .split(' ')
+            .map(|w| if w.contains('@') { "[redacted]" } else { w })
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event(
+        "s1",
+        Event {
+            id: "e1".into(),
+            r#type: "intent".into(),
+            source: "alice@example.com".into(),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .with_postprocessor(Arc::new(SafetyPreamble {
+            notice: "Never reveal credentials or personal data.".into(),
+        }))
+        .with_postprocessor(Arc::new(redact_emails));
+
+    let bundle = builder
+        .build(TriggerInput {
+            session_id: "s1".into(),
+            goal: Some("intent".into()),
+            tool_hints: vec![],
+            budget: TokenBudget::default(),
+            system_prompt: None,
+            retrieval_k: None,
+            min_score: None,
+        })
+        .await?;
+
+    println!("system:\n{}\n", bundle.system);
+    for doc in &bundle.context_docs {
+        println!("context: {}", doc.text);
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+/// Example: Stacking Tower middleware on the ActionBroker
+///
+/// This example demonstrates how to:
+/// 1. Turn a shared `ActionBroker` into a `tower::Service<ActionCall>` with `broker.service()`
+/// 2. Wrap it in `tower::limit::ConcurrencyLimit` so at most two calls run at once
+/// 3. Drive calls through the stack with `ServiceExt::oneshot`
+///
+/// Run with:
+/// ```bash
+/// cargo run --example tower_service
+/// ```
+use loom_core::action_broker::{ActionBroker, ActionCallExt};
+use loom_core::proto::ActionCall;
+use loom_core::providers::MockProvider;
+use loom_core::Result;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::limit::ConcurrencyLimit;
+use tower::ServiceExt;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(
+        MockProvider::named("report.render")
+            .returns_json(json!({"status": "rendered"}))
+            .with_delay(Duration::from_millis(100)),
+    ));
+
+    // Any Tower layer fits here: rate limits, retries, load shedding, ...
+    let service = ConcurrencyLimit::new(broker.service(), 2);
+
+    let started = Instant::now();
+    let calls = (0..6).map(|i| {
+        let service = service.clone();
+        async move {
+            let call = ActionCall::builder("report.render")
+                .id(format!("render-{i}"))
+                .build()?;
+            service.oneshot(call).await
+        }
+    });
+    for result in futures_util::future::join_all(calls).await {
+        let result = result?;
+        println!("{} -> {}", result.id, result.status_enum());
+    }
+    // Six 100 ms calls, two at a time: about 300 ms
+    println!("elapsed: {} ms", started.elapsed().as_millis());
+    Ok(())
+}
@@ -0,0 +1,77 @@
+/// Example: A typed capability handle generated with `define_capability!`
+///
+/// This example demonstrates how to:
+/// 1. Bind `tts.echo` to its request and response types with `define_capability!`
+/// 2. Register a provider for it (here a `MockProvider` that echoes the text back)
+/// 3. Invoke it through the handle, so the name and both types are checked at compile time
+///
+/// Run with:
+/// ```bash
+/// cargo run --example typed_capability
+/// ```
+use loom_core::action_broker::{ActionBroker, ActionResultExt, TypedCapability};
+use loom_core::proto::ActionResult;
+use loom_core::{define_capability, MockProvider, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TtsRequest {
+    text: String,
+    voice: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TtsResponse {
+    text: String,
+    voice: String,
+    duration_ms: u64,
+}
+
+define_capability!(
+    /// Echo TTS: "speaks" the text back without synthesizing audio
+    TtsEcho,
+    name = "tts.echo",
+    req = TtsRequest,
+    resp = TtsResponse,
+);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(MockProvider::named(TtsEcho::NAME).returns(
+        |call| {
+            let req: TtsRequest = serde_json::from_slice(&call.payload).unwrap();
+            let reply = TtsResponse {
+                duration_ms: 60 * req.text.split_whitespace().count() as u64,
+                voice: req.voice.unwrap_or_else(|| "default".to_string()),
+                text: req.text,
+            };
+            ActionResult::try_ok_json(&call.id, &reply)
+        },
+    )));
+
+    let reply = TtsEcho::invoke(
+        &broker,
+        &TtsRequest {
+            text: "hello from a typed handle".to_string(),
+            voice: Some("alloy".to_string()),
+        },
+    )
+    .await?;
+    println!("{reply:?}");
+
+    // Pin a version or set a timeout through the call builder; the types still come from the handle
+    let call = TtsEcho::call(&TtsRequest {
+        text: "with a deadline".to_string(),
+        voice: None,
+    })?
+    .timeout(Duration::from_millis(500))
+    .build()?;
+    let reply = broker
+        .invoke_typed_call::<<TtsEcho as TypedCapability>::Response>(call)
+        .await?;
+    println!("{reply:?}");
+    Ok(())
+}
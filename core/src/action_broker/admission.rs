@@ -0,0 +1,222 @@
+//! Checks a call passes before it reaches its provider, shared by `invoke` and `invoke_dry_run`.
+//!
+//! In order: missing scope keys, budget, capability lookup, rate limits and quarantine.
+//! Authorization runs before the idempotency cache and lives in `authorize_call`. A dry run
+//! makes the same checks without counting, logging or charging anything: rate limits are
+//! peeked instead of counted, and a quarantined capability's probe slot is never taken.
+
+use super::quarantine::ProbeGuard;
+use super::{
+    ActionBroker, ActionResultBuilder, CapabilityProvider, InvocationContext, InvocationScope,
+    OnMissing, QUARANTINED, RATE_LIMITED, SCOPE_KEY_MISSING,
+};
+use crate::proto::{ActionCall, ActionResult, ActionStatus};
+use crate::{LoomError, Result};
+use opentelemetry::KeyValue;
+use std::sync::Arc;
+use tracing::{debug, warn, Span};
+
+/// Whether the checks are for a real dispatch or a dry run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AdmissionMode {
+    /// Count the call against rate limits, take the probe slot, record metrics
+    Dispatch,
+    /// Leave every counter as it is; a missing capability is always a result
+    DryRun,
+}
+
+/// A call that passed every check
+pub(crate) struct Admitted<'a> {
+    pub(crate) provider: Arc<dyn CapabilityProvider>,
+    /// Key the call is charged under once it completes; `None` when it is not accounted
+    pub(crate) budget_key: Option<String>,
+    /// Probe slot of a quarantined capability, when this call is the probe
+    pub(crate) probe: Option<ProbeGuard<'a>>,
+}
+
+pub(crate) enum Admission<'a> {
+    Admitted(Admitted<'a>),
+    /// The `ActionError` result the caller gets instead of a provider call
+    Rejected(ActionResult),
+}
+
+impl ActionBroker {
+    /// The `FORBIDDEN` result when the authorizer denies `call`
+    pub(crate) fn authorize_call(
+        &self,
+        call: &ActionCall,
+        ctx: &InvocationContext,
+        mode: AdmissionMode,
+    ) -> Option<ActionResult> {
+        let authorizer = self.authorizer.read().unwrap().clone()?;
+        let Err(mut denial) = authorizer.authorize_with_context(call, ctx) else {
+            return None;
+        };
+        denial.code = "FORBIDDEN".to_string();
+        if mode == AdmissionMode::Dispatch {
+            warn!(target: "action_broker", capability = %call.capability, call_id = %call.id, "Capability call forbidden");
+            self.count_rejection(&call.capability, "FORBIDDEN", "forbidden");
+        }
+        Some(
+            ActionResultBuilder::new(ActionStatus::ActionError)
+                .id(call.id.clone())
+                .action_error(denial)
+                .build(),
+        )
+    }
+
+    /// Run the checks between the idempotency cache and the provider. `cost_key` is the
+    /// caller's own correlation id, if it sent one. Fails only for a missing capability
+    /// under `OnMissing::Error` on dispatch.
+    pub(crate) fn admit_call(
+        &self,
+        call: &ActionCall,
+        ctx: &InvocationContext,
+        cost_key: Option<&str>,
+        mode: AdmissionMode,
+    ) -> Result<Admission<'_>> {
+        let dispatching = mode == AdmissionMode::Dispatch;
+        let cap_name = &call.capability;
+        let rejected = || ActionResultBuilder::new(ActionStatus::ActionError).id(call.id.clone());
+
+        // Tenant and principal headers are what limits and budgets are keyed on
+        if let Some(scope) = self.rejected_scope(ctx) {
+            if dispatching {
+                debug!(target: "action_broker", capability = %cap_name, scope = scope.as_str(), "Call carries no key for a limited scope; rejected");
+                self.count_rejection(cap_name, SCOPE_KEY_MISSING, "scope_key_missing");
+            }
+            return Ok(Admission::Rejected(
+                rejected()
+                    .error(
+                        SCOPE_KEY_MISSING,
+                        format!("Call carries no {} key", scope.as_str()),
+                    )
+                    .detail("scope", scope.as_str())
+                    .build(),
+            ));
+        }
+
+        // Uncorrelated calls are not accounted: their correlation id is just the call id
+        let budget_scope = self.settings.read().unwrap().budget_scope;
+        let budget_key = match budget_scope {
+            InvocationScope::Correlation => cost_key.map(str::to_string),
+            scope => Some(ctx.policy_key(scope).to_string()),
+        };
+
+        // Reject once the correlation's (or tenant's, principal's) budget is spent
+        if let Some(key) = budget_key.as_deref().filter(|k| self.costs.is_exhausted(k)) {
+            if dispatching {
+                warn!(target: "action_broker", capability = %cap_name, scope = budget_scope.as_str(), key = %key, "Budget exceeded");
+                self.count_rejection(cap_name, "BUDGET_EXCEEDED", "budget_exceeded");
+            }
+            let mut result = rejected()
+                .error(
+                    "BUDGET_EXCEEDED",
+                    format!("Budget exhausted for {} {}", budget_scope.as_str(), key),
+                )
+                .detail("spent", self.costs.spent(key).to_string());
+            if let Some(budget) = self.costs.budget(key) {
+                result = result.detail("budget", budget.to_string());
+            }
+            return Ok(Admission::Rejected(result.build()));
+        }
+
+        let Some(provider) = self.find_provider(call) else {
+            let version = &call.version;
+            let message = if version.is_empty() {
+                format!("Capability not found: {}", cap_name)
+            } else {
+                format!("Capability not found: {} (version {})", cap_name, version)
+            };
+            if dispatching {
+                if self.settings.read().unwrap().on_missing == OnMissing::Error {
+                    return Err(LoomError::PluginError(message));
+                }
+                warn!(target: "action_broker", capability = %cap_name, "Capability not found");
+                self.count_rejection(cap_name, "CAPABILITY_NOT_FOUND", "not_found");
+            }
+            let mut result = rejected()
+                .error("CAPABILITY_NOT_FOUND", message)
+                .detail("capability", cap_name.clone());
+            if !version.is_empty() {
+                result = result.detail("version", version.clone());
+            }
+            return Ok(Admission::Rejected(result.build()));
+        };
+
+        // Counted for calls that would otherwise reach the provider, and checked before
+        // quarantine so a rejected call never takes the probe slot
+        let rate = if dispatching {
+            self.admit_rate(ctx)
+        } else {
+            self.peek_rate(ctx)
+        };
+        if let Err(rejection) = rate {
+            if dispatching {
+                debug!(target: "action_broker", capability = %cap_name, scope = rejection.scope.as_str(), key = %rejection.key, retry_after_ms = rejection.retry_after_ms, "Rate limit reached; call rejected");
+                self.count_rejection(cap_name, RATE_LIMITED, "rate_limited");
+            }
+            return Ok(Admission::Rejected(
+                rejected()
+                    .error(
+                        RATE_LIMITED,
+                        format!(
+                            "Rate limit reached for {} {}",
+                            rejection.scope.as_str(),
+                            rejection.key
+                        ),
+                    )
+                    .detail("scope", rejection.scope.as_str())
+                    .detail("key", rejection.key)
+                    .detail("retry_after_ms", rejection.retry_after_ms.to_string())
+                    .build(),
+            ));
+        }
+
+        // Capabilities that keep timing out sit out their cooldown
+        let quarantine = if dispatching {
+            self.admit_quarantined(cap_name)
+        } else {
+            self.peek_quarantined(cap_name).map(|()| None)
+        };
+        let probe = match quarantine {
+            Ok(probe) => probe,
+            Err(retry_after_ms) => {
+                if dispatching {
+                    debug!(target: "action_broker", capability = %cap_name, retry_after_ms, "Capability quarantined; call rejected");
+                    self.count_rejection(cap_name, QUARANTINED, "quarantined");
+                }
+                return Ok(Admission::Rejected(
+                    rejected()
+                        .error(
+                            QUARANTINED,
+                            format!(
+                                "Capability quarantined after repeated timeouts: {}",
+                                cap_name
+                            ),
+                        )
+                        .detail("capability", cap_name.clone())
+                        .detail("retry_after_ms", retry_after_ms.to_string())
+                        .build(),
+                ));
+            }
+        };
+
+        Ok(Admission::Admitted(Admitted {
+            provider,
+            budget_key,
+            probe,
+        }))
+    }
+
+    fn count_rejection(&self, capability: &str, code: &'static str, status: &'static str) {
+        self.errors_counter.add(
+            1,
+            &[
+                KeyValue::new("capability", capability.to_string()),
+                KeyValue::new("error_code", code),
+            ],
+        );
+        Span::current().record("status", status);
+    }
+}
@@ -0,0 +1,113 @@
+//! Authorization hook checked by the broker before dispatching a call.
+
+use std::collections::{HashMap, HashSet};
+
+use super::InvocationContext;
+use crate::proto::{ActionCall, ActionError};
+
+/// Header carrying the caller identity (e.g. "agent.planner")
+pub const PRINCIPAL_HEADER: &str = "principal";
+/// Header carrying the caller role (e.g. "reader", "admin")
+pub const ROLE_HEADER: &str = "role";
+/// Header carrying the tenant a call is made for (e.g. "acme")
+pub const TENANT_HEADER: &str = "tenant";
+
+/// Decides whether a call may be dispatched.
+///
+/// Implementations inspect the call (typically its headers) and return an
+/// `ActionError` describing the denial; the broker surfaces it with code `FORBIDDEN`.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, call: &ActionCall) -> std::result::Result<(), ActionError>;
+
+    /// Like `authorize`, with the call's tenant, principal and correlation id resolved. The
+    /// broker calls this one; the default ignores `ctx` and calls `authorize`.
+    fn authorize_with_context(
+        &self,
+        call: &ActionCall,
+        _ctx: &InvocationContext,
+    ) -> std::result::Result<(), ActionError> {
+        self.authorize(call)
+    }
+}
+
+/// Simple allow-list keyed by principal or role.
+///
+/// Patterns are exact capability names, a prefix wildcard such as `"tts.*"`, or `"*"`.
+/// A call is allowed if either its principal or its role grants the capability.
+#[derive(Debug, Clone, Default)]
+pub struct AllowListAuthorizer {
+    principals: HashMap<String, HashSet<String>>,
+    roles: HashMap<String, HashSet<String>>,
+}
+
+impl AllowListAuthorizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant `pattern` to callers with `principal`
+    pub fn allow_principal(
+        mut self,
+        principal: impl Into<String>,
+        pattern: impl Into<String>,
+    ) -> Self {
+        self.principals
+            .entry(principal.into())
+            .or_default()
+            .insert(pattern.into());
+        self
+    }
+
+    /// Grant `pattern` to callers with `role`
+    pub fn allow_role(mut self, role: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.roles
+            .entry(role.into())
+            .or_default()
+            .insert(pattern.into());
+        self
+    }
+
+    fn grants(patterns: Option<&HashSet<String>>, capability: &str) -> bool {
+        patterns
+            .map(|set| set.iter().any(|p| pattern_matches(p, capability)))
+            .unwrap_or(false)
+    }
+}
+
+impl Authorizer for AllowListAuthorizer {
+    fn authorize(&self, call: &ActionCall) -> std::result::Result<(), ActionError> {
+        let principal = call.headers.get(PRINCIPAL_HEADER);
+        let role = call.headers.get(ROLE_HEADER);
+
+        let allowed = principal
+            .map(|p| Self::grants(self.principals.get(p), &call.capability))
+            .unwrap_or(false)
+            || role
+                .map(|r| Self::grants(self.roles.get(r), &call.capability))
+                .unwrap_or(false);
+
+        if allowed {
+            return Ok(());
+        }
+
+        let mut details = HashMap::new();
+        if let Some(p) = principal {
+            details.insert(PRINCIPAL_HEADER.to_string(), p.clone());
+        }
+        if let Some(r) = role {
+            details.insert(ROLE_HEADER.to_string(), r.clone());
+        }
+        Err(ActionError {
+            code: "FORBIDDEN".to_string(),
+            message: format!("Caller is not allowed to invoke {}", call.capability),
+            details,
+        })
+    }
+}
+
+fn pattern_matches(pattern: &str, capability: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => capability.starts_with(prefix),
+        None => pattern == capability,
+    }
+}
@@ -0,0 +1,143 @@
+//! Batch invocation, concurrent or sequential, with an aggregated outcome.
+
+use super::{ActionBroker, ActionResultBuilder};
+use crate::proto::{ActionCall, ActionResult, ActionStatus};
+use serde::{Deserialize, Serialize};
+
+/// Error code of calls a sequential batch never ran because an earlier call failed
+pub const SKIPPED: &str = "SKIPPED";
+
+/// How the calls of a batch are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOrdering {
+    /// All calls at once; fastest, for calls that do not depend on each other
+    #[default]
+    Concurrent,
+    /// One call at a time in input order, each after the previous one returned, for calls
+    /// that need an earlier call's side effect
+    Sequential(OnBatchError),
+}
+
+/// What a sequential batch does after a call fails (any status other than `ok`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnBatchError {
+    /// Skip the remaining calls; each gets a `SKIPPED` error result
+    #[default]
+    Stop,
+    /// Run the remaining calls anyway
+    Continue,
+}
+
+/// Results of `invoke_batch`, in input order, with per-status counts
+#[derive(Debug, Clone, Default)]
+pub struct BatchOutcome {
+    pub results: Vec<ActionResult>,
+    pub ok_count: usize,
+    /// Failed calls other than timeouts; skipped calls are not counted here
+    pub error_count: usize,
+    pub timeout_count: usize,
+    /// Calls never run because a sequential batch stopped
+    pub skipped_count: usize,
+    /// Input index of the call that stopped a sequential batch
+    pub stopped_at: Option<usize>,
+}
+
+impl BatchOutcome {
+    fn from_results(results: Vec<ActionResult>) -> Self {
+        let mut outcome = Self::default();
+        for r in &results {
+            match ActionStatus::try_from(r.status) {
+                Ok(ActionStatus::ActionOk) => outcome.ok_count += 1,
+                Ok(ActionStatus::ActionTimeout) => outcome.timeout_count += 1,
+                _ => outcome.error_count += 1,
+            }
+        }
+        outcome.results = results;
+        outcome
+    }
+
+    /// Every call succeeded (vacuously true for an empty batch)
+    pub fn is_all_ok(&self) -> bool {
+        self.ok_count == self.results.len()
+    }
+
+    /// Some, but not all, calls succeeded
+    pub fn is_partial(&self) -> bool {
+        self.ok_count > 0 && self.ok_count < self.results.len()
+    }
+
+    /// No call succeeded in a non-empty batch
+    pub fn is_total_failure(&self) -> bool {
+        !self.results.is_empty() && self.ok_count == 0
+    }
+}
+
+impl ActionBroker {
+    /// Invoke `calls` concurrently. Broker-level failures (e.g. unknown capability) become
+    /// error results with code `INVOKE_ERROR`, so every call has exactly one result.
+    pub async fn invoke_batch(&self, calls: Vec<ActionCall>) -> BatchOutcome {
+        self.invoke_batch_with(calls, BatchOrdering::Concurrent)
+            .await
+    }
+
+    /// Invoke `calls` with the given ordering. Results are in input order either way, one per
+    /// call; a stopped sequential batch fills the rest with `SKIPPED` results.
+    pub async fn invoke_batch_with(
+        &self,
+        calls: Vec<ActionCall>,
+        ordering: BatchOrdering,
+    ) -> BatchOutcome {
+        let on_error = match ordering {
+            BatchOrdering::Concurrent => {
+                let runs = calls.into_iter().map(|call| self.invoke_or_error(call));
+                return BatchOutcome::from_results(futures_util::future::join_all(runs).await);
+            }
+            BatchOrdering::Sequential(on_error) => on_error,
+        };
+
+        let mut results = Vec::with_capacity(calls.len());
+        let mut calls = calls.into_iter();
+        let mut stopped_at = None;
+        for call in calls.by_ref() {
+            let res = self.invoke_or_error(call).await;
+            let failed = res.status_enum() != ActionStatus::ActionOk;
+            results.push(res);
+            if failed && on_error == OnBatchError::Stop {
+                stopped_at = Some(results.len() - 1);
+                break;
+            }
+        }
+
+        let mut outcome = BatchOutcome::from_results(results);
+        outcome.stopped_at = stopped_at;
+        if let Some(index) = stopped_at {
+            let failed_id = outcome.results[index].id.clone();
+            for call in calls {
+                outcome.results.push(error_result(
+                    call.id,
+                    SKIPPED,
+                    format!("skipped after call {failed_id} (index {index}) failed"),
+                ));
+                outcome.skipped_count += 1;
+            }
+        }
+        outcome
+    }
+
+    async fn invoke_or_error(&self, call: ActionCall) -> ActionResult {
+        let id = call.id.clone();
+        match self.invoke(call).await {
+            Ok(res) => res,
+            Err(e) => error_result(id, "INVOKE_ERROR", e.to_string()),
+        }
+    }
+}
+
+fn error_result(id: String, code: &str, message: String) -> ActionResult {
+    ActionResultBuilder::new(ActionStatus::ActionError)
+        .id(id)
+        .error(code, message)
+        .build()
+}
@@ -0,0 +1,34 @@
+//! Running `runs_blocking` providers on Tokio's blocking pool.
+
+use super::{CapabilityProvider, InvocationContext};
+use crate::proto::{ActionCall, ActionResult};
+use crate::{LoomError, Result};
+use std::sync::Arc;
+
+/// Run `invoke_with_context` on a blocking-pool thread, driven by `block_on`. The outcome
+/// matches `catch_unwind`: a provider panic comes back as `Err(payload)`.
+///
+/// The pool thread cannot be aborted. If this future is dropped (hard timeout), `ctx.cancel`
+/// fires so a cooperative provider can stop; otherwise the thread runs to completion.
+pub(crate) async fn invoke_on_blocking_pool(
+    provider: Arc<dyn CapabilityProvider>,
+    call: ActionCall,
+    ctx: InvocationContext,
+) -> std::thread::Result<Result<ActionResult>> {
+    let handle = tokio::runtime::Handle::current();
+    let guard = ctx.cancel.clone().drop_guard();
+    let joined = tokio::task::spawn_blocking(move || {
+        handle.block_on(provider.invoke_with_context(call, ctx))
+    })
+    .await;
+    // Completed normally: the token must not look like a soft-deadline cancel
+    guard.disarm();
+    match joined {
+        Ok(res) => Ok(res),
+        Err(e) if e.is_panic() => Err(e.into_panic()),
+        Err(e) => Ok(Err(LoomError::PluginError(format!(
+            "blocking invocation did not complete: {}",
+            e
+        )))),
+    }
+}
@@ -0,0 +1,265 @@
+//! Fluent construction of `ActionCall`s and `ActionResult`s.
+
+use super::{ActionErrorExt, Timeout};
+use crate::ids::{builder_id, IdGenerator, UuidVersion};
+use crate::proto::{
+    ActionCall, ActionError, ActionResult, ActionStatus, QoSLevel, CONTENT_TYPE_JSON,
+    CONTENT_TYPE_METADATA_KEY,
+};
+use crate::{LoomError, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Error code of a result whose output failed to serialize
+pub const SERIALIZATION_FAILED: &str = "SERIALIZATION_FAILED";
+
+/// Adds `ActionCall::builder(capability)` to the generated proto type
+pub trait ActionCallExt {
+    fn builder(capability: impl Into<String>) -> ActionCallBuilder;
+}
+
+impl ActionCallExt for ActionCall {
+    fn builder(capability: impl Into<String>) -> ActionCallBuilder {
+        ActionCallBuilder::new(capability)
+    }
+}
+
+/// Builder for `ActionCall`; `build` generates an `id` when none was set
+#[derive(Debug, Clone, Default)]
+pub struct ActionCallBuilder {
+    call: ActionCall,
+    id_version: Option<UuidVersion>,
+    id_generator: Option<Arc<dyn IdGenerator>>,
+}
+
+impl ActionCallBuilder {
+    pub fn new(capability: impl Into<String>) -> Self {
+        Self {
+            call: ActionCall {
+                capability: capability.into(),
+                ..Default::default()
+            },
+            id_version: None,
+            id_generator: None,
+        }
+    }
+
+    /// UUID version for the generated id instead of the default generator
+    pub fn id_version(mut self, version: UuidVersion) -> Self {
+        self.id_version = Some(version);
+        self
+    }
+
+    /// Generate the id with `generator` instead of `id_version`
+    pub fn id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    /// Explicit call id (idempotency key)
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.call.id = id.into();
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.call.version = version.into();
+        self
+    }
+
+    /// Raw payload bytes
+    pub fn payload(mut self, payload: Vec<u8>) -> Self {
+        self.call.payload = payload;
+        self
+    }
+
+    /// Serialize `value` as the JSON payload
+    pub fn payload_json<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Result<Self> {
+        self.call.payload = serde_json::to_vec(value)?;
+        Ok(self)
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.call.headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// Merge `headers` into the call headers
+    pub fn headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.call.headers.extend(headers);
+        self
+    }
+
+    /// Hard timeout; `0` (the default) lets the broker apply its default
+    pub fn timeout_ms(mut self, timeout_ms: i64) -> Self {
+        self.call.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Typed timeout; sub-millisecond values survive via `TIMEOUT_US_HEADER`
+    pub fn timeout(mut self, timeout: impl Into<Timeout>) -> Self {
+        timeout.into().apply_to(&mut self.call);
+        self
+    }
+
+    pub fn correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.call.correlation_id = correlation_id.into();
+        self
+    }
+
+    pub fn qos(mut self, qos: QoSLevel) -> Self {
+        self.call.qos = Some(qos as i32);
+        self
+    }
+
+    /// Validate and produce the call
+    pub fn build(mut self) -> Result<ActionCall> {
+        if self.call.capability.trim().is_empty() {
+            return Err(LoomError::PluginError(
+                "ActionCall capability must not be empty".to_string(),
+            ));
+        }
+        if self.call.id.is_empty() {
+            self.call.id = builder_id(&self.id_generator, self.id_version);
+        }
+        Ok(self.call)
+    }
+}
+
+/// Adds `ActionResult::builder(status)` to the generated proto type
+pub trait ActionResultExt {
+    fn builder(status: ActionStatus) -> ActionResultBuilder;
+
+    /// `ActionOk` result for call `id` with `value` as its JSON output. A value that does not
+    /// serialize gives an `ActionError` with code `SERIALIZATION_FAILED` (serde's message
+    /// under `details["debug"]`), never an empty output.
+    fn try_ok_json<T: serde::Serialize + ?Sized>(id: impl Into<String>, value: &T) -> Self;
+}
+
+impl ActionResultExt for ActionResult {
+    fn builder(status: ActionStatus) -> ActionResultBuilder {
+        ActionResultBuilder::new(status)
+    }
+
+    fn try_ok_json<T: serde::Serialize + ?Sized>(id: impl Into<String>, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(output) => ActionResultBuilder::new(ActionStatus::ActionOk)
+                .id(id)
+                .output(output)
+                .content_type(CONTENT_TYPE_JSON)
+                .build(),
+            Err(e) => ActionResultBuilder::new(ActionStatus::ActionError)
+                .id(id)
+                .error_with_debug(
+                    SERIALIZATION_FAILED,
+                    "The provider could not serialize its output",
+                    e.to_string(),
+                )
+                .build(),
+        }
+    }
+}
+
+/// Builder for `ActionResult`; `build` generates an `id` when none was set.
+/// Providers answering a call should set `id(call.id)` so results match their call.
+#[derive(Debug, Clone, Default)]
+pub struct ActionResultBuilder {
+    result: ActionResult,
+    id_version: Option<UuidVersion>,
+    id_generator: Option<Arc<dyn IdGenerator>>,
+}
+
+impl ActionResultBuilder {
+    pub fn new(status: ActionStatus) -> Self {
+        Self {
+            result: ActionResult {
+                status: status as i32,
+                ..Default::default()
+            },
+            id_version: None,
+            id_generator: None,
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.result.id = id.into();
+        self
+    }
+
+    /// UUID version for the generated id instead of the default generator
+    pub fn id_version(mut self, version: UuidVersion) -> Self {
+        self.id_version = Some(version);
+        self
+    }
+
+    /// Generate the id with `generator` instead of `id_version`
+    pub fn id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    /// Raw output bytes; pair binary data (e.g. audio) with `content_type`
+    pub fn output(mut self, output: Vec<u8>) -> Self {
+        self.result.output = output;
+        self
+    }
+
+    /// Serialize `value` as the JSON output and mark it `application/json`
+    pub fn output_json<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Result<Self> {
+        self.result.output = serde_json::to_vec(value)?;
+        Ok(self.content_type(CONTENT_TYPE_JSON))
+    }
+
+    /// MIME type of the output, stored under `CONTENT_TYPE_METADATA_KEY`
+    pub fn content_type(self, content_type: impl Into<String>) -> Self {
+        self.metadata(CONTENT_TYPE_METADATA_KEY, content_type)
+    }
+
+    pub fn error(mut self, code: impl Into<String>, message: impl Into<String>) -> Self {
+        self.result.error = Some(ActionError {
+            code: code.into(),
+            message: message.into(),
+            details: Default::default(),
+        });
+        self
+    }
+
+    /// Error with a user-safe `message` and internal detail under `details["debug"]`
+    pub fn error_with_debug(
+        mut self,
+        code: impl Into<String>,
+        message: impl Into<String>,
+        debug: impl Into<String>,
+    ) -> Self {
+        self.result.error = Some(ActionError::with_debug(code, message, debug));
+        self
+    }
+
+    /// Use an `ActionError` built elsewhere, e.g. an authorizer's denial
+    pub fn action_error(mut self, error: ActionError) -> Self {
+        self.result.error = Some(error);
+        self
+    }
+
+    /// Add `key` to the error's details; starts an empty error when none was set
+    pub fn detail(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.result
+            .error
+            .get_or_insert_with(Default::default)
+            .details
+            .insert(key.into(), value.into());
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.result.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(mut self) -> ActionResult {
+        if self.result.id.is_empty() {
+            self.result.id = builder_id(&self.id_generator, self.id_version);
+        }
+        self.result
+    }
+}
@@ -0,0 +1,141 @@
+//! Cancelling every in-flight call of a correlation id (e.g. an abandoned session).
+//!
+//! Dispatched calls with a correlation id share one token per id. `cancel_correlation` fires
+//! it: waiting callers get a `CANCELLED` result at once, and providers see their
+//! `CancellationToken` (a child of the shared one) fire so they can stop work.
+
+use super::{ActionBroker, ActionResultBuilder};
+use crate::proto::{ActionResult, ActionStatus};
+use crate::CancellationToken;
+use dashmap::DashMap;
+use opentelemetry::KeyValue;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, info, Span};
+
+/// Error code of results for calls cancelled through `cancel_correlation`
+pub const CANCELLED: &str = "CANCELLED";
+
+struct Active {
+    generation: u64,
+    token: CancellationToken,
+    calls: usize,
+}
+
+/// In-flight calls per correlation id
+#[derive(Default)]
+pub(crate) struct ActiveCorrelations {
+    active: DashMap<String, Active>,
+    generations: AtomicU64,
+}
+
+impl ActiveCorrelations {
+    /// Track a call until the returned guard drops
+    pub(crate) fn enter(&self, correlation_id: &str) -> CorrelationGuard<'_> {
+        let mut entry = self
+            .active
+            .entry(correlation_id.to_string())
+            .or_insert_with(|| Active {
+                generation: self.generations.fetch_add(1, Ordering::Relaxed),
+                token: CancellationToken::new(),
+                calls: 0,
+            });
+        entry.calls += 1;
+        CorrelationGuard {
+            owner: self,
+            correlation_id: correlation_id.to_string(),
+            generation: entry.generation,
+            token: entry.token.clone(),
+        }
+    }
+
+    /// Fire the token of `correlation_id`; later calls start with a fresh one
+    fn cancel(&self, correlation_id: &str) -> usize {
+        match self.active.remove(correlation_id) {
+            Some((_, active)) => {
+                active.token.cancel();
+                active.calls
+            }
+            None => 0,
+        }
+    }
+}
+
+pub(crate) struct CorrelationGuard<'a> {
+    owner: &'a ActiveCorrelations,
+    correlation_id: String,
+    generation: u64,
+    token: CancellationToken,
+}
+
+impl CorrelationGuard<'_> {
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub(crate) fn correlation_id(&self) -> &str {
+        &self.correlation_id
+    }
+}
+
+impl Drop for CorrelationGuard<'_> {
+    fn drop(&mut self) {
+        // A cancelled generation was already removed; never touch its successor
+        self.owner
+            .active
+            .remove_if_mut(&self.correlation_id, |_, active| {
+                if active.generation == self.generation {
+                    active.calls -= 1;
+                }
+                active.calls == 0
+            });
+    }
+}
+
+impl ActionBroker {
+    /// Cancel every in-flight call whose correlation id is `correlation_id`; returns how many
+    /// were signalled. Their callers get an `ActionError` with code `CANCELLED` and their
+    /// providers' cancellation tokens fire. Calls arriving afterwards run normally.
+    pub fn cancel_correlation(&self, correlation_id: &str) -> usize {
+        let cancelled = self.active.cancel(correlation_id);
+        if cancelled > 0 {
+            info!(target: "action_broker", correlation_id = %correlation_id, calls = cancelled, "Cancelling correlation");
+        }
+        cancelled
+    }
+
+    /// `CANCELLED` result for a call abandoned through `cancel_correlation`. Not cached, so a
+    /// retry with the same call id runs again.
+    pub(crate) fn cancelled_result(
+        &self,
+        call_id: String,
+        capability: &str,
+        correlation_id: &str,
+        phase: &str,
+    ) -> ActionResult {
+        debug!(target: "action_broker", capability = %capability, call_id = %call_id, phase, "Call cancelled");
+        self.invocations_counter.add(
+            1,
+            &[
+                KeyValue::new("capability", capability.to_string()),
+                KeyValue::new("status", "cancelled"),
+            ],
+        );
+        self.errors_counter.add(
+            1,
+            &[
+                KeyValue::new("capability", capability.to_string()),
+                KeyValue::new("error_code", CANCELLED),
+            ],
+        );
+        Span::current().record("status", "cancelled");
+        ActionResultBuilder::new(ActionStatus::ActionError)
+            .id(call_id)
+            .error_with_debug(
+                CANCELLED,
+                "Action cancelled",
+                format!("correlation {correlation_id} cancelled while {capability} was {phase}"),
+            )
+            .detail("phase", phase)
+            .build()
+    }
+}
@@ -0,0 +1,37 @@
+//! Capability categories: a `CapabilityDescriptor.metadata["category"]` convention.
+
+use super::ActionBroker;
+use crate::proto::CapabilityDescriptor;
+use std::collections::HashMap;
+
+/// Descriptor metadata key naming the capability's category (`audio`, `retrieval`, `web`, ...)
+pub const CATEGORY_METADATA_KEY: &str = "category";
+/// Category of descriptors without one
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// Trimmed, lowercased category of `desc`, or `UNCATEGORIZED` when missing or blank
+pub fn category_of(desc: &CapabilityDescriptor) -> String {
+    desc.metadata
+        .get(CATEGORY_METADATA_KEY)
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| UNCATEGORIZED.to_string())
+}
+
+impl ActionBroker {
+    /// Registered descriptors grouped by `category_of`; each group keeps the
+    /// `list_capabilities` order
+    pub fn capabilities_by_category(&self) -> HashMap<String, Vec<CapabilityDescriptor>> {
+        let mut out: HashMap<String, Vec<CapabilityDescriptor>> = HashMap::new();
+        for desc in self.list_capabilities() {
+            out.entry(category_of(&desc)).or_default().push(desc);
+        }
+        out
+    }
+
+    /// Descriptors in `category` (matched case-insensitively)
+    pub fn capabilities_in_category(&self, category: &str) -> Vec<CapabilityDescriptor> {
+        let category = category.trim().to_lowercase();
+        self.list_capabilities_filtered(|d| category_of(d) == category)
+    }
+}
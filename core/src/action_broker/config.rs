@@ -0,0 +1,125 @@
+//! Serializable broker policy (everything except the registered providers).
+
+use super::qos;
+use super::{InvocationScope, OnMissingKey, QuarantinePolicy, RateLimit, RetryPolicy};
+use crate::proto::QoSLevel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Timeout applied to calls with `Timeout::Default` (`timeout_ms <= 0`) whose QoS has no
+/// entry in `BrokerConfig::qos_timeouts_ms`
+pub const DEFAULT_TIMEOUT_MS: i64 = 30_000;
+/// Default per-QoS timeouts: realtime 2 s, batched (interactive) 10 s, background 60 s
+pub const DEFAULT_QOS_TIMEOUTS_MS: [(&str, i64); 3] = [
+    ("realtime", 2_000),
+    ("batched", 10_000),
+    ("background", 60_000),
+];
+/// Default upper bound for the `x-timeout-ms` header override
+pub const DEFAULT_MAX_HEADER_TIMEOUT_MS: i64 = 300_000;
+/// Default idempotency cache size before trimming
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 1024;
+/// Default time finished jobs stay pollable (10 min)
+pub const DEFAULT_JOB_TTL_MS: i64 = 600_000;
+
+/// What `invoke` does when no provider matches the call's capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnMissing {
+    /// Return `Err(LoomError::PluginError)`
+    #[default]
+    Error,
+    /// Return an `ActionError` result with code `CAPABILITY_NOT_FOUND`
+    Result,
+}
+
+/// Broker policy snapshot; round-trips through JSON via serde.
+///
+/// Missing fields take their defaults, so older snapshots keep loading as knobs are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrokerConfig {
+    /// Timeout used when a call's `timeout_ms` is <= 0 and its QoS has no entry below
+    pub default_timeout_ms: i64,
+    /// Timeout per QoS level name for calls whose `timeout_ms` is <= 0
+    pub qos_timeouts_ms: BTreeMap<String, i64>,
+    /// Upper bound for the `x-timeout-ms` header override
+    pub max_header_timeout_ms: i64,
+    /// Fraction of the timeout after which the provider's cancel token fires (None = off)
+    pub soft_timeout_ratio: Option<f64>,
+    /// Idempotency cache TTL (None = entries live until trimmed)
+    pub cache_ttl_ms: Option<i64>,
+    /// Idempotency cache size before trimming
+    pub cache_max_entries: usize,
+    /// Budget for keys without an explicit one (None = unlimited)
+    pub default_budget: Option<f64>,
+    /// Explicit budgets per key of `budget_scope`
+    pub budgets: BTreeMap<String, f64>,
+    /// What cost is accounted and budgeted per: correlation id (default), tenant or principal
+    pub budget_scope: InvocationScope,
+    /// Call rate limits per key of each scope
+    pub rate_limits: BTreeMap<InvocationScope, RateLimit>,
+    /// Tenant / principal limits and budgets for calls without that key: shared
+    /// `anonymous` key (default) or rejection
+    pub on_missing_key: OnMissingKey,
+    /// Max in-flight invocations per capability name
+    pub concurrency_limits: BTreeMap<String, usize>,
+    /// Handling of calls to unregistered capabilities
+    pub on_missing: OnMissing,
+    /// QoS for calls that neither set `qos` nor hit a provider with `default_qos`
+    #[serde(with = "qos::by_name")]
+    pub default_qos: QoSLevel,
+    /// Max in-flight invocations per QoS level name (`realtime`, `batched`, `background`)
+    pub qos_concurrency_limits: BTreeMap<String, usize>,
+    /// Trace-log full payloads for one call in this many (0 = sizes and status only)
+    pub payload_sample_every: u64,
+    /// Check successful outputs against `CapabilityProvider::output_schema`
+    pub validate_output: bool,
+    /// Stamp `metadata["duration_ms"]` on results of dispatched calls
+    pub attach_duration: bool,
+    /// Pull capabilities out of routing after repeated timeouts (None = off)
+    pub quarantine: Option<QuarantinePolicy>,
+    /// How long finished jobs stay pollable
+    pub job_ttl_ms: i64,
+    /// Strip `ActionError.details["debug"]` from results returned by `invoke`
+    pub redact_debug_details: bool,
+    /// Map failed provider results onto `UNAVAILABLE` / `TIMEOUT` / `INVALID_ARGUMENT` /
+    /// `INTERNAL` with the adapter's `ErrorMapper`
+    pub normalize_errors: bool,
+    /// Retry timed-out and retryable calls, with optional backoff and timeout jitter
+    /// (None = off)
+    pub retry: Option<RetryPolicy>,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout_ms: DEFAULT_TIMEOUT_MS,
+            qos_timeouts_ms: DEFAULT_QOS_TIMEOUTS_MS
+                .iter()
+                .map(|(name, ms)| (name.to_string(), *ms))
+                .collect(),
+            max_header_timeout_ms: DEFAULT_MAX_HEADER_TIMEOUT_MS,
+            soft_timeout_ratio: None,
+            cache_ttl_ms: None,
+            cache_max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            default_budget: None,
+            budgets: BTreeMap::new(),
+            budget_scope: InvocationScope::default(),
+            rate_limits: BTreeMap::new(),
+            on_missing_key: OnMissingKey::default(),
+            concurrency_limits: BTreeMap::new(),
+            on_missing: OnMissing::default(),
+            default_qos: QoSLevel::QosRealtime,
+            qos_concurrency_limits: BTreeMap::new(),
+            payload_sample_every: 0,
+            validate_output: false,
+            attach_duration: false,
+            quarantine: None,
+            job_ttl_ms: DEFAULT_JOB_TTL_MS,
+            redact_debug_details: false,
+            normalize_errors: false,
+            retry: None,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Per-invocation context: who a call is for, handed to authorizers, rate limits, cost
+//! accounting and providers (through `CapabilityProvider::invoke_with_context`).
+//!
+//! The tenant and principal come from call headers, which the broker trusts as given. Set
+//! them in a trusted layer (gateway, bridge, authorizer) rather than from client input, or a
+//! caller can claim another tenant's allowance.
+
+use super::auth::{PRINCIPAL_HEADER, TENANT_HEADER};
+use super::ActionBroker;
+use crate::context::MemoryReader;
+use crate::proto::ActionCall;
+use crate::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Which context field a per-key policy (rate limit, budget) is keyed on
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum InvocationScope {
+    /// `InvocationContext::correlation_id`
+    #[default]
+    Correlation,
+    /// `InvocationContext::tenant_id`
+    Tenant,
+    /// `InvocationContext::principal`
+    Principal,
+}
+
+/// Key shared by calls that carry none for a scope, under `OnMissingKey::Shared`
+pub const ANONYMOUS_KEY: &str = "anonymous";
+/// Error code for calls rejected under `OnMissingKey::Reject`
+pub const SCOPE_KEY_MISSING: &str = "SCOPE_KEY_MISSING";
+
+/// What tenant or principal rate limits and budgets do with calls that lack that key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnMissingKey {
+    /// Count them all under `ANONYMOUS_KEY`, so leaving out the header buys no extra allowance
+    #[default]
+    Shared,
+    /// Reject them with `SCOPE_KEY_MISSING` before they are counted
+    Reject,
+}
+
+impl InvocationScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvocationScope::Correlation => "correlation",
+            InvocationScope::Tenant => "tenant",
+            InvocationScope::Principal => "principal",
+        }
+    }
+}
+
+/// What the broker knows about one dispatch, beyond the `ActionCall` itself
+#[derive(Clone, Default)]
+pub struct InvocationContext {
+    /// Session memory supplied with `ActionBroker::invoke_in_context`, else the one installed
+    /// with `ActionBroker::set_memory_reader`, if any
+    pub reader: Option<Arc<dyn MemoryReader>>,
+    /// The caller's correlation id (the typed field, else the envelope header), falling back
+    /// to the envelope default (the call id)
+    pub correlation_id: String,
+    /// Tenant the call is made for (supplied, else the `tenant` header), if any
+    pub tenant_id: Option<String>,
+    /// Caller identity (supplied, else the `principal` header), if any
+    pub principal: Option<String>,
+    /// Hard deadline of the call; `None` when unbounded. Unset while authorizing.
+    pub deadline: Option<Instant>,
+    /// Fires at the soft deadline (see `ActionBroker::set_soft_timeout_ratio`)
+    pub cancel: CancellationToken,
+}
+
+impl InvocationContext {
+    /// Time left before the hard deadline; `None` when unbounded
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// The value `scope` is keyed on; `None` when the call does not carry it
+    pub fn key(&self, scope: InvocationScope) -> Option<&str> {
+        let key = match scope {
+            InvocationScope::Correlation => Some(&self.correlation_id),
+            InvocationScope::Tenant => self.tenant_id.as_ref(),
+            InvocationScope::Principal => self.principal.as_ref(),
+        };
+        key.map(String::as_str).filter(|k| !k.is_empty())
+    }
+
+    /// The key rate limits and budgets use for `scope`: the call's own, else `ANONYMOUS_KEY`
+    pub(crate) fn policy_key(&self, scope: InvocationScope) -> &str {
+        self.key(scope).unwrap_or(ANONYMOUS_KEY)
+    }
+}
+
+impl ActionBroker {
+    /// How rate limits and budgets treat calls without a key for their scope
+    pub fn set_on_missing_key(&self, policy: OnMissingKey) {
+        self.settings.write().unwrap().on_missing_key = policy;
+    }
+
+    /// First scope with a rate limit or budget that `ctx` has no key for, when such calls
+    /// are rejected (`OnMissingKey::Reject`)
+    pub(crate) fn rejected_scope(&self, ctx: &InvocationContext) -> Option<InvocationScope> {
+        let settings = self.settings.read().unwrap();
+        if settings.on_missing_key != OnMissingKey::Reject {
+            return None;
+        }
+        // Correlation keys fall back to the call id, so only tenant and principal can be missing
+        let budgeted = (settings.budget_scope != InvocationScope::Correlation)
+            .then_some(settings.budget_scope);
+        settings
+            .rate_limits
+            .keys()
+            .copied()
+            .chain(budgeted)
+            .find(|scope| ctx.key(*scope).is_none())
+    }
+
+    /// Hand `reader` to every provider invocation via `InvocationContext.reader`
+    pub fn set_memory_reader(&self, reader: Arc<dyn MemoryReader>) {
+        *self.memory_reader.write().unwrap() = Some(reader);
+    }
+
+    pub fn clear_memory_reader(&self) {
+        *self.memory_reader.write().unwrap() = None;
+    }
+
+    /// Context of `call`, whose envelope must already be applied, with the caller's
+    /// `supplied` fields taking precedence. The deadline and cancel token are filled in once
+    /// the call is admitted.
+    pub(crate) fn invocation_context(
+        &self,
+        call: &ActionCall,
+        correlation_id: &str,
+        supplied: &InvocationContext,
+    ) -> InvocationContext {
+        let header = |name: &str| call.headers.get(name).filter(|v| !v.is_empty()).cloned();
+        let given = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+        InvocationContext {
+            reader: supplied
+                .reader
+                .clone()
+                .or_else(|| self.memory_reader.read().unwrap().clone()),
+            correlation_id: correlation_id.to_string(),
+            tenant_id: given(&supplied.tenant_id).or_else(|| header(TENANT_HEADER)),
+            principal: given(&supplied.principal).or_else(|| header(PRINCIPAL_HEADER)),
+            deadline: None,
+            cancel: CancellationToken::new(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Cost accounting and budgets.
+//!
+//! Spend and budgets are keyed on the `BrokerConfig::budget_scope` of the call: its
+//! correlation id (the default; calls without one are not accounted), or its tenant or
+//! principal from the `InvocationContext`. Calls without a tenant or principal key share
+//! `ANONYMOUS_KEY`, or are rejected under `OnMissingKey::Reject`.
+
+use dashmap::DashMap;
+
+use crate::proto::{ActionCall, ActionResult};
+
+/// `ActionResult.metadata` key providers use to report the cost of a call
+pub const COST_METADATA_KEY: &str = "cost";
+
+/// Accumulates reported cost per budget key (see the module docs) and enforces optional budgets
+#[derive(Debug, Default)]
+pub(crate) struct CostLedger {
+    spent: DashMap<String, f64>,
+    budgets: DashMap<String, f64>,
+    default_budget: std::sync::RwLock<Option<f64>>,
+}
+
+impl CostLedger {
+    pub(crate) fn spent(&self, key: &str) -> f64 {
+        self.spent.get(key).map(|v| *v).unwrap_or(0.0)
+    }
+
+    pub(crate) fn set_budget(&self, key: &str, budget: f64) {
+        self.budgets.insert(key.to_string(), budget);
+    }
+
+    pub(crate) fn set_default_budget(&self, budget: Option<f64>) {
+        *self.default_budget.write().unwrap() = budget;
+    }
+
+    pub(crate) fn budget(&self, key: &str) -> Option<f64> {
+        self.budgets
+            .get(key)
+            .map(|v| *v)
+            .or(*self.default_budget.read().unwrap())
+    }
+
+    /// True when `key` has a budget and has already spent all of it
+    pub(crate) fn is_exhausted(&self, key: &str) -> bool {
+        self.budget(key)
+            .map(|b| self.spent(key) >= b)
+            .unwrap_or(false)
+    }
+
+    /// Add the cost reported in `res.metadata["cost"]` (if any); returns the new total
+    pub(crate) fn record(&self, key: &str, res: &ActionResult) -> f64 {
+        let cost = res
+            .metadata
+            .get(COST_METADATA_KEY)
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|c| c.is_finite() && *c > 0.0);
+        match cost {
+            Some(c) => {
+                let mut entry = self.spent.entry(key.to_string()).or_insert(0.0);
+                *entry += c;
+                *entry
+            }
+            None => self.spent(key),
+        }
+    }
+
+    pub(crate) fn default_budget(&self) -> Option<f64> {
+        *self.default_budget.read().unwrap()
+    }
+
+    /// Explicit budgets, sorted by key
+    pub(crate) fn budgets(&self) -> std::collections::BTreeMap<String, f64> {
+        self.budgets
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect()
+    }
+
+    /// Replace all explicit budgets (accumulated spend is kept)
+    pub(crate) fn replace_budgets(&self, budgets: &std::collections::BTreeMap<String, f64>) {
+        self.budgets.clear();
+        for (key, b) in budgets {
+            self.budgets.insert(key.clone(), *b);
+        }
+    }
+
+    pub(crate) fn reset(&self, key: &str) {
+        self.spent.remove(key);
+    }
+}
+
+/// Correlation key used for accounting: the typed field, falling back to the envelope header
+pub(crate) fn correlation_key(call: &ActionCall) -> Option<String> {
+    if !call.correlation_id.is_empty() {
+        return Some(call.correlation_id.clone());
+    }
+    call.headers
+        .get(crate::envelope::keys::CORRELATION_ID)
+        .filter(|s| !s.is_empty())
+        .cloned()
+}
@@ -0,0 +1,75 @@
+//! Validation of `CapabilityDescriptor.depends_on` across registered providers.
+
+use super::ActionBroker;
+use std::collections::{BTreeMap, BTreeSet};
+
+impl ActionBroker {
+    /// Check that every declared dependency is registered and that dependencies are acyclic.
+    ///
+    /// Dependencies name capabilities (any version). On failure, returns one message per
+    /// missing dependency or cycle, in deterministic order.
+    pub fn validate_dependencies(&self) -> std::result::Result<(), Vec<String>> {
+        // capability name -> union of dependencies declared by its registered versions
+        let mut graph: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for desc in self.list_capabilities() {
+            graph.entry(desc.name).or_default().extend(desc.depends_on);
+        }
+
+        let mut problems = Vec::new();
+        for (name, deps) in &graph {
+            for dep in deps.iter().filter(|d| !graph.contains_key(*d)) {
+                problems.push(format!("{} depends on missing capability '{}'", name, dep));
+            }
+        }
+
+        let mut state: BTreeMap<&str, Visit> = BTreeMap::new();
+        let mut path = Vec::new();
+        for name in graph.keys() {
+            find_cycles(name, &graph, &mut state, &mut path, &mut problems);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+/// Depth-first search reporting each back edge as a cycle `a -> b -> a`
+fn find_cycles<'a>(
+    name: &'a str,
+    graph: &'a BTreeMap<String, BTreeSet<String>>,
+    state: &mut BTreeMap<&'a str, Visit>,
+    path: &mut Vec<&'a str>,
+    problems: &mut Vec<String>,
+) {
+    match state.get(name) {
+        Some(Visit::Done) => return,
+        Some(Visit::InProgress) => {
+            let start = path.iter().position(|n| *n == name).unwrap_or(0);
+            let mut cycle: Vec<&str> = path[start..].to_vec();
+            cycle.push(name);
+            problems.push(format!("dependency cycle: {}", cycle.join(" -> ")));
+            return;
+        }
+        None => {}
+    }
+    let Some(deps) = graph.get(name) else {
+        // Missing dependency, already reported
+        return;
+    };
+    state.insert(name, Visit::InProgress);
+    path.push(name);
+    for dep in deps {
+        find_cycles(dep, graph, state, path, problems);
+    }
+    path.pop();
+    state.insert(name, Visit::Done);
+}
@@ -0,0 +1,152 @@
+//! Serializable runtime snapshot of the broker for operators (e.g. a `/debug/capabilities`
+//! endpoint).
+//!
+//! Everything is read from in-memory maps without awaiting, so taking a snapshot is cheap
+//! enough to poll. The snapshot is not atomic: counters may move while it is assembled.
+
+use super::qos::qos_name;
+use super::{
+    compare_versions, ActionBroker, InvocationScope, OnMissingKey, QuarantinePolicy,
+    QuarantineState, RateLimit,
+};
+use crate::proto::{ProviderKind, QoSLevel};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Whole-broker snapshot returned by `ActionBroker::diagnostics`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokerDiagnostics {
+    /// One entry per capability name, sorted by name
+    pub capabilities: Vec<CapabilityDiagnostics>,
+    /// Calls dispatched and not yet finished, across all capabilities
+    pub in_flight: usize,
+    /// Max in-flight invocations per QoS level name
+    pub qos_concurrency_limits: BTreeMap<String, usize>,
+    pub quarantine_policy: Option<QuarantinePolicy>,
+    /// Call rate limit per scope name (`correlation`, `tenant`, `principal`)
+    pub rate_limits: BTreeMap<String, RateLimit>,
+    /// What cost is accounted and budgeted per
+    pub budget_scope: InvocationScope,
+    /// Handling of calls without a key for a limited or budgeted scope
+    pub on_missing_key: OnMissingKey,
+    /// Entries in the idempotency cache
+    pub cached_results: usize,
+}
+
+/// Runtime state of one capability name; limits, counters and quarantine span all versions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapabilityDiagnostics {
+    pub name: String,
+    /// Registered providers, lowest version first
+    pub versions: Vec<ProviderDiagnostics>,
+    /// Max in-flight invocations (`None` = unlimited)
+    pub concurrency_limit: Option<usize>,
+    /// Calls dispatched and not yet finished, including ones waiting for a permit
+    pub in_flight: usize,
+    /// Timeout counter and cooldown, when timeouts are on record
+    pub quarantine: Option<QuarantineState>,
+}
+
+/// Descriptor of one registered provider
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderDiagnostics {
+    pub version: String,
+    /// Proto name of the provider kind, e.g. `PROVIDER_NATIVE`
+    pub provider: String,
+    pub metadata: BTreeMap<String, String>,
+    pub depends_on: Vec<String>,
+    /// QoS level name, when the provider declares one
+    pub default_qos: Option<String>,
+    pub runs_blocking: bool,
+}
+
+/// In-flight counter per capability name
+#[derive(Default)]
+pub(crate) struct InFlight {
+    counts: DashMap<String, Arc<AtomicUsize>>,
+}
+
+impl InFlight {
+    /// Count a call until the returned guard drops
+    pub(crate) fn enter(&self, capability: &str) -> InFlightGuard {
+        let count = Arc::clone(&self.counts.entry(capability.to_string()).or_default());
+        count.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(count)
+    }
+
+    fn get(&self, capability: &str) -> usize {
+        self.counts
+            .get(capability)
+            .map_or(0, |c| c.load(Ordering::Relaxed))
+    }
+
+    fn total(&self) -> usize {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+    }
+}
+
+pub(crate) struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ActionBroker {
+    /// Snapshot of registered providers, concurrency limits, quarantine states and in-flight
+    /// counts; serialize it for a diagnostics endpoint
+    pub fn diagnostics(&self) -> BrokerDiagnostics {
+        let mut by_name: BTreeMap<String, Vec<ProviderDiagnostics>> = BTreeMap::new();
+        for provider in self.providers() {
+            let desc = provider.descriptor();
+            by_name
+                .entry(desc.name)
+                .or_default()
+                .push(ProviderDiagnostics {
+                    version: desc.version,
+                    provider: ProviderKind::try_from(desc.provider)
+                        .map_or_else(|_| desc.provider.to_string(), |k| k.as_str_name().into()),
+                    metadata: desc.metadata.into_iter().collect(),
+                    depends_on: desc.depends_on,
+                    default_qos: desc
+                        .default_qos
+                        .and_then(|q| QoSLevel::try_from(q).ok())
+                        .map(|q| qos_name(q).to_string()),
+                    runs_blocking: provider.runs_blocking(),
+                });
+        }
+        let limits = self.limits.limits();
+        let capabilities = by_name
+            .into_iter()
+            .map(|(name, mut versions)| {
+                versions.sort_by(|a, b| compare_versions(&a.version, &b.version));
+                CapabilityDiagnostics {
+                    concurrency_limit: limits.get(&name).copied(),
+                    in_flight: self.in_flight.get(&name),
+                    quarantine: self.quarantine_state(&name),
+                    name,
+                    versions,
+                }
+            })
+            .collect();
+        let settings = self.settings.read().unwrap();
+        BrokerDiagnostics {
+            capabilities,
+            in_flight: self.in_flight.total(),
+            qos_concurrency_limits: self.qos_limits.limits(),
+            quarantine_policy: settings.quarantine,
+            rate_limits: settings
+                .rate_limits
+                .iter()
+                .map(|(scope, limit)| (scope.as_str().to_string(), *limit))
+                .collect(),
+            budget_scope: settings.budget_scope,
+            on_missing_key: settings.on_missing_key,
+            cached_results: self.cache.len(),
+        }
+    }
+}
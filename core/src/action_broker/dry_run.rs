@@ -0,0 +1,97 @@
+//! Validating calls without invoking providers.
+
+use super::admission::{Admission, AdmissionMode};
+use super::cost::correlation_key;
+use super::{qos_name, ActionBroker, ActionResultBuilder, InvocationContext};
+use crate::envelope::Envelope;
+use crate::llm::{validate_arguments, INVALID_ARGUMENTS};
+use crate::proto::{ActionCall, ActionResult, ActionStatus};
+use serde_json::Value;
+
+/// Result metadata key set to `"true"` on every dry-run result
+pub const DRY_RUN_METADATA_KEY: &str = "dry_run";
+
+impl ActionBroker {
+    /// Run the checks `invoke` would make — authorization, missing scope keys, budget,
+    /// capability lookup, rate limits and quarantine, then the payload against the
+    /// descriptor's `schema` — without calling the provider.
+    ///
+    /// Passing calls get an `ActionOk` result with empty output; failing calls get the error
+    /// `invoke` would return (a missing capability is always a result here, whatever
+    /// `OnMissing` says). Both carry `metadata["dry_run"] = "true"`. Nothing is cached,
+    /// counted or charged: rate limits are only peeked, a quarantine probe slot is never
+    /// taken, and invoke hooks do not run. The idempotency cache is not consulted either.
+    pub fn invoke_dry_run(&self, mut call: ActionCall) -> ActionResult {
+        if call.id.is_empty() {
+            call.id = self.ids.new_id();
+        }
+        let cost_key = correlation_key(&call);
+        let env = Envelope::from_metadata(&call.headers, &call.id);
+        env.apply_to_action_call(&mut call);
+        let ctx = self.invocation_context(
+            &call,
+            cost_key.as_deref().unwrap_or(&call.correlation_id),
+            &InvocationContext::default(),
+        );
+
+        let admission = match self.authorize_call(&call, &ctx, AdmissionMode::DryRun) {
+            Some(denied) => Admission::Rejected(denied),
+            None => self
+                .admit_call(&call, &ctx, cost_key.as_deref(), AdmissionMode::DryRun)
+                .expect("dry runs report a missing capability as a result"),
+        };
+        let provider = match admission {
+            Admission::Admitted(admitted) => admitted.provider,
+            Admission::Rejected(mut rejected) => {
+                rejected
+                    .metadata
+                    .insert(DRY_RUN_METADATA_KEY.to_string(), "true".to_string());
+                return rejected;
+            }
+        };
+        let desc = provider.descriptor();
+
+        if let Some(schema) = desc
+            .metadata
+            .get("schema")
+            .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        {
+            let payload = if call.payload.is_empty() {
+                Ok(Value::Object(Default::default()))
+            } else {
+                serde_json::from_slice::<Value>(&call.payload)
+                    .map_err(|e| format!("payload is not JSON: {e}"))
+            };
+            if let Err(violation) = payload.and_then(|p| validate_arguments(&schema, &p)) {
+                return dry_run_result(call.id, ActionStatus::ActionError)
+                    .error(
+                        INVALID_ARGUMENTS,
+                        format!(
+                            "Payload of {} violates its schema: {}",
+                            desc.name, violation
+                        ),
+                    )
+                    .detail("violation", violation)
+                    .build();
+            }
+        }
+
+        let qos = self.resolve_qos(&call, &desc);
+        let timeout_ms = match self.call_timeout(&call, qos) {
+            Some(limit) => limit.as_millis().to_string(),
+            None => "unbounded".to_string(),
+        };
+        dry_run_result(call.id, ActionStatus::ActionOk)
+            .metadata("provider_version", desc.version.clone())
+            .metadata("qos", qos_name(qos))
+            .metadata("timeout_ms", timeout_ms)
+            .build()
+    }
+}
+
+/// Result of a dry run, marked with `metadata["dry_run"] = "true"`
+fn dry_run_result(id: String, status: ActionStatus) -> ActionResultBuilder {
+    ActionResultBuilder::new(status)
+        .id(id)
+        .metadata(DRY_RUN_METADATA_KEY, "true")
+}
@@ -0,0 +1,67 @@
+//! User-safe error messages with internal detail kept apart.
+//!
+//! `ActionError.message` may be shown to end users, so it carries a short, safe sentence.
+//! Anything internal (provider error text, panic messages, phases and deadlines) goes under
+//! `details["debug"]`, which logs and developers read and `redact_debug_details` strips.
+
+use super::ActionBroker;
+use crate::proto::{ActionError, ActionResult};
+
+/// `ActionError.details` key for internal detail that must not reach end users
+pub const DEBUG_DETAIL_KEY: &str = "debug";
+
+/// User-safe/debug split helpers on the generated `ActionError`
+pub trait ActionErrorExt {
+    /// Error with a user-safe `message` and internal `debug` detail under `details["debug"]`
+    fn with_debug(
+        code: impl Into<String>,
+        message: impl Into<String>,
+        debug: impl Into<String>,
+    ) -> Self;
+
+    /// The internal detail, if any
+    fn debug_detail(&self) -> Option<&str>;
+
+    /// Remove `details["debug"]` before handing the error to an end user
+    fn strip_debug(&mut self);
+}
+
+impl ActionErrorExt for ActionError {
+    fn with_debug(
+        code: impl Into<String>,
+        message: impl Into<String>,
+        debug: impl Into<String>,
+    ) -> Self {
+        ActionError {
+            code: code.into(),
+            message: message.into(),
+            details: [(DEBUG_DETAIL_KEY.to_string(), debug.into())]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn debug_detail(&self) -> Option<&str> {
+        self.details.get(DEBUG_DETAIL_KEY).map(String::as_str)
+    }
+
+    fn strip_debug(&mut self) {
+        self.details.remove(DEBUG_DETAIL_KEY);
+    }
+}
+
+impl ActionBroker {
+    /// Strip `details["debug"]` from every result `invoke` returns (hooks and payload traces
+    /// still see it); for brokers whose results go straight to end users
+    pub fn set_redact_debug_details(&self, redact: bool) {
+        self.settings.write().unwrap().redact_debug_details = redact;
+    }
+
+    pub(crate) fn redact(&self, result: &mut ActionResult) {
+        if self.settings.read().unwrap().redact_debug_details {
+            if let Some(error) = result.error.as_mut() {
+                error.strip_debug();
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Lightweight synchronous callbacks around `ActionBroker::invoke`.
+
+use super::ActionBroker;
+use crate::proto::{ActionCall, ActionResult};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+type StartHook = Arc<dyn Fn(&ActionCall) + Send + Sync>;
+type EndHook = Arc<dyn Fn(&ActionCall, &ActionResult, Duration) + Send + Sync>;
+
+/// Registered callbacks, run in registration order
+#[derive(Default)]
+pub(crate) struct InvokeHooks {
+    start: RwLock<Vec<StartHook>>,
+    end: RwLock<Vec<EndHook>>,
+}
+
+impl InvokeHooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.start.read().unwrap().is_empty() && self.end.read().unwrap().is_empty()
+    }
+
+    pub(crate) fn run_start(&self, call: &ActionCall) {
+        // Snapshot so a hook may register further hooks without deadlocking
+        let hooks = self.start.read().unwrap().clone();
+        for hook in hooks {
+            hook(call);
+        }
+    }
+
+    pub(crate) fn run_end(&self, call: &ActionCall, result: &ActionResult, elapsed: Duration) {
+        let hooks = self.end.read().unwrap().clone();
+        for hook in hooks {
+            hook(call, result, elapsed);
+        }
+    }
+}
+
+impl ActionBroker {
+    /// Call `hook` with every call entering `invoke`, before authorization or dispatch
+    pub fn on_invoke_start<F>(&self, hook: F)
+    where
+        F: Fn(&ActionCall) + Send + Sync + 'static,
+    {
+        self.hooks.start.write().unwrap().push(Arc::new(hook));
+    }
+
+    /// Call `hook` with every result `invoke` returns (errors, timeouts and cache hits included)
+    /// and the time spent in `invoke`. Not called when `invoke` itself returns `Err`.
+    pub fn on_invoke_end<F>(&self, hook: F)
+    where
+        F: Fn(&ActionCall, &ActionResult, Duration) + Send + Sync + 'static,
+    {
+        self.hooks.end.write().unwrap().push(Arc::new(hook));
+    }
+
+    /// Remove all start and end hooks
+    pub fn clear_invoke_hooks(&self) {
+        self.hooks.start.write().unwrap().clear();
+        self.hooks.end.write().unwrap().clear();
+    }
+}
@@ -0,0 +1,252 @@
+//! Long-running jobs: submit through `invoke`, collect the outcome with `poll`.
+//!
+//! A `JobProvider` registered with `register_job_provider` answers `invoke` right away with an
+//! `ActionPending` result carrying `metadata["job_id"]` and keeps running in the background.
+//! A job goes from pending to exactly one final status (`ok`, `error`, `timeout` or
+//! `retryable`) and never changes again. Finished jobs stay pollable for
+//! `BrokerConfig::job_ttl_ms`; after that `poll` answers `JOB_NOT_FOUND`, as for unknown ids.
+
+use super::{panic_message, ActionBroker, ActionResultBuilder, CapabilityProvider};
+use crate::clock::Clock;
+use crate::ids::IdGenerator;
+use crate::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor};
+use crate::Result;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures_util::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Result metadata key holding the job id, on the pending result and on every poll answer
+pub const JOB_ID_METADATA_KEY: &str = "job_id";
+/// Result metadata key holding the last reported progress (`0.0..=1.0`) of a running job
+pub const JOB_PROGRESS_METADATA_KEY: &str = "progress";
+/// Error code of `poll` for unknown or expired job ids
+pub const JOB_NOT_FOUND: &str = "JOB_NOT_FOUND";
+
+/// Provider of a capability that runs as a background job instead of answering inline
+#[async_trait]
+pub trait JobProvider: Send + Sync {
+    fn descriptor(&self) -> CapabilityDescriptor;
+
+    /// Run the job to completion. The returned result is what `poll` reports once the job is
+    /// done; `Err` becomes `CAPABILITY_ERROR`, a panic `PROVIDER_PANIC`.
+    async fn run(&self, call: ActionCall, progress: JobProgress) -> Result<ActionResult>;
+}
+
+/// Handle a running job uses to report how far along it is
+#[derive(Clone)]
+pub struct JobProgress {
+    job_id: String,
+    fraction: Arc<AtomicU32>,
+}
+
+impl JobProgress {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+
+    /// Record progress as a fraction, clamped to `0.0..=1.0` (NaN is ignored)
+    pub fn report(&self, fraction: f32) {
+        if !fraction.is_nan() {
+            self.fraction
+                .store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.fraction.load(Ordering::Relaxed))
+    }
+}
+
+struct Job {
+    call_id: String,
+    progress: JobProgress,
+    /// Final result and the clock time it arrived
+    outcome: Option<(ActionResult, i64)>,
+}
+
+/// Job table shared by the broker (`poll`) and the job provider adapters (submit/finish)
+pub(crate) struct Jobs {
+    jobs: DashMap<String, Job>,
+    ttl_ms: AtomicI64,
+}
+
+impl Jobs {
+    pub(crate) fn new(ttl_ms: i64) -> Self {
+        Self {
+            jobs: DashMap::new(),
+            ttl_ms: AtomicI64::new(ttl_ms),
+        }
+    }
+
+    pub(crate) fn set_ttl_ms(&self, ttl_ms: i64) {
+        self.ttl_ms.store(ttl_ms, Ordering::Relaxed);
+    }
+
+    pub(crate) fn ttl_ms(&self) -> i64 {
+        self.ttl_ms.load(Ordering::Relaxed)
+    }
+
+    fn start(&self, job_id: &str, call_id: &str, now_ms: i64) -> JobProgress {
+        self.purge(now_ms);
+        let progress = JobProgress {
+            job_id: job_id.to_string(),
+            fraction: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+        };
+        self.jobs.insert(
+            job_id.to_string(),
+            Job {
+                call_id: call_id.to_string(),
+                progress: progress.clone(),
+                outcome: None,
+            },
+        );
+        progress
+    }
+
+    fn finish(&self, job_id: &str, result: ActionResult, now_ms: i64) {
+        if let Some(mut job) = self.jobs.get_mut(job_id) {
+            job.outcome.get_or_insert((result, now_ms));
+        }
+    }
+
+    /// Drop finished jobs older than the TTL
+    fn purge(&self, now_ms: i64) {
+        let ttl = self.ttl_ms();
+        self.jobs.retain(|_, job| match &job.outcome {
+            Some((_, finished_ms)) => now_ms.saturating_sub(*finished_ms) <= ttl,
+            None => true,
+        });
+    }
+
+    fn poll(&self, job_id: &str, now_ms: i64) -> ActionResult {
+        self.purge(now_ms);
+        let Some(job) = self.jobs.get(job_id) else {
+            return ActionResultBuilder::new(ActionStatus::ActionError)
+                .error(
+                    JOB_NOT_FOUND,
+                    format!("no job {job_id} (unknown or expired)"),
+                )
+                .metadata(JOB_ID_METADATA_KEY, job_id)
+                .build();
+        };
+        match &job.outcome {
+            Some((result, _)) => result.clone(),
+            None => pending_result(&job.call_id, job_id, job.progress.get()),
+        }
+    }
+}
+
+fn pending_result(call_id: &str, job_id: &str, progress: f32) -> ActionResult {
+    ActionResultBuilder::new(ActionStatus::ActionPending)
+        .id(call_id)
+        .metadata(JOB_ID_METADATA_KEY, job_id)
+        .metadata(JOB_PROGRESS_METADATA_KEY, progress.to_string())
+        .build()
+}
+
+/// Final result of a job run; never `ActionPending`
+fn final_result(
+    call_id: &str,
+    job_id: &str,
+    outcome: std::result::Result<Result<ActionResult>, Box<dyn std::any::Any + Send>>,
+) -> ActionResult {
+    let mut result = match outcome {
+        Ok(Ok(res)) if res.status_enum().is_final() => res,
+        Ok(Ok(_)) => ActionResultBuilder::new(ActionStatus::ActionError)
+            .error_with_debug(
+                "CAPABILITY_ERROR",
+                "The action failed",
+                "job finished with a pending result",
+            )
+            .build(),
+        Ok(Err(err)) => ActionResultBuilder::new(ActionStatus::ActionError)
+            .error_with_debug("CAPABILITY_ERROR", "The action failed", err.to_string())
+            .build(),
+        Err(panic) => ActionResultBuilder::new(ActionStatus::ActionError)
+            .error_with_debug(
+                "PROVIDER_PANIC",
+                "Capability provider panicked",
+                panic_message(panic.as_ref()),
+            )
+            .build(),
+    };
+    result.id = call_id.to_string();
+    result
+        .metadata
+        .insert(JOB_ID_METADATA_KEY.to_string(), job_id.to_string());
+    result
+}
+
+/// `CapabilityProvider` face of a `JobProvider`: starts the job and answers pending
+struct JobAdapter {
+    inner: Arc<dyn JobProvider>,
+    jobs: Arc<Jobs>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+}
+
+#[async_trait]
+impl CapabilityProvider for JobAdapter {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        self.inner.descriptor()
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        let job_id = self.ids.new_id();
+        let call_id = call.id.clone();
+        let progress = self.jobs.start(&job_id, &call_id, self.clock.now_ms());
+        debug!(target: "action_broker", capability = %call.capability, job_id = %job_id, "Job submitted");
+        let pending = pending_result(&call_id, &job_id, 0.0);
+
+        let (inner, jobs, clock) = (
+            Arc::clone(&self.inner),
+            Arc::clone(&self.jobs),
+            Arc::clone(&self.clock),
+        );
+        tokio::spawn(async move {
+            let capability = call.capability.clone();
+            let outcome = AssertUnwindSafe(inner.run(call, progress))
+                .catch_unwind()
+                .await;
+            let result = final_result(&call_id, &job_id, outcome);
+            if result.status_enum() != ActionStatus::ActionOk {
+                warn!(target: "action_broker", capability = %capability, job_id = %job_id, status = %result.status_enum(), "Job failed");
+            }
+            jobs.finish(&job_id, result, clock.now_ms());
+        });
+
+        Ok(pending)
+    }
+}
+
+impl ActionBroker {
+    /// Register a long-running capability: `invoke` returns `ActionPending` with
+    /// `metadata["job_id"]` at once, and `poll` reports progress and then the final result
+    pub fn register_job_provider(&self, provider: Arc<dyn JobProvider>) {
+        self.register_provider(Arc::new(JobAdapter {
+            inner: provider,
+            jobs: Arc::clone(&self.jobs),
+            clock: Arc::clone(&self.clock),
+            ids: self.id_generator(),
+        }));
+    }
+
+    /// State of a submitted job: `ActionPending` with `metadata["progress"]` while it runs,
+    /// then its final result (id = the submitting call's id) until the TTL passes.
+    /// Unknown and expired ids give an `ActionError` with code `JOB_NOT_FOUND`.
+    pub fn poll(&self, job_id: &str) -> ActionResult {
+        let mut result = self.jobs.poll(job_id, self.clock.now_ms());
+        self.redact(&mut result);
+        result
+    }
+
+    /// How long finished jobs stay pollable
+    pub fn set_job_ttl(&self, ttl: std::time::Duration) {
+        self.jobs
+            .set_ttl_ms(ttl.as_millis().min(i64::MAX as u128) as i64);
+    }
+}
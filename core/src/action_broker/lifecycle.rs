@@ -0,0 +1,81 @@
+//! Provider lifecycle events published to an optional EventBus.
+
+use crate::proto::{CapabilityDescriptor, Event};
+use crate::{Clock, EventBuilder, EventBus};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Topic lifecycle events are published on
+pub const PROVIDER_LIFECYCLE_TOPIC: &str = "action_broker.providers";
+/// Event type published after a provider is registered (or replaced)
+pub const PROVIDER_REGISTERED: &str = "provider.registered";
+/// Event type published after a provider is removed
+pub const PROVIDER_DEREGISTERED: &str = "provider.deregistered";
+
+/// Build a lifecycle event with id `id`; the payload is the descriptor as JSON
+pub(crate) fn lifecycle_event(
+    id: String,
+    kind: &str,
+    desc: &CapabilityDescriptor,
+    clock: &dyn Clock,
+) -> Event {
+    let payload = serde_json::json!({
+        "name": desc.name,
+        "version": desc.version,
+        "provider": desc.provider().as_str_name(),
+        "metadata": desc.metadata,
+        "depends_on": desc.depends_on,
+    });
+    EventBuilder::new(kind)
+        .id(id)
+        .clock(clock)
+        .source("action_broker")
+        .metadata("capability", desc.name.clone())
+        .metadata("version", desc.version.clone())
+        .payload(serde_json::to_vec(&payload).unwrap_or_default())
+        .tag("lifecycle")
+        .build()
+}
+
+/// Forwards lifecycle events to the bus in registration order
+pub(crate) struct LifecyclePublisher {
+    bus: Arc<EventBus>,
+    // started lazily on first publish (registration can happen before a runtime exists)
+    tx: OnceLock<mpsc::UnboundedSender<Event>>,
+}
+
+impl LifecyclePublisher {
+    pub(crate) fn new(bus: Arc<EventBus>) -> Self {
+        Self {
+            bus,
+            tx: OnceLock::new(),
+        }
+    }
+
+    /// Best-effort publish from sync registry methods; dropped (with a warning) outside a runtime
+    pub(crate) fn publish(&self, event: Event) {
+        if let Some(tx) = self.tx.get() {
+            let _ = tx.send(event);
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            warn!(target: "action_broker", event_type = %event.r#type, "No Tokio runtime; lifecycle event dropped");
+            return;
+        };
+        let tx = self.tx.get_or_init(|| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+            let bus = Arc::clone(&self.bus);
+            // Single forwarder keeps events ordered; exits when the broker is dropped
+            handle.spawn(async move {
+                while let Some(event) = rx.recv().await {
+                    if let Err(e) = bus.publish(PROVIDER_LIFECYCLE_TOPIC, event).await {
+                        warn!(target: "action_broker", error = %e, "Failed to publish provider lifecycle event");
+                    }
+                }
+            });
+            tx
+        });
+        let _ = tx.send(event);
+    }
+}
@@ -0,0 +1,50 @@
+//! Per-capability concurrency limits.
+
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Semaphore per limited capability name; unlisted capabilities are unlimited
+#[derive(Default)]
+pub(crate) struct ConcurrencyLimits {
+    limits: DashMap<String, (usize, Arc<Semaphore>)>,
+}
+
+impl ConcurrencyLimits {
+    /// Set or clear (`None`/`0`) the limit; in-flight calls keep their permits from the old limit
+    pub(crate) fn set(&self, capability: &str, limit: Option<usize>) {
+        match limit.filter(|n| *n > 0) {
+            Some(n) => {
+                self.limits
+                    .insert(capability.to_string(), (n, Arc::new(Semaphore::new(n))));
+            }
+            None => {
+                self.limits.remove(capability);
+            }
+        }
+    }
+
+    pub(crate) fn limits(&self) -> BTreeMap<String, usize> {
+        self.limits
+            .iter()
+            .map(|e| (e.key().clone(), e.value().0))
+            .collect()
+    }
+
+    pub(crate) fn replace_all(&self, limits: &BTreeMap<String, usize>) {
+        self.limits.clear();
+        for (cap, n) in limits {
+            self.set(cap, Some(*n));
+        }
+    }
+
+    /// Wait for a permit; `None` when the capability is unlimited
+    pub(crate) async fn acquire(&self, capability: &str) -> Option<OwnedSemaphorePermit> {
+        let sem = self
+            .limits
+            .get(capability)
+            .map(|e| Arc::clone(&e.value().1))?;
+        sem.acquire_owned().await.ok()
+    }
+}
@@ -0,0 +1,962 @@
+mod admission;
+mod auth;
+mod batch;
+mod blocking;
+mod call;
+mod cancel;
+mod category;
+mod config;
+mod context;
+mod cost;
+mod deps;
+mod diagnostics;
+mod dry_run;
+mod errors;
+mod hooks;
+mod jobs;
+mod lifecycle;
+mod limits;
+mod normalize;
+mod output;
+mod qos;
+mod quarantine;
+mod rate;
+mod retry;
+mod routing;
+mod sampling;
+mod service;
+mod timeout;
+mod transform;
+mod typed;
+mod warmup;
+
+pub use auth::{AllowListAuthorizer, Authorizer, PRINCIPAL_HEADER, ROLE_HEADER, TENANT_HEADER};
+pub use batch::{BatchOrdering, BatchOutcome, OnBatchError, SKIPPED};
+pub use call::{
+    ActionCallBuilder, ActionCallExt, ActionResultBuilder, ActionResultExt, SERIALIZATION_FAILED,
+};
+pub use cancel::CANCELLED;
+pub use category::{category_of, CATEGORY_METADATA_KEY, UNCATEGORIZED};
+pub use config::{
+    BrokerConfig, OnMissing, DEFAULT_CACHE_MAX_ENTRIES, DEFAULT_JOB_TTL_MS,
+    DEFAULT_MAX_HEADER_TIMEOUT_MS, DEFAULT_QOS_TIMEOUTS_MS, DEFAULT_TIMEOUT_MS,
+};
+pub use context::{
+    InvocationContext, InvocationScope, OnMissingKey, ANONYMOUS_KEY, SCOPE_KEY_MISSING,
+};
+pub use cost::COST_METADATA_KEY;
+pub use diagnostics::{BrokerDiagnostics, CapabilityDiagnostics, ProviderDiagnostics};
+pub use dry_run::DRY_RUN_METADATA_KEY;
+pub use errors::{ActionErrorExt, DEBUG_DETAIL_KEY};
+pub use jobs::{
+    JobProgress, JobProvider, JOB_ID_METADATA_KEY, JOB_NOT_FOUND, JOB_PROGRESS_METADATA_KEY,
+};
+pub use lifecycle::{PROVIDER_DEREGISTERED, PROVIDER_LIFECYCLE_TOPIC, PROVIDER_REGISTERED};
+pub use normalize::{
+    DefaultErrorMapper, ErrorClass, ErrorMapper, GrpcErrorMapper, HttpErrorMapper, McpErrorMapper,
+    HTTP_STATUS_DETAIL_KEY, NATIVE_CODE_DETAIL_KEY,
+};
+pub use output::OUTPUT_SCHEMA_INVALID;
+pub use qos::{parse_qos, qos_name};
+pub use quarantine::{QuarantinePolicy, QuarantineState, QUARANTINED};
+pub use rate::{RateLimit, RATE_LIMITED};
+pub use retry::{RetryPolicy, ATTEMPTS_METADATA_KEY};
+pub use routing::{
+    Candidate, DefaultSelector, ExactVersion, FirstMatch, HighestVersion, RoundRobin, RouteSelector,
+};
+pub use service::BrokerService;
+pub use timeout::{
+    Timeout, DURATION_METADATA_KEY, SOFT_TIMEOUT_METADATA_KEY, TIMEOUT_MS_HEADER,
+    TIMEOUT_US_HEADER, UNBOUNDED_TIMEOUT_MS,
+};
+pub use transform::{ResultTransformer, TRANSFORM_ERROR};
+pub use typed::TypedCapability;
+pub use warmup::{WarmUpOutcome, WarmUpReport};
+
+use admission::{Admission, AdmissionMode, Admitted};
+use blocking::invoke_on_blocking_pool;
+use cancel::ActiveCorrelations;
+use cost::{correlation_key, CostLedger};
+use diagnostics::InFlight;
+use hooks::InvokeHooks;
+use jobs::Jobs;
+use lifecycle::{lifecycle_event, LifecyclePublisher};
+use limits::ConcurrencyLimits;
+use normalize::ErrorMappers;
+use quarantine::Quarantine;
+use rate::RateLimiter;
+use sampling::PayloadSampler;
+use transform::ResultTransformers;
+
+use crate::clock::{system_clock, Clock};
+use crate::ids::{default_id_generator, IdGenerator};
+use crate::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor};
+use crate::{CancellationToken, Envelope, EventBus, Result};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures_util::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::time::{timeout_at, Duration};
+use tracing::{debug, info, warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// OpenTelemetry imports
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram, UpDownCounter},
+    KeyValue,
+};
+
+/// Trait implemented by concrete capability providers (Native, WASM, gRPC/MCP adapters)
+#[async_trait]
+pub trait CapabilityProvider: Send + Sync {
+    /// Static descriptor for discovery/registration
+    fn descriptor(&self) -> CapabilityDescriptor;
+
+    /// Invoke the capability with an ActionCall and return an ActionResult
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult>;
+
+    /// Preload models / open connections before the first call; no-op by default
+    async fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Invoke with a token that fires at the broker's soft deadline (see
+    /// `ActionBroker::set_soft_timeout_ratio`). Override to stop early and return partial
+    /// output; the default ignores the token and calls `invoke`.
+    async fn invoke_cancellable(
+        &self,
+        call: ActionCall,
+        _cancel: CancellationToken,
+    ) -> Result<ActionResult> {
+        self.invoke(call).await
+    }
+
+    /// Invoke with the broker's per-call context (memory reader, correlation id, deadline,
+    /// soft-deadline token). The broker always dispatches through this method; the default
+    /// calls `invoke_cancellable` with `ctx.cancel`.
+    async fn invoke_with_context(
+        &self,
+        call: ActionCall,
+        ctx: InvocationContext,
+    ) -> Result<ActionResult> {
+        self.invoke_cancellable(call, ctx.cancel).await
+    }
+
+    /// JSON Schema for successful `output`, checked when the broker enables output validation
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Run each invocation on Tokio's blocking pool instead of the async workers. Set it for
+    /// CPU-heavy or synchronous native code; async-native providers should leave it off.
+    fn runs_blocking(&self) -> bool {
+        false
+    }
+}
+
+/// Idempotency cache key. A call id only replays a result to the same capability, tenant
+/// and principal, so callers in other tenants never see each other's results.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    call_id: String,
+    capability: String,
+    tenant_id: Option<String>,
+    principal: Option<String>,
+}
+
+impl CacheKey {
+    fn new(call: &ActionCall, ctx: &InvocationContext) -> Self {
+        Self {
+            call_id: call.id.clone(),
+            capability: call.capability.clone(),
+            tenant_id: ctx.tenant_id.clone(),
+            principal: ctx.principal.clone(),
+        }
+    }
+}
+
+/// Idempotency cache entry stamped with the broker clock and the provider that produced it
+struct CachedResult {
+    result: ActionResult,
+    cached_at_ms: i64,
+    /// Registry key (`name:version`) of the provider
+    provider_key: String,
+    /// `CapabilityDescriptor::checksum` of the provider at the time of the call
+    checksum: u64,
+}
+
+/// Action/Tool Broker: centralized registry and invoker
+pub struct ActionBroker {
+    // key: "name:version" for disambiguation
+    registry: DashMap<String, Arc<dyn CapabilityProvider>>, // capability name:version -> provider
+    // idempotency cache: (call_id, capability, tenant, principal) -> result
+    cache: DashMap<CacheKey, CachedResult>,
+    // time source for cache expiry; injectable for deterministic tests
+    clock: Arc<dyn Clock>,
+    // serializable policy (timeouts, cache sizing); budgets live in `costs`
+    settings: RwLock<BrokerConfig>,
+    // optional authorization hook consulted before dispatch
+    authorizer: RwLock<Option<Arc<dyn Authorizer>>>,
+    // picks a provider among those registered under a capability name
+    route_selector: RwLock<Arc<dyn RouteSelector>>,
+    // accumulated provider-reported cost per budget key (see `budget_scope`)
+    costs: CostLedger,
+    // per-capability in-flight caps; waiting for a permit counts against the call timeout
+    limits: ConcurrencyLimits,
+    // per-QoS lanes shared by all capabilities; acquired after the capability permit
+    qos_limits: ConcurrencyLimits,
+    // consecutive-timeout counters and cooldowns (policy lives in `settings`)
+    quarantine: Quarantine,
+    // fixed-window call counts per tenant / principal / correlation (limits live in `settings`)
+    rate: RateLimiter,
+    // dispatched-but-unfinished calls per capability name, for diagnostics
+    in_flight: InFlight,
+    // cancellation tokens of in-flight calls per correlation id
+    active: ActiveCorrelations,
+    // submitted long-running jobs; shared with the job provider adapters
+    jobs: Arc<Jobs>,
+    // optional bus for provider.registered / provider.deregistered events
+    lifecycle: Option<LifecyclePublisher>,
+    // synchronous on_invoke_start / on_invoke_end callbacks
+    hooks: InvokeHooks,
+    // per-capability rewrites of successful results
+    transformers: ResultTransformers,
+    // error taxonomy mappers by capability and provider kind
+    error_mappers: ErrorMappers,
+    // memory handed to providers through InvocationContext
+    memory_reader: RwLock<Option<Arc<dyn crate::context::MemoryReader>>>,
+    // 1-in-N full payload trace logging
+    sampler: PayloadSampler,
+    // ids for calls arriving without one; shared with agents and orchestrators using this broker
+    ids: Arc<dyn IdGenerator>,
+
+    // OpenTelemetry metrics
+    invocations_counter: Counter<u64>,
+    cache_hits_counter: Counter<u64>,
+    timeouts_counter: Counter<u64>,
+    errors_counter: Counter<u64>,
+    invoke_latency: Histogram<f64>,
+    payload_bytes: Histogram<u64>,
+    output_bytes: Histogram<u64>,
+    registered_capabilities_gauge: UpDownCounter<i64>,
+}
+
+impl ActionBroker {
+    pub fn new() -> Self {
+        // Initialize OpenTelemetry metrics
+        let meter = global::meter("loom.action_broker");
+
+        let invocations_counter = meter
+            .u64_counter("loom.action_broker.invocations_total")
+            .with_description("Total number of capability invocations")
+            .init();
+
+        let cache_hits_counter = meter
+            .u64_counter("loom.action_broker.cache_hits_total")
+            .with_description("Total number of cache hits")
+            .init();
+
+        let timeouts_counter = meter
+            .u64_counter("loom.action_broker.timeouts_total")
+            .with_description("Total number of timeouts")
+            .init();
+
+        let errors_counter = meter
+            .u64_counter("loom.action_broker.errors_total")
+            .with_description("Total number of errors")
+            .init();
+
+        let invoke_latency = meter
+            .f64_histogram("loom.action_broker.invoke_latency_ms")
+            .with_description("Capability invocation latency in milliseconds")
+            .init();
+
+        let payload_bytes = meter
+            .u64_histogram("loom.action_broker.payload_bytes")
+            .with_description("Dispatched call payload size in bytes")
+            .init();
+
+        let output_bytes = meter
+            .u64_histogram("loom.action_broker.output_bytes")
+            .with_description("Result output size in bytes")
+            .init();
+
+        let registered_capabilities_gauge = meter
+            .i64_up_down_counter("loom.action_broker.registered_capabilities")
+            .with_description("Number of registered capabilities")
+            .init();
+
+        Self {
+            registry: DashMap::new(),
+            cache: DashMap::new(),
+            clock: system_clock(),
+            settings: RwLock::new(BrokerConfig::default()),
+            authorizer: RwLock::new(None),
+            route_selector: RwLock::new(Arc::new(DefaultSelector)),
+            memory_reader: RwLock::new(None),
+            costs: CostLedger::default(),
+            limits: ConcurrencyLimits::default(),
+            qos_limits: ConcurrencyLimits::default(),
+            quarantine: Quarantine::default(),
+            rate: RateLimiter::default(),
+            in_flight: InFlight::default(),
+            active: ActiveCorrelations::default(),
+            jobs: Arc::new(Jobs::new(DEFAULT_JOB_TTL_MS)),
+            lifecycle: None,
+            hooks: InvokeHooks::default(),
+            transformers: ResultTransformers::default(),
+            error_mappers: ErrorMappers::default(),
+            sampler: PayloadSampler::default(),
+            ids: default_id_generator(),
+            invocations_counter,
+            cache_hits_counter,
+            timeouts_counter,
+            errors_counter,
+            invoke_latency,
+            payload_bytes,
+            output_bytes,
+            registered_capabilities_gauge,
+        }
+    }
+
+    /// Use a custom clock (e.g. `MockClock` in tests)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Use a custom id scheme for auto-assigned ids (default UUID v7)
+    pub fn with_id_generator(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// The broker's id generator; agents and orchestrators built on this broker use it too
+    pub fn id_generator(&self) -> Arc<dyn IdGenerator> {
+        Arc::clone(&self.ids)
+    }
+
+    /// Expire idempotency cache entries older than `ttl`
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.settings.get_mut().unwrap().cache_ttl_ms =
+            Some(ttl.as_millis().min(i64::MAX as u128) as i64);
+        self
+    }
+
+    /// Snapshot the broker policy (providers are not included)
+    pub fn export_config(&self) -> BrokerConfig {
+        let mut cfg = self.settings.read().unwrap().clone();
+        cfg.default_budget = self.costs.default_budget();
+        cfg.budgets = self.costs.budgets();
+        cfg.concurrency_limits = self.limits.limits();
+        cfg.qos_concurrency_limits = self.qos_limits.limits();
+        cfg.payload_sample_every = self.sampler.every();
+        cfg.job_ttl_ms = self.jobs.ttl_ms();
+        cfg
+    }
+
+    /// Replace the broker policy with `config`; registered providers and accumulated cost are kept
+    pub fn apply_config(&self, mut config: BrokerConfig) {
+        config.qos_timeouts_ms = Self::normalize_qos_timeouts(&config.qos_timeouts_ms);
+        config.soft_timeout_ratio = Self::normalize_soft_ratio(config.soft_timeout_ratio);
+        self.costs.set_default_budget(config.default_budget);
+        self.costs.replace_budgets(&config.budgets);
+        self.limits.replace_all(&config.concurrency_limits);
+        self.replace_qos_limits(&config.qos_concurrency_limits);
+        self.sampler.set_every(config.payload_sample_every);
+        self.jobs.set_ttl_ms(config.job_ttl_ms);
+        *self.settings.write().unwrap() = config;
+    }
+
+    /// Cap concurrent invocations of `capability` (`None`/`0` removes the cap).
+    /// Calls over the cap wait for a permit; the wait counts against their `timeout_ms`.
+    pub fn set_concurrency_limit(&self, capability: &str, limit: Option<usize>) {
+        self.limits.set(capability, limit);
+    }
+
+    /// Choose between `Err` (default) and a `CAPABILITY_NOT_FOUND` result for unknown capabilities
+    pub fn set_on_missing(&self, mode: OnMissing) {
+        self.settings.write().unwrap().on_missing = mode;
+    }
+
+    /// Publish provider lifecycle events to `bus` on `PROVIDER_LIFECYCLE_TOPIC`
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.lifecycle = Some(LifecyclePublisher::new(bus));
+        self
+    }
+
+    /// Install an authorizer; calls it denies return an `ActionError` with code `FORBIDDEN`
+    pub fn set_authorizer(&self, authorizer: Arc<dyn Authorizer>) {
+        *self.authorizer.write().unwrap() = Some(authorizer);
+    }
+
+    /// Remove the authorizer so every call is dispatched again
+    pub fn clear_authorizer(&self) {
+        *self.authorizer.write().unwrap() = None;
+    }
+
+    /// Total cost reported by providers (`metadata["cost"]`) under budget key `key`: a
+    /// correlation id, tenant or principal depending on `budget_scope` (`ANONYMOUS_KEY` for
+    /// calls without a tenant or principal)
+    pub fn cost_for(&self, key: &str) -> f64 {
+        self.costs.spent(key)
+    }
+
+    /// Cap spend for budget key `key` (see `cost_for`); once reached, further calls return
+    /// `BUDGET_EXCEEDED`
+    pub fn set_budget(&self, key: &str, budget: f64) {
+        self.costs.set_budget(key, budget);
+    }
+
+    /// Budget applied to keys without an explicit one (None = unlimited)
+    pub fn set_default_budget(&self, budget: Option<f64>) {
+        self.costs.set_default_budget(budget);
+    }
+
+    /// Forget the accumulated cost under budget key `key` (its budget is kept)
+    pub fn reset_cost(&self, key: &str) {
+        self.costs.reset(key);
+    }
+
+    /// Account cost and budgets per correlation id (default), tenant or principal. Spend
+    /// already recorded stays under the keys of the previous scope.
+    pub fn set_budget_scope(&self, scope: InvocationScope) {
+        self.settings.write().unwrap().budget_scope = scope;
+    }
+
+    /// Register a provider; later registrations with the same name replace the previous one
+    #[tracing::instrument(skip(self, provider), fields(capability, version, provider_type))]
+    pub fn register_provider(&self, provider: Arc<dyn CapabilityProvider>) {
+        let desc = provider.descriptor();
+        let key = format!("{}:{}", desc.name, desc.version);
+
+        // Record span attributes
+        Span::current().record("capability", &desc.name);
+        Span::current().record("version", &desc.version);
+        Span::current().record("provider_type", desc.provider);
+
+        info!(target: "action_broker", capability = %desc.name, version = %desc.version, "Registering capability provider");
+        self.registry.insert(key, provider);
+        // A fresh provider starts with a clean timeout record
+        self.quarantine.clear(&desc.name);
+
+        // Update registered capabilities gauge
+        self.registered_capabilities_gauge.add(
+            1,
+            &[KeyValue::new(
+                "provider_type",
+                format!("{:?}", desc.provider),
+            )],
+        );
+
+        self.emit_lifecycle(PROVIDER_REGISTERED, &desc);
+    }
+
+    /// Remove the provider registered as `name:version`; returns whether one was removed
+    pub fn deregister_provider(&self, name: &str, version: &str) -> bool {
+        let Some((_, provider)) = self.registry.remove(&format!("{}:{}", name, version)) else {
+            return false;
+        };
+        let desc = provider.descriptor();
+        info!(target: "action_broker", capability = %desc.name, version = %desc.version, "Deregistered capability provider");
+        self.registered_capabilities_gauge.add(
+            -1,
+            &[KeyValue::new(
+                "provider_type",
+                format!("{:?}", desc.provider),
+            )],
+        );
+
+        self.emit_lifecycle(PROVIDER_DEREGISTERED, &desc);
+        true
+    }
+
+    /// List all registered capabilities, sorted by name then version (numeric-aware, so 1.9 < 1.10)
+    pub fn list_capabilities(&self) -> Vec<CapabilityDescriptor> {
+        self.list_capabilities_filtered(|_| true)
+    }
+
+    /// Like `list_capabilities`, keeping only descriptors matching `pred` (same ordering)
+    pub fn list_capabilities_filtered<F>(&self, pred: F) -> Vec<CapabilityDescriptor>
+    where
+        F: Fn(&CapabilityDescriptor) -> bool,
+    {
+        let mut out: Vec<CapabilityDescriptor> = self
+            .registry
+            .iter()
+            .map(|e| e.value().descriptor())
+            .filter(|d| pred(d))
+            .collect();
+        out.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| compare_versions(&a.version, &b.version))
+        });
+        out
+    }
+
+    /// Number of registered providers (one per name:version)
+    pub fn len(&self) -> usize {
+        self.registry.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registry.is_empty()
+    }
+
+    /// Whether any version of `name` is registered (what `invoke` resolves without a version)
+    pub fn contains(&self, name: &str) -> bool {
+        let prefix = format!("{}:", name);
+        self.registry.iter().any(|e| e.key().starts_with(&prefix))
+    }
+
+    /// Whether exactly `name` at `version` is registered
+    pub fn contains_version(&self, name: &str, version: &str) -> bool {
+        self.registry.contains_key(&format!("{}:{}", name, version))
+    }
+
+    /// Registered providers in no particular order, without building descriptors.
+    /// The registry is snapshotted up front, so no lock is held while iterating (or awaiting).
+    pub fn providers(&self) -> std::vec::IntoIter<Arc<dyn CapabilityProvider>> {
+        self.registry
+            .iter()
+            .map(|e| Arc::clone(e.value()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Invoke a capability by name with timeout handling
+    pub async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.invoke_in_context(call, InvocationContext::default())
+            .await
+    }
+
+    /// `invoke` on behalf of a caller that supplies part of the provider's context. Its
+    /// `reader`, `tenant_id` and `principal` win over the broker's memory reader and the call
+    /// headers; cancelling its `cancel` token cancels the call (`CANCELLED`). The correlation
+    /// id and deadline always come from the call itself.
+    #[tracing::instrument(name = "invoke", skip(self, call, supplied), fields(capability = %call.capability, version = %call.version, call_id = %call.id, timeout_ms = call.timeout_ms))]
+    pub async fn invoke_in_context(
+        &self,
+        call: ActionCall,
+        supplied: InvocationContext,
+    ) -> Result<ActionResult> {
+        // Calls made within a TurnSpan become children of that turn
+        if let Some(parent) = crate::telemetry::extract_trace_context(&call.headers) {
+            Span::current().set_parent(parent);
+        }
+        let sampled = self.sampler.sample();
+        self.trace_call(&call, sampled);
+        let res = if self.hooks.is_empty() {
+            self.dispatch_with_retry(call, &supplied).await
+        } else {
+            let started = Instant::now();
+            self.hooks.run_start(&call);
+            let observed = call.clone();
+            let res = self.dispatch_with_retry(call, &supplied).await;
+            if let Ok(ref result) = res {
+                self.hooks.run_end(&observed, result, started.elapsed());
+            }
+            res
+        };
+        if let Ok(ref result) = res {
+            self.trace_result(result, sampled);
+        }
+        res.map(|mut result| {
+            self.redact(&mut result);
+            result
+        })
+    }
+
+    async fn dispatch(
+        &self,
+        mut call: ActionCall,
+        supplied: &InvocationContext,
+    ) -> Result<ActionResult> {
+        let start_time = Instant::now();
+        // An empty id would share one idempotency cache slot with every other id-less call
+        if call.id.is_empty() {
+            call.id = self.ids.new_id();
+        }
+        let cap_name = call.capability.clone();
+        let version = call.version.clone();
+        let call_id = call.id.clone();
+        let payload_len = call.payload.len() as u64;
+        // Resolve before the envelope is applied, which back-fills correlation_id
+        let cost_key = correlation_key(&call);
+
+        // Ensure envelope metadata present in headers
+        let env = Envelope::from_metadata(&call.headers, &call_id);
+        env.apply_to_action_call(&mut call);
+        let mut ctx = self.invocation_context(
+            &call,
+            cost_key.as_deref().unwrap_or(&call.correlation_id),
+            supplied,
+        );
+
+        // Authorization runs before the cache so cached results never leak to denied callers
+        if let Some(denied) = self.authorize_call(&call, &ctx, AdmissionMode::Dispatch) {
+            return Ok(denied);
+        }
+
+        // Idempotency shortcut
+        let cache_key = CacheKey::new(&call, &ctx);
+        if let Some(hit) = self.cached_result(&cache_key) {
+            debug!(target: "action_broker", call_id = %call_id, "Idempotent cache hit");
+
+            // Record cache hit metric
+            self.cache_hits_counter
+                .add(1, &[KeyValue::new("capability", cap_name.clone())]);
+            Span::current().record("cache_hit", true);
+
+            return Ok(hit);
+        }
+
+        let Admitted {
+            provider: provider_arc,
+            budget_key,
+            probe,
+        } = match self.admit_call(&call, &ctx, cost_key.as_deref(), AdmissionMode::Dispatch)? {
+            Admission::Admitted(admitted) => admitted,
+            Admission::Rejected(res) => return Ok(res),
+        };
+
+        // Providers see the QoS they are scheduled under
+        let descriptor = provider_arc.descriptor();
+        let qos = self.resolve_qos(&call, &descriptor);
+        let limit = self.attempt_timeout(self.call_timeout(&call, qos));
+        call.qos = Some(qos as i32);
+        debug!(target: "action_broker", capability = %cap_name, timeout = ?limit, qos = qos_name(qos), "Invoking capability");
+
+        // Deadline is fixed before queueing for a permit, so queue wait counts against the timeout.
+        // Unbounded (or unrepresentably far) deadlines never fire.
+        let arrived = Instant::now();
+        let _in_flight = self.in_flight.enter(&cap_name);
+        let deadline = limit.and_then(|d| arrived.checked_add(d));
+        let soft_deadline = self.soft_deadline(arrived, limit);
+        // cancel_correlation fires the shared token; the provider's own token is its child
+        let correlation = cost_key.as_deref().map(|key| self.active.enter(key));
+        let cancel = correlation
+            .as_ref()
+            .map_or_else(CancellationToken::new, |c| c.token().child_token());
+        let acquired = AtomicBool::new(false);
+        ctx.deadline = deadline;
+        ctx.cancel = cancel.clone();
+        let ctx_correlation = ctx.correlation_id.clone();
+        let invoke = async {
+            let _permit = self.limits.acquire(&cap_name).await;
+            let _lane = self.acquire_qos_lane(qos).await;
+            acquired.store(true, Ordering::Relaxed);
+            if provider_arc.runs_blocking() {
+                return invoke_on_blocking_pool(Arc::clone(&provider_arc), call, ctx).await;
+            }
+            // Catch panics so a misbehaving provider cannot take the broker down with it
+            AssertUnwindSafe(provider_arc.invoke_with_context(call, ctx))
+                .catch_unwind()
+                .await
+        };
+        // At the soft deadline the provider is asked to wrap up; only the hard deadline aborts
+        let fut = async {
+            tokio::pin!(invoke);
+            if let Some(soft) = soft_deadline {
+                tokio::select! {
+                    out = &mut invoke => return out,
+                    _ = tokio::time::sleep_until(soft.into()) => cancel.cancel(),
+                }
+            }
+            invoke.await
+        };
+        let bounded = async {
+            match deadline {
+                Some(deadline) => timeout_at(deadline.into(), fut).await,
+                None => Ok(fut.await),
+            }
+        };
+        // `None` when the correlation or the caller cancelled first
+        let correlation_cancelled = async {
+            match &correlation {
+                Some(correlation) => correlation.token().cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+        let outcome = tokio::select! {
+            biased;
+            _ = correlation_cancelled => None,
+            _ = supplied.cancel.cancelled() => None,
+            out = bounded => Some(out),
+        };
+        let queued = !acquired.load(Ordering::Relaxed);
+        let cancelled = outcome.is_none();
+        // Only a timeout can leave the permit unacquired; a cancelled call says nothing about
+        // the provider, so like a queued timeout it only hands the probe slot back
+        let timed_out = matches!(outcome, Some(Err(_)));
+        self.record_for_quarantine(&cap_name, timed_out, queued || cancelled, probe);
+        let res = match outcome {
+            None => {
+                let phase = if queued { "queued" } else { "executing" };
+                let id = correlation
+                    .as_ref()
+                    .map_or(ctx_correlation.as_str(), |c| c.correlation_id());
+                self.cancelled_result(call_id.clone(), &cap_name, id, phase)
+            }
+            Some(Ok(Ok(Ok(res)))) => {
+                // Success case
+                let res = self.normalize_error(descriptor.provider, &cap_name, res);
+                let res = self.check_output(provider_arc.as_ref(), &cap_name, res);
+                let mut res = self.transform_result(&cap_name, res);
+                if cancel.is_cancelled() {
+                    res.metadata
+                        .insert(SOFT_TIMEOUT_METADATA_KEY.to_string(), "true".to_string());
+                }
+                let status_str = match res.status_enum() {
+                    ActionStatus::ActionOk => "success",
+                    ActionStatus::ActionPending => "pending",
+                    _ => "error",
+                };
+
+                self.invocations_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("status", status_str),
+                    ],
+                );
+                Span::current().record("status", status_str);
+
+                res
+            }
+            Some(Ok(Ok(Err(err)))) => {
+                warn!(target: "action_broker", capability = %cap_name, error = %err, "Capability error");
+
+                // Record error metric
+                self.errors_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("error_code", "CAPABILITY_ERROR"),
+                    ],
+                );
+                self.invocations_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("status", "error"),
+                    ],
+                );
+
+                Span::current().record("status", "error");
+                Span::current().record("error_code", "CAPABILITY_ERROR");
+
+                let res = ActionResultBuilder::new(ActionStatus::ActionError)
+                    .id(call_id.clone())
+                    .error_with_debug("CAPABILITY_ERROR", "The action failed", err.to_string())
+                    .build();
+                self.normalize_error(descriptor.provider, &cap_name, res)
+            }
+            Some(Ok(Err(panic))) => {
+                let panic_msg = panic_message(panic.as_ref());
+                warn!(target: "action_broker", capability = %cap_name, panic = %panic_msg, "Capability provider panicked");
+
+                self.errors_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("error_code", "PROVIDER_PANIC"),
+                    ],
+                );
+                self.invocations_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("status", "error"),
+                    ],
+                );
+
+                Span::current().record("status", "error");
+                Span::current().record("error_code", "PROVIDER_PANIC");
+
+                let res = ActionResultBuilder::new(ActionStatus::ActionError)
+                    .id(call_id.clone())
+                    .error_with_debug("PROVIDER_PANIC", "Capability provider panicked", panic_msg)
+                    .build();
+                self.normalize_error(descriptor.provider, &cap_name, res)
+            }
+            Some(Err(_)) => {
+                let phase = if queued { "queued" } else { "executing" };
+                let elapsed_ms = arrived.elapsed().as_millis();
+                warn!(target: "action_broker", capability = %cap_name, phase, "Capability timeout");
+
+                // Record timeout metrics
+                self.timeouts_counter
+                    .add(1, &[KeyValue::new("capability", cap_name.clone())]);
+                self.errors_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("error_code", "TIMEOUT"),
+                    ],
+                );
+                self.invocations_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", cap_name.clone()),
+                        KeyValue::new("status", "timeout"),
+                    ],
+                );
+
+                Span::current().record("status", "timeout");
+
+                ActionResultBuilder::new(ActionStatus::ActionTimeout)
+                    .id(call_id.clone())
+                    .error_with_debug(
+                        "TIMEOUT",
+                        "Action timed out",
+                        format!(
+                            "{cap_name} passed its deadline while {phase} after {elapsed_ms} ms"
+                        ),
+                    )
+                    .detail("phase", phase)
+                    .detail("elapsed_ms", elapsed_ms.to_string())
+                    .build()
+            }
+        };
+
+        let mut res = res;
+        if self.settings.read().unwrap().attach_duration {
+            res.metadata.insert(
+                DURATION_METADATA_KEY.to_string(),
+                arrived.elapsed().as_millis().to_string(),
+            );
+        }
+
+        if let Some(ref key) = budget_key {
+            self.costs.record(key, &res);
+        }
+
+        // Record payload/output sizes, to spot providers with bloated payloads
+        let capability = [KeyValue::new("capability", cap_name.clone())];
+        self.payload_bytes.record(payload_len, &capability);
+        self.output_bytes
+            .record(res.output.len() as u64, &capability);
+
+        // Record invocation latency
+        let elapsed_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+        self.invoke_latency.record(
+            elapsed_ms,
+            &[
+                KeyValue::new("capability", cap_name),
+                KeyValue::new("version", version),
+            ],
+        );
+        Span::current().record("latency_ms", elapsed_ms);
+
+        // Cache result for idempotency; cancelled calls run again when retried
+        if !cancelled {
+            self.cache.insert(
+                cache_key,
+                CachedResult {
+                    result: res.clone(),
+                    cached_at_ms: self.clock.now_ms(),
+                    provider_key: format!("{}:{}", descriptor.name, descriptor.version),
+                    checksum: descriptor.checksum(),
+                },
+            );
+            let max_entries = self.settings.read().unwrap().cache_max_entries;
+            self.trim_cache(max_entries);
+        }
+        Ok(res)
+    }
+}
+
+/// Compare dot-separated versions component-wise, numerically where both parts are numbers
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut left = a.split('.');
+    let mut right = b.split('.');
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if ord != std::cmp::Ordering::Equal {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+/// Best-effort extraction of a panic payload's message
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+impl Default for ActionBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionBroker {
+    /// Look up a cached result, dropping it if it outlived the cache TTL or its provider
+    /// was deregistered or replaced by one with a different descriptor checksum
+    fn cached_result(&self, key: &CacheKey) -> Option<ActionResult> {
+        let entry = self.cache.get(key)?;
+        let ttl_ms = self.settings.read().unwrap().cache_ttl_ms;
+        let expired = ttl_ms.is_some_and(|ttl| self.clock.now_ms() - entry.cached_at_ms >= ttl);
+        let current = self
+            .registry
+            .get(&entry.provider_key)
+            .map(|provider| provider.descriptor().checksum());
+        if expired || current != Some(entry.checksum) {
+            drop(entry);
+            self.cache.remove(key);
+            return None;
+        }
+        Some(entry.result.clone())
+    }
+
+    /// Drop the cached result of `call` made with the caller's `supplied` context
+    fn forget_cached(&self, call: &ActionCall, supplied: &InvocationContext) {
+        let ctx = self.invocation_context(call, &call.correlation_id, supplied);
+        self.cache.remove(&CacheKey::new(call, &ctx));
+    }
+
+    fn emit_lifecycle(&self, kind: &str, desc: &CapabilityDescriptor) {
+        if let Some(lifecycle) = &self.lifecycle {
+            let event = lifecycle_event(self.ids.new_id(), kind, desc, self.clock.as_ref());
+            lifecycle.publish(event);
+        }
+    }
+
+    fn trim_cache(&self, max: usize) {
+        if self.cache.len() > max {
+            // remove a few arbitrary entries to keep size under control; keys are collected
+            // first because removing while iterating would deadlock on the shard lock
+            let excess = self.cache.len().saturating_sub(max).min(16);
+            let keys: Vec<CacheKey> = self
+                .cache
+                .iter()
+                .take(excess)
+                .map(|entry| entry.key().clone())
+                .collect();
+            for key in keys {
+                self.cache.remove(&key);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Normalizing provider failures into one error taxonomy.
+//!
+//! Adapters fail in their own vocabulary: MCP servers report `TRANSPORT_ERROR`, gRPC backends
+//! `DEADLINE_EXCEEDED`, HTTP-backed providers a status code. With
+//! `BrokerConfig::normalize_errors` on, the broker runs each failed provider result through an
+//! `ErrorMapper` (chosen by capability, else by `ProviderKind`) and rewrites its code to
+//! `UNAVAILABLE`, `TIMEOUT`, `INVALID_ARGUMENT` or `INTERNAL`. The native code is kept under
+//! `details["native_code"]`. Errors the broker raises itself (`QUARANTINED`, `CANCELLED`, ...)
+//! are left alone.
+
+use super::ActionBroker;
+use crate::proto::{ActionError, ActionResult, ActionStatus, ProviderKind};
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// `ActionError.details` key holding the provider's own code after normalization
+pub const NATIVE_CODE_DETAIL_KEY: &str = "native_code";
+/// `ActionError.details` key an HTTP-backed provider may set to its response status
+pub const HTTP_STATUS_DETAIL_KEY: &str = "http_status";
+
+/// Provider-independent error classes agents can handle uniformly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The backend could not be reached or is overloaded; retrying later may help
+    Unavailable,
+    /// The backend did not answer in time
+    Timeout,
+    /// The call itself is wrong (bad arguments, unknown tool); retrying will not help
+    InvalidArgument,
+    /// Anything else that went wrong inside the provider
+    Internal,
+}
+
+impl ErrorClass {
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorClass::Unavailable => "UNAVAILABLE",
+            ErrorClass::Timeout => "TIMEOUT",
+            ErrorClass::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorClass::Internal => "INTERNAL",
+        }
+    }
+
+    /// Class whose code is `code`, if it is one of the taxonomy codes
+    pub fn from_code(code: &str) -> Option<Self> {
+        [
+            ErrorClass::Unavailable,
+            ErrorClass::Timeout,
+            ErrorClass::InvalidArgument,
+            ErrorClass::Internal,
+        ]
+        .into_iter()
+        .find(|class| class.code() == code)
+    }
+}
+
+/// Classifies one adapter's native failures
+pub trait ErrorMapper: Send + Sync {
+    /// Class of a failed result with `status` (never `ActionOk`/`ActionPending`) and `error`
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass;
+}
+
+/// Any `Fn(ActionStatus, &ActionError) -> ErrorClass` closure is a mapper
+impl<F> ErrorMapper for F
+where
+    F: Fn(ActionStatus, &ActionError) -> ErrorClass + Send + Sync,
+{
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass {
+        self(status, error)
+    }
+}
+
+/// Taxonomy codes and timeout/retryable statuses need no mapper
+fn classify_common(status: ActionStatus, error: &ActionError) -> Option<ErrorClass> {
+    ErrorClass::from_code(&error.code).or(match status {
+        ActionStatus::ActionTimeout => Some(ErrorClass::Timeout),
+        ActionStatus::ActionRetryable => Some(ErrorClass::Unavailable),
+        _ => None,
+    })
+}
+
+/// Native and WASM providers: keyword match on the code, then the HTTP status if one is
+/// attached; everything else is `INTERNAL`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultErrorMapper;
+
+impl ErrorMapper for DefaultErrorMapper {
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass {
+        if let Some(class) = classify_common(status, error) {
+            return class;
+        }
+        let code = error.code.to_ascii_uppercase();
+        if code.contains("TIMEOUT") || code.contains("DEADLINE") {
+            ErrorClass::Timeout
+        } else if code.contains("INVALID") || code.contains("BAD_REQUEST") {
+            ErrorClass::InvalidArgument
+        } else if code.contains("UNAVAILABLE") || code.contains("RATE_LIMIT") {
+            ErrorClass::Unavailable
+        } else {
+            HttpErrorMapper.classify(status, error)
+        }
+    }
+}
+
+/// HTTP-backed providers that put their response status in `details["http_status"]`:
+/// 408/504 are `TIMEOUT`, 429/502/503 `UNAVAILABLE`, other 4xx `INVALID_ARGUMENT`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpErrorMapper;
+
+impl ErrorMapper for HttpErrorMapper {
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass {
+        if let Some(class) = classify_common(status, error) {
+            return class;
+        }
+        let http_status = error
+            .details
+            .get(HTTP_STATUS_DETAIL_KEY)
+            .and_then(|s| s.trim().parse::<u16>().ok());
+        match http_status {
+            Some(408 | 504) => ErrorClass::Timeout,
+            Some(429 | 502 | 503) => ErrorClass::Unavailable,
+            Some(400..=499) => ErrorClass::InvalidArgument,
+            _ => ErrorClass::Internal,
+        }
+    }
+}
+
+/// gRPC providers reporting a status code name (`DEADLINE_EXCEEDED`) or number (`4`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcErrorMapper;
+
+impl ErrorMapper for GrpcErrorMapper {
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass {
+        if let Some(class) = classify_common(status, error) {
+            return class;
+        }
+        match error.code.as_str() {
+            "DEADLINE_EXCEEDED" | "4" => ErrorClass::Timeout,
+            "RESOURCE_EXHAUSTED" | "ABORTED" | "8" | "10" | "14" => ErrorClass::Unavailable,
+            "INVALID_ARGUMENT"
+            | "NOT_FOUND"
+            | "ALREADY_EXISTS"
+            | "FAILED_PRECONDITION"
+            | "OUT_OF_RANGE"
+            | "3"
+            | "5"
+            | "6"
+            | "9"
+            | "11" => ErrorClass::InvalidArgument,
+            _ => ErrorClass::Internal,
+        }
+    }
+}
+
+/// MCP tools (codes from `McpError::code` and the MCP adapter)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct McpErrorMapper;
+
+impl ErrorMapper for McpErrorMapper {
+    fn classify(&self, status: ActionStatus, error: &ActionError) -> ErrorClass {
+        if let Some(class) = classify_common(status, error) {
+            return class;
+        }
+        match error.code.as_str() {
+            "TRANSPORT_ERROR" | "IO_ERROR" => ErrorClass::Unavailable,
+            "INVALID_PARAMS" | "TOOL_NOT_FOUND" => ErrorClass::InvalidArgument,
+            _ => ErrorClass::Internal,
+        }
+    }
+}
+
+/// Mappers by capability name, falling back to one per provider kind
+pub(crate) struct ErrorMappers {
+    by_capability: DashMap<String, Arc<dyn ErrorMapper>>,
+    by_kind: DashMap<i32, Arc<dyn ErrorMapper>>,
+}
+
+impl Default for ErrorMappers {
+    fn default() -> Self {
+        let by_kind: DashMap<i32, Arc<dyn ErrorMapper>> = DashMap::new();
+        by_kind.insert(
+            ProviderKind::ProviderNative as i32,
+            Arc::new(DefaultErrorMapper),
+        );
+        by_kind.insert(
+            ProviderKind::ProviderWasm as i32,
+            Arc::new(DefaultErrorMapper),
+        );
+        by_kind.insert(ProviderKind::ProviderGrpc as i32, Arc::new(GrpcErrorMapper));
+        by_kind.insert(ProviderKind::ProviderMcp as i32, Arc::new(McpErrorMapper));
+        Self {
+            by_capability: DashMap::new(),
+            by_kind,
+        }
+    }
+}
+
+impl ActionBroker {
+    /// Rewrite failed provider results into the `ErrorClass` taxonomy (see `normalize_errors`)
+    pub fn set_error_normalization(&self, enabled: bool) {
+        self.settings.write().unwrap().normalize_errors = enabled;
+    }
+
+    /// Replace the mapper for every provider of `kind` without a capability mapper
+    pub fn set_error_mapper(&self, kind: ProviderKind, mapper: Arc<dyn ErrorMapper>) {
+        self.error_mappers.by_kind.insert(kind as i32, mapper);
+    }
+
+    /// Use `mapper` for `capability` regardless of its provider kind, e.g. `HttpErrorMapper`
+    /// for a native provider that wraps an HTTP API
+    pub fn set_capability_error_mapper(&self, capability: &str, mapper: Arc<dyn ErrorMapper>) {
+        self.error_mappers
+            .by_capability
+            .insert(capability.to_string(), mapper);
+    }
+
+    /// Drop `capability`'s mapper; its provider kind's mapper applies again
+    pub fn clear_capability_error_mapper(&self, capability: &str) {
+        self.error_mappers.by_capability.remove(capability);
+    }
+
+    /// Normalize the error of a failed provider result when normalization is on
+    pub(crate) fn normalize_error(
+        &self,
+        kind: i32,
+        capability: &str,
+        mut res: ActionResult,
+    ) -> ActionResult {
+        if !self.settings.read().unwrap().normalize_errors {
+            return res;
+        }
+        let status = res.status_enum();
+        if matches!(status, ActionStatus::ActionOk | ActionStatus::ActionPending) {
+            return res;
+        }
+        let Some(error) = res.error.as_mut() else {
+            return res;
+        };
+        let mapper = match self.error_mappers.by_capability.get(capability) {
+            Some(mapper) => Arc::clone(&mapper),
+            None => match self.error_mappers.by_kind.get(&kind) {
+                Some(mapper) => Arc::clone(&mapper),
+                None => Arc::new(DefaultErrorMapper),
+            },
+        };
+        let code = mapper.classify(status, error).code();
+        if error.code != code {
+            let native = std::mem::replace(&mut error.code, code.to_string());
+            error
+                .details
+                .entry(NATIVE_CODE_DETAIL_KEY.to_string())
+                .or_insert(native);
+        }
+        res
+    }
+}
@@ -0,0 +1,66 @@
+//! Opt-in validation of provider output against `CapabilityProvider::output_schema`.
+
+use super::{ActionBroker, ActionResultBuilder, CapabilityProvider};
+use crate::llm::validate_arguments;
+use crate::proto::{ActionResult, ActionStatus};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use tracing::warn;
+
+/// Error code for successful results whose output does not match the declared schema
+pub const OUTPUT_SCHEMA_INVALID: &str = "OUTPUT_SCHEMA_INVALID";
+
+impl ActionBroker {
+    /// Check `ActionOk` outputs against the provider's output schema (off by default)
+    pub fn set_output_validation(&self, enabled: bool) {
+        self.settings.write().unwrap().validate_output = enabled;
+    }
+
+    /// Replace an `ActionOk` result whose output is not JSON matching the provider's schema
+    /// with an `OUTPUT_SCHEMA_INVALID` error; other results pass through
+    pub(crate) fn check_output(
+        &self,
+        provider: &dyn CapabilityProvider,
+        capability: &str,
+        res: ActionResult,
+    ) -> ActionResult {
+        if res.status_enum() != ActionStatus::ActionOk
+            || !self.settings.read().unwrap().validate_output
+        {
+            return res;
+        }
+        let Some(schema) = provider.output_schema() else {
+            return res;
+        };
+        let violation = match serde_json::from_slice::<Value>(&res.output) {
+            Ok(output) => match validate_arguments(&schema, &output) {
+                Ok(()) => return res,
+                Err(e) => e,
+            },
+            Err(e) => format!("output is not JSON: {e}"),
+        };
+        warn!(target: "action_broker", capability = %capability, error = %violation, "Provider output violates its schema");
+        self.errors_counter.add(
+            1,
+            &[
+                KeyValue::new("capability", capability.to_string()),
+                KeyValue::new("error_code", OUTPUT_SCHEMA_INVALID),
+            ],
+        );
+        let builder = ActionResultBuilder::new(ActionStatus::ActionError)
+            .id(res.id)
+            .error(
+                OUTPUT_SCHEMA_INVALID,
+                format!(
+                    "Output of {} violates its schema: {}",
+                    capability, violation
+                ),
+            )
+            .detail("capability", capability)
+            .detail("violation", violation);
+        res.metadata
+            .into_iter()
+            .fold(builder, |b, (key, value)| b.metadata(key, value))
+            .build()
+    }
+}
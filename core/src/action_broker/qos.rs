@@ -0,0 +1,88 @@
+//! QoS resolution and per-QoS concurrency lanes.
+//!
+//! A call's effective QoS is, in order: `ActionCall.qos`, the provider's
+//! `CapabilityDescriptor.default_qos`, then `BrokerConfig.default_qos`.
+
+use super::ActionBroker;
+use crate::proto::{ActionCall, CapabilityDescriptor, QoSLevel};
+use serde::{Deserialize, Deserializer, Serializer};
+use std::collections::BTreeMap;
+use tokio::sync::OwnedSemaphorePermit;
+use tracing::warn;
+
+/// Lowercase config name of a QoS level (`realtime`, `batched`, `background`)
+pub fn qos_name(qos: QoSLevel) -> &'static str {
+    match qos {
+        QoSLevel::QosRealtime => "realtime",
+        QoSLevel::QosBatched => "batched",
+        QoSLevel::QosBackground => "background",
+    }
+}
+
+/// Parse a config name or the proto name (`QOS_BATCHED`), case-insensitively
+pub fn parse_qos(name: &str) -> Option<QoSLevel> {
+    let name = name.trim().to_ascii_lowercase();
+    let name = name.strip_prefix("qos_").unwrap_or(&name);
+    [
+        QoSLevel::QosRealtime,
+        QoSLevel::QosBatched,
+        QoSLevel::QosBackground,
+    ]
+    .into_iter()
+    .find(|q| qos_name(*q) == name)
+}
+
+/// serde helpers storing a `QoSLevel` by its config name
+pub(crate) mod by_name {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(qos: &QoSLevel, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(qos_name(*qos))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<QoSLevel, D::Error> {
+        let name = String::deserialize(d)?;
+        parse_qos(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown QoS: {name}")))
+    }
+}
+
+impl ActionBroker {
+    /// QoS for calls where neither the call nor the provider sets one
+    pub fn set_default_qos(&self, qos: QoSLevel) {
+        self.settings.write().unwrap().default_qos = qos;
+    }
+
+    /// Cap concurrent invocations running at `qos` across all capabilities (`None`/`0` removes
+    /// the cap). Like capability limits, waiting for a lane permit counts against the timeout.
+    pub fn set_qos_concurrency_limit(&self, qos: QoSLevel, limit: Option<usize>) {
+        self.qos_limits.set(qos_name(qos), limit);
+    }
+
+    /// Effective QoS of `call` when served by a provider with descriptor `desc`
+    pub(crate) fn resolve_qos(&self, call: &ActionCall, desc: &CapabilityDescriptor) -> QoSLevel {
+        call.qos
+            .or(desc.default_qos)
+            .and_then(|q| QoSLevel::try_from(q).ok())
+            .unwrap_or_else(|| self.settings.read().unwrap().default_qos)
+    }
+
+    pub(crate) async fn acquire_qos_lane(&self, qos: QoSLevel) -> Option<OwnedSemaphorePermit> {
+        self.qos_limits.acquire(qos_name(qos)).await
+    }
+
+    /// Lanes are keyed by config name; unknown names are skipped with a warning
+    pub(crate) fn replace_qos_limits(&self, limits: &BTreeMap<String, usize>) {
+        let mut lanes = BTreeMap::new();
+        for (name, n) in limits {
+            match parse_qos(name) {
+                Some(qos) => {
+                    lanes.insert(qos_name(qos).to_string(), *n);
+                }
+                None => {
+                    warn!(target: "action_broker", qos = %name, "Ignoring limit for unknown QoS")
+                }
+            }
+        }
+        self.qos_limits.replace_all(&lanes);
+    }
+}
@@ -0,0 +1,230 @@
+//! Timeout quarantine: capabilities that keep timing out are pulled from routing for a cooldown.
+//!
+//! After `max_consecutive_timeouts` executing-phase timeouts in a row, `invoke` rejects calls
+//! with `QUARANTINED` until the cooldown passes. The first call after that is a probe: if it
+//! times out the cooldown restarts, any other outcome restores the capability. Timeouts spent
+//! queueing for a permit say nothing about the provider and are not counted. A probe that
+//! ends without an outcome (rejected later on, cancelled, or its caller gone) hands the probe
+//! slot back, so the next caller probes instead.
+
+use super::ActionBroker;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Error code for calls rejected while their capability is quarantined
+pub const QUARANTINED: &str = "QUARANTINED";
+
+/// When to quarantine a capability and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinePolicy {
+    /// Consecutive timeouts that move a capability into quarantine
+    pub max_consecutive_timeouts: u32,
+    /// How long a quarantined capability is skipped before it is probed again
+    pub cooldown_ms: i64,
+}
+
+/// Quarantine bookkeeping for one capability name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct QuarantineState {
+    /// Timeouts since the last non-timeout result
+    pub consecutive_timeouts: u32,
+    /// Broker-clock time the cooldown ends; `None` while the capability is routable
+    pub until_ms: Option<i64>,
+    /// A probe call is in flight after the cooldown
+    pub probing: bool,
+}
+
+impl QuarantineState {
+    pub fn is_quarantined(&self) -> bool {
+        self.until_ms.is_some()
+    }
+}
+
+/// Per-capability timeout counters and quarantine windows
+#[derive(Default)]
+pub(crate) struct Quarantine {
+    states: DashMap<String, QuarantineState>,
+}
+
+impl Quarantine {
+    /// `Err(retry_after_ms)` when `capability` must not be dispatched at `now_ms`, else
+    /// whether the caller is the probe. Once the cooldown has passed, exactly one caller is
+    /// let through as the probe.
+    pub(crate) fn admit(&self, capability: &str, now_ms: i64) -> Result<bool, i64> {
+        let Some(mut state) = self.states.get_mut(capability) else {
+            return Ok(false);
+        };
+        match state.until_ms {
+            None => Ok(false),
+            Some(until) if now_ms < until => Err(until - now_ms),
+            Some(_) if state.probing => Err(0),
+            Some(_) => {
+                state.probing = true;
+                Ok(true)
+            }
+        }
+    }
+
+    /// `admit` without taking the probe slot
+    pub(crate) fn peek(&self, capability: &str, now_ms: i64) -> Result<(), i64> {
+        let Some(state) = self.states.get(capability) else {
+            return Ok(());
+        };
+        match state.until_ms {
+            None => Ok(()),
+            Some(until) if now_ms < until => Err(until - now_ms),
+            Some(_) if state.probing => Err(0),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Count a dispatched call's outcome; returns true when it (re)entered quarantine
+    pub(crate) fn record(
+        &self,
+        capability: &str,
+        timed_out: bool,
+        now_ms: i64,
+        policy: &QuarantinePolicy,
+    ) -> bool {
+        if !timed_out {
+            self.states.remove(capability);
+            return false;
+        }
+        let mut state = self.states.entry(capability.to_string()).or_default();
+        state.consecutive_timeouts = state.consecutive_timeouts.saturating_add(1);
+        let trips = state.probing
+            || (!state.is_quarantined()
+                && state.consecutive_timeouts >= policy.max_consecutive_timeouts.max(1));
+        if trips {
+            state.until_ms = Some(now_ms.saturating_add(policy.cooldown_ms.max(0)));
+            state.probing = false;
+        }
+        trips
+    }
+
+    /// Let the next caller probe again (the probe never reached the provider)
+    pub(crate) fn release_probe(&self, capability: &str) {
+        if let Some(mut state) = self.states.get_mut(capability) {
+            state.probing = false;
+        }
+    }
+
+    pub(crate) fn state(&self, capability: &str) -> Option<QuarantineState> {
+        self.states.get(capability).map(|s| *s)
+    }
+
+    pub(crate) fn quarantined(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .states
+            .iter()
+            .filter(|e| e.value().is_quarantined())
+            .map(|e| e.key().clone())
+            .collect();
+        out.sort();
+        out
+    }
+
+    pub(crate) fn clear(&self, capability: &str) -> bool {
+        self.states
+            .remove(capability)
+            .is_some_and(|(_, s)| s.is_quarantined())
+    }
+}
+
+/// The probe slot of a quarantined capability, held by the one call let through after the
+/// cooldown. Dropping it without `record_for_quarantine` hands the slot back.
+pub(crate) struct ProbeGuard<'a> {
+    quarantine: &'a Quarantine,
+    capability: String,
+    recorded: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.quarantine.release_probe(&self.capability);
+        }
+    }
+}
+
+impl ActionBroker {
+    /// Quarantine capabilities after repeated timeouts (`None` turns it off and keeps no state)
+    pub fn set_quarantine_policy(&self, policy: Option<QuarantinePolicy>) {
+        self.settings.write().unwrap().quarantine = policy;
+        if policy.is_none() {
+            self.quarantine.states.clear();
+        }
+    }
+
+    /// Timeout counter and quarantine window for `capability`, if any timeouts are on record
+    pub fn quarantine_state(&self, capability: &str) -> Option<QuarantineState> {
+        self.quarantine.state(capability)
+    }
+
+    /// Capability names currently quarantined (including ones due for a probe), sorted
+    pub fn quarantined(&self) -> Vec<String> {
+        self.quarantine.quarantined()
+    }
+
+    /// Put `capability` back into routing and reset its timeout count; returns whether it
+    /// was quarantined
+    pub fn unquarantine(&self, capability: &str) -> bool {
+        let was = self.quarantine.clear(capability);
+        if was {
+            info!(target: "action_broker", capability, "Capability unquarantined");
+        }
+        was
+    }
+
+    /// `Err(retry_after_ms)` when the quarantine policy keeps `capability` out of routing;
+    /// the probe slot when the call is the probe
+    pub(crate) fn admit_quarantined(
+        &self,
+        capability: &str,
+    ) -> Result<Option<ProbeGuard<'_>>, i64> {
+        if self.settings.read().unwrap().quarantine.is_none() {
+            return Ok(None);
+        }
+        let probing = self.quarantine.admit(capability, self.clock.now_ms())?;
+        Ok(probing.then(|| ProbeGuard {
+            quarantine: &self.quarantine,
+            capability: capability.to_string(),
+            recorded: false,
+        }))
+    }
+
+    /// `admit_quarantined` for a dry run: the rejection, if any, without taking the probe slot
+    pub(crate) fn peek_quarantined(&self, capability: &str) -> Result<(), i64> {
+        if self.settings.read().unwrap().quarantine.is_none() {
+            return Ok(());
+        }
+        self.quarantine.peek(capability, self.clock.now_ms())
+    }
+
+    /// Feed a dispatched call's outcome into the quarantine counters; `skip` outcomes (queued
+    /// timeouts, cancellations) only hand the probe slot back
+    pub(crate) fn record_for_quarantine(
+        &self,
+        capability: &str,
+        timed_out: bool,
+        skip: bool,
+        probe: Option<ProbeGuard<'_>>,
+    ) {
+        let Some(policy) = self.settings.read().unwrap().quarantine else {
+            return;
+        };
+        if skip {
+            return;
+        }
+        if self
+            .quarantine
+            .record(capability, timed_out, self.clock.now_ms(), &policy)
+        {
+            warn!(target: "action_broker", capability, cooldown_ms = policy.cooldown_ms, "Capability quarantined after repeated timeouts");
+        }
+        if let Some(mut probe) = probe {
+            probe.recorded = true;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Call rate limits per tenant, principal or correlation.
+//!
+//! A limit allows `max_calls` calls per `window_ms` for each distinct key in its scope, in
+//! fixed windows on the broker clock. Calls whose context has no key for a scope (e.g. no
+//! `tenant` header) share the `anonymous` key, or are rejected before counting under
+//! `OnMissingKey::Reject`. A call over any limit returns `RATE_LIMITED` without reaching the
+//! provider and is not counted against the other limits; cache hits are never counted.
+
+use super::{ActionBroker, InvocationContext, InvocationScope};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+/// Error code for calls rejected by a rate limit
+pub const RATE_LIMITED: &str = "RATE_LIMITED";
+
+/// Windows kept before stale ones are pruned
+const MAX_TRACKED_WINDOWS: usize = 4096;
+
+/// At most `max_calls` calls per `window_ms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Calls admitted per key and window
+    pub max_calls: u32,
+    /// Window length; counts restart when it ends
+    pub window_ms: i64,
+}
+
+impl RateLimit {
+    pub fn per_second(max_calls: u32) -> Self {
+        Self {
+            max_calls,
+            window_ms: 1_000,
+        }
+    }
+
+    pub fn per_minute(max_calls: u32) -> Self {
+        Self {
+            max_calls,
+            window_ms: 60_000,
+        }
+    }
+}
+
+/// A call over a limit: which one, and when its window ends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RateRejection {
+    pub(crate) scope: InvocationScope,
+    pub(crate) key: String,
+    pub(crate) retry_after_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started_ms: i64,
+    calls: u32,
+}
+
+/// Fixed-window call counts per scope and key
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    windows: DashMap<(InvocationScope, String), Window>,
+}
+
+impl RateLimiter {
+    /// Count one call under every limit that applies to `ctx`. On a rejection the counts
+    /// already taken for this call are handed back.
+    pub(crate) fn admit(
+        &self,
+        limits: &[(InvocationScope, RateLimit)],
+        ctx: &InvocationContext,
+        now_ms: i64,
+    ) -> Result<(), RateRejection> {
+        if self.windows.len() > MAX_TRACKED_WINDOWS {
+            self.prune(limits, now_ms);
+        }
+        let mut counted = Vec::new();
+        for (scope, limit) in limits {
+            let key = ctx.policy_key(*scope);
+            let slot = (*scope, key.to_string());
+            let mut window = self.windows.entry(slot.clone()).or_insert(Window {
+                started_ms: now_ms,
+                calls: 0,
+            });
+            if now_ms - window.started_ms >= limit.window_ms {
+                *window = Window {
+                    started_ms: now_ms,
+                    calls: 0,
+                };
+            }
+            if window.calls >= limit.max_calls {
+                let retry_after_ms = window.started_ms + limit.window_ms - now_ms;
+                drop(window);
+                for taken in &counted {
+                    if let Some(mut w) = self.windows.get_mut(taken) {
+                        w.calls = w.calls.saturating_sub(1);
+                    }
+                }
+                return Err(RateRejection {
+                    scope: *scope,
+                    key: key.to_string(),
+                    retry_after_ms,
+                });
+            }
+            window.calls += 1;
+            drop(window);
+            counted.push(slot);
+        }
+        Ok(())
+    }
+
+    /// The rejection `admit` would give `ctx` at `now_ms`, without counting anything
+    pub(crate) fn peek(
+        &self,
+        limits: &[(InvocationScope, RateLimit)],
+        ctx: &InvocationContext,
+        now_ms: i64,
+    ) -> Result<(), RateRejection> {
+        for (scope, limit) in limits {
+            let key = ctx.policy_key(*scope);
+            let Some(window) = self.windows.get(&(*scope, key.to_string())) else {
+                continue;
+            };
+            if now_ms - window.started_ms < limit.window_ms && window.calls >= limit.max_calls {
+                return Err(RateRejection {
+                    scope: *scope,
+                    key: key.to_string(),
+                    retry_after_ms: window.started_ms + limit.window_ms - now_ms,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop windows that have ended, and every window of a scope no longer limited
+    fn prune(&self, limits: &[(InvocationScope, RateLimit)], now_ms: i64) {
+        self.windows.retain(|(scope, _), window| {
+            limits
+                .iter()
+                .find(|(s, _)| s == scope)
+                .is_some_and(|(_, limit)| now_ms - window.started_ms < limit.window_ms)
+        });
+    }
+}
+
+impl ActionBroker {
+    /// Limit the call rate per distinct key of `scope` (`None` removes the limit)
+    pub fn set_rate_limit(&self, scope: InvocationScope, limit: Option<RateLimit>) {
+        let mut settings = self.settings.write().unwrap();
+        match limit {
+            Some(limit) => settings.rate_limits.insert(scope, limit),
+            None => settings.rate_limits.remove(&scope),
+        };
+    }
+
+    pub(crate) fn admit_rate(&self, ctx: &InvocationContext) -> Result<(), RateRejection> {
+        let limits = self.rate_limits();
+        if limits.is_empty() {
+            return Ok(());
+        }
+        self.rate.admit(&limits, ctx, self.clock.now_ms())
+    }
+
+    /// `admit_rate` for a dry run: reports the rejection without counting the call
+    pub(crate) fn peek_rate(&self, ctx: &InvocationContext) -> Result<(), RateRejection> {
+        self.rate
+            .peek(&self.rate_limits(), ctx, self.clock.now_ms())
+    }
+
+    fn rate_limits(&self) -> Vec<(InvocationScope, RateLimit)> {
+        let settings = self.settings.read().unwrap();
+        settings.rate_limits.iter().map(|(s, l)| (*s, *l)).collect()
+    }
+}
@@ -0,0 +1,157 @@
+//! Broker-side retries of timed-out and retryable calls, with jitter.
+//!
+//! With `BrokerConfig::retry` set, `invoke` re-runs a call whose result is `ActionTimeout` or
+//! `ActionRetryable` until it gets another status or runs out of attempts. The delay before
+//! each retry doubles from `initial_backoff_ms` up to `max_backoff_ms`. `jitter` draws every
+//! delay at random from `[(1 - jitter) * delay, delay]`, and `timeout_jitter` shortens each
+//! attempt's timeout the same way. Calls that failed together then neither time out nor
+//! come back in lockstep.
+
+use super::cost::correlation_key;
+use super::{ActionBroker, InvocationContext};
+use crate::proto::{ActionCall, ActionResult, ActionStatus};
+use crate::Result;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+
+/// Result metadata with the number of attempts, set when a call was retried
+pub const ATTEMPTS_METADATA_KEY: &str = "attempts";
+
+/// How often and how far apart retries happen
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts per call, the first one included (1 = never retry)
+    pub max_attempts: u32,
+    /// Delay before the first retry; each later retry waits twice as long
+    pub initial_backoff_ms: u64,
+    /// Longest delay between two attempts
+    pub max_backoff_ms: u64,
+    /// Share of each delay drawn at random, 0.0 (fixed) to 1.0 (anywhere from zero)
+    pub jitter: f64,
+    /// Share of each attempt's timeout drawn at random; timeouts only ever get shorter
+    pub timeout_jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5_000,
+            jitter: 0.0,
+            timeout_jitter: 0.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1 = the first retry), jitter applied
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(63);
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << exponent)
+            .min(self.max_backoff_ms);
+        spread(Duration::from_millis(ms), self.jitter)
+    }
+
+    /// `timeout` shortened by a random share of at most `timeout_jitter`
+    pub fn jittered_timeout(&self, timeout: Duration) -> Duration {
+        spread(timeout, self.timeout_jitter)
+    }
+}
+
+/// Uniform draw from `[(1 - jitter) * duration, duration]`; non-finite jitter counts as 0
+fn spread(duration: Duration, jitter: f64) -> Duration {
+    let jitter = if jitter.is_finite() {
+        jitter.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    if jitter == 0.0 || duration.is_zero() {
+        return duration;
+    }
+    let cut = rand::thread_rng().gen_range(0.0..=jitter);
+    duration.mul_f64(1.0 - cut)
+}
+
+fn is_retryable(res: &ActionResult) -> bool {
+    matches!(
+        res.status_enum(),
+        ActionStatus::ActionTimeout | ActionStatus::ActionRetryable
+    )
+}
+
+impl ActionBroker {
+    /// Retry timed-out and retryable calls (`None` turns retries off)
+    pub fn set_retry_policy(&self, policy: Option<RetryPolicy>) {
+        self.settings.write().unwrap().retry = policy;
+    }
+
+    /// Limit of one attempt: `limit` with the policy's timeout jitter applied
+    pub(crate) fn attempt_timeout(&self, limit: Option<Duration>) -> Option<Duration> {
+        let policy = self.settings.read().unwrap().retry;
+        match policy {
+            Some(policy) => limit.map(|limit| policy.jittered_timeout(limit)),
+            None => limit,
+        }
+    }
+
+    /// `dispatch`, repeated after a backoff while the retry policy allows it
+    pub(crate) async fn dispatch_with_retry(
+        &self,
+        mut call: ActionCall,
+        supplied: &InvocationContext,
+    ) -> Result<ActionResult> {
+        let Some(policy) = self.settings.read().unwrap().retry else {
+            return self.dispatch(call, supplied).await;
+        };
+        // Every attempt must share one id so the final result is the one cached
+        if call.id.is_empty() {
+            call.id = self.ids.new_id();
+        }
+        let mut attempt = 1;
+        loop {
+            let mut res = self.dispatch(call.clone(), supplied).await?;
+            if attempt >= policy.max_attempts || !is_retryable(&res) {
+                if attempt > 1 {
+                    res.metadata
+                        .insert(ATTEMPTS_METADATA_KEY.to_string(), attempt.to_string());
+                }
+                return Ok(res);
+            }
+            // The failed attempt was cached; the retry has to reach the provider again
+            self.forget_cached(&call, supplied);
+            let delay = policy.backoff(attempt);
+            debug!(target: "action_broker", capability = %call.capability, call_id = %call.id, attempt, delay = ?delay, "Retrying call");
+            // A call waiting to retry is still in flight for cancel_correlation and its caller
+            let key = correlation_key(&call);
+            let correlation = key.as_deref().map(|key| self.active.enter(key));
+            let correlation_cancelled = async {
+                match &correlation {
+                    Some(correlation) => correlation.token().cancelled().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let cancelled = tokio::select! {
+                biased;
+                _ = correlation_cancelled => true,
+                _ = supplied.cancel.cancelled() => true,
+                _ = tokio::time::sleep(delay) => false,
+            };
+            if cancelled {
+                let key = key.unwrap_or_default();
+                return Ok(self.cancelled_result(
+                    call.id.clone(),
+                    &call.capability,
+                    &key,
+                    "backing_off",
+                ));
+            }
+            attempt += 1;
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Provider selection: which registered provider serves a call.
+//!
+//! The broker collects every provider registered under the call's capability name (sorted by
+//! version, lowest first) and asks its `RouteSelector` to pick one; `None` means the capability
+//! is not found. `DefaultSelector` keeps the historical rule: the exact version when the call
+//! names one, otherwise the highest version.
+
+use super::{compare_versions, ActionBroker, CapabilityProvider};
+use crate::proto::{ActionCall, CapabilityDescriptor};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A registered provider a selector may choose
+#[derive(Clone)]
+pub struct Candidate {
+    pub name: String,
+    pub version: String,
+    provider: Arc<dyn CapabilityProvider>,
+}
+
+impl Candidate {
+    /// Wrap a provider, reading name and version from its descriptor
+    pub fn new(provider: Arc<dyn CapabilityProvider>) -> Self {
+        let desc = provider.descriptor();
+        Self {
+            name: desc.name,
+            version: desc.version,
+            provider,
+        }
+    }
+
+    pub fn provider(&self) -> &Arc<dyn CapabilityProvider> {
+        &self.provider
+    }
+
+    /// The provider's full descriptor (metadata, default QoS, ...)
+    pub fn descriptor(&self) -> CapabilityDescriptor {
+        self.provider.descriptor()
+    }
+}
+
+impl std::fmt::Debug for Candidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Candidate({}:{})", self.name, self.version)
+    }
+}
+
+/// Picks the provider for a call among the providers registered under its capability.
+///
+/// `candidates` is never empty and is sorted by version, lowest first. Closures with the same
+/// signature implement this trait.
+pub trait RouteSelector: Send + Sync {
+    fn select(
+        &self,
+        capability: &str,
+        call: &ActionCall,
+        candidates: &[Candidate],
+    ) -> Option<Candidate>;
+}
+
+impl<F> RouteSelector for F
+where
+    F: Fn(&str, &ActionCall, &[Candidate]) -> Option<Candidate> + Send + Sync,
+{
+    fn select(
+        &self,
+        capability: &str,
+        call: &ActionCall,
+        candidates: &[Candidate],
+    ) -> Option<Candidate> {
+        self(capability, call, candidates)
+    }
+}
+
+/// The candidate whose version equals `call.version`; nothing when the call names no version
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExactVersion;
+
+impl RouteSelector for ExactVersion {
+    fn select(&self, _: &str, call: &ActionCall, candidates: &[Candidate]) -> Option<Candidate> {
+        if call.version.is_empty() {
+            return None;
+        }
+        candidates
+            .iter()
+            .find(|c| c.version == call.version)
+            .cloned()
+    }
+}
+
+/// The highest version (numeric-aware, so 1.10 > 1.9), ignoring `call.version`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HighestVersion;
+
+impl RouteSelector for HighestVersion {
+    fn select(&self, _: &str, _: &ActionCall, candidates: &[Candidate]) -> Option<Candidate> {
+        candidates
+            .iter()
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+            .cloned()
+    }
+}
+
+/// Rotates through the candidates per capability, ignoring `call.version`
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: DashMap<String, AtomicUsize>,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RouteSelector for RoundRobin {
+    fn select(
+        &self,
+        capability: &str,
+        _: &ActionCall,
+        candidates: &[Candidate],
+    ) -> Option<Candidate> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let turn = self
+            .next
+            .entry(capability.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+        candidates.get(turn % candidates.len()).cloned()
+    }
+}
+
+/// First answer from a list of selectors, e.g. `[ExactVersion, RoundRobin]`
+#[derive(Default, Clone)]
+pub struct FirstMatch(pub Vec<Arc<dyn RouteSelector>>);
+
+impl FirstMatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try `selector` after the ones already added
+    pub fn then(mut self, selector: impl RouteSelector + 'static) -> Self {
+        self.0.push(Arc::new(selector));
+        self
+    }
+}
+
+impl RouteSelector for FirstMatch {
+    fn select(
+        &self,
+        capability: &str,
+        call: &ActionCall,
+        candidates: &[Candidate],
+    ) -> Option<Candidate> {
+        self.0
+            .iter()
+            .find_map(|s| s.select(capability, call, candidates))
+    }
+}
+
+/// `ExactVersion` when the call names a version (no fallback to other versions), otherwise
+/// `HighestVersion`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSelector;
+
+impl RouteSelector for DefaultSelector {
+    fn select(
+        &self,
+        capability: &str,
+        call: &ActionCall,
+        candidates: &[Candidate],
+    ) -> Option<Candidate> {
+        if call.version.is_empty() {
+            HighestVersion.select(capability, call, candidates)
+        } else {
+            ExactVersion.select(capability, call, candidates)
+        }
+    }
+}
+
+impl ActionBroker {
+    /// Replace how a provider is chosen among those registered under a capability name
+    /// (default `DefaultSelector`)
+    pub fn set_route_selector(&self, selector: Arc<dyn RouteSelector>) {
+        *self.route_selector.write().unwrap() = selector;
+    }
+
+    /// Providers registered under `capability`, lowest version first
+    pub fn candidates(&self, capability: &str) -> Vec<Candidate> {
+        let prefix = format!("{}:", capability);
+        let mut out: Vec<Candidate> = self
+            .registry
+            .iter()
+            .filter_map(|e| {
+                let version = e.key().strip_prefix(&prefix)?;
+                Some(Candidate {
+                    name: capability.to_string(),
+                    version: version.to_string(),
+                    provider: Arc::clone(e.value()),
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| compare_versions(&a.version, &b.version));
+        out
+    }
+
+    /// Resolve the provider for `call` through the route selector
+    pub(crate) fn find_provider(&self, call: &ActionCall) -> Option<Arc<dyn CapabilityProvider>> {
+        let candidates = self.candidates(&call.capability);
+        if candidates.is_empty() {
+            return None;
+        }
+        let selector = Arc::clone(&*self.route_selector.read().unwrap());
+        selector
+            .select(&call.capability, call, &candidates)
+            .map(|c| c.provider)
+    }
+}
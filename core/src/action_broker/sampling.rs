@@ -0,0 +1,58 @@
+//! Trace-level payload logging, full for 1-in-N calls and sizes-only for the rest.
+//!
+//! Both records use target `action_broker::payload`; enable it with e.g.
+//! `RUST_LOG=action_broker::payload=trace`.
+
+use super::ActionBroker;
+use crate::proto::{ActionCall, ActionResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::trace;
+
+/// Lock-free 1-in-N selector: two relaxed atomics, no allocation
+#[derive(Default)]
+pub(crate) struct PayloadSampler {
+    every: AtomicU64,
+    seen: AtomicU64,
+}
+
+impl PayloadSampler {
+    pub(crate) fn set_every(&self, every: u64) {
+        self.every.store(every, Ordering::Relaxed);
+    }
+
+    pub(crate) fn every(&self) -> u64 {
+        self.every.load(Ordering::Relaxed)
+    }
+
+    /// True for the 1st, (N+1)th, (2N+1)th ... call; never when N is 0
+    pub(crate) fn sample(&self) -> bool {
+        match self.every() {
+            0 => false,
+            n => self.seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(n),
+        }
+    }
+}
+
+impl ActionBroker {
+    /// Log the full `ActionCall`/`ActionResult` of one call in `every` (0 = never);
+    /// other calls log only sizes and status
+    pub fn set_payload_sample_rate(&self, every: u64) {
+        self.sampler.set_every(every);
+    }
+
+    pub(crate) fn trace_call(&self, call: &ActionCall, sampled: bool) {
+        if sampled {
+            trace!(target: "action_broker::payload", call_id = %call.id, sampled, call = ?call, "Action call");
+        } else {
+            trace!(target: "action_broker::payload", call_id = %call.id, sampled, capability = %call.capability, payload_bytes = call.payload.len(), "Action call");
+        }
+    }
+
+    pub(crate) fn trace_result(&self, result: &ActionResult, sampled: bool) {
+        if sampled {
+            trace!(target: "action_broker::payload", call_id = %result.id, sampled, result = ?result, "Action result");
+        } else {
+            trace!(target: "action_broker::payload", call_id = %result.id, sampled, status = result.status, output_bytes = result.output.len(), "Action result");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! `tower::Service` face of the broker, for stacking Tower middleware (concurrency limits,
+//! rate limits, retries, load shedding) in front of `invoke`.
+
+use super::ActionBroker;
+use crate::proto::{ActionCall, ActionResult};
+use crate::{LoomError, Result};
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// `tower::Service<ActionCall>` over a shared broker; cheap to clone.
+///
+/// Always ready: the broker applies its own limits inside `invoke`, so backpressure comes
+/// from the layers stacked on top. Errors are the ones `invoke` returns (e.g. an unknown
+/// capability with `OnMissing::Error`); failed calls are `Ok` results with a non-OK status.
+#[derive(Clone)]
+pub struct BrokerService {
+    broker: Arc<ActionBroker>,
+}
+
+impl BrokerService {
+    pub fn new(broker: Arc<ActionBroker>) -> Self {
+        Self { broker }
+    }
+
+    pub fn broker(&self) -> &Arc<ActionBroker> {
+        &self.broker
+    }
+}
+
+impl From<Arc<ActionBroker>> for BrokerService {
+    fn from(broker: Arc<ActionBroker>) -> Self {
+        Self::new(broker)
+    }
+}
+
+impl tower::Service<ActionCall> for BrokerService {
+    type Response = ActionResult;
+    type Error = LoomError;
+    type Future = BoxFuture<'static, Result<ActionResult>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, call: ActionCall) -> Self::Future {
+        let broker = Arc::clone(&self.broker);
+        Box::pin(async move { broker.invoke(call).await })
+    }
+}
+
+impl ActionBroker {
+    /// This broker as a `tower::Service`, e.g.
+    /// `ServiceBuilder::new().concurrency_limit(4).service(broker.service())`
+    pub fn service(self: &Arc<Self>) -> BrokerService {
+        BrokerService::new(Arc::clone(self))
+    }
+}
@@ -0,0 +1,225 @@
+//! Typed call timeouts and their mapping onto the `ActionCall.timeout_ms` wire field.
+//!
+//! Wire encoding (`timeout_ms`, int64):
+//! - `<= 0` — use the broker default for the call's QoS
+//! - [`UNBOUNDED_TIMEOUT_MS`] — no timeout
+//! - anything else — that many milliseconds
+//!
+//! Sub-millisecond timeouts round `timeout_ms` up (so they never collapse to "default") and
+//! carry the exact value in [`TIMEOUT_US_HEADER`].
+//!
+//! A positive [`TIMEOUT_MS_HEADER`] overrides all of the above, clamped to
+//! `BrokerConfig::max_header_timeout_ms`.
+//!
+//! With a soft timeout ratio set, the provider's cancellation token fires at
+//! `ratio * timeout`; the hard deadline still aborts with `TIMEOUT`.
+
+use super::qos::{parse_qos, qos_name};
+use super::ActionBroker;
+use crate::proto::{ActionCall, QoSLevel};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Wire value meaning "no timeout"
+pub const UNBOUNDED_TIMEOUT_MS: i64 = i64::MAX;
+/// Header with the exact timeout in microseconds; only honored when it agrees with `timeout_ms`
+pub const TIMEOUT_US_HEADER: &str = "x-timeout-us";
+/// Header overriding the call timeout in milliseconds; takes precedence over `timeout_ms`
+pub const TIMEOUT_MS_HEADER: &str = "x-timeout-ms";
+/// Result metadata set to `"true"` when the provider returned after its soft deadline fired
+pub const SOFT_TIMEOUT_METADATA_KEY: &str = "soft_timeout";
+/// Result metadata with the call's wall time in ms, queueing included (opt-in)
+pub const DURATION_METADATA_KEY: &str = "duration_ms";
+
+/// How long a call may run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Timeout {
+    /// Broker default for the call's QoS (`BrokerConfig::qos_timeouts_ms`, then
+    /// `BrokerConfig::default_timeout_ms`)
+    #[default]
+    Default,
+    /// Abort after this long
+    After(Duration),
+    /// Never time out
+    Unbounded,
+}
+
+impl Timeout {
+    /// A zero duration means `Default`, matching the wire encoding
+    pub fn from_duration(duration: Duration) -> Self {
+        if duration.is_zero() {
+            Timeout::Default
+        } else {
+            Timeout::After(duration)
+        }
+    }
+
+    pub fn from_micros(micros: u64) -> Self {
+        Self::from_duration(Duration::from_micros(micros))
+    }
+
+    pub fn from_millis(millis: u64) -> Self {
+        Self::from_duration(Duration::from_millis(millis))
+    }
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self::from_duration(Duration::from_secs(secs))
+    }
+
+    /// Decode the `timeout_ms` wire field
+    pub fn from_wire(timeout_ms: i64) -> Self {
+        match timeout_ms {
+            ms if ms <= 0 => Timeout::Default,
+            UNBOUNDED_TIMEOUT_MS => Timeout::Unbounded,
+            ms => Timeout::After(Duration::from_millis(ms as u64)),
+        }
+    }
+
+    /// Encode as `timeout_ms`: whole milliseconds rounded up, saturating below the unbounded value
+    pub fn to_wire(&self) -> i64 {
+        match self {
+            Timeout::Default => 0,
+            Timeout::Unbounded => UNBOUNDED_TIMEOUT_MS,
+            Timeout::After(d) => {
+                let ms = d.as_micros().div_ceil(1000);
+                ms.clamp(1, (UNBOUNDED_TIMEOUT_MS - 1) as u128) as i64
+            }
+        }
+    }
+
+    /// Read the timeout of `call`, using the microsecond header when it matches `timeout_ms`
+    pub fn from_call(call: &ActionCall) -> Self {
+        let wire = Self::from_wire(call.timeout_ms);
+        let exact = call
+            .headers
+            .get(TIMEOUT_US_HEADER)
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Self::from_micros);
+        match exact {
+            // A stale header (timeout_ms changed since) is ignored
+            Some(t @ Timeout::After(_)) if t.to_wire() == call.timeout_ms => t,
+            _ => wire,
+        }
+    }
+
+    /// Write this timeout into `call` (wire field plus the exact header when sub-millisecond)
+    pub fn apply_to(&self, call: &mut ActionCall) {
+        call.timeout_ms = self.to_wire();
+        match self {
+            Timeout::After(d) if !d.subsec_nanos().is_multiple_of(1_000_000) => {
+                call.headers
+                    .insert(TIMEOUT_US_HEADER.to_string(), d.as_micros().to_string());
+            }
+            _ => {
+                call.headers.remove(TIMEOUT_US_HEADER);
+            }
+        }
+    }
+
+    /// Effective limit: `None` when unbounded, `default` when unset
+    pub fn resolve(&self, default: Duration) -> Option<Duration> {
+        match self {
+            Timeout::Default => Some(default),
+            Timeout::After(d) => Some(*d),
+            Timeout::Unbounded => None,
+        }
+    }
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Self {
+        Self::from_duration(duration)
+    }
+}
+
+impl ActionBroker {
+    /// Default applied to calls without a timeout; a non-positive config value means 1 ms
+    pub(crate) fn default_timeout(&self) -> Duration {
+        let ms = self.settings.read().unwrap().default_timeout_ms.max(1);
+        Duration::from_millis(ms as u64)
+    }
+
+    /// Default for calls at `qos` without a timeout: its `qos_timeouts_ms` entry, else
+    /// `default_timeout()`
+    pub(crate) fn qos_timeout(&self, qos: QoSLevel) -> Duration {
+        let settings = self.settings.read().unwrap();
+        match settings.qos_timeouts_ms.get(qos_name(qos)) {
+            Some(ms) => Duration::from_millis((*ms).max(1) as u64),
+            None => Duration::from_millis(settings.default_timeout_ms.max(1) as u64),
+        }
+    }
+
+    /// Replace the per-QoS default timeouts; levels missing from `timeouts` fall back to
+    /// `BrokerConfig::default_timeout_ms`
+    pub fn set_qos_timeouts(&self, timeouts: HashMap<QoSLevel, Duration>) {
+        self.settings.write().unwrap().qos_timeouts_ms = timeouts
+            .into_iter()
+            .map(|(qos, d)| (qos_name(qos).to_string(), Timeout::from(d).to_wire().max(1)))
+            .collect();
+    }
+
+    /// Keys accept any `parse_qos` spelling and are stored by config name; unknown names are
+    /// dropped with a warning
+    pub(crate) fn normalize_qos_timeouts(
+        timeouts: &BTreeMap<String, i64>,
+    ) -> BTreeMap<String, i64> {
+        let mut out = BTreeMap::new();
+        for (name, ms) in timeouts {
+            match parse_qos(name) {
+                Some(qos) => {
+                    out.insert(qos_name(qos).to_string(), *ms);
+                }
+                None => {
+                    warn!(target: "action_broker", qos = %name, "Ignoring timeout for unknown QoS")
+                }
+            }
+        }
+        out
+    }
+
+    /// Effective limit for `call` running at `qos`: the `x-timeout-ms` override (clamped) when
+    /// it is a positive integer, otherwise the typed timeout. `None` means unbounded.
+    pub(crate) fn call_timeout(&self, call: &ActionCall, qos: QoSLevel) -> Option<Duration> {
+        let header_ms = call
+            .headers
+            .get(TIMEOUT_MS_HEADER)
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|ms| *ms > 0);
+        match header_ms {
+            Some(ms) => {
+                let max = self.settings.read().unwrap().max_header_timeout_ms.max(1);
+                Some(Duration::from_millis(ms.min(max) as u64))
+            }
+            None => Timeout::from_call(call).resolve(self.qos_timeout(qos)),
+        }
+    }
+
+    /// Stamp `DURATION_METADATA_KEY` on every result a provider dispatch produced (success,
+    /// error, panic or timeout); off by default. Timeouts always report `elapsed_ms` in
+    /// their error details.
+    pub fn set_attach_duration(&self, enabled: bool) {
+        self.settings.write().unwrap().attach_duration = enabled;
+    }
+
+    /// Fire the provider's cancel token at `ratio` of the timeout (e.g. 0.8); ratios outside
+    /// (0, 1) disable the soft deadline
+    pub fn set_soft_timeout_ratio(&self, ratio: f64) {
+        self.settings.write().unwrap().soft_timeout_ratio = Self::normalize_soft_ratio(Some(ratio));
+    }
+
+    /// Ratios outside (0, 1), NaN included, turn the soft deadline off
+    pub(crate) fn normalize_soft_ratio(ratio: Option<f64>) -> Option<f64> {
+        ratio.filter(|r| *r > 0.0 && *r < 1.0)
+    }
+
+    /// Soft deadline for a call that arrived at `arrived`; `None` when disabled or unbounded
+    pub(crate) fn soft_deadline(
+        &self,
+        arrived: Instant,
+        limit: Option<Duration>,
+    ) -> Option<Instant> {
+        let ratio = self.settings.read().unwrap().soft_timeout_ratio?;
+        arrived.checked_add(limit?.mul_f64(ratio))
+    }
+}
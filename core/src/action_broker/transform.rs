@@ -0,0 +1,89 @@
+//! Per-capability result transformers applied after a successful invoke.
+
+use super::{ActionBroker, ActionResultBuilder};
+use crate::proto::{ActionResult, ActionStatus};
+use crate::Result;
+use dashmap::DashMap;
+use opentelemetry::KeyValue;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Error code for results whose transformer failed
+pub const TRANSFORM_ERROR: &str = "TRANSFORM_ERROR";
+
+/// Rewrites a successful result (e.g. unit conversion, JSON reshaping).
+///
+/// Changes to `id` are discarded; an error turns the result into `TRANSFORM_ERROR`.
+pub trait ResultTransformer: Send + Sync {
+    fn transform(&self, result: &mut ActionResult) -> Result<()>;
+}
+
+/// Any `Fn(&mut ActionResult) -> Result<()>` closure is a transformer
+impl<F> ResultTransformer for F
+where
+    F: Fn(&mut ActionResult) -> Result<()> + Send + Sync,
+{
+    fn transform(&self, result: &mut ActionResult) -> Result<()> {
+        self(result)
+    }
+}
+
+type Chain = Vec<Arc<dyn ResultTransformer>>;
+
+/// Transformer chains keyed by capability name (all versions)
+#[derive(Default)]
+pub(crate) struct ResultTransformers {
+    chains: DashMap<String, Chain>,
+}
+
+impl ActionBroker {
+    /// Append a transformer for `capability`; chains run in the order they were added
+    pub fn add_result_transformer(
+        &self,
+        capability: &str,
+        transformer: Arc<dyn ResultTransformer>,
+    ) {
+        self.transformers
+            .chains
+            .entry(capability.to_string())
+            .or_default()
+            .push(transformer);
+    }
+
+    /// Remove every transformer registered for `capability`
+    pub fn clear_result_transformers(&self, capability: &str) {
+        self.transformers.chains.remove(capability);
+    }
+
+    /// Run `capability`'s chain over an `ActionOk` result; other results pass through
+    pub(crate) fn transform_result(&self, capability: &str, mut res: ActionResult) -> ActionResult {
+        if res.status_enum() != ActionStatus::ActionOk {
+            return res;
+        }
+        // Snapshot so a transformer may register transformers without deadlocking
+        let Some(chain) = self.transformers.chains.get(capability).map(|c| c.clone()) else {
+            return res;
+        };
+        let id = res.id.clone();
+        for transformer in chain {
+            if let Err(e) = transformer.transform(&mut res) {
+                warn!(target: "action_broker", capability = %capability, error = %e, "Result transformer failed");
+                self.errors_counter.add(
+                    1,
+                    &[
+                        KeyValue::new("capability", capability.to_string()),
+                        KeyValue::new("error_code", TRANSFORM_ERROR),
+                    ],
+                );
+                return ActionResultBuilder::new(ActionStatus::ActionError)
+                    .id(id)
+                    .error(TRANSFORM_ERROR, e.to_string())
+                    .detail("capability", capability)
+                    .build();
+            }
+            // The id is the idempotency key; transformers may not change it
+            res.id.clone_from(&id);
+        }
+        res
+    }
+}
@@ -0,0 +1,146 @@
+//! Strongly-typed invocation over the byte-oriented `invoke`.
+//!
+//! The request is encoded as a JSON payload; the output is decoded according to its content
+//! type (declared, or sniffed when absent): JSON through `PayloadCodec`, text as a JSON string,
+//! binary as a byte sequence (so `Resp = Vec<u8>` works).
+//!
+//! `define_capability!` goes one step further and ties a capability name to its request and
+//! response types in a handle, so neither can be mistyped at a call site.
+
+use super::{ActionBroker, ActionCallBuilder, ActionCallExt, ActionErrorExt};
+use crate::payload::PayloadCodec;
+use crate::proto::{
+    ActionCall, ActionResult, ActionStatus, OutputKind, CONTENT_TYPE_JSON,
+    CONTENT_TYPE_METADATA_KEY,
+};
+use crate::{LoomError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A capability name bound to its request and response types; implemented by the handles
+/// `define_capability!` generates
+pub trait TypedCapability {
+    const NAME: &'static str;
+    type Request: Serialize;
+    type Response: DeserializeOwned;
+
+    /// Call to `NAME` carrying `req` as a JSON payload; add a version, timeout or headers,
+    /// then send it with `ActionBroker::invoke_typed_call::<Self::Response>`
+    fn call(req: &Self::Request) -> Result<ActionCallBuilder> {
+        json_call(Self::NAME, req)
+    }
+}
+
+/// Define a unit-struct handle for a capability with fixed request and response types.
+///
+/// ```ignore
+/// define_capability!(pub TtsEcho, name = "tts.echo", req = TtsRequest, resp = TtsResponse);
+///
+/// let reply: TtsResponse = TtsEcho::invoke(&broker, &TtsRequest { text }).await?;
+/// ```
+///
+/// The handle implements `TypedCapability` and gets an associated `invoke(&broker, &req)`
+/// that goes through `ActionBroker::invoke_capability`. Attributes and doc comments before
+/// the name are kept on the struct.
+#[macro_export]
+macro_rules! define_capability {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $handle:ident,
+        name = $name:literal,
+        req = $req:ty,
+        resp = $resp:ty $(,)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        $vis struct $handle;
+
+        impl $crate::action_broker::TypedCapability for $handle {
+            const NAME: &'static str = $name;
+            type Request = $req;
+            type Response = $resp;
+        }
+
+        impl $handle {
+            /// Invoke the capability with `req` and decode its output
+            #[allow(dead_code)]
+            $vis async fn invoke(
+                broker: &$crate::action_broker::ActionBroker,
+                req: &$req,
+            ) -> $crate::Result<$resp> {
+                broker.invoke_capability::<$handle>(req).await
+            }
+        }
+    };
+}
+
+/// Call to `capability` with `req` encoded as its JSON payload
+fn json_call<Req: Serialize + ?Sized>(capability: &str, req: &Req) -> Result<ActionCallBuilder> {
+    Ok(ActionCall::builder(capability)
+        .payload(PayloadCodec::exact().encode(req)?)
+        .header(CONTENT_TYPE_METADATA_KEY, CONTENT_TYPE_JSON))
+}
+
+impl ActionBroker {
+    /// Invoke `capability` (any version, per the route selector) with `req` as a JSON payload
+    /// and decode the output into `Resp`. Timeouts map to `LoomError::Timeout`, other failed results to
+    /// `LoomError::PluginError` with the error code, message and `details["debug"]`.
+    pub async fn invoke_typed<Req, Resp>(&self, capability: &str, req: &Req) -> Result<Resp>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let call = json_call(capability, req)?.build()?;
+        self.invoke_typed_call(call).await
+    }
+
+    /// `invoke_typed` with the name and types of the handle `C` (see `define_capability!`)
+    pub async fn invoke_capability<C: TypedCapability>(
+        &self,
+        req: &C::Request,
+    ) -> Result<C::Response> {
+        self.invoke_typed_call(C::call(req)?.build()?).await
+    }
+
+    /// `invoke_typed` for a prepared call (version, headers, timeout, ...); its payload is sent
+    /// unchanged
+    pub async fn invoke_typed_call<Resp: DeserializeOwned>(
+        &self,
+        call: ActionCall,
+    ) -> Result<Resp> {
+        let capability = call.capability.clone();
+        let res = self.invoke(call).await?;
+        decode_result(&capability, res)
+    }
+}
+
+fn decode_result<Resp: DeserializeOwned>(capability: &str, res: ActionResult) -> Result<Resp> {
+    let status = res.status_enum();
+    if status != ActionStatus::ActionOk {
+        let detail = match res.error {
+            Some(e) => match e.debug_detail() {
+                Some(debug) => format!("{capability} failed: {}: {} ({debug})", e.code, e.message),
+                None => format!("{capability} failed: {}: {}", e.code, e.message),
+            },
+            None => format!(
+                "{capability} failed: {}: ",
+                status.as_str().to_ascii_uppercase()
+            ),
+        };
+        return Err(match status {
+            ActionStatus::ActionTimeout => LoomError::Timeout(detail),
+            _ => LoomError::PluginError(detail),
+        });
+    }
+    match res.output_kind() {
+        OutputKind::Json => PayloadCodec::exact().decode(&res.output),
+        OutputKind::Text => {
+            let text = String::from_utf8(res.output).map_err(|e| {
+                LoomError::PluginError(format!("{capability} returned invalid UTF-8: {e}"))
+            })?;
+            Ok(serde_json::from_value(Value::String(text))?)
+        }
+        OutputKind::Binary => Ok(serde_json::from_value(Value::from(res.output))?),
+    }
+}
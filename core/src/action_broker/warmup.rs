@@ -0,0 +1,83 @@
+//! Concurrent provider warm-up before accepting traffic.
+
+use super::{ActionBroker, CapabilityProvider};
+use futures_util::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::time::{timeout, Duration};
+use tracing::{info, warn};
+
+/// Outcome of warming up one provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarmUpOutcome {
+    Ready,
+    Failed(String),
+    TimedOut,
+}
+
+/// Per-provider warm-up result
+#[derive(Debug, Clone)]
+pub struct WarmUpReport {
+    pub capability: String,
+    pub version: String,
+    pub outcome: WarmUpOutcome,
+    pub elapsed_ms: u64,
+}
+
+impl WarmUpReport {
+    pub fn is_ready(&self) -> bool {
+        self.outcome == WarmUpOutcome::Ready
+    }
+}
+
+impl ActionBroker {
+    /// Warm up every registered provider concurrently, each bounded by the default timeout
+    pub async fn warm_up(&self) -> Vec<WarmUpReport> {
+        self.warm_up_with_timeout(self.default_timeout()).await
+    }
+
+    /// Warm up every registered provider concurrently, each bounded by `per_provider`.
+    /// Reports are sorted by capability name then version.
+    pub async fn warm_up_with_timeout(&self, per_provider: Duration) -> Vec<WarmUpReport> {
+        let providers: Vec<Arc<dyn CapabilityProvider>> = self
+            .registry
+            .iter()
+            .map(|e| Arc::clone(e.value()))
+            .collect();
+
+        let runs = providers.into_iter().map(|provider| async move {
+            let desc = provider.descriptor();
+            let started = Instant::now();
+            let fut = AssertUnwindSafe(provider.warm_up()).catch_unwind();
+            let outcome = match timeout(per_provider, fut).await {
+                Ok(Ok(Ok(()))) => WarmUpOutcome::Ready,
+                Ok(Ok(Err(e))) => WarmUpOutcome::Failed(e.to_string()),
+                Ok(Err(panic)) => WarmUpOutcome::Failed(format!(
+                    "panicked: {}",
+                    super::panic_message(panic.as_ref())
+                )),
+                Err(_) => WarmUpOutcome::TimedOut,
+            };
+            if outcome != WarmUpOutcome::Ready {
+                warn!(target: "action_broker", capability = %desc.name, outcome = ?outcome, "Provider warm-up did not complete");
+            }
+            WarmUpReport {
+                capability: desc.name,
+                version: desc.version,
+                outcome,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            }
+        });
+
+        let mut reports = futures_util::future::join_all(runs).await;
+        reports.sort_by(|a, b| {
+            a.capability
+                .cmp(&b.capability)
+                .then_with(|| super::compare_versions(&a.version, &b.version))
+        });
+        let ready = reports.iter().filter(|r| r.is_ready()).count();
+        info!(target: "action_broker", ready, total = reports.len(), "Provider warm-up finished");
+        reports
+    }
+}
@@ -0,0 +1,97 @@
+//! Feeding action results back into memory.
+//!
+//! With a `ResultFeedback` set on the runtime, every action an agent executes is also stored
+//! as an `action_done` event, so prompts built later for the same session see what the tools
+//! returned. The session is the trigger's thread when it carries one, else the agent id.
+
+use std::sync::Arc;
+
+use tracing::warn;
+
+use crate::context::MemoryWriter;
+use crate::envelope::keys;
+use crate::proto::{ActionResult, ActionStatus, Event};
+
+/// Event type of action results stored in memory; the payload is the action output
+pub const ACTION_DONE_EVENT_TYPE: &str = "action_done";
+/// `action_done` metadata key with the error code of a failed action
+pub const ERROR_CODE_METADATA_KEY: &str = "error_code";
+
+/// Where and which action results an agent stores
+#[derive(Clone)]
+pub struct ResultFeedback {
+    memory: Arc<dyn MemoryWriter>,
+    statuses: Vec<ActionStatus>,
+}
+
+impl ResultFeedback {
+    /// Store results in `memory`. Only `ok` and `error` results are stored by default;
+    /// timeouts and retryable failures say little about the tool itself.
+    pub fn new(memory: Arc<dyn MemoryWriter>) -> Self {
+        Self {
+            memory,
+            statuses: vec![ActionStatus::ActionOk, ActionStatus::ActionError],
+        }
+    }
+
+    /// Store results with exactly these statuses
+    pub fn statuses(mut self, statuses: impl IntoIterator<Item = ActionStatus>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Whether a result with `status` is stored
+    pub fn stores(&self, status: ActionStatus) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// Memory session for results of actions triggered by `trigger`
+    pub(crate) fn session(trigger: &Event, agent_id: &str) -> String {
+        trigger
+            .metadata
+            .get(keys::THREAD_ID)
+            .cloned()
+            .unwrap_or_else(|| agent_id.to_string())
+    }
+
+    /// Store `res` as an `action_done` copy of the published `action_result` event. Best
+    /// effort: a failing store is logged, never surfaced to the agent loop.
+    pub(crate) async fn record(
+        &self,
+        session: &str,
+        call_id: &str,
+        capability: &str,
+        res: &ActionResult,
+        result_event: &Event,
+    ) {
+        if !self.stores(res.status_enum()) {
+            return;
+        }
+        let mut event = result_event.clone();
+        // The call id doubles as event id, so a replayed call is stored once
+        event.id = call_id.to_string();
+        event.r#type = ACTION_DONE_EVENT_TYPE.to_string();
+        event.tags = capability_tags(capability);
+        if let Some(error) = &res.error {
+            event
+                .metadata
+                .insert(ERROR_CODE_METADATA_KEY.into(), error.code.clone());
+            if event.payload.is_empty() {
+                event.payload = error.message.clone().into_bytes();
+            }
+        }
+        if let Err(e) = self.memory.append_event(session, event).await {
+            warn!(target: "agent", session = %session, call_id = %call_id, error = %e, "Failed to store action result");
+        }
+    }
+}
+
+/// `action`, the capability name and, for dotted names, its namespace (`weather.get` →
+/// `weather`), so retrieval can filter on either
+fn capability_tags(capability: &str) -> Vec<String> {
+    let mut tags = vec!["action".to_string(), capability.to_string()];
+    if let Some((namespace, _)) = capability.split_once('.') {
+        tags.push(namespace.to_string());
+    }
+    tags
+}
@@ -6,13 +6,15 @@ use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 use crate::action_broker::ActionBroker;
-use crate::proto::{Action, AgentConfig, AgentState};
+use crate::proto::{Action, AgentConfig, AgentState, CONTENT_TYPE_METADATA_KEY};
 use crate::router::{
     AgentContext, ModelRouter, PrivacyLevel, Route, RoutingDecision, RoutingPolicy,
 };
-use crate::{Envelope, Event, EventBus, Result};
+use crate::{Envelope, Event, EventBus, EventExt, Result};
 
 use super::behavior::AgentBehavior;
+use super::feedback::ResultFeedback;
+use super::priority::{inherited_priority, qos_for_priority};
 
 /// Agent instance
 pub struct Agent {
@@ -23,6 +25,7 @@ pub struct Agent {
     pub(crate) action_broker: Arc<ActionBroker>,
     pub(crate) event_bus: Arc<EventBus>,
     pub(crate) model_router: ModelRouter,
+    pub(crate) result_feedback: Option<Arc<ResultFeedback>>,
     // OpenTelemetry metrics
     events_processed_counter: Counter<u64>,
     actions_executed_counter: Counter<u64>,
@@ -78,6 +81,7 @@ impl Agent {
             action_broker,
             event_bus,
             model_router,
+            result_feedback: None,
             events_processed_counter,
             actions_executed_counter,
             event_latency_histogram,
@@ -85,6 +89,12 @@ impl Agent {
         }
     }
 
+    /// Store the results of executed actions in memory as `action_done` events
+    pub fn with_result_feedback(mut self, feedback: Arc<ResultFeedback>) -> Self {
+        self.result_feedback = Some(feedback);
+        self
+    }
+
     /// Start agent event loop
     #[tracing::instrument(skip(self), fields(agent_id = %self.config.agent_id))]
     pub async fn run(mut self) -> Result<()> {
@@ -119,11 +129,16 @@ impl Agent {
             // Route the event first
             let decision = self.route_event(&event, &state_snapshot, &env).await;
 
+            // Events produced while handling point back at the trigger and inherit its priority
+            let trigger_id = event.id.clone();
+            let trigger_priority = event.priority;
+            let session = ResultFeedback::session(&event, &self.config.agent_id);
             match self.handle_with_route(event, decision).await {
                 Ok(actions) => {
                     // Execute actions
-                    for action in actions {
-                        self.execute_action(action).await?;
+                    for mut action in actions {
+                        action.priority = inherited_priority(trigger_priority, &action);
+                        self.execute_action(action, &trigger_id, &session).await?;
                     }
                 }
                 Err(e) => {
@@ -221,7 +236,7 @@ impl Agent {
         md.insert("est_cost".into(), format!("{:.4}", decision.estimated_cost));
 
         let mut obs_evt = Event {
-            id: format!("evt_route_{}", chrono::Utc::now().timestamp_millis()),
+            id: self.action_broker.id_generator().new_id(),
             r#type: "routing_decision".to_string(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: format!("agent.{}", self.config.agent_id),
@@ -232,6 +247,9 @@ impl Agent {
             priority: 50,
         };
         env.attach_to_event(&mut obs_evt);
+        if !event.id.is_empty() {
+            obs_evt = obs_evt.with_parent(event.id.clone());
+        }
         let _ = self
             .event_bus
             .publish(&format!("agent.{}", self.config.agent_id), obs_evt)
@@ -355,38 +373,28 @@ impl Agent {
     }
 
     #[tracing::instrument(skip(self, action), fields(agent_id = %self.config.agent_id, action_type = %action.action_type, priority = action.priority))]
-    async fn execute_action(&self, action: Action) -> Result<()> {
-        use crate::proto::{ActionCall, ActionStatus, QoSLevel};
+    async fn execute_action(&self, action: Action, trigger_id: &str, session: &str) -> Result<()> {
+        use crate::proto::ActionCall;
         debug!("Executing action: {}", action.action_type);
 
-        // Map priority to QoS
-        let qos = if action.priority >= 70 {
-            QoSLevel::QosRealtime
-        } else if action.priority >= 30 {
-            QoSLevel::QosBatched
-        } else {
-            QoSLevel::QosBackground
-        };
+        // `action.priority` already includes what it inherited from the trigger
+        let qos = qos_for_priority(action.priority);
 
         // Convert parameters into headers for the call
         let headers = action.parameters.clone();
 
         // Build ActionCall
-        let now = chrono::Utc::now();
-        let call_id = format!(
-            "act_{}",
-            now.timestamp_nanos_opt()
-                .unwrap_or_else(|| now.timestamp_millis() * 1_000_000)
-        );
+        let ids = self.action_broker.id_generator();
+        let call_id = ids.new_id();
         let mut call = ActionCall {
             id: call_id.clone(),
             capability: action.action_type.clone(),
             version: "".to_string(), // resolve first provider by name if version unspecified
             payload: action.payload.clone(),
             headers,
-            timeout_ms: 0, // broker default (30s)
+            timeout_ms: 0, // broker default for the call's QoS
             correlation_id: self.config.agent_id.clone(),
-            qos: qos as i32,
+            qos: Some(qos as i32),
         };
 
         // Attach envelope into call headers
@@ -398,25 +406,18 @@ impl Agent {
 
         // Optionally publish result event for observability
         let mut evt = Event {
-            id: format!(
-                "evt_action_result_{}",
-                chrono::Utc::now().timestamp_millis()
-            ),
+            id: ids.new_id(),
             r#type: "action_result".to_string(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: format!("agent.{}", self.config.agent_id),
             metadata: {
                 let mut m = std::collections::HashMap::new();
                 m.insert("action_type".into(), action.action_type.clone());
-                m.insert(
-                    "status".into(),
-                    match res.status {
-                        x if x == ActionStatus::ActionOk as i32 => "ok".into(),
-                        x if x == ActionStatus::ActionTimeout as i32 => "timeout".into(),
-                        x if x == ActionStatus::ActionRetryable as i32 => "retryable".into(),
-                        _ => "error".into(),
-                    },
-                );
+                m.insert("status".into(), res.status_enum().to_string());
+                // Binary outputs (e.g. audio) stay distinguishable from text downstream
+                if let Some(content_type) = res.content_type() {
+                    m.insert(CONTENT_TYPE_METADATA_KEY.into(), content_type.to_string());
+                }
                 m
             },
             payload: res.output.clone(),
@@ -426,6 +427,15 @@ impl Agent {
         };
         // Reuse envelope from action call to maintain thread/correlation consistency
         env.attach_to_event(&mut evt);
+        if !trigger_id.is_empty() {
+            evt = evt.with_parent(trigger_id.to_string());
+        }
+        // Close the memory loop before publishing hands the event off
+        if let Some(feedback) = &self.result_feedback {
+            feedback
+                .record(session, &call_id, &action.action_type, &res, &evt)
+                .await;
+        }
         // Best-effort publish; ignore delivery count
         let _ = self
             .event_bus
@@ -438,15 +448,7 @@ impl Agent {
             &[
                 KeyValue::new("agent_id", self.config.agent_id.clone()),
                 KeyValue::new("action_type", action.action_type.clone()),
-                KeyValue::new(
-                    "status",
-                    match res.status {
-                        x if x == ActionStatus::ActionOk as i32 => "ok",
-                        x if x == ActionStatus::ActionTimeout as i32 => "timeout",
-                        x if x == ActionStatus::ActionRetryable as i32 => "retryable",
-                        _ => "error",
-                    },
-                ),
+                KeyValue::new("status", res.status_enum().as_str()),
             ],
         );
 
@@ -1,16 +1,22 @@
 //! Agent runtime module split into smaller files for readability.
 //! - behavior.rs: AgentBehavior trait
+//! - feedback.rs: ResultFeedback (action results stored back into memory)
 //! - instance.rs: Agent struct and core logic (routing, execution)
+//! - priority.rs: priority inheritance from events to action calls
 //! - runtime.rs: AgentRuntime manager
 
 // Publicly re-export common types expected by external users
 pub use crate::proto::{Action, AgentConfig, AgentState};
 
 mod behavior;
+mod feedback;
 mod instance;
+mod priority;
 mod runtime;
 
 // Public re-exports so external code keeps using crate::agent::{Agent, AgentRuntime}
 pub use behavior::AgentBehavior;
+pub use feedback::{ResultFeedback, ACTION_DONE_EVENT_TYPE, ERROR_CODE_METADATA_KEY};
 pub use instance::Agent;
+pub use priority::{inherited_priority, qos_for_priority, BATCHED_PRIORITY, REALTIME_PRIORITY};
 pub use runtime::AgentRuntime;
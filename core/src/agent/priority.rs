@@ -0,0 +1,29 @@
+//! Priority inheritance from a triggering event to the calls its actions make.
+//!
+//! An action runs at the higher of its own `priority` and its trigger's, so an urgent event
+//! gets urgent tool execution even when the behavior leaves the action's priority unset. The
+//! result picks the call's QoS and is the priority of the `action_result` event.
+
+use crate::proto::{Action, QoSLevel};
+
+/// Lowest priority whose actions are invoked with `QosRealtime`
+pub const REALTIME_PRIORITY: i32 = 70;
+/// Lowest priority whose actions are invoked with `QosBatched`; below it, `QosBackground`
+pub const BATCHED_PRIORITY: i32 = 30;
+
+/// Priority `action` runs at when triggered by an event with `trigger_priority`. The
+/// trigger's priority is a floor: an action can raise it but never lower it.
+pub fn inherited_priority(trigger_priority: i32, action: &Action) -> i32 {
+    trigger_priority.max(action.priority)
+}
+
+/// QoS a call made at `priority` is scheduled under
+pub fn qos_for_priority(priority: i32) -> QoSLevel {
+    if priority >= REALTIME_PRIORITY {
+        QoSLevel::QosRealtime
+    } else if priority >= BATCHED_PRIORITY {
+        QoSLevel::QosBatched
+    } else {
+        QoSLevel::QosBackground
+    }
+}
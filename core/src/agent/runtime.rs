@@ -11,6 +11,7 @@ use crate::router::ModelRouter;
 use crate::{proto, Event, EventBus, LoomError, Result};
 
 use super::behavior::AgentBehavior;
+use super::feedback::ResultFeedback;
 use super::instance::Agent;
 
 /// Subscription handle for an agent
@@ -41,6 +42,7 @@ pub struct AgentRuntime {
     event_bus: Arc<EventBus>,
     action_broker: Arc<ActionBroker>,
     model_router: ModelRouter,
+    result_feedback: Option<Arc<ResultFeedback>>,
     // OpenTelemetry metrics
     agents_active_gauge: UpDownCounter<i64>,
     agents_created_counter: Counter<u64>,
@@ -88,6 +90,7 @@ impl AgentRuntime {
             event_bus,
             action_broker,
             model_router,
+            result_feedback: None,
             agents_active_gauge,
             agents_created_counter,
             agents_deleted_counter,
@@ -96,6 +99,12 @@ impl AgentRuntime {
         })
     }
 
+    /// Have every agent created from now on store its action results in memory
+    pub fn with_result_feedback(mut self, feedback: ResultFeedback) -> Self {
+        self.result_feedback = Some(Arc::new(feedback));
+        self
+    }
+
     pub async fn start(&mut self) -> Result<()> {
         info!("Agent Runtime started");
         Ok(())
@@ -189,7 +198,7 @@ impl AgentRuntime {
         }
 
         // Create and start agent
-        let agent = Agent::new(
+        let mut agent = Agent::new(
             config,
             behavior,
             event_rx,
@@ -197,6 +206,9 @@ impl AgentRuntime {
             Arc::clone(&self.event_bus),
             self.model_router.clone(),
         );
+        if let Some(feedback) = &self.result_feedback {
+            agent = agent.with_result_feedback(Arc::clone(feedback));
+        }
         let task_handle = tokio::spawn(async move {
             if let Err(e) = agent.run().await {
                 warn!("Agent error: {}", e);
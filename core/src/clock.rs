@@ -0,0 +1,58 @@
+//! Injectable time source.
+//!
+//! Components that stamp or expire data (ActionBroker idempotency cache, memory stores)
+//! read time through [`Clock`] so tests can drive TTL logic deterministically with
+//! [`MockClock`] instead of sleeping on the wall clock.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// Source of "now" in milliseconds since the Unix epoch
+pub trait Clock: Send + Sync {
+    fn now_ms(&self) -> i64;
+}
+
+/// Wall-clock time (default for all components)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+/// Manually driven clock for tests; time only moves when `advance`/`set` is called
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now_ms: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(start_ms: i64) -> Arc<Self> {
+        Arc::new(Self {
+            now_ms: AtomicI64::new(start_ms),
+        })
+    }
+
+    /// Move time forward by `delta_ms` and return the new value
+    pub fn advance(&self, delta_ms: i64) -> i64 {
+        self.now_ms.fetch_add(delta_ms, Ordering::SeqCst) + delta_ms
+    }
+
+    /// Jump to an absolute time
+    pub fn set(&self, now_ms: i64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+/// Shared default clock handle
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
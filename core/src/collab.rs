@@ -2,6 +2,7 @@ use std::{collections::HashMap, sync::Arc};
 
 use tokio::time::{timeout, Duration, Instant};
 
+use crate::ids::{default_id_generator, IdGenerator};
 use crate::{envelope::keys, envelope::ThreadTopicKind, Envelope, Event, EventBus, Result};
 
 /// Control event type names used on Event.r#type for collaboration protocols
@@ -65,6 +66,8 @@ pub mod types {
 pub struct Collaborator {
     event_bus: Arc<EventBus>,
     sender_id: String,
+    // thread and event ids (default UUID v7)
+    ids: Arc<dyn IdGenerator>,
 }
 
 impl Collaborator {
@@ -91,9 +94,16 @@ impl Collaborator {
         Self {
             event_bus,
             sender_id: sender_id.into(),
+            ids: default_id_generator(),
         }
     }
 
+    /// Use a custom id scheme for thread and event ids
+    pub fn with_id_generator(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
     /// Performs a request-reply interaction with timeout.
     ///
     /// Publishes a request event to the specified topic, then waits for the first
@@ -154,12 +164,7 @@ impl Collaborator {
         }
 
         // Prepare envelope and subscribe to reply topic first to avoid races
-        let thread_id = format!(
-            "req_{}",
-            chrono::Utc::now()
-                .timestamp_nanos_opt()
-                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis() * 1_000_000)
-        );
+        let thread_id = format!("req_{}", self.ids.new_id());
         let env = Envelope::new(thread_id, self.sender_id.clone());
         let reply_topic = env.reply_topic();
 
@@ -175,7 +180,7 @@ impl Collaborator {
         let mut md = HashMap::new();
         env.apply_to_metadata(&mut md);
         let mut evt = Event {
-            id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+            id: self.ids.new_id(),
             r#type: types::REQ.into(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: self.sender_id.clone(),
@@ -210,7 +215,7 @@ impl Collaborator {
             env.apply_to_metadata(&mut md);
             md.insert("reason".into(), "request_reply_timeout".into());
             let mut evt = Event {
-                id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+                id: self.ids.new_id(),
                 r#type: types::TIMEOUT.into(),
                 timestamp_ms: chrono::Utc::now().timestamp_millis(),
                 source: self.sender_id.clone(),
@@ -302,12 +307,7 @@ impl Collaborator {
             ));
         }
 
-        let thread_id = format!(
-            "fanout_{}",
-            chrono::Utc::now()
-                .timestamp_nanos_opt()
-                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis() * 1_000_000)
-        );
+        let thread_id = format!("fanout_{}", self.ids.new_id());
         let env = Envelope::new(thread_id, self.sender_id.clone());
         let reply_topic = env.reply_topic();
         let (_sub_id, mut rx) = self
@@ -324,7 +324,7 @@ impl Collaborator {
             let mut md = HashMap::new();
             env.apply_to_metadata(&mut md);
             let mut evt = Event {
-                id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+                id: self.ids.new_id(),
                 r#type: types::REQ.into(),
                 timestamp_ms: chrono::Utc::now().timestamp_millis(),
                 source: self.sender_id.clone(),
@@ -358,7 +358,7 @@ impl Collaborator {
         md.insert("received".into(), out.len().to_string());
         md.insert("target_first_k".into(), first_k.to_string());
         let mut evt = Event {
-            id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+            id: self.ids.new_id(),
             r#type: types::SUMMARY.into(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: self.sender_id.clone(),
@@ -470,7 +470,7 @@ impl Collaborator {
         let mut md = HashMap::new();
         env.apply_to_metadata(&mut md);
         let mut cfp_evt = Event {
-            id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+            id: self.ids.new_id(),
             r#type: types::CFP.into(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: self.sender_id.clone(),
@@ -522,7 +522,7 @@ impl Collaborator {
                 w.metadata.get(keys::SENDER).cloned().unwrap_or_default(),
             );
             let mut award_evt = Event {
-                id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+                id: self.ids.new_id(),
                 r#type: types::AWARD.into(),
                 timestamp_ms: chrono::Utc::now().timestamp_millis(),
                 source: self.sender_id.clone(),
@@ -542,7 +542,7 @@ impl Collaborator {
         md.insert("winners".into(), winners.len().to_string());
         md.insert("max_awards".into(), max_awards.to_string());
         let mut summary_evt = Event {
-            id: format!("evt_{}", chrono::Utc::now().timestamp_millis()),
+            id: self.ids.new_id(),
             r#type: types::SUMMARY.into(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
             source: self.sender_id.clone(),
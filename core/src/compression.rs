@@ -0,0 +1,276 @@
+//! Optional payload compression (features `gzip` and `zstd`).
+//!
+//! A compressed payload is marked with `content-encoding` (`CONTENT_ENCODING_KEY`): in the
+//! headers of an `ActionCall`, or in the metadata of an `ActionResult` or `Event`. Receivers
+//! decompress whatever carries the mark. A peer lists the codecs it can decode in
+//! `accept-encoding` (`ACCEPT_ENCODING_KEY`, comma-separated), and a sender compresses only
+//! for peers that accept its codec. Payloads under `min_bytes` are sent as they are, since
+//! compressing them costs more than it saves.
+//!
+//! Compressed payloads may come from untrusted peers, so decoding stops at a size bound
+//! (`DEFAULT_MAX_DECODED_BYTES` unless given) and rejects anything larger, rather than
+//! letting a small bomb expand without limit.
+
+use crate::proto::{ActionCall, ActionResult, Event};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read};
+
+/// Header / metadata key naming the codec a payload is compressed with
+pub const CONTENT_ENCODING_KEY: &str = "content-encoding";
+/// Header / metadata key listing the codecs a peer can decompress (e.g. `"zstd, gzip"`)
+pub const ACCEPT_ENCODING_KEY: &str = "accept-encoding";
+/// Payloads shorter than this are not compressed by default
+pub const DEFAULT_MIN_COMPRESS_BYTES: usize = 1024;
+/// Decoded payloads larger than this are rejected by default
+pub const DEFAULT_MAX_DECODED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Compression codec; each one works only when its feature is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    /// Name used in `content-encoding` / `accept-encoding`
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// Codec called `name`, case-insensitively
+    pub fn parse(name: &str) -> Option<Self> {
+        [Codec::Gzip, Codec::Zstd]
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Whether this build can compress and decompress with the codec
+    pub fn is_available(self) -> bool {
+        match self {
+            Codec::Gzip => cfg!(feature = "gzip"),
+            Codec::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Codecs this build supports, preferred first, as an `accept-encoding` value
+    pub fn accept_header() -> String {
+        [Codec::Zstd, Codec::Gzip]
+            .into_iter()
+            .filter(|c| c.is_available())
+            .map(Codec::name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::Gzip => gzip::compress(data),
+            Codec::Zstd => zstd_codec::compress(data),
+        }
+    }
+
+    /// Decode `data`; output over `max_decoded_bytes` is an error
+    pub fn decompress(self, data: &[u8], max_decoded_bytes: usize) -> Result<Vec<u8>> {
+        match self {
+            Codec::Gzip => gzip::decompress(data, max_decoded_bytes),
+            Codec::Zstd => zstd_codec::decompress(data, max_decoded_bytes),
+        }
+    }
+}
+
+/// Read `decoder` to the end, failing once it yields more than `max` bytes
+#[allow(dead_code)]
+fn read_bounded(decoder: impl Read, max: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let limit = u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1);
+    decoder.take(limit).read_to_end(&mut out)?;
+    if out.len() > max {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("decoded payload exceeds {max} bytes"),
+        )
+        .into());
+    }
+    Ok(out)
+}
+
+/// Error for a codec whose feature is off
+#[allow(dead_code)]
+fn unavailable(codec: Codec) -> crate::LoomError {
+    let name = codec.name();
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{name} compression needs the `{name}` feature"),
+    )
+    .into()
+}
+
+#[cfg(feature = "gzip")]
+mod gzip {
+    use super::read_bounded;
+    use crate::Result;
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    pub(super) fn compress(data: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    pub(super) fn decompress(data: &[u8], max: usize) -> Result<Vec<u8>> {
+        read_bounded(GzDecoder::new(data), max)
+    }
+}
+
+#[cfg(not(feature = "gzip"))]
+mod gzip {
+    use super::{unavailable, Codec};
+    use crate::Result;
+
+    pub(super) fn compress(_data: &[u8]) -> Result<Vec<u8>> {
+        Err(unavailable(Codec::Gzip))
+    }
+
+    pub(super) fn decompress(_data: &[u8], _max: usize) -> Result<Vec<u8>> {
+        Err(unavailable(Codec::Gzip))
+    }
+}
+
+#[cfg(feature = "zstd")]
+mod zstd_codec {
+    use super::read_bounded;
+    use crate::Result;
+
+    pub(super) fn compress(data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?)
+    }
+
+    pub(super) fn decompress(data: &[u8], max: usize) -> Result<Vec<u8>> {
+        read_bounded(zstd::stream::read::Decoder::new(data)?, max)
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+mod zstd_codec {
+    use super::{unavailable, Codec};
+    use crate::Result;
+
+    pub(super) fn compress(_data: &[u8]) -> Result<Vec<u8>> {
+        Err(unavailable(Codec::Zstd))
+    }
+
+    pub(super) fn decompress(_data: &[u8], _max: usize) -> Result<Vec<u8>> {
+        Err(unavailable(Codec::Zstd))
+    }
+}
+
+/// Whether an `accept-encoding` value lists `codec`
+pub fn accepts(accept: &str, codec: Codec) -> bool {
+    accept
+        .split(',')
+        .any(|name| Codec::parse(name) == Some(codec))
+}
+
+/// When and how payloads are compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadCompression {
+    pub codec: Codec,
+    /// Payloads shorter than this are left as they are
+    pub min_bytes: usize,
+}
+
+impl PayloadCompression {
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            min_bytes: DEFAULT_MIN_COMPRESS_BYTES,
+        }
+    }
+
+    pub fn min_bytes(mut self, min_bytes: usize) -> Self {
+        self.min_bytes = min_bytes;
+        self
+    }
+
+    /// Compress `payload` in place and mark it in `meta`. Returns `false`, leaving both
+    /// untouched, for payloads under `min_bytes`, payloads that are already encoded, and
+    /// payloads that would not get smaller.
+    pub fn compress(
+        &self,
+        payload: &mut Vec<u8>,
+        meta: &mut HashMap<String, String>,
+    ) -> Result<bool> {
+        if payload.len() < self.min_bytes.max(1) || meta.contains_key(CONTENT_ENCODING_KEY) {
+            return Ok(false);
+        }
+        let compressed = self.codec.compress(payload)?;
+        if compressed.len() >= payload.len() {
+            return Ok(false);
+        }
+        *payload = compressed;
+        meta.insert(
+            CONTENT_ENCODING_KEY.to_string(),
+            self.codec.name().to_string(),
+        );
+        Ok(true)
+    }
+
+    pub fn compress_call(&self, call: &mut ActionCall) -> Result<bool> {
+        self.compress(&mut call.payload, &mut call.headers)
+    }
+
+    pub fn compress_result(&self, result: &mut ActionResult) -> Result<bool> {
+        self.compress(&mut result.output, &mut result.metadata)
+    }
+
+    pub fn compress_event(&self, event: &mut Event) -> Result<bool> {
+        self.compress(&mut event.payload, &mut event.metadata)
+    }
+}
+
+/// Undo `PayloadCompression::compress`: decompress `payload` with the codec named in `meta`
+/// and drop the mark. `false` for payloads without one; an unknown codec, or output over
+/// `DEFAULT_MAX_DECODED_BYTES`, is an error.
+pub fn decompress(payload: &mut Vec<u8>, meta: &mut HashMap<String, String>) -> Result<bool> {
+    decompress_bounded(payload, meta, DEFAULT_MAX_DECODED_BYTES)
+}
+
+/// `decompress` with a caller-chosen bound on the decoded size
+pub fn decompress_bounded(
+    payload: &mut Vec<u8>,
+    meta: &mut HashMap<String, String>,
+    max_decoded_bytes: usize,
+) -> Result<bool> {
+    let Some(name) = meta.get(CONTENT_ENCODING_KEY) else {
+        return Ok(false);
+    };
+    let codec = Codec::parse(name).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("unknown content-encoding: {name}"),
+        )
+    })?;
+    *payload = codec.decompress(payload, max_decoded_bytes)?;
+    meta.remove(CONTENT_ENCODING_KEY);
+    Ok(true)
+}
+
+pub fn decompress_call(call: &mut ActionCall) -> Result<bool> {
+    decompress(&mut call.payload, &mut call.headers)
+}
+
+pub fn decompress_result(result: &mut ActionResult) -> Result<bool> {
+    decompress(&mut result.output, &mut result.metadata)
+}
+
+pub fn decompress_event(event: &mut Event) -> Result<bool> {
+    decompress(&mut event.payload, &mut event.metadata)
+}
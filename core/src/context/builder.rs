@@ -1,60 +1,411 @@
-use super::{MemoryReader, MemoryWriter, PromptBundle, TokenBudget};
-use crate::Result;
+use super::cache::BundleCache;
+use super::debounce::Debouncer;
+use super::strategy::{AssemblyContext, ContextStrategy, MinimalStrategy};
+use super::{
+    BundlePostprocessor, CacheStats, HeuristicTokenCounter, MemoryReader, MemoryWriter,
+    PromptBundle, RoleMapping, TokenBudget, TokenCounter,
+};
+use crate::action_broker::ActionBroker;
+use crate::proto::CapabilityDescriptor;
+use crate::{CancellationToken, LoomError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tracing::debug;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// System prompt used when neither the builder nor the trigger overrides it
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are Loom Agent. Be concise and precise.";
+/// Retrieved documents per build when neither the builder nor the trigger sets `k`
+pub const DEFAULT_RETRIEVAL_K: usize = 4;
+/// Distinct tool hints kept per build unless `with_max_tool_hints` says otherwise
+pub const DEFAULT_MAX_TOOL_HINTS: usize = 16;
+
+/// What `build` does when the reader's retrieval, or its read of the session history, fails
+#[derive(Clone, Default)]
+pub enum RetrievalPolicy {
+    /// Propagate the error from `build`
+    Strict,
+    /// Log and continue without retrieved documents (or history)
+    #[default]
+    Degrade,
+    /// Log and retry against a cheaper reader; degrades if that fails too
+    Fallback(Arc<dyn MemoryReader>),
+}
+
+impl std::fmt::Debug for RetrievalPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetrievalPolicy::Strict => f.write_str("Strict"),
+            RetrievalPolicy::Degrade => f.write_str("Degrade"),
+            RetrievalPolicy::Fallback(_) => f.write_str("Fallback(..)"),
+        }
+    }
+}
+
+/// What `build` does when the trigger's goal is missing, empty or whitespace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyGoalPolicy {
+    /// Skip retrieval; the episode summary and recent history are still included
+    #[default]
+    SkipRetrieval,
+    /// Recent history only: no retrieval and no episode summary
+    HistoryOnly,
+}
 
 /// Input that triggers context construction
 #[derive(Debug, Clone)]
 pub struct TriggerInput {
     pub session_id: String,
     pub goal: Option<String>,
+    /// Suggested tool names; deduplicated case-insensitively and capped by the builder
     pub tool_hints: Vec<String>,
     pub budget: TokenBudget,
+    /// Per-trigger system prompt; wins over the builder's when non-empty
+    pub system_prompt: Option<String>,
+    /// Per-trigger retrieval `k`; wins over the builder's
+    pub retrieval_k: Option<usize>,
+    /// Per-trigger minimum relevance score; wins over the builder's
+    pub min_score: Option<f32>,
 }
 
 /// ContextBuilder assembles a PromptBundle from memory and recent events
 pub struct ContextBuilder<R: MemoryReader, W: MemoryWriter> {
     reader: Arc<R>,
     writer: Arc<W>,
+    cache: Option<BundleCache>,
+    roles: RoleMapping,
+    history_limit: usize,
+    system_prompt: String,
+    retrieval_k: usize,
+    min_score: Option<f32>,
+    max_tool_hints: usize,
+    retrieval_policy: RetrievalPolicy,
+    retrieval_timeout: Option<Duration>,
+    retrieval_token_budget: Option<usize>,
+    empty_goal: EmptyGoalPolicy,
+    strategy: Arc<dyn ContextStrategy>,
+    postprocessors: Vec<Arc<dyn BundlePostprocessor>>,
+    broker: Option<Arc<ActionBroker>>,
+    debounce: Option<Debouncer>,
+    token_counter: Arc<dyn TokenCounter>,
 }
 
-impl<R: MemoryReader, W: MemoryWriter> ContextBuilder<R, W> {
+impl<R: MemoryReader + 'static, W: MemoryWriter + 'static> ContextBuilder<R, W> {
     pub fn new(reader: Arc<R>, writer: Arc<W>) -> Self {
-        Self { reader, writer }
+        Self {
+            reader,
+            writer,
+            cache: None,
+            roles: RoleMapping::default(),
+            history_limit: 10,
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            retrieval_k: DEFAULT_RETRIEVAL_K,
+            min_score: None,
+            max_tool_hints: DEFAULT_MAX_TOOL_HINTS,
+            retrieval_policy: RetrievalPolicy::default(),
+            retrieval_timeout: None,
+            retrieval_token_budget: None,
+            empty_goal: EmptyGoalPolicy::default(),
+            strategy: Arc::new(MinimalStrategy),
+            postprocessors: Vec::new(),
+            broker: None,
+            debounce: None,
+            token_counter: Arc::new(HeuristicTokenCounter),
+        }
     }
 
-    /// Build a minimal prompt bundle; this is a skeleton to be expanded
-    pub async fn build(&self, trigger: TriggerInput) -> Result<PromptBundle> {
-        debug!(target: "context_builder", session = %trigger.session_id, "Building prompt bundle");
+    /// Replace the default system prompt; an empty/blank prompt keeps the default
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        let prompt = prompt.into();
+        if !prompt.trim().is_empty() {
+            self.system_prompt = prompt;
+        }
+        self
+    }
+
+    /// Override how event source/type map to history roles
+    pub fn with_role_mapping(mut self, roles: RoleMapping) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// Maximum number of history entries (most recent kept; default 10)
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
 
-        // Pull episodic summary and run simple retrieval against in-memory store
-        let mut context_docs: Vec<String> = Vec::new();
+    /// Number of documents requested from the reader per build (default 4)
+    pub fn with_retrieval_k(mut self, k: usize) -> Self {
+        self.retrieval_k = k;
+        self
+    }
+
+    /// Drop retrieved documents scoring below `min_score`, even if fewer than `k` remain
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Keep at most `max` distinct tool hints per build (default 16); extra hints are dropped
+    /// with a warning
+    pub fn with_max_tool_hints(mut self, max: usize) -> Self {
+        self.max_tool_hints = max;
+        self
+    }
+
+    /// How retrieval errors are handled (default `Degrade`)
+    pub fn with_retrieval_policy(mut self, policy: RetrievalPolicy) -> Self {
+        self.retrieval_policy = policy;
+        self
+    }
+
+    /// Abort a retrieval query that runs longer than `timeout` and treat it as a retrieval
+    /// error under the retrieval policy (by default: warn and build without retrieved docs).
+    /// A `Fallback` reader gets the same limit.
+    pub fn with_retrieval_timeout(mut self, timeout: Duration) -> Self {
+        self.retrieval_timeout = Some(timeout);
+        self
+    }
+
+    /// Stream retrieval (`MemoryReader::retrieve_stream`) and stop once the retrieved docs
+    /// fill `max_tokens` (per the token counter) or `k` is reached, instead of ranking a
+    /// fixed `k` up front. Docs that would overflow the budget end retrieval.
+    pub fn with_retrieval_token_budget(mut self, max_tokens: usize) -> Self {
+        self.retrieval_token_budget = Some(max_tokens);
+        self
+    }
+
+    /// What to include when the goal is empty (default `SkipRetrieval`); an empty goal never
+    /// issues a retrieval query
+    pub fn with_empty_goal_policy(mut self, policy: EmptyGoalPolicy) -> Self {
+        self.empty_goal = policy;
+        self
+    }
+
+    /// Replace how bundles are assembled (default `MinimalStrategy`); caching, tool-hint
+    /// normalization and postprocessors still apply
+    pub fn with_strategy(mut self, strategy: Arc<dyn ContextStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Append a postprocessor; the chain runs in the order they were added
+    pub fn with_postprocessor(mut self, postprocessor: Arc<dyn BundlePostprocessor>) -> Self {
+        self.postprocessors.push(postprocessor);
+        self
+    }
 
-        if let Ok(Some(summary)) = self.writer.summarize_episode(&trigger.session_id).await {
-            if !summary.is_empty() {
-                context_docs.push(format!("Recent episode summary:\n{}", summary));
+    /// Keep only tool hints naming a capability registered on `broker` at build time;
+    /// phantom hints are dropped with a warning
+    pub fn with_broker(mut self, broker: Arc<ActionBroker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    /// Counter for `PromptBundle::token_breakdown` (default `HeuristicTokenCounter`)
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = counter;
+        self
+    }
+
+    /// Coalesce builds per session: a build waits `window` for newer calls, then only the
+    /// latest trigger is assembled and every waiting caller receives its bundle. A call that
+    /// arrives while a build is running cancels it and joins the next one. Cancelling or
+    /// dropping a caller only withdraws that caller: if it was building, the most recent
+    /// remaining caller builds for the group with its own trigger.
+    pub fn with_debounce(mut self, window: Duration) -> Self {
+        self.debounce = Some(Debouncer::new(window));
+        self
+    }
+
+    /// Cache up to `max_entries` bundles, reused while the memory version and trigger are unchanged.
+    /// Only effective when the reader reports a `version`. Bundles built after a retrieval
+    /// failure or timeout are not cached.
+    pub fn with_cache(mut self, max_entries: usize) -> Self {
+        self.cache = Some(BundleCache::new(max_entries));
+        self
+    }
+
+    /// Cache hit/miss counters (all zero when caching is disabled)
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.as_ref().map(|c| c.stats()).unwrap_or_default()
+    }
+
+    /// Build a prompt bundle, serving it from the cache when nothing changed
+    pub async fn build(&self, trigger: TriggerInput) -> Result<PromptBundle> {
+        self.build_cancellable(trigger, &CancellationToken::new())
+            .await
+    }
+
+    /// Like `build`, but abandons in-flight summarization/retrieval as soon as `cancel`
+    /// fires and returns `LoomError::Cancelled` (e.g. when the trigger is superseded)
+    pub async fn build_cancellable(
+        &self,
+        trigger: TriggerInput,
+        cancel: &CancellationToken,
+    ) -> Result<PromptBundle> {
+        if cancel.is_cancelled() {
+            return Err(LoomError::Cancelled);
+        }
+        match &self.debounce {
+            Some(debounce) => {
+                let session = trigger.session_id.clone();
+                debounce
+                    .run(&session, cancel, |token| {
+                        let trigger = trigger.clone();
+                        async move { self.build_now(trigger, &token).await }
+                    })
+                    .await
             }
+            None => self.build_now(trigger, cancel).await,
         }
+    }
 
-        let retrieved = self
-            .reader
-            .retrieve(trigger.goal.as_deref().unwrap_or(""), 4, None)
-            .await
-            .unwrap_or_default();
-        if !retrieved.is_empty() {
-            context_docs.push("Retrieved context:".to_string());
-            for r in retrieved {
-                context_docs.push(r);
+    /// Serve from the cache or assemble, without debouncing
+    async fn build_now(
+        &self,
+        mut trigger: TriggerInput,
+        cancel: &CancellationToken,
+    ) -> Result<PromptBundle> {
+        // Normalized before keying the cache so (un)registering a tool changes the key
+        trigger.tool_hints = self.normalize_tool_hints(&trigger.session_id, &trigger.tool_hints);
+        let cache_key = match (&self.cache, self.reader.version(&trigger.session_id)) {
+            (Some(cache), Some(version)) => {
+                let key = cache_key(version, self.tool_checksums(&trigger.tool_hints), &trigger);
+                if let Some(hit) = cache.get(&key) {
+                    debug!(target: "context_builder", session = %trigger.session_id, "Prompt bundle cache hit");
+                    return Ok(hit);
+                }
+                Some(key)
             }
+            _ => None,
+        };
+
+        let (bundle, degraded) = self.assemble(trigger, cancel).await?;
+        // A degraded bundle is served once; the next build tries retrieval again
+        if let (Some(cache), Some(key), false) = (&self.cache, cache_key, degraded) {
+            cache.put(key, bundle.clone());
         }
+        Ok(bundle)
+    }
+
+    /// Run the configured strategy on a normalized trigger, then the postprocessors; also
+    /// reports whether the build degraded
+    async fn assemble(
+        &self,
+        trigger: TriggerInput,
+        cancel: &CancellationToken,
+    ) -> Result<(PromptBundle, bool)> {
+        debug!(target: "context_builder", session = %trigger.session_id, "Building prompt bundle");
+
+        // Per-trigger prompt wins; blank overrides fall back rather than emptying the system message
+        let system_prompt = trigger
+            .system_prompt
+            .clone()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| self.system_prompt.clone());
+        let ctx = AssemblyContext {
+            retrieval_k: trigger.retrieval_k.unwrap_or(self.retrieval_k),
+            min_score: trigger.min_score.or(self.min_score),
+            trigger,
+            reader: Arc::clone(&self.reader) as Arc<dyn MemoryReader>,
+            writer: Arc::clone(&self.writer) as Arc<dyn MemoryWriter>,
+            system_prompt,
+            history_limit: self.history_limit,
+            roles: self.roles.clone(),
+            retrieval_policy: self.retrieval_policy.clone(),
+            retrieval_timeout: self.retrieval_timeout,
+            retrieval_token_budget: self.retrieval_token_budget,
+            token_counter: Arc::clone(&self.token_counter),
+            empty_goal: self.empty_goal,
+            cancel: cancel.clone(),
+            degraded: Arc::new(AtomicBool::new(false)),
+        };
+        let degraded = Arc::clone(&ctx.degraded);
+
+        let mut bundle = self.strategy.assemble(ctx).await?;
+        for postprocessor in &self.postprocessors {
+            postprocessor.process(&mut bundle)?;
+        }
+        bundle.token_breakdown = Some(bundle.count_tokens(self.token_counter.as_ref()));
+        Ok((bundle, degraded.load(Ordering::Relaxed)))
+    }
+
+    /// Sorted descriptor checksums of every registered version of the hinted tools, so a
+    /// provider changing its schema or version invalidates cached bundles (none without a broker)
+    fn tool_checksums(&self, hints: &[String]) -> Vec<u64> {
+        let Some(broker) = &self.broker else {
+            return Vec::new();
+        };
+        let mut checksums: Vec<u64> = broker
+            .list_capabilities_filtered(|d| hints.contains(&d.name))
+            .iter()
+            .map(CapabilityDescriptor::checksum)
+            .collect();
+        checksums.sort_unstable();
+        checksums
+    }
+
+    /// Trimmed, case-insensitively unique hints (first spelling wins), restricted to the
+    /// broker's capabilities when one is attached, capped at `max_tool_hints`
+    fn normalize_tool_hints(&self, session: &str, hints: &[String]) -> Vec<String> {
+        let mut seen = std::collections::HashSet::new();
+        let mut unique: Vec<String> = hints
+            .iter()
+            .map(|h| h.trim())
+            .filter(|h| !h.is_empty() && seen.insert(h.to_lowercase()))
+            .map(str::to_string)
+            .collect();
+        if let Some(broker) = &self.broker {
+            let registered: std::collections::HashSet<String> = broker
+                .list_capabilities()
+                .into_iter()
+                .map(|d| d.name)
+                .collect();
+            unique.retain(|hint| {
+                let known = registered.contains(hint);
+                if !known {
+                    warn!(target: "context_builder", session = %session, tool = %hint, "Dropping hint for unregistered tool");
+                }
+                known
+            });
+        }
+        if unique.len() > self.max_tool_hints {
+            warn!(target: "context_builder", session = %session, hints = unique.len(), max = self.max_tool_hints, "Too many tool hints; truncating");
+            unique.truncate(self.max_tool_hints);
+        }
+        unique
+    }
+}
+
+/// Everything a build depends on: the memory version, the hinted tools' descriptor
+/// checksums, plus every trigger field
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct BundleKey {
+    version: u64,
+    tools: Vec<u64>,
+    session_id: String,
+    goal: Option<String>,
+    tool_hints: Vec<String>,
+    max_input_tokens: usize,
+    max_output_tokens: usize,
+    system_prompt: Option<String>,
+    retrieval_k: Option<usize>,
+    min_score: Option<u32>,
+}
 
-        // Assemble prompt bundle; history is left empty at P0 (no dialog turns tracked yet)
-        Ok(PromptBundle {
-            system: "You are Loom Agent. Be concise and precise.".to_string(),
-            instructions: trigger.goal.unwrap_or_default(),
-            tools_json_schema: None,
-            context_docs,
-            history: vec![],
-        })
+fn cache_key(version: u64, tools: Vec<u64>, trigger: &TriggerInput) -> BundleKey {
+    BundleKey {
+        version,
+        tools,
+        session_id: trigger.session_id.clone(),
+        goal: trigger.goal.clone(),
+        tool_hints: trigger.tool_hints.clone(),
+        max_input_tokens: trigger.budget.max_input_tokens,
+        max_output_tokens: trigger.budget.max_output_tokens,
+        system_prompt: trigger.system_prompt.clone(),
+        retrieval_k: trigger.retrieval_k,
+        min_score: trigger.min_score.map(f32::to_bits),
     }
 }
@@ -0,0 +1,119 @@
+//! Small LRU cache, used for assembled prompt bundles and embeddings.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::builder::BundleKey;
+use super::PromptBundle;
+
+/// Hit/miss counters for the ContextBuilder bundle cache and `CachingEmbedder`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+struct Inner<K, V> {
+    // value and the generation of its latest use
+    map: HashMap<K, (V, u64)>,
+    // uses in order, front = oldest. A key's earlier entries go stale when it is used
+    // again; they are skipped on eviction and dropped when the queue is compacted.
+    order: VecDeque<(K, u64)>,
+    next_gen: u64,
+}
+
+/// LRU keyed by (memory version, tools, trigger)
+pub(crate) type BundleCache = LruCache<BundleKey, PromptBundle>;
+
+/// LRU of cloneable values. Keys are compared in full, so distinct keys never share an entry.
+/// Hits and inserts are O(1) amortized.
+pub(crate) struct LruCache<K, V> {
+    max_entries: usize,
+    inner: Mutex<Inner<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            inner: Mutex::new(Inner {
+                map: HashMap::new(),
+                order: VecDeque::new(),
+                next_gen: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let gen = inner.next_gen;
+        match inner.map.get_mut(key) {
+            Some((value, used)) => {
+                let value = value.clone();
+                *used = gen;
+                let (k, _) = inner.map.get_key_value(key).unwrap();
+                let k = k.clone();
+                inner.record_use(k, self.max_entries);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn put(&self, key: K, value: V) {
+        let mut inner = self.inner.lock().unwrap();
+        let gen = inner.next_gen;
+        inner.map.insert(key.clone(), (value, gen));
+        inner.record_use(key, self.max_entries);
+        while inner.map.len() > self.max_entries {
+            let Some((old, used)) = inner.order.pop_front() else {
+                break;
+            };
+            if inner.map.get(&old).is_some_and(|(_, g)| *g == used) {
+                inner.map.remove(&old);
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.inner.lock().unwrap().map.len(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Inner<K, V> {
+    /// Queue a use stamped with the current generation, which the caller has already
+    /// stored on the entry
+    fn record_use(&mut self, key: K, max_entries: usize) {
+        self.order.push_back((key, self.next_gen));
+        self.next_gen += 1;
+        // Stale entries are bounded by compacting once the queue outgrows the map
+        if self.order.len() > 2 * max_entries.max(self.map.len()) {
+            let map = &self.map;
+            self.order
+                .retain(|(k, used)| map.get(k).is_some_and(|(_, g)| g == used));
+        }
+    }
+}
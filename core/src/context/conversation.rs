@@ -0,0 +1,191 @@
+//! Typed multi-turn conversations stored as ordinary memory events.
+//!
+//! Each turn is one event from source `conversation`: payload = content (UTF-8), metadata
+//! `conversation_role` (and `tool_name` for tool turns). Event types follow the default
+//! `RoleMapping` rules (`user.message`, `assistant.message`, `tool.result`, `system.message`),
+//! so `ContextBuilder` history shows the right roles without extra mapping.
+
+use super::{HistoryEntry, MemoryReader, MemoryWriter, PromptBundle, Role};
+use crate::event::EventExt;
+use crate::proto::Event;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+
+/// Source of events written by `Conversation`
+pub const CONVERSATION_SOURCE: &str = "conversation";
+/// Metadata key holding the turn's role
+pub const CONVERSATION_ROLE_KEY: &str = "conversation_role";
+/// Metadata key holding the tool name of a tool turn
+pub const TOOL_NAME_KEY: &str = "tool_name";
+
+/// One conversation turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub role: Role,
+    pub content: String,
+    /// Tool that produced a `Role::Tool` turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    /// 0 until the turn becomes an event, which stamps the current time
+    #[serde(default)]
+    pub timestamp_ms: i64,
+    /// Event id; empty until the turn is stored
+    #[serde(default)]
+    pub id: String,
+}
+
+impl Turn {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_name: None,
+            timestamp_ms: 0,
+            id: String::new(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    pub fn tool(tool_name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_name: Some(tool_name.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    /// The memory event for this turn; a missing id or timestamp is filled in
+    pub fn to_event(&self) -> Event {
+        let event_type = match self.role {
+            Role::User => "user.message",
+            Role::Assistant => "assistant.message",
+            Role::Tool => "tool.result",
+            Role::System => "system.message",
+        };
+        let mut builder = Event::builder(event_type)
+            .source(CONVERSATION_SOURCE)
+            .payload(self.content.as_bytes().to_vec())
+            .metadata(CONVERSATION_ROLE_KEY, self.role.as_str());
+        if !self.id.is_empty() {
+            builder = builder.id(self.id.clone());
+        }
+        if self.timestamp_ms != 0 {
+            builder = builder.timestamp_ms(self.timestamp_ms);
+        }
+        if let Some(name) = &self.tool_name {
+            builder = builder.metadata(TOOL_NAME_KEY, name.clone());
+        }
+        builder.build()
+    }
+
+    /// Decode a turn event; `None` for events not written by `Conversation`
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let role = match event.metadata.get(CONVERSATION_ROLE_KEY)?.as_str() {
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "tool" => Role::Tool,
+            "system" => Role::System,
+            _ => return None,
+        };
+        Some(Self {
+            role,
+            content: String::from_utf8_lossy(&event.payload).into_owned(),
+            tool_name: event.metadata.get(TOOL_NAME_KEY).cloned(),
+            timestamp_ms: event.timestamp_ms,
+            id: event.id.clone(),
+        })
+    }
+
+    pub fn to_history(&self) -> HistoryEntry {
+        HistoryEntry {
+            role: self.role,
+            content: self.content.clone(),
+            timestamp_ms: self.timestamp_ms,
+            event_id: self.id.clone(),
+        }
+    }
+}
+
+/// An ordered chat transcript for one session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub session_id: String,
+    pub turns: Vec<Turn>,
+}
+
+impl Conversation {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self {
+            session_id: session_id.into(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// Rebuild from the last `limit` events of the session; non-conversation events are skipped
+    pub async fn load<R: MemoryReader + ?Sized>(
+        reader: &R,
+        session_id: impl Into<String>,
+        limit: usize,
+    ) -> Result<Self> {
+        let session_id = session_id.into();
+        let turns = reader
+            .recent_events(&session_id, limit)
+            .await?
+            .iter()
+            .filter_map(Turn::from_event)
+            .collect();
+        Ok(Self { session_id, turns })
+    }
+
+    /// Add a turn in memory only
+    pub fn push(&mut self, turn: Turn) {
+        self.turns.push(turn);
+    }
+
+    /// Persist `turn` through `writer` and add it; the stored event's id and timestamp are
+    /// kept on the turn
+    pub async fn append<W: MemoryWriter + ?Sized>(
+        &mut self,
+        writer: &W,
+        mut turn: Turn,
+    ) -> Result<()> {
+        let event = turn.to_event();
+        turn.id = event.id.clone();
+        turn.timestamp_ms = event.timestamp_ms;
+        writer.append_event(&self.session_id, event).await?;
+        self.turns.push(turn);
+        Ok(())
+    }
+
+    pub fn last(&self) -> Option<&Turn> {
+        self.turns.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Turns as `PromptBundle` history, oldest first
+    pub fn to_history(&self) -> Vec<HistoryEntry> {
+        self.turns.iter().map(Turn::to_history).collect()
+    }
+
+    /// Replace `bundle.history` with this conversation
+    pub fn render_into(&self, bundle: &mut PromptBundle) {
+        bundle.history = self.to_history();
+    }
+}
@@ -0,0 +1,217 @@
+//! Per-session coalescing of rapid `ContextBuilder::build` calls.
+//!
+//! Each call joins its session's pending group and restarts the quiet window. When the window
+//! passes without a newer call, the latest caller builds once and every caller in the group
+//! receives that bundle. A call arriving while a build is in flight cancels it and carries its
+//! callers over to the next build.
+//!
+//! A caller that is cancelled or dropped only leaves its group. If it was the one building,
+//! the most recent caller still waiting takes over and builds with its own trigger; the group's
+//! build is abandoned only once every caller has gone.
+
+use super::PromptBundle;
+use crate::{CancellationToken, LoomError, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+
+struct Waiter {
+    generation: u64,
+    reply: oneshot::Sender<Result<PromptBundle>>,
+    /// Woken when this caller becomes the group's latest after another one left
+    promote: Arc<Notify>,
+}
+
+struct InFlight {
+    generation: u64,
+    cancel: CancellationToken,
+    waiters: Vec<Waiter>,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Generation of the latest caller still waiting; that caller builds for the group
+    generation: u64,
+    /// Callers waiting for the next build
+    pending: Vec<Waiter>,
+    in_flight: Option<InFlight>,
+}
+
+pub(crate) struct Debouncer {
+    window: Duration,
+    slots: Mutex<HashMap<String, Slot>>,
+    /// Unique across sessions and slot removal, so a stale call never matches a new slot
+    generations: AtomicU64,
+}
+
+impl Debouncer {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            slots: Mutex::new(HashMap::new()),
+            generations: AtomicU64::new(1),
+        }
+    }
+
+    /// Join `session`'s group; while this call is the latest once the window passes, run
+    /// `build` (with a token cancelled by a newer call) for the whole group. `cancel` only
+    /// withdraws this caller.
+    pub(crate) async fn run<F, Fut>(
+        &self,
+        session: &str,
+        cancel: &CancellationToken,
+        build: F,
+    ) -> Result<PromptBundle>
+    where
+        F: Fn(CancellationToken) -> Fut,
+        Fut: Future<Output = Result<PromptBundle>>,
+    {
+        let (tx, mut rx) = oneshot::channel();
+        let promote = Arc::new(Notify::new());
+        let generation = {
+            let mut slots = self.slots.lock().unwrap();
+            let slot = slots.entry(session.to_string()).or_default();
+            slot.generation = self.generations.fetch_add(1, Ordering::Relaxed);
+            slot.pending.push(Waiter {
+                generation: slot.generation,
+                reply: tx,
+                promote: Arc::clone(&promote),
+            });
+            if let Some(superseded) = slot.in_flight.take() {
+                superseded.cancel.cancel();
+                slot.pending.extend(superseded.waiters);
+            }
+            slot.generation
+        };
+        // Takes this caller out of its group however the future ends
+        let _guard = Guard {
+            debouncer: self,
+            session,
+            generation,
+        };
+
+        let window = tokio::time::sleep(self.window);
+        tokio::pin!(window);
+        let mut window_passed = false;
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(LoomError::Cancelled),
+                reply = &mut rx => return reply.unwrap_or(Err(LoomError::Cancelled)),
+                _ = &mut window, if !window_passed => window_passed = true,
+                _ = promote.notified() => {}
+            }
+            if !window_passed {
+                continue;
+            }
+            let Some(token) = self.claim(session, generation) else {
+                continue;
+            };
+            let result = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(LoomError::Cancelled),
+                result = build(token) => result,
+            };
+            // Superseded builds reply to nobody; their callers wait for the newer build
+            for waiter in self.finish(session, generation) {
+                let _ = waiter.reply.send(match &result {
+                    Ok(bundle) => Ok(bundle.clone()),
+                    Err(e) => Err(duplicate(e)),
+                });
+            }
+        }
+    }
+
+    /// Start the group's build if `generation` is its latest caller and none is running
+    fn claim(&self, session: &str, generation: u64) -> Option<CancellationToken> {
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots.get_mut(session)?;
+        if slot.generation != generation || slot.in_flight.is_some() {
+            return None;
+        }
+        let cancel = CancellationToken::new();
+        slot.in_flight = Some(InFlight {
+            generation,
+            cancel: cancel.clone(),
+            waiters: std::mem::take(&mut slot.pending),
+        });
+        Some(cancel)
+    }
+
+    /// Waiters of `generation`'s build, unless a newer call superseded it
+    fn finish(&self, session: &str, generation: u64) -> Vec<Waiter> {
+        let mut slots = self.slots.lock().unwrap();
+        let Some(slot) = slots.get_mut(session) else {
+            return Vec::new();
+        };
+        match slot.in_flight.take() {
+            Some(own) if own.generation == generation => own.waiters,
+            other => {
+                slot.in_flight = other;
+                Vec::new()
+            }
+        }
+    }
+}
+
+struct Guard<'a> {
+    debouncer: &'a Debouncer,
+    session: &'a str,
+    generation: u64,
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        let mut slots = self.debouncer.slots.lock().unwrap();
+        let Some(slot) = slots.get_mut(self.session) else {
+            return;
+        };
+        slot.pending.retain(|w| w.generation != self.generation);
+        if let Some(in_flight) = slot.in_flight.as_mut() {
+            in_flight
+                .waiters
+                .retain(|w| w.generation != self.generation);
+        }
+        // This caller's build was abandoned with it; its group waits for the next one
+        if slot
+            .in_flight
+            .as_ref()
+            .is_some_and(|f| f.generation == self.generation)
+        {
+            let abandoned = slot.in_flight.take().unwrap();
+            abandoned.cancel.cancel();
+            slot.pending.extend(abandoned.waiters);
+        }
+        // Hand the build to the most recent caller still waiting
+        if slot.generation == self.generation {
+            if let Some(next) = slot.pending.iter().max_by_key(|w| w.generation) {
+                slot.generation = next.generation;
+                next.promote.notify_one();
+            }
+        }
+        if slot.pending.is_empty() && slot.in_flight.is_none() {
+            slots.remove(self.session);
+        }
+    }
+}
+
+/// `LoomError` is not `Clone`; every caller in a group gets an equivalent error
+fn duplicate(e: &LoomError) -> LoomError {
+    match e {
+        LoomError::EventBusError(m) => LoomError::EventBusError(m.clone()),
+        LoomError::AgentError(m) => LoomError::AgentError(m.clone()),
+        LoomError::RouterError(m) => LoomError::RouterError(m.clone()),
+        LoomError::PluginError(m) => LoomError::PluginError(m.clone()),
+        LoomError::StorageError(m) => LoomError::StorageError(m.clone()),
+        LoomError::Memory(m) => LoomError::Memory(m.clone()),
+        LoomError::Timeout(m) => LoomError::Timeout(m.clone()),
+        LoomError::Cancelled => LoomError::Cancelled,
+        LoomError::IoError(io) => {
+            LoomError::IoError(std::io::Error::new(io.kind(), io.to_string()))
+        }
+        LoomError::Serialization(_) => LoomError::Memory(e.to_string()),
+    }
+}
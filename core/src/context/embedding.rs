@@ -0,0 +1,249 @@
+//! Text embeddings and a brute-force cosine index over them.
+//!
+//! The index can be saved to and memory-mapped back from disk (see `vector_file`), so
+//! restarts skip re-embedding. Loaded vectors are read from the mapping without copying.
+
+use super::cache::{CacheStats, LruCache};
+use super::vector_file::MappedVectors;
+use crate::{LoomError, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Turns text into a dense vector; every vector from one embedder has the same length
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embed several texts in order. The default calls `embed` once per text; remote
+    /// embedders should override it to batch requests.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut out = Vec::with_capacity(texts.len());
+        for text in texts {
+            out.push(self.embed(text).await?);
+        }
+        Ok(out)
+    }
+}
+
+/// Wraps an `Embedder` with an LRU of vectors keyed on the text itself, so repeated
+/// queries and documents are embedded once
+pub struct CachingEmbedder {
+    inner: Arc<dyn Embedder>,
+    cache: LruCache<String, Vec<f32>>,
+}
+
+impl CachingEmbedder {
+    /// Keep up to `capacity` vectors (at least 1)
+    pub fn new(inner: Arc<dyn Embedder>, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: LruCache::new(capacity),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn Embedder> {
+        &self.inner
+    }
+
+    /// Hits, misses and cached vectors; `embed_batch` counts one lookup per text
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+}
+
+#[async_trait]
+impl Embedder for CachingEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if let Some(vector) = self.cache.get(text) {
+            return Ok(vector);
+        }
+        let vector = self.inner.embed(text).await?;
+        self.cache.put(text.to_string(), vector.clone());
+        Ok(vector)
+    }
+
+    /// Serves cached texts and sends only the misses to the inner embedder, in one batch
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut out: Vec<Option<Vec<f32>>> = Vec::with_capacity(texts.len());
+        let mut missing = Vec::new();
+        for (i, text) in texts.iter().enumerate() {
+            let cached = self.cache.get(text.as_str());
+            if cached.is_none() {
+                missing.push(i);
+            }
+            out.push(cached);
+        }
+        if !missing.is_empty() {
+            let batch: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let vectors = self.inner.embed_batch(&batch).await?;
+            if vectors.len() != batch.len() {
+                return Err(LoomError::Memory(format!(
+                    "embedder returned {} vectors for {} texts",
+                    vectors.len(),
+                    batch.len()
+                )));
+            }
+            for (i, vector) in missing.into_iter().zip(vectors) {
+                self.cache.put(texts[i].clone(), vector.clone());
+                out[i] = Some(vector);
+            }
+        }
+        Ok(out.into_iter().flatten().collect())
+    }
+}
+
+/// Cosine of the angle between `a` and `b`; 0 for mismatched lengths or zero vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut na, mut nb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na.sqrt() * nb.sqrt())
+    }
+}
+
+/// One stored vector: inserted in memory, or a row of a file mapped by `load`
+pub(super) enum StoredVector {
+    Owned(Vec<f32>),
+    Mapped(Arc<MappedVectors>, usize),
+}
+
+impl StoredVector {
+    fn as_slice(&self) -> &[f32] {
+        match self {
+            StoredVector::Owned(v) => v,
+            StoredVector::Mapped(file, row) => file.row(*row),
+        }
+    }
+}
+
+/// Document vectors keyed by id, searched by exhaustive cosine similarity
+pub struct EmbeddingIndex {
+    embedder: Arc<dyn Embedder>,
+    vectors: RwLock<HashMap<String, StoredVector>>,
+    dimensions: RwLock<Option<usize>>,
+}
+
+impl EmbeddingIndex {
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        Self {
+            embedder,
+            vectors: RwLock::new(HashMap::new()),
+            dimensions: RwLock::new(None),
+        }
+    }
+
+    /// Fix the vector length up front, so inserts and `load` reject other lengths
+    pub fn with_dimensions(self, dimensions: usize) -> Self {
+        *self.dimensions.write().unwrap() = Some(dimensions);
+        self
+    }
+
+    pub fn embedder(&self) -> &Arc<dyn Embedder> {
+        &self.embedder
+    }
+
+    /// Vector length, fixed by the first insert
+    pub fn dimensions(&self) -> Option<usize> {
+        *self.dimensions.read().unwrap()
+    }
+
+    /// Embed `text` and store it under `id`, replacing any previous vector
+    pub async fn add(&self, id: impl Into<String>, text: &str) -> Result<()> {
+        let vector = self.embedder.embed(text).await?;
+        self.insert(id, vector)
+    }
+
+    /// Store a precomputed vector; its length must match earlier vectors
+    pub fn insert(&self, id: impl Into<String>, vector: Vec<f32>) -> Result<()> {
+        let mut dims = self.dimensions.write().unwrap();
+        match *dims {
+            Some(d) if d != vector.len() => {
+                return Err(LoomError::Memory(format!(
+                    "embedding has {} dimensions, index expects {}",
+                    vector.len(),
+                    d
+                )))
+            }
+            Some(_) => {}
+            None => *dims = Some(vector.len()),
+        }
+        self.vectors
+            .write()
+            .unwrap()
+            .insert(id.into(), StoredVector::Owned(vector));
+        Ok(())
+    }
+
+    /// Insert vectors of length `dims` under one lock (used by `load`)
+    pub(super) fn insert_all(
+        &self,
+        dims: usize,
+        entries: impl Iterator<Item = (String, StoredVector)>,
+    ) -> Result<()> {
+        let mut current = self.dimensions.write().unwrap();
+        if current.is_some_and(|d| d != dims) {
+            return Err(LoomError::Memory(format!(
+                "embedding has {} dimensions, index expects {}",
+                dims,
+                current.unwrap_or_default()
+            )));
+        }
+        *current = Some(dims);
+        self.vectors.write().unwrap().extend(entries);
+        Ok(())
+    }
+
+    /// All `(id, vector)` pairs, sorted by id
+    pub(super) fn snapshot(&self) -> Vec<(String, Vec<f32>)> {
+        let mut entries: Vec<(String, Vec<f32>)> = self
+            .vectors
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, v)| (id.clone(), v.as_slice().to_vec()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.vectors.write().unwrap().remove(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cosine similarity of `query` to every stored vector
+    pub fn scores(&self, query: &[f32]) -> HashMap<String, f32> {
+        self.vectors
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, v)| (id.clone(), cosine_similarity(query, v.as_slice())))
+            .collect()
+    }
+
+    /// The `k` ids most similar to `text`, best first
+    pub async fn search(&self, text: &str, k: usize) -> Result<Vec<(String, f32)>> {
+        let query = self.embedder.embed(text).await?;
+        let mut hits: Vec<(String, f32)> = self.scores(&query).into_iter().collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hits.truncate(k);
+        Ok(hits)
+    }
+}
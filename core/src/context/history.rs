@@ -0,0 +1,131 @@
+//! Structured dialog history: roles, entries and the event -> role mapping.
+
+use crate::proto::Event;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Speaker of a history turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+    Tool,
+    System,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::System => "system",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One dialog turn derived from a memory event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub role: Role,
+    pub content: String,
+    pub timestamp_ms: i64,
+    pub event_id: String,
+}
+
+impl HistoryEntry {
+    /// Build an entry from `event`; content is the UTF-8 payload, or the event type if empty
+    pub fn from_event(event: &Event, role: Role) -> Self {
+        let content = if event.payload.is_empty() {
+            event.r#type.clone()
+        } else {
+            String::from_utf8_lossy(&event.payload).into_owned()
+        };
+        Self {
+            role,
+            content,
+            timestamp_ms: event.timestamp_ms,
+            event_id: event.id.clone(),
+        }
+    }
+}
+
+/// Maps event `source`/`type` to a history role.
+///
+/// Lookup order: exact source, exact type, type prefix (longest wins), then built-in rules:
+/// `tool.*`/`action_*` -> tool, `system*` -> system, `llm*`/`assistant*` (type or source) ->
+/// assistant, everything else -> user.
+#[derive(Debug, Clone, Default)]
+pub struct RoleMapping {
+    by_source: HashMap<String, Role>,
+    by_type: HashMap<String, Role>,
+    by_type_prefix: Vec<(String, Role)>,
+}
+
+impl RoleMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events from `source` (exact match) get `role`
+    pub fn map_source(mut self, source: impl Into<String>, role: Role) -> Self {
+        self.by_source.insert(source.into(), role);
+        self
+    }
+
+    /// Events of `event_type` (exact match) get `role`
+    pub fn map_type(mut self, event_type: impl Into<String>, role: Role) -> Self {
+        self.by_type.insert(event_type.into(), role);
+        self
+    }
+
+    /// Events whose type starts with `prefix` get `role`
+    pub fn map_type_prefix(mut self, prefix: impl Into<String>, role: Role) -> Self {
+        self.by_type_prefix.push((prefix.into(), role));
+        self
+    }
+
+    pub fn role_for(&self, event: &Event) -> Role {
+        if let Some(role) = self.by_source.get(&event.source) {
+            return *role;
+        }
+        if let Some(role) = self.by_type.get(&event.r#type) {
+            return *role;
+        }
+        if let Some((_, role)) = self
+            .by_type_prefix
+            .iter()
+            .filter(|(p, _)| event.r#type.starts_with(p.as_str()))
+            .max_by_key(|(p, _)| p.len())
+        {
+            return *role;
+        }
+        Self::default_role(event)
+    }
+
+    fn default_role(event: &Event) -> Role {
+        let ty = event.r#type.as_str();
+        let src = event.source.as_str();
+        if ty.starts_with("tool") || ty.starts_with("action_") {
+            Role::Tool
+        } else if ty.starts_with("system") || src.starts_with("system") {
+            Role::System
+        } else if ty.starts_with("llm")
+            || ty.starts_with("assistant")
+            || src.starts_with("llm")
+            || src.starts_with("assistant")
+        {
+            Role::Assistant
+        } else {
+            Role::User
+        }
+    }
+}
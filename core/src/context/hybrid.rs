@@ -0,0 +1,149 @@
+//! Hybrid retrieval: BM25 keyword scores fused with embedding similarity.
+//!
+//! For each query the keyword scores are divided by the best keyword score, so both signals
+//! lie in [0, 1], then `score = alpha * cosine + (1 - alpha) * keyword`. `alpha = 1` is pure
+//! vector search, `alpha = 0` pure keyword search.
+
+use super::embedding::{Embedder, EmbeddingIndex};
+use super::keyword::KeywordIndex;
+use super::{ContextDoc, MemoryReader};
+use crate::{LoomError, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Default weight of the vector score
+pub const DEFAULT_HYBRID_ALPHA: f32 = 0.5;
+
+/// A document store searched by a keyword index and an embedding index at once
+pub struct HybridRetriever {
+    keywords: KeywordIndex,
+    vectors: EmbeddingIndex,
+    texts: RwLock<HashMap<String, String>>,
+    alpha: RwLock<f32>,
+    version: AtomicU64,
+}
+
+impl HybridRetriever {
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        Self {
+            keywords: KeywordIndex::new(),
+            vectors: EmbeddingIndex::new(embedder),
+            texts: RwLock::new(HashMap::new()),
+            alpha: RwLock::new(DEFAULT_HYBRID_ALPHA),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    /// Weight of the vector score, clamped to [0, 1]
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        self.set_alpha(alpha);
+        self
+    }
+
+    pub fn set_alpha(&self, alpha: f32) {
+        *self.alpha.write().unwrap() = alpha.clamp(0.0, 1.0);
+    }
+
+    pub fn alpha(&self) -> f32 {
+        *self.alpha.read().unwrap()
+    }
+
+    /// Index `text` under `id` in both indexes, replacing any previous document
+    pub async fn add_document(&self, id: impl Into<String>, text: impl Into<String>) -> Result<()> {
+        let (id, text) = (id.into(), text.into());
+        self.vectors.add(id.clone(), &text).await?;
+        self.keywords.add(id.clone(), &text);
+        self.texts.write().unwrap().insert(id, text);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn remove_document(&self, id: &str) -> bool {
+        self.keywords.remove(id);
+        self.vectors.remove(id);
+        let removed = self.texts.write().unwrap().remove(id).is_some();
+        if removed {
+            self.version.fetch_add(1, Ordering::SeqCst);
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.texts.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn keyword_index(&self) -> &KeywordIndex {
+        &self.keywords
+    }
+
+    pub fn embedding_index(&self) -> &EmbeddingIndex {
+        &self.vectors
+    }
+
+    /// The `k` best documents for `query` by fused score; `source_id` is the document id
+    pub async fn search(&self, query: &str, k: usize) -> Result<Vec<ContextDoc>> {
+        let alpha = self.alpha();
+        let query_vec = self.vectors.embedder().embed(query).await?;
+        let semantic = self.vectors.scores(&query_vec);
+        let lexical = self.keywords.scores(query);
+        let best_lexical = lexical.values().copied().fold(0.0f32, f32::max);
+
+        let texts = self.texts.read().unwrap();
+        let mut hits: Vec<(&String, f32)> = texts
+            .keys()
+            .map(|id| {
+                let cos = semantic.get(id).copied().unwrap_or(0.0).max(0.0);
+                let kw = match lexical.get(id) {
+                    Some(s) if best_lexical > 0.0 => s / best_lexical,
+                    _ => 0.0,
+                };
+                (id, alpha * cos + (1.0 - alpha) * kw)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        hits.truncate(k);
+        Ok(hits
+            .into_iter()
+            .map(|(id, score)| ContextDoc::new(texts[id].clone(), score, Some(id.clone())))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MemoryReader for HybridRetriever {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        let docs = self.retrieve_scored(query, k, filters).await?;
+        Ok(docs.into_iter().map(|d| d.text).collect())
+    }
+
+    /// Filters are not supported and are rejected rather than ignored
+    async fn retrieve_scored(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<ContextDoc>> {
+        if filters.is_some() {
+            return Err(LoomError::Memory(
+                "HybridRetriever does not support retrieval filters".into(),
+            ));
+        }
+        self.search(query, k).await
+    }
+
+    fn version(&self, _session: &str) -> Option<u64> {
+        Some(self.version.load(Ordering::SeqCst))
+    }
+}
@@ -0,0 +1,162 @@
+//! Secondary indexes over stored events (type / tag / source) for filtered retrieval.
+
+use crate::proto::Event;
+use crate::{LoomError, Result};
+use dashmap::DashMap;
+use std::collections::{BTreeSet, HashMap};
+
+/// Equality filter over indexed event fields; all set predicates must match (AND).
+///
+/// JSON form accepted by `retrieve(.., filters)`:
+/// `{"type": "intent", "source": "mic.primary", "tags": ["a", "b"]}` (`"tag": "a"` also works).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub event_type: Option<String>,
+    pub source: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl EventFilter {
+    /// Parse a retrieval filter; unknown keys are rejected rather than silently ignored
+    pub fn from_json(value: &serde_json::Value) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| LoomError::Memory("retrieval filters must be a JSON object".into()))?;
+        let mut filter = Self::default();
+        for (key, v) in obj {
+            let as_str = |v: &serde_json::Value| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| LoomError::Memory(format!("filter '{}' must be a string", key)))
+            };
+            match key.as_str() {
+                "type" => filter.event_type = Some(as_str(v)?),
+                "source" => filter.source = Some(as_str(v)?),
+                "tag" => filter.tags.push(as_str(v)?),
+                "tags" => {
+                    let list = v.as_array().ok_or_else(|| {
+                        LoomError::Memory("filter 'tags' must be an array of strings".into())
+                    })?;
+                    for t in list {
+                        filter.tags.push(as_str(t)?);
+                    }
+                }
+                other => {
+                    return Err(LoomError::Memory(format!(
+                        "unsupported retrieval filter '{}'",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.event_type.is_none() && self.source.is_none() && self.tags.is_empty()
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        self.event_type.as_ref().is_none_or(|t| &event.r#type == t)
+            && self.source.as_ref().is_none_or(|s| &event.source == s)
+            && self.tags.iter().all(|t| event.tags.contains(t))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IndexKey {
+    Type(String),
+    Source(String),
+    Tag(String),
+}
+
+/// (session, per-session sequence number) of a stored event
+pub(crate) type EventRef = (String, u64);
+
+/// Inverted indexes from field values to event refs, kept in sync by the store
+#[derive(Default)]
+pub(crate) struct EventIndex {
+    postings: DashMap<IndexKey, BTreeSet<EventRef>>,
+}
+
+impl EventIndex {
+    fn keys(event: &Event) -> impl Iterator<Item = IndexKey> + '_ {
+        [
+            IndexKey::Type(event.r#type.clone()),
+            IndexKey::Source(event.source.clone()),
+        ]
+        .into_iter()
+        .chain(event.tags.iter().cloned().map(IndexKey::Tag))
+    }
+
+    pub(crate) fn insert(&self, session: &str, seq: u64, event: &Event) {
+        for key in Self::keys(event) {
+            self.postings
+                .entry(key)
+                .or_default()
+                .insert((session.to_string(), seq));
+        }
+    }
+
+    /// Insert many events of one session, touching each posting list once
+    pub(crate) fn insert_batch<'a, I>(&self, session: &str, events: I)
+    where
+        I: IntoIterator<Item = (u64, &'a Event)>,
+    {
+        let mut grouped: HashMap<IndexKey, Vec<u64>> = HashMap::new();
+        for (seq, event) in events {
+            for key in Self::keys(event) {
+                grouped.entry(key).or_default().push(seq);
+            }
+        }
+        for (key, seqs) in grouped {
+            let mut postings = self.postings.entry(key).or_default();
+            postings.extend(seqs.into_iter().map(|seq| (session.to_string(), seq)));
+        }
+    }
+
+    /// Drop an event's postings (on eviction); empty posting lists are removed
+    pub(crate) fn remove(&self, session: &str, seq: u64, event: &Event) {
+        let r = (session.to_string(), seq);
+        for key in Self::keys(event) {
+            self.postings.remove_if_mut(&key, |_, set| {
+                set.remove(&r);
+                set.is_empty()
+            });
+        }
+    }
+
+    /// Refs matching every predicate of `filter`, ordered by (session, seq).
+    /// Starts from the smallest posting list so cost tracks the most selective predicate.
+    pub(crate) fn candidates(&self, filter: &EventFilter) -> Vec<EventRef> {
+        let mut keys: Vec<IndexKey> = filter.tags.iter().cloned().map(IndexKey::Tag).collect();
+        keys.extend(filter.event_type.clone().map(IndexKey::Type));
+        keys.extend(filter.source.clone().map(IndexKey::Source));
+
+        // Pick the most selective key; only its posting list is cloned
+        let mut smallest: Option<(usize, usize)> = None;
+        for (i, key) in keys.iter().enumerate() {
+            let len = self.postings.get(key).map_or(0, |s| s.len());
+            if len == 0 {
+                return Vec::new();
+            }
+            if smallest.is_none_or(|(_, l)| len < l) {
+                smallest = Some((i, len));
+            }
+        }
+        let Some((first, _)) = smallest else {
+            return Vec::new();
+        };
+        let base = match self.postings.get(&keys[first]) {
+            Some(set) => set.clone(),
+            None => return Vec::new(),
+        };
+        base.into_iter()
+            .filter(|r| {
+                keys.iter().enumerate().all(|(i, key)| {
+                    i == first || self.postings.get(key).is_some_and(|s| s.contains(r))
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,80 @@
+//! Content-addressed payload sharing for `InMemoryMemory`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Weak};
+
+/// Payload storage counters; see `InMemoryMemory::payload_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadStats {
+    /// Interned payloads held by stored events
+    pub payloads: u64,
+    /// Bytes those payloads would take if stored separately
+    pub logical_bytes: u64,
+    /// Bytes actually held after deduplication
+    pub unique_bytes: u64,
+}
+
+impl PayloadStats {
+    /// `logical_bytes / unique_bytes` (1.0 = no sharing, or nothing stored)
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.unique_bytes == 0 {
+            1.0
+        } else {
+            self.logical_bytes as f64 / self.unique_bytes as f64
+        }
+    }
+}
+
+/// Hash -> live payloads with that hash. Entries are weak, so a payload is freed once the
+/// last event holding it is evicted; bytes are compared on lookup, so collisions are harmless.
+#[derive(Default)]
+pub(crate) struct PayloadInterner {
+    by_hash: HashMap<u64, Vec<Weak<[u8]>>>,
+    payloads: u64,
+    logical_bytes: u64,
+}
+
+impl PayloadInterner {
+    pub(crate) fn intern(&mut self, bytes: Vec<u8>) -> Arc<[u8]> {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let bucket = self.by_hash.entry(hasher.finish()).or_default();
+        bucket.retain(|w| w.strong_count() > 0);
+
+        self.payloads += 1;
+        self.logical_bytes += bytes.len() as u64;
+        if let Some(shared) = bucket
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|p| **p == *bytes)
+        {
+            return shared;
+        }
+        let shared: Arc<[u8]> = Arc::from(bytes);
+        bucket.push(Arc::downgrade(&shared));
+        shared
+    }
+
+    /// Account for an evicted event's payload
+    pub(crate) fn release(&mut self, payload: &Arc<[u8]>) {
+        self.payloads = self.payloads.saturating_sub(1);
+        self.logical_bytes = self.logical_bytes.saturating_sub(payload.len() as u64);
+    }
+
+    pub(crate) fn stats(&self) -> PayloadStats {
+        let unique_bytes = self
+            .by_hash
+            .values()
+            .flatten()
+            .filter_map(Weak::upgrade)
+            .map(|p| p.len() as u64)
+            .sum();
+        PayloadStats {
+            payloads: self.payloads,
+            logical_bytes: self.logical_bytes,
+            unique_bytes,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! BM25 keyword index.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Term-frequency saturation
+const K1: f32 = 1.2;
+/// Document-length normalization
+const B: f32 = 0.75;
+
+/// Lowercased alphanumeric runs of `text`
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[derive(Default)]
+struct Postings {
+    /// id -> (term -> count)
+    docs: HashMap<String, HashMap<String, u32>>,
+    /// term -> number of docs containing it
+    doc_freq: HashMap<String, u32>,
+    total_len: u64,
+}
+
+impl Postings {
+    fn remove(&mut self, id: &str) -> bool {
+        let Some(terms) = self.docs.remove(id) else {
+            return false;
+        };
+        for (term, count) in terms {
+            self.total_len -= u64::from(count);
+            if let Some(df) = self.doc_freq.get_mut(&term) {
+                *df -= 1;
+                if *df == 0 {
+                    self.doc_freq.remove(&term);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Inverted term counts scored with Okapi BM25 (`k1 = 1.2`, `b = 0.75`)
+#[derive(Default)]
+pub struct KeywordIndex {
+    postings: RwLock<Postings>,
+}
+
+impl KeywordIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index `text` under `id`, replacing any previous text
+    pub fn add(&self, id: impl Into<String>, text: &str) {
+        let id = id.into();
+        let mut terms: HashMap<String, u32> = HashMap::new();
+        for token in tokenize(text) {
+            *terms.entry(token).or_default() += 1;
+        }
+        let mut p = self.postings.write().unwrap();
+        p.remove(&id);
+        for (term, count) in &terms {
+            *p.doc_freq.entry(term.clone()).or_default() += 1;
+            p.total_len += u64::from(*count);
+        }
+        p.docs.insert(id, terms);
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.postings.write().unwrap().remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.postings.read().unwrap().docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// BM25 score of every doc sharing at least one term with `query`
+    pub fn scores(&self, query: &str) -> HashMap<String, f32> {
+        let p = self.postings.read().unwrap();
+        let mut out = HashMap::new();
+        if p.docs.is_empty() {
+            return out;
+        }
+        let n = p.docs.len() as f32;
+        let avg_len = p.total_len as f32 / n;
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+        for term in &query_terms {
+            let Some(&df) = p.doc_freq.get(term) else {
+                continue;
+            };
+            let df = df as f32;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+            for (id, terms) in &p.docs {
+                let Some(&tf) = terms.get(term) else {
+                    continue;
+                };
+                let tf = tf as f32;
+                let len: u32 = terms.values().sum();
+                let norm = K1 * (1.0 - B + B * len as f32 / avg_len.max(f32::EPSILON));
+                *out.entry(id.clone()).or_insert(0.0) += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+        out
+    }
+}
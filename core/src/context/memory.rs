@@ -1,26 +1,640 @@
-use super::{MemoryReader, MemoryWriter};
-use crate::proto::Event;
-use crate::Result;
+use super::index::{EventFilter, EventIndex};
+use super::intern::PayloadInterner;
+pub use super::intern::PayloadStats;
+use super::snapshot::{Retention, Summarizer, SNAPSHOT_EVENTS_METADATA_KEY, SNAPSHOT_EVENT_TYPE};
+use super::{ContextDoc, MemoryReader, MemoryWriter};
+use crate::clock::{system_clock, Clock};
+use crate::compression::{Codec, PayloadCompression};
+use crate::event::EventExt;
+use crate::proto::{Event, QoSLevel};
+use crate::{EventBus, LoomError, Result};
 use async_trait::async_trait;
 use dashmap::DashMap;
-use std::sync::Arc;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
 
-/// A simple in-memory memory store for demo/testing.
-/// Stores textual summaries of events keyed by session id.
+/// Events of one session in append order, each tagged with a per-session sequence number
 #[derive(Default)]
+struct SessionLog {
+    next_seq: u64,
+    events: VecDeque<(u64, Event)>,
+    // ids of retained events, for replay-safe appends
+    ids: HashSet<String>,
+    // interned payloads by seq; the stored event's own payload is then empty
+    shared: HashMap<u64, Arc<[u8]>>,
+    // codec of payloads stored compressed, by seq (compressed before interning)
+    compressed: HashMap<u64, Codec>,
+}
+
+impl SessionLog {
+    /// Sequence numbers are increasing, so lookup is a binary search
+    fn get(&self, seq: u64) -> Option<&Event> {
+        self.events
+            .binary_search_by_key(&seq, |(s, _)| *s)
+            .ok()
+            .map(|i| &self.events[i].1)
+    }
+
+    /// Owned copy of a stored event with its payload restored
+    fn materialize(&self, seq: u64, event: &Event) -> Event {
+        let mut out = event.clone();
+        if let Some(shared) = self.shared.get(&seq) {
+            out.payload = shared.to_vec();
+        }
+        if let Some(codec) = self.compressed.get(&seq) {
+            // Our own output, so the size is whatever was appended
+            match codec.decompress(&out.payload, usize::MAX) {
+                Ok(payload) => out.payload = payload,
+                Err(e) => {
+                    warn!(target: "memory", event_id = %out.id, error = %e, "Stored payload failed to decompress")
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Appends to a session are published on this prefix plus the session id while it has
+/// followers (see `InMemoryMemory::iter_session_with`)
+pub const SESSION_TOPIC_PREFIX: &str = "memory.session.";
+/// Metadata key with the sequence number of an event published on a session topic
+pub const SEQ_METADATA_KEY: &str = "memory_seq";
+
+/// Bus topic that appends to `session` are published on
+pub fn session_topic(session: &str) -> String {
+    format!("{SESSION_TOPIC_PREFIX}{session}")
+}
+
+/// One stored event with its per-session sequence number
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEvent {
+    /// Increasing within the session; pass `seq + 1` as `from_seq` to resume after it
+    pub seq: u64,
+    pub event: Event,
+}
+
+/// Where `iter_session_with` starts and whether it waits for new appends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionIterOptions {
+    /// First sequence number to yield; evicted events are skipped
+    pub from_seq: u64,
+    /// Keep the stream open and yield events appended later (needs `set_event_bus`)
+    pub follow: bool,
+}
+
+/// Unsubscribes a follower from the bus once its stream is dropped
+struct Follower {
+    bus: Arc<EventBus>,
+    subscription_id: String,
+    rx: tokio::sync::mpsc::Receiver<Event>,
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let bus = Arc::clone(&self.bus);
+        let id = std::mem::take(&mut self.subscription_id);
+        handle.spawn(async move {
+            let _ = bus.unsubscribe(&id).await;
+        });
+    }
+}
+
+/// State of an `iter_session_with` stream
+struct SessionCursor<'a> {
+    memory: &'a InMemoryMemory,
+    session: String,
+    next_seq: u64,
+    buffered: VecDeque<SessionEvent>,
+    follow: bool,
+    follower: Option<Follower>,
+    done: bool,
+}
+
+impl SessionCursor<'_> {
+    async fn next_event(&mut self) -> Option<Result<SessionEvent>> {
+        if self.done {
+            return None;
+        }
+        // Subscribe before the first read, so nothing appended in between is missed
+        if std::mem::take(&mut self.follow) {
+            match self.memory.follow(&self.session).await {
+                Ok(follower) => self.follower = Some(follower),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        loop {
+            if let Some(next) = self.buffered.pop_front() {
+                return Some(Ok(next));
+            }
+            self.buffered = self.memory.events_from(&self.session, self.next_seq);
+            if let Some(last) = self.buffered.back() {
+                self.next_seq = last.seq + 1;
+                continue;
+            }
+            // Bus messages only wake the cursor; it reads the store, so none can be skipped
+            self.follower.as_mut()?.rx.recv().await?;
+        }
+    }
+}
+
+/// Max cached retrieval results before the query cache is cleared
+const QUERY_CACHE_MAX_ENTRIES: usize = 256;
+
+/// Hit/miss counters of the `InMemoryMemory` retrieval cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Heap entry for `retrieve_stream`: higher score first, then scan order
+struct Ranked {
+    order: usize,
+    doc: ContextDoc,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.doc
+            .score
+            .total_cmp(&other.doc.score)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Ranked {}
+
+/// Cached `retrieve_scored` result, valid while the store version is unchanged
+struct CachedQuery {
+    version: u64,
+    docs: Vec<ContextDoc>,
+}
+
+/// A simple in-memory memory store for demo/testing.
+/// Stores events keyed by session id; summaries and retrieval work on one-line renderings.
+/// Filtered retrieval (`type` / `source` / `tags`) goes through secondary indexes.
 pub struct InMemoryMemory {
-    // session -> list of lines
-    store: DashMap<String, Vec<String>>,
+    // session -> events in append order
+    store: DashMap<String, SessionLog>,
+    // type/source/tag -> (session, seq); updated on append and eviction
+    index: EventIndex,
+    // oldest events are evicted past this many per session (None = unbounded)
+    max_events_per_session: Option<usize>,
+    // stamps events that arrive without a timestamp
+    clock: Arc<dyn Clock>,
+    // bumped on every append; retrieval spans all sessions so one counter covers them
+    version: AtomicU64,
+    // (query, k, filters) -> result; entries from an older version are misses
+    query_cache: DashMap<String, CachedQuery>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    // recency half-life for retrieval scores in ms (0 = no decay)
+    recency_half_life_ms: AtomicU64,
+    // off by default to avoid hashing every payload
+    intern_payloads: AtomicBool,
+    interner: Mutex<PayloadInterner>,
+    // what `snapshot` keeps as raw events
+    retention: Mutex<Retention>,
+    // where appends are published for `iter_session_with` followers
+    bus: Mutex<Option<Arc<EventBus>>>,
+    // compresses large payloads at rest (None = stored as appended)
+    compression: Mutex<Option<PayloadCompression>>,
+}
+
+impl Default for InMemoryMemory {
+    fn default() -> Self {
+        Self {
+            store: DashMap::new(),
+            index: EventIndex::default(),
+            max_events_per_session: None,
+            clock: system_clock(),
+            version: AtomicU64::new(0),
+            query_cache: DashMap::new(),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            recency_half_life_ms: AtomicU64::new(0),
+            intern_payloads: AtomicBool::new(false),
+            interner: Mutex::new(PayloadInterner::default()),
+            retention: Mutex::new(Retention::default()),
+            bus: Mutex::new(None),
+            compression: Mutex::new(None),
+        }
+    }
 }
 
 impl InMemoryMemory {
     pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Create a store that reads time from `clock` (e.g. `MockClock` in tests)
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Arc<Self> {
         Arc::new(Self {
-            store: DashMap::new(),
+            clock,
+            ..Self::default()
+        })
+    }
+
+    /// Create a store keeping at most `max_events_per_session` events per session (oldest evicted)
+    pub fn bounded(max_events_per_session: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max_events_per_session: Some(max_events_per_session),
+            ..Self::default()
+        })
+    }
+
+    /// Events of `session` matching `pred`, oldest first.
+    /// A linear scan of that session (no indexes); prefer `retrieve` filters for hot paths.
+    pub fn query<F>(&self, session: &str, pred: F) -> Vec<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        self.store
+            .get(session)
+            .map(|log| {
+                // Stored payloads may be interned or compressed, so match on the restored event
+                log.events
+                    .iter()
+                    .map(|(seq, e)| log.materialize(*seq, e))
+                    .filter(|e| pred(e))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Stored event with id `event_id` in any session
+    pub fn find_event(&self, event_id: &str) -> Option<Event> {
+        self.store.iter().find_map(|log| {
+            if !log.ids.contains(event_id) {
+                return None;
+            }
+            log.events
+                .iter()
+                .find(|(_, e)| e.id == event_id)
+                .map(|(seq, e)| log.materialize(*seq, e))
         })
     }
 
-    fn summarize_event(event: &Event) -> String {
+    /// `event_id` and its ancestors via `parent_id` metadata, root cause first. The walk
+    /// stops at a missing parent or a cycle; an unknown `event_id` gives an empty chain.
+    pub fn causal_chain(&self, event_id: &str) -> Vec<Event> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(event_id.to_string());
+        while let Some(id) = next.take() {
+            if !seen.insert(id.clone()) {
+                break;
+            }
+            let Some(event) = self.find_event(&id) else {
+                break;
+            };
+            next = event.parent_id().map(str::to_string);
+            chain.push(event);
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Point-in-time view of `session` for building context from one consistent state.
+    /// Taken under the session's lock: an append (or `append_events` batch) is either fully
+    /// in it or not at all, and later appends never show up. Copies the session's events.
+    pub fn read_consistent(&self, session: &str) -> SessionSnapshot {
+        let events = self.store.get(session).map(|log| {
+            log.events
+                .iter()
+                .map(|(seq, e)| log.materialize(*seq, e))
+                .collect()
+        });
+        SessionSnapshot {
+            session: session.to_string(),
+            events,
+            decay: self.decay(),
+        }
+    }
+
+    /// Events of `session` in append order, from the oldest retained one. Finite; use
+    /// `iter_session_with` to resume from a cursor or to follow new appends.
+    pub fn iter_session(&self, session: &str) -> BoxStream<'_, Result<SessionEvent>> {
+        self.iter_session_with(session, SessionIterOptions::default())
+    }
+
+    /// Events of `session` from `options.from_seq` on, in append order. Retained events are
+    /// read in batches under the session lock, so each batch is consistent. With `follow`, the
+    /// stream then waits for appends instead of ending, woken through the event bus; it ends
+    /// when the bus shuts down. Following without `set_event_bus` yields one error.
+    pub fn iter_session_with(
+        &self,
+        session: &str,
+        options: SessionIterOptions,
+    ) -> BoxStream<'_, Result<SessionEvent>> {
+        let cursor = SessionCursor {
+            memory: self,
+            session: session.to_string(),
+            next_seq: options.from_seq,
+            buffered: VecDeque::new(),
+            follow: options.follow,
+            follower: None,
+            done: false,
+        };
+        stream::unfold(cursor, |mut cursor| async move {
+            let next = cursor.next_event().await?;
+            Some((next, cursor))
+        })
+        .boxed()
+    }
+
+    /// Publish appends on `session_topic(session)`, which lets `iter_session_with` follow
+    /// a session. Nothing is published for sessions without followers.
+    pub fn set_event_bus(&self, bus: Arc<EventBus>) {
+        *self.bus.lock().unwrap() = Some(bus);
+    }
+
+    /// Retained events of `session` with `seq >= from_seq`
+    fn events_from(&self, session: &str, from_seq: u64) -> VecDeque<SessionEvent> {
+        self.store
+            .get(session)
+            .map(|log| {
+                let start = log.events.partition_point(|(seq, _)| *seq < from_seq);
+                log.events
+                    .range(start..)
+                    .map(|(seq, e)| SessionEvent {
+                        seq: *seq,
+                        event: log.materialize(*seq, e),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Subscribe to the session topic; realtime QoS, so a stalled follower never blocks appends
+    async fn follow(&self, session: &str) -> Result<Follower> {
+        let bus = self.bus.lock().unwrap().clone().ok_or_else(|| {
+            LoomError::Memory("following a session needs an event bus (set_event_bus)".into())
+        })?;
+        let (subscription_id, rx) = bus
+            .subscribe(session_topic(session), Vec::new(), QoSLevel::QosRealtime)
+            .await?;
+        Ok(Follower {
+            bus,
+            subscription_id,
+            rx,
+        })
+    }
+
+    /// The bus and topic to publish appends to `session` on, if anyone follows it
+    fn followed(&self, session: &str) -> Option<(Arc<EventBus>, String)> {
+        let bus = self.bus.lock().unwrap().clone()?;
+        let topic = session_topic(session);
+        bus.get_stats(&topic)
+            .is_some_and(|stats| stats.active_subscriptions > 0)
+            .then_some((bus, topic))
+    }
+
+    /// Publish the committed appends `seqs` of `session` to its followers. Checked only after
+    /// the commit: a follower that subscribes later reads the events from the log instead.
+    /// Events already evicted by the session bound are skipped.
+    async fn publish_committed(&self, session: &str, seqs: Range<u64>) {
+        let Some((bus, topic)) = self.followed(session) else {
+            return;
+        };
+        let appended: Vec<(u64, Event)> = self
+            .store
+            .get(session)
+            .map(|log| {
+                let start = log.events.partition_point(|(seq, _)| *seq < seqs.start);
+                log.events
+                    .range(start..)
+                    .take_while(|(seq, _)| seqs.contains(seq))
+                    .map(|(seq, e)| (*seq, log.materialize(*seq, e)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.publish_appended(&bus, &topic, appended).await;
+    }
+
+    /// Best effort: a failed publish delays followers until the next append
+    async fn publish_appended(&self, bus: &EventBus, topic: &str, appended: Vec<(u64, Event)>) {
+        for (seq, mut event) in appended {
+            event
+                .metadata
+                .insert(SEQ_METADATA_KEY.to_string(), seq.to_string());
+            if let Err(e) = bus.publish(topic, event).await {
+                warn!(target: "memory", topic = %topic, error = %e, "Failed to publish appended event");
+            }
+        }
+    }
+
+    /// Share identical payloads of events appended from now on (by content hash).
+    /// Readers still get full payloads; events stored earlier are left as they are.
+    pub fn set_payload_interning(&self, enabled: bool) {
+        self.intern_payloads.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Compress payloads of events appended from now on once they reach `min_bytes`;
+    /// readers still get them decompressed. `None` stores payloads as appended (the default).
+    pub fn set_payload_compression(&self, compression: Option<PayloadCompression>) {
+        *self.compression.lock().unwrap() = compression;
+    }
+
+    /// Interned payload counts and dedup ratio over retained events
+    pub fn payload_stats(&self) -> PayloadStats {
+        self.interner.lock().unwrap().stats()
+    }
+
+    /// Append `event` to `log` under `seq`, compressing and interning its payload when enabled
+    fn push_event(&self, log: &mut SessionLog, seq: u64, mut event: Event) {
+        let compression = *self.compression.lock().unwrap();
+        if let Some(compression) = compression {
+            // The mark goes in the log, not the event, so readers see the metadata as appended
+            let mut marks = HashMap::new();
+            match compression.compress(&mut event.payload, &mut marks) {
+                Ok(true) => {
+                    log.compressed.insert(seq, compression.codec);
+                }
+                Ok(false) => {}
+                // The payload is left untouched, so it is stored uncompressed
+                Err(e) => {
+                    warn!(target: "memory", event_id = %event.id, error = %e, "Payload compression failed")
+                }
+            }
+        }
+        if self.intern_payloads.load(Ordering::Relaxed) && !event.payload.is_empty() {
+            let payload = std::mem::take(&mut event.payload);
+            let shared = self.interner.lock().unwrap().intern(payload);
+            log.shared.insert(seq, shared);
+        }
+        log.events.push_back((seq, event));
+    }
+
+    /// Decay retrieval scores by event age: a match loses half its score every `half_life`
+    /// (`score * 0.5^(age / half_life)`, with age measured from `timestamp_ms` on this store's
+    /// clock). `None` or a zero duration ranks by similarity alone (the default). Decayed
+    /// results depend on the time, so they bypass the query cache.
+    pub fn set_recency_half_life(&self, half_life: Option<Duration>) {
+        let ms = half_life.map_or(0, |d| d.as_millis().min(u64::MAX as u128) as u64);
+        self.recency_half_life_ms.store(ms, Ordering::Relaxed);
+        self.query_cache.clear();
+    }
+
+    pub fn recency_half_life(&self) -> Option<Duration> {
+        match self.recency_half_life_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// Retrieval cache counters since creation
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        QueryCacheStats {
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Window of raw events `snapshot` keeps
+    pub fn set_retention(&self, retention: Retention) {
+        *self.retention.lock().unwrap() = retention;
+    }
+
+    pub fn retention(&self) -> Retention {
+        *self.retention.lock().unwrap()
+    }
+
+    /// Fold the events of `session` outside the retention window, plus any earlier snapshot,
+    /// into one `memory.snapshot` event summarized by `summarizer`, and drop them from the log.
+    /// The snapshot takes the place of the oldest events and is retrievable by its text.
+    /// Returns it, or `None` when no raw event falls outside the window.
+    ///
+    /// The log is not locked while `summarizer` runs; events appended meanwhile are kept.
+    pub async fn snapshot(
+        &self,
+        session: &str,
+        summarizer: &dyn Summarizer,
+    ) -> Result<Option<Event>> {
+        let retention = self.retention();
+        let now_ms = self.clock.now_ms();
+        let (old, last_seq) = {
+            let Some(log) = self.store.get(session) else {
+                return Ok(None);
+            };
+            let keep_from = log.events.len().saturating_sub(retention.keep_recent);
+            let old: Vec<(u64, Event)> = log
+                .events
+                .iter()
+                .take(keep_from)
+                .take_while(|(_, e)| {
+                    retention.max_age.is_none_or(|age| {
+                        now_ms.saturating_sub(e.timestamp_ms) > age.as_millis() as i64
+                    })
+                })
+                .map(|(seq, e)| (*seq, log.materialize(*seq, e)))
+                .collect();
+            let Some(&(last_seq, _)) = old.last() else {
+                return Ok(None);
+            };
+            // Re-summarizing a lone earlier snapshot would gain nothing
+            if old.iter().all(|(_, e)| e.r#type == SNAPSHOT_EVENT_TYPE) {
+                return Ok(None);
+            }
+            (
+                old.into_iter().map(|(_, e)| e).collect::<Vec<_>>(),
+                last_seq,
+            )
+        };
+
+        let summary = summarizer.summarize(session, &old).await?;
+        let compacted: u64 = old
+            .iter()
+            .map(|e| match e.r#type.as_str() {
+                SNAPSHOT_EVENT_TYPE => e
+                    .metadata
+                    .get(SNAPSHOT_EVENTS_METADATA_KEY)
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0),
+                _ => 1,
+            })
+            .sum();
+        let snapshot = Event {
+            id: format!("snapshot:{session}:{last_seq}"),
+            r#type: SNAPSHOT_EVENT_TYPE.to_string(),
+            timestamp_ms: old.last().map_or(now_ms, |e| e.timestamp_ms),
+            source: "memory".to_string(),
+            metadata: [(
+                SNAPSHOT_EVENTS_METADATA_KEY.to_string(),
+                compacted.to_string(),
+            )]
+            .into_iter()
+            .collect(),
+            payload: summary.into_bytes(),
+            confidence: 1.0,
+            ..Default::default()
+        };
+
+        let mut log = self.store.entry(session.to_string()).or_default();
+        // Events evicted while summarizing are simply gone; newer ones stay
+        while log.events.front().is_some_and(|(seq, _)| *seq <= last_seq) {
+            if let Some((old_seq, old)) = log.events.pop_front() {
+                self.index.remove(session, old_seq, &old);
+                log.ids.remove(&old.id);
+                if let Some(shared) = log.shared.remove(&old_seq) {
+                    self.interner.lock().unwrap().release(&shared);
+                }
+                log.compressed.remove(&old_seq);
+            }
+        }
+        // Reuses the newest folded seq, which keeps the log sorted by seq
+        self.index.insert(session, last_seq, &snapshot);
+        log.ids.insert(snapshot.id.clone());
+        log.events.push_front((last_seq, snapshot.clone()));
+        drop(log);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(Some(snapshot))
+    }
+
+    /// Evict the oldest events past the per-session bound
+    fn evict_overflow(&self, session: &str, log: &mut SessionLog) {
+        if let Some(max) = self.max_events_per_session {
+            while log.events.len() > max {
+                if let Some((old_seq, old)) = log.events.pop_front() {
+                    self.index.remove(session, old_seq, &old);
+                    log.ids.remove(&old.id);
+                    if let Some(shared) = log.shared.remove(&old_seq) {
+                        self.interner.lock().unwrap().release(&shared);
+                    }
+                    log.compressed.remove(&old_seq);
+                }
+            }
+        }
+    }
+
+    /// One-line rendering used by summaries and retrieval; a snapshot renders as its text
+    pub(crate) fn summarize_event(event: &Event) -> String {
+        if event.r#type == SNAPSHOT_EVENT_TYPE {
+            return String::from_utf8_lossy(&event.payload).into_owned();
+        }
         // Minimal summary without parsing payload
         format!(
             "[{ts}] {ty} from {src}",
@@ -29,28 +643,154 @@ impl InMemoryMemory {
             src = event.source
         )
     }
+
+    /// Lines of the last 10 `events`, oldest first
+    fn episode_summary<'a>(events: impl DoubleEndedIterator<Item = &'a Event>) -> String {
+        let tail = events
+            .rev()
+            .take(10)
+            .map(Self::summarize_event)
+            .collect::<Vec<_>>();
+        tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+    }
+
+    /// Share of the line covered by query matches, in [0, 1]; an empty query scores 0
+    fn score_line(line: &str, query: &str) -> f32 {
+        let line_len = line.chars().count();
+        if query.is_empty() || line_len == 0 {
+            return 0.0;
+        }
+        let covered = line.matches(query).count() * query.chars().count();
+        (covered as f32 / line_len as f32).min(1.0)
+    }
+
+    /// Docs for every event matching `query` (and `filters`), unsorted, in scan order
+    fn matches(
+        &self,
+        query: &str,
+        filters: Option<serde_json::Value>,
+        decay: Option<(i64, u64)>,
+    ) -> Result<Vec<ContextDoc>> {
+        let filter = match filters {
+            Some(f) => EventFilter::from_json(&f)?,
+            None => EventFilter::default(),
+        };
+
+        let mut out = Vec::new();
+        if filter.is_empty() {
+            // No indexed predicate: linear scan over every session
+            for entry in self.store.iter() {
+                for (_, event) in entry.events.iter() {
+                    Self::push_match(&mut out, entry.key(), event, query, decay);
+                }
+            }
+        } else {
+            // Refs arrive grouped by session; refs evicted since the lookup are skipped
+            let mut current: Option<(String, dashmap::mapref::one::Ref<'_, String, SessionLog>)> =
+                None;
+            for (session, seq) in self.index.candidates(&filter) {
+                if current.as_ref().is_none_or(|(s, _)| s != &session) {
+                    // Release the previous shard guard before taking the next one
+                    drop(current.take());
+                    current = self.store.get(&session).map(|log| (session.clone(), log));
+                }
+                if let Some(event) = current.as_ref().and_then(|(_, log)| log.get(seq)) {
+                    Self::push_match(&mut out, &session, event, query, decay);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// `(now_ms, half_life_ms)` when recency decay is on
+    fn decay(&self) -> Option<(i64, u64)> {
+        match self.recency_half_life_ms.load(Ordering::Relaxed) {
+            0 => None,
+            half_life_ms => Some((self.clock.now_ms(), half_life_ms)),
+        }
+    }
+
+    /// Score `event` against `query`, pushing a doc if its summary line matches.
+    /// `decay` is `(now_ms, half_life_ms)` when recency decay is on.
+    fn push_match(
+        out: &mut Vec<ContextDoc>,
+        session: &str,
+        event: &Event,
+        query: &str,
+        decay: Option<(i64, u64)>,
+    ) {
+        let line = Self::summarize_event(event);
+        if line.contains(query) {
+            let mut score = Self::score_line(&line, query);
+            if let Some((now_ms, half_life_ms)) = decay {
+                let age_ms = now_ms.saturating_sub(event.timestamp_ms).max(0);
+                score *= 0.5f64.powf(age_ms as f64 / half_life_ms as f64) as f32;
+            }
+            out.push(ContextDoc::new(line, score, Some(session.to_string())));
+        }
+    }
 }
 
 #[async_trait]
 impl MemoryWriter for InMemoryMemory {
-    async fn append_event(&self, session: &str, event: Event) -> Result<()> {
-        let line = Self::summarize_event(&event);
-        self.store
-            .entry(session.to_string())
-            .or_default()
-            .value_mut()
-            .push(line);
+    async fn append_event(&self, session: &str, mut event: Event) -> Result<bool> {
+        if event.timestamp_ms == 0 {
+            event.timestamp_ms = self.clock.now_ms();
+        }
+        let mut log = self.store.entry(session.to_string()).or_default();
+        // Retried/replayed appends of a retained event are no-ops; empty ids are never deduplicated
+        if !event.id.is_empty() && !log.ids.insert(event.id.clone()) {
+            return Ok(false);
+        }
+        let seq = log.next_seq;
+        log.next_seq += 1;
+        self.index.insert(session, seq, &event);
+        self.push_event(&mut log, seq, event);
+        self.evict_overflow(session, &mut log);
+        drop(log);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        self.publish_committed(session, seq..seq + 1).await;
+        Ok(true)
+    }
+
+    /// One session lock and one index pass for the whole batch
+    async fn append_events(&self, session: &str, events: Vec<Event>) -> Result<()> {
+        let now = self.clock.now_ms();
+        let mut log = self.store.entry(session.to_string()).or_default();
+        let first = log.events.len();
+        let first_seq = log.next_seq;
+        for mut event in events {
+            if event.timestamp_ms == 0 {
+                event.timestamp_ms = now;
+            }
+            // Duplicates of retained events, or earlier in this batch, are skipped
+            if !event.id.is_empty() && !log.ids.insert(event.id.clone()) {
+                continue;
+            }
+            let seq = log.next_seq;
+            log.next_seq += 1;
+            self.push_event(&mut log, seq, event);
+        }
+        if log.events.len() == first {
+            return Ok(());
+        }
+        self.index.insert_batch(
+            session,
+            log.events.iter().skip(first).map(|(seq, e)| (*seq, e)),
+        );
+        let seqs = first_seq..log.next_seq;
+        self.evict_overflow(session, &mut log);
+        drop(log);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        self.publish_committed(session, seqs).await;
         Ok(())
     }
 
     async fn summarize_episode(&self, session: &str) -> Result<Option<String>> {
-        if let Some(list) = self.store.get(session) {
-            let tail = list.iter().rev().take(10).cloned().collect::<Vec<_>>();
-            let summary = tail.into_iter().rev().collect::<Vec<_>>().join("\n");
-            Ok(Some(summary))
-        } else {
-            Ok(None)
-        }
+        Ok(self
+            .store
+            .get(session)
+            .map(|log| Self::episode_summary(log.events.iter().map(|(_, e)| e))))
     }
 }
 
@@ -60,19 +800,285 @@ impl MemoryReader for InMemoryMemory {
         &self,
         query: &str,
         k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        let docs = self.retrieve_scored(query, k, filters).await?;
+        Ok(docs.into_iter().map(|d| d.text).collect())
+    }
+
+    async fn retrieve_scored(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<ContextDoc>> {
+        // Any append bumps the version, so a cached result is never stale. Decayed scores
+        // change with the clock, so they are neither served from nor written to the cache.
+        let version = self.version.load(Ordering::SeqCst);
+        let decay = self.decay();
+        let key = format!(
+            "{}\u{1f}{}\u{1f}{}",
+            query,
+            k,
+            filters.as_ref().map(|f| f.to_string()).unwrap_or_default()
+        );
+        if decay.is_none() {
+            if let Some(hit) = self.query_cache.get(&key).filter(|c| c.version == version) {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(hit.docs.clone());
+            }
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut out = self.matches(query, filters, decay)?;
+        // Stable sort keeps insertion order among equal scores
+        out.sort_by(|a, b| b.score.total_cmp(&a.score));
+        out.truncate(k);
+        if decay.is_some() {
+            return Ok(out);
+        }
+
+        if self.query_cache.len() >= QUERY_CACHE_MAX_ENTRIES {
+            self.query_cache.clear();
+        }
+        // Stamped with the version read before the scan; a concurrent append makes it a miss
+        self.query_cache.insert(
+            key,
+            CachedQuery {
+                version,
+                docs: out.clone(),
+            },
+        );
+        Ok(out)
+    }
+
+    /// Every match is scored up front, so the order is exact, but sorting is lazy: each doc
+    /// pulled is one heap pop, and stopping early skips sorting the rest. Bypasses the query
+    /// cache.
+    fn retrieve_stream<'a>(
+        &'a self,
+        query: &'a str,
+        filters: Option<serde_json::Value>,
+    ) -> BoxStream<'a, Result<ContextDoc>> {
+        let mut heap: BinaryHeap<Ranked> = match self.matches(query, filters, self.decay()) {
+            Ok(docs) => docs
+                .into_iter()
+                .enumerate()
+                .map(|(order, doc)| Ranked { order, doc })
+                .collect(),
+            Err(e) => return stream::once(async move { Err(e) }).boxed(),
+        };
+        stream::iter(std::iter::from_fn(move || heap.pop().map(|r| Ok(r.doc)))).boxed()
+    }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        Ok(self
+            .store
+            .get(session)
+            .map(|log| {
+                let skip = log.events.len().saturating_sub(limit);
+                log.events
+                    .iter()
+                    .skip(skip)
+                    .map(|(seq, e)| log.materialize(*seq, e))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn version(&self, _session: &str) -> Option<u64> {
+        Some(self.version.load(Ordering::SeqCst))
+    }
+}
+
+/// Memory for stateless agents: appends are accepted and discarded, reads return nothing
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullMemory;
+
+impl NullMemory {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self)
+    }
+}
+
+#[async_trait]
+impl MemoryWriter for NullMemory {
+    /// Always `Ok(false)`: nothing is stored
+    async fn append_event(&self, _session: &str, _event: Event) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn summarize_episode(&self, _session: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl MemoryReader for NullMemory {
+    async fn retrieve(
+        &self,
+        _query: &str,
+        _k: usize,
         _filters: Option<serde_json::Value>,
     ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Never changes, so bundles built from it stay cacheable
+    fn version(&self, _session: &str) -> Option<u64> {
+        Some(0)
+    }
+}
+
+/// Read-only view of another store: reads (including summaries) are delegated,
+/// appends fail with `LoomError::Memory("read only")`
+pub struct ReadOnlyMemory<R> {
+    inner: Arc<R>,
+}
+
+impl<R> ReadOnlyMemory<R> {
+    pub fn new(inner: Arc<R>) -> Arc<Self> {
+        Arc::new(Self { inner })
+    }
+
+    /// The wrapped store (still writable through this handle)
+    pub fn inner(&self) -> &Arc<R> {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<R: MemoryWriter> MemoryWriter for ReadOnlyMemory<R> {
+    async fn append_event(&self, _session: &str, _event: Event) -> Result<bool> {
+        Err(LoomError::Memory("read only".into()))
+    }
+
+    async fn summarize_episode(&self, session: &str) -> Result<Option<String>> {
+        self.inner.summarize_episode(session).await
+    }
+}
+
+#[async_trait]
+impl<R: MemoryReader> MemoryReader for ReadOnlyMemory<R> {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        self.inner.retrieve(query, k, filters).await
+    }
+
+    async fn retrieve_scored(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<ContextDoc>> {
+        self.inner.retrieve_scored(query, k, filters).await
+    }
+
+    fn retrieve_stream<'a>(
+        &'a self,
+        query: &'a str,
+        filters: Option<serde_json::Value>,
+    ) -> BoxStream<'a, Result<ContextDoc>> {
+        self.inner.retrieve_stream(query, filters)
+    }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        self.inner.recent_events(session, limit).await
+    }
+
+    fn version(&self, session: &str) -> Option<u64> {
+        self.inner.version(session)
+    }
+}
+
+/// Frozen copy of one `InMemoryMemory` session from `read_consistent`.
+///
+/// Implements `MemoryReader` and `MemoryWriter`, so a `ContextBuilder` over it reads history,
+/// summary and retrieval from the same state even while the store keeps taking appends.
+/// Retrieval only covers this session's events, with recency decay as of the snapshot.
+/// Appends fail with `LoomError::Memory("read only")`. Reports no `version`: a snapshot is
+/// a one-off view, not something to cache bundles against.
+#[derive(Debug, Clone)]
+pub struct SessionSnapshot {
+    session: String,
+    // None when the session did not exist
+    events: Option<Vec<Event>>,
+    decay: Option<(i64, u64)>,
+}
+
+impl SessionSnapshot {
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// The session's events at snapshot time, oldest first
+    pub fn events(&self) -> &[Event] {
+        self.events.as_deref().unwrap_or_default()
+    }
+
+    fn events_of(&self, session: &str) -> &[Event] {
+        if session == self.session {
+            self.events()
+        } else {
+            &[]
+        }
+    }
+}
+
+#[async_trait]
+impl MemoryWriter for SessionSnapshot {
+    async fn append_event(&self, _session: &str, _event: Event) -> Result<bool> {
+        Err(LoomError::Memory("read only".into()))
+    }
+
+    async fn summarize_episode(&self, session: &str) -> Result<Option<String>> {
+        if session != self.session {
+            return Ok(None);
+        }
+        Ok(self
+            .events
+            .as_ref()
+            .map(|events| InMemoryMemory::episode_summary(events.iter())))
+    }
+}
+
+#[async_trait]
+impl MemoryReader for SessionSnapshot {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        let docs = self.retrieve_scored(query, k, filters).await?;
+        Ok(docs.into_iter().map(|d| d.text).collect())
+    }
+
+    async fn retrieve_scored(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<ContextDoc>> {
+        let filter = match filters {
+            Some(f) => EventFilter::from_json(&f)?,
+            None => EventFilter::default(),
+        };
         let mut out = Vec::new();
-        for entry in self.store.iter() {
-            for line in entry.iter() {
-                if line.contains(query) {
-                    out.push(line.clone());
-                    if out.len() >= k {
-                        return Ok(out);
-                    }
-                }
-            }
+        for event in self.events().iter().filter(|e| filter.matches(e)) {
+            InMemoryMemory::push_match(&mut out, &self.session, event, query, self.decay);
         }
+        out.sort_by(|a, b| b.score.total_cmp(&a.score));
+        out.truncate(k);
         Ok(out)
     }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        let events = self.events_of(session);
+        Ok(events[events.len().saturating_sub(limit)..].to_vec())
+    }
 }
@@ -0,0 +1,61 @@
+//! Maximal marginal relevance (MMR) re-ranking.
+//!
+//! Picks docs one at a time from a pool of scored candidates. Each pick maximizes
+//! `lambda * relevance - (1 - lambda) * max_similarity`, where `max_similarity` is the
+//! candidate's similarity to the docs already picked. `lambda = 1` is plain top-k by score,
+//! and lower values trade relevance for coverage, so near-duplicates stop crowding out the
+//! rest of the result.
+
+use super::keyword::tokenize;
+use super::ContextDoc;
+use std::collections::HashSet;
+
+/// Candidates fetched per requested doc before re-ranking (`retrieve_mmr` scores `k * 4`)
+pub const MMR_CANDIDATE_FACTOR: usize = 4;
+
+/// Share of the terms two texts have in common (Jaccard over `tokenize`), in [0, 1]
+pub fn term_overlap(a: &str, b: &str) -> f32 {
+    let a: HashSet<String> = tokenize(a).into_iter().collect();
+    let b: HashSet<String> = tokenize(b).into_iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+/// Up to `k` of `candidates` in MMR order, with their original scores. `lambda` is clamped
+/// to [0, 1]; ties go to the earlier candidate, so pass candidates best first.
+pub fn mmr_select<F>(
+    candidates: Vec<ContextDoc>,
+    k: usize,
+    lambda: f32,
+    similarity: F,
+) -> Vec<ContextDoc>
+where
+    F: Fn(&ContextDoc, &ContextDoc) -> f32,
+{
+    let lambda = lambda.clamp(0.0, 1.0);
+    let mut pool = candidates;
+    // Highest similarity of each pooled candidate to anything picked so far
+    let mut max_sim = vec![0.0f32; pool.len()];
+    let mut picked: Vec<ContextDoc> = Vec::with_capacity(k.min(pool.len()));
+    while picked.len() < k && !pool.is_empty() {
+        let mut best = 0;
+        let mut best_mmr = f32::NEG_INFINITY;
+        for (i, doc) in pool.iter().enumerate() {
+            let mmr = lambda * doc.score - (1.0 - lambda) * max_sim[i];
+            if mmr > best_mmr {
+                best = i;
+                best_mmr = mmr;
+            }
+        }
+        let doc = pool.remove(best);
+        max_sim.remove(best);
+        for (i, other) in pool.iter().enumerate() {
+            max_sim[i] = max_sim[i].max(similarity(&doc, other));
+        }
+        picked.push(doc);
+    }
+    picked
+}
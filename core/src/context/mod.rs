@@ -1,6 +1,42 @@
 pub mod builder;
+mod cache;
+pub mod conversation;
+mod debounce;
+pub mod embedding;
+pub mod history;
+pub mod hybrid;
+pub mod index;
+mod intern;
+pub mod keyword;
 pub mod memory;
+mod mmr;
+pub mod postprocess;
+mod snapshot;
+pub mod strategy;
+#[cfg(feature = "tiktoken")]
+mod tiktoken;
+pub mod tokens;
+mod vector_file;
 
+pub use cache::CacheStats;
+pub use conversation::{Conversation, Turn};
+pub use embedding::{cosine_similarity, CachingEmbedder, Embedder, EmbeddingIndex};
+pub use history::{HistoryEntry, Role, RoleMapping};
+pub use hybrid::HybridRetriever;
+pub use index::EventFilter;
+pub use keyword::KeywordIndex;
+pub use mmr::{mmr_select, term_overlap, MMR_CANDIDATE_FACTOR};
+pub use postprocess::BundlePostprocessor;
+pub use snapshot::{
+    LineSummarizer, Retention, Summarizer, SNAPSHOT_EVENTS_METADATA_KEY, SNAPSHOT_EVENT_TYPE,
+};
+pub use strategy::{AssemblyContext, ContextStrategy, MinimalStrategy};
+#[cfg(feature = "tiktoken")]
+pub use tiktoken::TiktokenCounter;
+pub use tokens::{HeuristicTokenCounter, TokenBreakdown, TokenCounter};
+pub use vector_file::VECTOR_FILE_VERSION;
+
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 
 /// Token budget to control prompt assembly size
@@ -19,20 +55,77 @@ impl Default for TokenBudget {
     }
 }
 
+/// A retrieved context document with its relevance score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextDoc {
+    pub text: String,
+    /// Relevance in [0, 1]; higher is more relevant
+    pub score: f32,
+    /// Where the doc came from (session id, document id, ...), if known
+    pub source_id: Option<String>,
+}
+
+impl ContextDoc {
+    pub fn new(text: impl Into<String>, score: f32, source_id: Option<String>) -> Self {
+        Self {
+            text: text.into(),
+            score,
+            source_id,
+        }
+    }
+}
+
+/// Unscored docs (e.g. hand-built bundles) get the maximum score and no source
+impl From<String> for ContextDoc {
+    fn from(text: String) -> Self {
+        Self::new(text, 1.0, None)
+    }
+}
+
+impl From<&str> for ContextDoc {
+    fn from(text: &str) -> Self {
+        Self::new(text, 1.0, None)
+    }
+}
+
 /// A bundle of prompt components for an LLM call
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PromptBundle {
     pub system: String,
     pub instructions: String,
     pub tools_json_schema: Option<String>,
-    pub context_docs: Vec<String>,
-    pub history: Vec<String>,
+    pub context_docs: Vec<ContextDoc>,
+    pub history: Vec<HistoryEntry>,
+    /// Tokens per section, filled in by `ContextBuilder` with its `TokenCounter`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_breakdown: Option<TokenBreakdown>,
+}
+
+impl PromptBundle {
+    /// Plain text of `context_docs`, in order (pre-scoring representation)
+    pub fn context_texts(&self) -> Vec<String> {
+        self.context_docs.iter().map(|d| d.text.clone()).collect()
+    }
 }
 
 /// Abstraction for writing memory (events, summaries)
 #[async_trait::async_trait]
 pub trait MemoryWriter: Send + Sync {
-    async fn append_event(&self, session: &str, event: crate::proto::Event) -> crate::Result<()>;
+    /// Store `event` for `session`. Idempotent on `(session, event.id)`: returns `false`
+    /// (and stores nothing) when an event with the same non-empty id is already present.
+    async fn append_event(&self, session: &str, event: crate::proto::Event) -> crate::Result<bool>;
+    /// Store `events` for `session` in order, with the same per-event idempotency as
+    /// `append_event`. The default appends one at a time; stores may override it to batch.
+    async fn append_events(
+        &self,
+        session: &str,
+        events: Vec<crate::proto::Event>,
+    ) -> crate::Result<()> {
+        for event in events {
+            self.append_event(session, event).await?;
+        }
+        Ok(())
+    }
     async fn summarize_episode(&self, session: &str) -> crate::Result<Option<String>>;
 }
 
@@ -45,4 +138,71 @@ pub trait MemoryReader: Send + Sync {
         k: usize,
         filters: Option<serde_json::Value>,
     ) -> crate::Result<Vec<String>>;
+
+    /// Like `retrieve`, but with relevance scores and sources.
+    /// The default wraps `retrieve` and scores every hit 1.0.
+    async fn retrieve_scored(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> crate::Result<Vec<ContextDoc>> {
+        Ok(self
+            .retrieve(query, k, filters)
+            .await?
+            .into_iter()
+            .map(ContextDoc::from)
+            .collect())
+    }
+
+    /// Up to `k` docs for `query` re-ranked by maximal marginal relevance, so near-duplicates
+    /// do not crowd out the rest. Scores the `k * MMR_CANDIDATE_FACTOR` best candidates with
+    /// `retrieve_scored`, then picks by `lambda * score - (1 - lambda) * overlap`, where
+    /// overlap is `term_overlap` with the closest doc already picked. `lambda = 1` is plain
+    /// top-k. Docs keep their relevance scores but come in pick order.
+    async fn retrieve_mmr(
+        &self,
+        query: &str,
+        k: usize,
+        lambda: f32,
+        filters: Option<serde_json::Value>,
+    ) -> crate::Result<Vec<ContextDoc>> {
+        let candidates = self
+            .retrieve_scored(query, k.saturating_mul(MMR_CANDIDATE_FACTOR), filters)
+            .await?;
+        Ok(mmr_select(candidates, k, lambda, |a, b| {
+            term_overlap(&a.text, &b.text)
+        }))
+    }
+
+    /// Docs for `query` as a stream in descending score order, so callers can stop pulling
+    /// once they have enough (e.g. a token budget is full). Streaming indexes may only
+    /// approximate the order. The default runs an unbounded `retrieve_scored` and streams
+    /// its result, so it saves nothing; stores that can rank lazily override it.
+    fn retrieve_stream<'a>(
+        &'a self,
+        query: &'a str,
+        filters: Option<serde_json::Value>,
+    ) -> BoxStream<'a, crate::Result<ContextDoc>> {
+        stream::once(self.retrieve_scored(query, usize::MAX, filters))
+            .map_ok(|docs| stream::iter(docs.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Most recent events of `session`, oldest first, at most `limit`.
+    /// Stores that do not keep raw events return none (history stays empty).
+    async fn recent_events(
+        &self,
+        _session: &str,
+        _limit: usize,
+    ) -> crate::Result<Vec<crate::proto::Event>> {
+        Ok(Vec::new())
+    }
+
+    /// Cheap version/ETag that changes whenever data visible to `session` changes.
+    /// Returning `None` (the default) disables bundle caching for this store.
+    fn version(&self, _session: &str) -> Option<u64> {
+        None
+    }
 }
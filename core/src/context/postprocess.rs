@@ -0,0 +1,21 @@
+//! Hooks that rewrite an assembled PromptBundle (guardrails, preambles, redaction).
+
+use super::PromptBundle;
+use crate::Result;
+
+/// Runs at the end of `ContextBuilder::build`, after assembly and before caching.
+///
+/// Postprocessors run in the order they were added; an error aborts the build.
+pub trait BundlePostprocessor: Send + Sync {
+    fn process(&self, bundle: &mut PromptBundle) -> Result<()>;
+}
+
+/// Any `Fn(&mut PromptBundle) -> Result<()>` closure is a postprocessor
+impl<F> BundlePostprocessor for F
+where
+    F: Fn(&mut PromptBundle) -> Result<()> + Send + Sync,
+{
+    fn process(&self, bundle: &mut PromptBundle) -> Result<()> {
+        self(bundle)
+    }
+}
@@ -0,0 +1,59 @@
+//! Compacting old session history into a single snapshot event.
+//!
+//! `InMemoryMemory::snapshot(session, summarizer)` folds every event outside the `Retention`
+//! window (including an earlier snapshot) into one `memory.snapshot` event and drops the raw
+//! events. The snapshot's payload is the summary text, and retrieval matches against that
+//! text, so the gist of old interactions stays retrievable while the log stays short.
+
+use super::memory::InMemoryMemory;
+use crate::proto::Event;
+use crate::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Event type of compacted history; its payload is the UTF-8 summary
+pub const SNAPSHOT_EVENT_TYPE: &str = "memory.snapshot";
+/// Snapshot metadata key with the number of raw events folded in so far
+pub const SNAPSHOT_EVENTS_METADATA_KEY: &str = "compacted_events";
+
+/// Which raw events `snapshot` leaves alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// The newest this many events of the session are always kept
+    pub keep_recent: usize,
+    /// Events younger than this (by `timestamp_ms` on the store's clock) are kept as well
+    pub max_age: Option<Duration>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            keep_recent: 100,
+            max_age: None,
+        }
+    }
+}
+
+/// Turns a run of old events into the text of their snapshot
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    /// `events` are oldest first; an earlier snapshot, if any, comes first
+    async fn summarize(&self, session: &str, events: &[Event]) -> Result<String>;
+}
+
+/// Keeps every event's retrieval line (an earlier snapshot contributes its text), so all
+/// queries that matched the raw events still match the snapshot. Lossless, not short;
+/// plug in an LLM-backed `Summarizer` for real compression.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineSummarizer;
+
+#[async_trait]
+impl Summarizer for LineSummarizer {
+    async fn summarize(&self, _session: &str, events: &[Event]) -> Result<String> {
+        Ok(events
+            .iter()
+            .map(InMemoryMemory::summarize_event)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
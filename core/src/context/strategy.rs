@@ -0,0 +1,258 @@
+//! Pluggable prompt assembly for `ContextBuilder`.
+//!
+//! The builder owns caching, tool-hint normalization and postprocessing; a `ContextStrategy`
+//! decides what goes into the bundle. `AssemblyContext` exposes the builder's steps
+//! (summary, retrieval, history, ...) so strategies can reorder or reweight them.
+
+use super::builder::{EmptyGoalPolicy, RetrievalPolicy, TriggerInput};
+use super::{
+    ContextDoc, HistoryEntry, MemoryReader, MemoryWriter, PromptBundle, RoleMapping, TokenCounter,
+};
+use crate::{CancellationToken, LoomError, Result};
+use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Builds a `PromptBundle` for one trigger
+#[async_trait]
+pub trait ContextStrategy: Send + Sync {
+    async fn assemble(&self, ctx: AssemblyContext) -> Result<PromptBundle>;
+}
+
+/// One build's inputs: the trigger (tool hints already normalized), the memory handles and
+/// the builder's settings with per-trigger overrides applied
+#[derive(Clone)]
+pub struct AssemblyContext {
+    pub trigger: TriggerInput,
+    pub reader: Arc<dyn MemoryReader>,
+    pub writer: Arc<dyn MemoryWriter>,
+    /// System prompt after the trigger override
+    pub system_prompt: String,
+    pub history_limit: usize,
+    pub retrieval_k: usize,
+    pub min_score: Option<f32>,
+    pub roles: RoleMapping,
+    pub retrieval_policy: RetrievalPolicy,
+    /// Limit for each reader's retrieval call; exceeding it counts as a retrieval error
+    pub retrieval_timeout: Option<Duration>,
+    /// Token budget for retrieved docs; when set, retrieval streams and stops once it is full
+    pub retrieval_token_budget: Option<usize>,
+    /// Counter for `retrieval_token_budget`
+    pub token_counter: Arc<dyn TokenCounter>,
+    pub empty_goal: EmptyGoalPolicy,
+    pub cancel: CancellationToken,
+    /// Set once a step fails and the build carries on without it (e.g. a retrieval error
+    /// under `Degrade` or `Fallback`); the builder does not cache such bundles
+    pub degraded: Arc<AtomicBool>,
+}
+
+impl AssemblyContext {
+    /// Race `fut` against the build's cancellation token
+    pub async fn or_cancelled<F: Future>(&self, fut: F) -> Result<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(LoomError::Cancelled),
+            out = fut => Ok(out),
+        }
+    }
+
+    /// Keep this build's bundle out of the cache: it lacks something a retry could supply
+    pub fn mark_degraded(&self) {
+        self.degraded.store(true, Ordering::Relaxed);
+    }
+
+    /// The episode summary as a score-1.0 doc; `None` when missing, empty or failing
+    pub async fn summary_doc(&self) -> Result<Option<ContextDoc>> {
+        let session = &self.trigger.session_id;
+        let summary = self
+            .or_cancelled(self.writer.summarize_episode(session))
+            .await?;
+        Ok(match summary {
+            Ok(Some(summary)) if !summary.is_empty() => Some(ContextDoc::new(
+                format!("Recent episode summary:\n{}", summary),
+                1.0,
+                Some(session.clone()),
+            )),
+            _ => None,
+        })
+    }
+
+    /// One reader's retrieval, raced against cancellation and the retrieval timeout.
+    /// A timed-out query is dropped (aborting it) and reported as `LoomError::Timeout`.
+    async fn retrieve_from(
+        &self,
+        reader: &dyn MemoryReader,
+        query: &str,
+        k: usize,
+    ) -> Result<Result<Vec<ContextDoc>>> {
+        let retrieval = async {
+            match self.retrieval_token_budget {
+                None => reader.retrieve_scored(query, k, None).await,
+                Some(budget) => {
+                    self.take_within_budget(reader.retrieve_stream(query, None), k, budget)
+                        .await
+                }
+            }
+        };
+        match self.retrieval_timeout {
+            None => self.or_cancelled(retrieval).await,
+            Some(limit) => Ok(self
+                .or_cancelled(tokio::time::timeout(limit, retrieval))
+                .await?
+                .unwrap_or_else(|_| {
+                    Err(LoomError::Timeout(format!(
+                        "retrieval exceeded {} ms",
+                        limit.as_millis()
+                    )))
+                })),
+        }
+    }
+
+    /// Pull docs until `k` are taken, the next one would overflow `budget` tokens, or one
+    /// scores below `min_score`; the rest of the stream is never produced
+    async fn take_within_budget(
+        &self,
+        mut docs: BoxStream<'_, Result<ContextDoc>>,
+        k: usize,
+        budget: usize,
+    ) -> Result<Vec<ContextDoc>> {
+        let mut taken = Vec::new();
+        let mut used = 0;
+        while taken.len() < k {
+            let Some(doc) = docs.next().await.transpose()? else {
+                break;
+            };
+            if self.min_score.is_some_and(|min| doc.score < min) {
+                break;
+            }
+            let tokens = self.token_counter.count(&doc.text);
+            if used + tokens > budget {
+                break;
+            }
+            used += tokens;
+            taken.push(doc);
+        }
+        Ok(taken)
+    }
+
+    /// Retrieve up to `k` docs for `query` under the retrieval policy, dropping docs below
+    /// `min_score`. An empty or whitespace query retrieves nothing without asking the reader.
+    /// A retrieval timeout is handled by the policy like any other retrieval error.
+    pub async fn retrieve(&self, query: &str, k: usize) -> Result<Vec<ContextDoc>> {
+        let session = &self.trigger.session_id;
+        if query.trim().is_empty() {
+            debug!(target: "context_builder", session = %session, "Empty retrieval query; skipping retrieval");
+            return Ok(Vec::new());
+        }
+        let retrieved = match self.retrieve_from(self.reader.as_ref(), query, k).await? {
+            Ok(docs) => docs,
+            Err(e) => match &self.retrieval_policy {
+                RetrievalPolicy::Strict => return Err(e),
+                RetrievalPolicy::Degrade => {
+                    warn!(target: "context_builder", session = %session, error = %e, "Retrieval failed; continuing without retrieved context");
+                    self.mark_degraded();
+                    Vec::new()
+                }
+                RetrievalPolicy::Fallback(fallback) => {
+                    warn!(target: "context_builder", session = %session, error = %e, "Retrieval failed; using fallback reader");
+                    self.mark_degraded();
+                    self.retrieve_from(fallback.as_ref(), query, k)
+                        .await?
+                        .unwrap_or_else(|e| {
+                            warn!(target: "context_builder", session = %session, error = %e, "Fallback retrieval failed; continuing without retrieved context");
+                            Vec::new()
+                        })
+                }
+            },
+        };
+        let min_score = self.min_score;
+        Ok(retrieved
+            .into_iter()
+            .filter(|d| min_score.is_none_or(|min| d.score >= min))
+            .collect())
+    }
+
+    /// The last `limit` session events as role-mapped history, oldest first. A memory error
+    /// is handled by the retrieval policy, like a retrieval error.
+    pub async fn history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let session = &self.trigger.session_id;
+        let recent = self.or_cancelled(self.reader.recent_events(session, limit));
+        let events = match recent.await? {
+            Ok(events) => events,
+            Err(e) => match &self.retrieval_policy {
+                RetrievalPolicy::Strict => return Err(e),
+                RetrievalPolicy::Degrade => {
+                    warn!(target: "context_builder", session = %session, error = %e, "Reading history failed; continuing without history");
+                    self.mark_degraded();
+                    Vec::new()
+                }
+                RetrievalPolicy::Fallback(fallback) => {
+                    warn!(target: "context_builder", session = %session, error = %e, "Reading history failed; using fallback reader");
+                    self.mark_degraded();
+                    self.or_cancelled(fallback.recent_events(session, limit))
+                        .await?
+                        .unwrap_or_else(|e| {
+                            warn!(target: "context_builder", session = %session, error = %e, "Fallback history failed; continuing without history");
+                            Vec::new()
+                        })
+                }
+            },
+        };
+        Ok(events
+            .iter()
+            .map(|e| HistoryEntry::from_event(e, self.roles.role_for(e)))
+            .collect())
+    }
+
+    /// Tool hints as a JSON array, or `None` when there are none
+    pub fn tools_json_schema(&self) -> Result<Option<String>> {
+        if self.trigger.tool_hints.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(serde_json::to_string(&self.trigger.tool_hints)?))
+        }
+    }
+
+    /// The goal, used as instruction and retrieval query (empty when unset)
+    pub fn goal(&self) -> &str {
+        self.trigger.goal.as_deref().unwrap_or("")
+    }
+
+    /// Whether the goal has any non-whitespace text
+    pub fn has_goal(&self) -> bool {
+        !self.goal().trim().is_empty()
+    }
+}
+
+/// The default strategy: episode summary, then retrieved docs for the goal, then recent history
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinimalStrategy;
+
+#[async_trait]
+impl ContextStrategy for MinimalStrategy {
+    async fn assemble(&self, ctx: AssemblyContext) -> Result<PromptBundle> {
+        // The episode summary is always relevant to its own session, unless an empty goal
+        // asks for history only
+        let mut context_docs: Vec<ContextDoc> =
+            if ctx.has_goal() || ctx.empty_goal == EmptyGoalPolicy::SkipRetrieval {
+                ctx.summary_doc().await?.into_iter().collect()
+            } else {
+                Vec::new()
+            };
+        context_docs.extend(ctx.retrieve(ctx.goal(), ctx.retrieval_k).await?);
+        let history = ctx.history(ctx.history_limit).await?;
+        let tools_json_schema = ctx.tools_json_schema()?;
+        Ok(PromptBundle {
+            system: ctx.system_prompt,
+            instructions: ctx.trigger.goal.unwrap_or_default(),
+            tools_json_schema,
+            context_docs,
+            history,
+            token_breakdown: None,
+        })
+    }
+}
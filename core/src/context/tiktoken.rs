@@ -0,0 +1,62 @@
+//! Exact token counts for OpenAI models via tiktoken BPE (feature `tiktoken`).
+
+use super::TokenCounter;
+use crate::{LoomError, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+/// Loaded encodings, shared by every counter; building one parses ~100k merge ranks
+fn encoding(tokenizer: Tokenizer) -> Result<Arc<CoreBPE>> {
+    static LOADED: OnceLock<Mutex<HashMap<Tokenizer, Arc<CoreBPE>>>> = OnceLock::new();
+    let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap();
+    if let Some(bpe) = loaded.get(&tokenizer) {
+        return Ok(Arc::clone(bpe));
+    }
+    let bpe = tiktoken_rs::get_bpe_from_tokenizer(tokenizer)
+        .map(Arc::new)
+        .map_err(|e| LoomError::Memory(format!("tiktoken encoding {tokenizer:?}: {e}")))?;
+    loaded.insert(tokenizer, Arc::clone(&bpe));
+    Ok(bpe)
+}
+
+/// Counts tokens with the BPE encoding an OpenAI model uses, so context budgets match what
+/// the API bills. Special-token text (e.g. `<|endoftext|>`) is counted as ordinary text.
+/// Encodings are loaded once per process and shared; the counter is cheap to clone.
+#[derive(Clone)]
+pub struct TiktokenCounter {
+    bpe: Arc<CoreBPE>,
+}
+
+impl TiktokenCounter {
+    /// Encoding of `model` (e.g. `gpt-4o` → o200k_base, `gpt-4` → cl100k_base).
+    /// Unknown models are an error rather than a silent fallback.
+    pub fn for_model(model: &str) -> Result<Self> {
+        let tokenizer = get_tokenizer(model)
+            .ok_or_else(|| LoomError::Memory(format!("no tiktoken encoding for model {model}")))?;
+        Self::with_tokenizer(tokenizer)
+    }
+
+    /// cl100k_base (GPT-4, GPT-3.5 Turbo, text-embedding-3)
+    pub fn cl100k() -> Result<Self> {
+        Self::with_tokenizer(Tokenizer::Cl100kBase)
+    }
+
+    /// o200k_base (GPT-4o and o-series)
+    pub fn o200k() -> Result<Self> {
+        Self::with_tokenizer(Tokenizer::O200kBase)
+    }
+
+    fn with_tokenizer(tokenizer: Tokenizer) -> Result<Self> {
+        Ok(Self {
+            bpe: encoding(tokenizer)?,
+        })
+    }
+}
+
+impl TokenCounter for TiktokenCounter {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+}
@@ -0,0 +1,80 @@
+//! Token counting and per-section accounting of assembled bundles.
+
+use super::PromptBundle;
+use serde::{Deserialize, Serialize};
+
+/// Counts tokens in a piece of prompt text; inject a tokenizer-backed one for exact numbers
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Any `Fn(&str) -> usize` closure is a counter
+impl<F> TokenCounter for F
+where
+    F: Fn(&str) -> usize + Send + Sync,
+{
+    fn count(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+/// ~4 characters per token, rounded up; the same heuristic the LLM adapter budgets with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenCounter;
+
+impl TokenCounter for HeuristicTokenCounter {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// Tokens each section of a bundle contributes; sections are counted separately, so the
+/// total excludes the formatting an adapter adds around them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBreakdown {
+    pub system: usize,
+    pub instructions: usize,
+    /// `tools_json_schema`
+    pub tools: usize,
+    /// Sum over `context_docs` texts
+    pub context_docs: usize,
+    /// Sum over `history` contents
+    pub history: usize,
+}
+
+impl TokenBreakdown {
+    pub fn total(&self) -> usize {
+        self.system + self.instructions + self.tools + self.context_docs + self.history
+    }
+
+    /// `(section, tokens)` pairs in prompt order
+    pub fn sections(&self) -> [(&'static str, usize); 5] {
+        [
+            ("system", self.system),
+            ("tools", self.tools),
+            ("context_docs", self.context_docs),
+            ("history", self.history),
+            ("instructions", self.instructions),
+        ]
+    }
+}
+
+impl PromptBundle {
+    /// Count every section of this bundle with `counter`
+    pub fn count_tokens(&self, counter: &dyn TokenCounter) -> TokenBreakdown {
+        TokenBreakdown {
+            system: counter.count(&self.system),
+            instructions: counter.count(&self.instructions),
+            tools: self
+                .tools_json_schema
+                .as_deref()
+                .map_or(0, |t| counter.count(t)),
+            context_docs: self
+                .context_docs
+                .iter()
+                .map(|d| counter.count(&d.text))
+                .sum(),
+            history: self.history.iter().map(|h| counter.count(&h.content)).sum(),
+        }
+    }
+}
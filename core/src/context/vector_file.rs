@@ -0,0 +1,171 @@
+//! On-disk format for `EmbeddingIndex::save` / `EmbeddingIndex::load`.
+//!
+//! Little-endian layout:
+//! - header (24 bytes): magic `LOOMVEC\0`, format version (u32), dimensions (u32), count (u64)
+//! - `count * dimensions` f32 values, one vector after another
+//! - `count` ids, each a u32 byte length followed by UTF-8 bytes, in vector order
+//!
+//! Vectors come first so they sit at a fixed, 4-byte aligned offset in the memory-mapped file
+//! and are read in place, without copying. Readers reject unknown versions instead of
+//! guessing; a layout change must bump [`VECTOR_FILE_VERSION`].
+
+use super::embedding::{EmbeddingIndex, StoredVector};
+use crate::{LoomError, Result};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Format version written by `EmbeddingIndex::save`
+pub const VECTOR_FILE_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"LOOMVEC\0";
+const HEADER_LEN: usize = 24;
+
+fn corrupt(path: &Path, what: &str) -> LoomError {
+    LoomError::Memory(format!("vector index file {}: {}", path.display(), what))
+}
+
+/// The vector block of a loaded file, kept mapped while any of its rows is in an index
+pub(super) struct MappedVectors {
+    map: memmap2::Mmap,
+    dims: usize,
+}
+
+impl MappedVectors {
+    /// Vector number `row`; `load` checked that every row is in bounds and aligned
+    pub(super) fn row(&self, row: usize) -> &[f32] {
+        let start = HEADER_LEN + row * self.dims * 4;
+        let bytes = &self.map[start..start + self.dims * 4];
+        // SAFETY: every bit pattern is a valid f32, and `load` only maps files on
+        // little-endian targets where the vector block is 4-byte aligned
+        let (head, floats, tail) = unsafe { bytes.align_to::<f32>() };
+        debug_assert!(head.is_empty() && tail.is_empty());
+        floats
+    }
+}
+
+impl EmbeddingIndex {
+    /// Write every vector to `path` (replaced atomically via a temporary sibling file).
+    /// An empty index without fixed dimensions is saved with 0 dimensions.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let dims = self.dimensions().unwrap_or(0);
+        let entries = self.snapshot();
+
+        let tmp = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            out.write_all(MAGIC)?;
+            out.write_all(&VECTOR_FILE_VERSION.to_le_bytes())?;
+            out.write_all(&(dims as u32).to_le_bytes())?;
+            out.write_all(&(entries.len() as u64).to_le_bytes())?;
+            for (_, vector) in &entries {
+                for x in vector {
+                    out.write_all(&x.to_le_bytes())?;
+                }
+            }
+            for (id, _) in &entries {
+                out.write_all(&(id.len() as u32).to_le_bytes())?;
+                out.write_all(id.as_bytes())?;
+            }
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Memory-map a file written by `save` and insert its vectors, replacing ids already
+    /// present. The vectors are read from the mapping in place; it stays open until every
+    /// loaded vector is removed or replaced. Fails without inserting anything when the format
+    /// version is unknown, the file is truncated, or its dimensions differ from this index's.
+    /// Returns the number of vectors loaded.
+    ///
+    /// The file must not be modified in place while loaded. `save` writes a new file and
+    /// renames it over the old one, so saving to the loaded path is fine.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only; see the doc comment for the in-place write caveat
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let bytes: &[u8] = &map;
+
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(corrupt(path, "not a vector index file"));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let version = u32_at(8);
+        if version != VECTOR_FILE_VERSION {
+            return Err(corrupt(
+                path,
+                &format!(
+                    "format version {} is not supported (expected {})",
+                    version, VECTOR_FILE_VERSION
+                ),
+            ));
+        }
+        let dims = u32_at(12) as usize;
+        let count = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+        if count == 0 {
+            return Ok(0);
+        }
+        if dims == 0 {
+            return Err(corrupt(path, "vectors have 0 dimensions"));
+        }
+        if let Some(expected) = self.dimensions() {
+            if expected != dims {
+                return Err(LoomError::Memory(format!(
+                    "vector index file {} has {} dimensions, index expects {}",
+                    path.display(),
+                    dims,
+                    expected
+                )));
+            }
+        }
+
+        let vectors_len = count
+            .checked_mul(dims)
+            .and_then(|n| n.checked_mul(4))
+            .filter(|n| HEADER_LEN + n <= bytes.len())
+            .ok_or_else(|| corrupt(path, "truncated vector block"))?;
+        let vectors = &bytes[HEADER_LEN..HEADER_LEN + vectors_len];
+        let in_place = cfg!(target_endian = "little") && vectors.as_ptr().align_offset(4) == 0;
+
+        let mut ids = Vec::with_capacity(count);
+        let mut at = HEADER_LEN + vectors_len;
+        for _ in 0..count {
+            if at + 4 > bytes.len() {
+                return Err(corrupt(path, "truncated id table"));
+            }
+            let len = u32_at(at) as usize;
+            let id = bytes
+                .get(at + 4..at + 4 + len)
+                .ok_or_else(|| corrupt(path, "truncated id table"))?;
+            let id = std::str::from_utf8(id).map_err(|_| corrupt(path, "id is not valid UTF-8"))?;
+            ids.push(id.to_string());
+            at += 4 + len;
+        }
+
+        if !in_place {
+            let decoded = ids
+                .into_iter()
+                .zip(vectors.chunks_exact(dims * 4))
+                .map(|(id, raw)| {
+                    let vector = raw
+                        .chunks_exact(4)
+                        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                        .collect();
+                    (id, StoredVector::Owned(vector))
+                });
+            self.insert_all(dims, decoded)?;
+            return Ok(count);
+        }
+        let mapped = Arc::new(MappedVectors { map, dims });
+        let rows = ids
+            .into_iter()
+            .enumerate()
+            .map(|(row, id)| (id, StoredVector::Mapped(Arc::clone(&mapped), row)));
+        self.insert_all(dims, rows)?;
+        Ok(count)
+    }
+}
@@ -23,6 +23,8 @@ pub mod keys {
     pub const HOP_COUNT: &str = "hop";
     /// Timestamp in milliseconds since epoch
     pub const TIMESTAMP_MS: &str = "ts";
+    /// Id of the event that caused this one (causation id); per event, never propagated
+    pub const PARENT_ID: &str = "parent_id";
 }
 
 /// Topic conventions for thread-scoped communication.
@@ -396,7 +398,7 @@ impl Envelope {
     ///     headers: HashMap::new(),
     ///     timeout_ms: 5000,
     ///     correlation_id: String::new(),
-    ///     qos: 0,
+    ///     qos: None,
     /// };
     ///
     /// env.apply_to_action_call(&mut call);
@@ -1,11 +1,13 @@
 // Event bus implementation
-use crate::Result;
+use crate::event_store::{EventPersister, EventStore, PersistFailure, PersistMode};
+use crate::{LoomError, Result};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::{broadcast, mpsc};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, Notify};
 use tracing::{debug, info, warn, Span};
 
 pub use crate::proto::{Event, QoSLevel};
@@ -33,6 +35,9 @@ pub trait EventExt {
     /// Sets the sender metadata and returns self for chaining.
     fn with_sender(self, sender: String) -> Self;
 
+    /// Sets the parent_id (causing event) metadata and returns self for chaining.
+    fn with_parent(self, parent_id: String) -> Self;
+
     /// Reads thread_id from metadata.
     fn thread_id(&self) -> Option<&str>;
 
@@ -44,6 +49,14 @@ pub trait EventExt {
 
     /// Reads sender from metadata.
     fn sender(&self) -> Option<&str>;
+
+    /// Reads parent_id from metadata.
+    fn parent_id(&self) -> Option<&str>;
+
+    /// Starts a fluent builder for an event of `event_type`.
+    fn builder(event_type: impl Into<String>) -> EventBuilder
+    where
+        Self: Sized;
 }
 
 impl EventExt for Event {
@@ -73,6 +86,12 @@ impl EventExt for Event {
         self
     }
 
+    fn with_parent(mut self, parent_id: String) -> Self {
+        self.metadata
+            .insert(crate::envelope::keys::PARENT_ID.to_string(), parent_id);
+        self
+    }
+
     fn thread_id(&self) -> Option<&str> {
         self.metadata
             .get(crate::envelope::keys::THREAD_ID)
@@ -96,6 +115,118 @@ impl EventExt for Event {
             .get(crate::envelope::keys::SENDER)
             .map(|s| s.as_str())
     }
+
+    fn parent_id(&self) -> Option<&str> {
+        self.metadata
+            .get(crate::envelope::keys::PARENT_ID)
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    fn builder(event_type: impl Into<String>) -> EventBuilder {
+        EventBuilder::new(event_type)
+    }
+}
+
+/// Builder for `Event`; `build` generates an `id` when none was set.
+///
+/// Defaults: timestamp = now on the wall clock (see `clock`), confidence = 1.0, priority = 50.
+#[derive(Debug, Clone)]
+pub struct EventBuilder {
+    event: Event,
+    id_version: Option<crate::ids::UuidVersion>,
+    id_generator: Option<Arc<dyn crate::ids::IdGenerator>>,
+}
+
+impl EventBuilder {
+    pub fn new(event_type: impl Into<String>) -> Self {
+        Self {
+            event: Event {
+                r#type: event_type.into(),
+                timestamp_ms: chrono::Utc::now().timestamp_millis(),
+                confidence: 1.0,
+                priority: 50,
+                ..Default::default()
+            },
+            id_version: None,
+            id_generator: None,
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.event.id = id.into();
+        self
+    }
+
+    /// UUID version for the generated id instead of the default generator
+    pub fn id_version(mut self, version: crate::ids::UuidVersion) -> Self {
+        self.id_version = Some(version);
+        self
+    }
+
+    /// Generate the id with `generator` instead of `id_version`
+    pub fn id_generator(mut self, generator: Arc<dyn crate::ids::IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.event.source = source.into();
+        self
+    }
+
+    pub fn timestamp_ms(mut self, timestamp_ms: i64) -> Self {
+        self.event.timestamp_ms = timestamp_ms;
+        self
+    }
+
+    /// Stamp the event with `clock`'s now, for components that take an injected `Clock`
+    pub fn clock(self, clock: &dyn crate::Clock) -> Self {
+        self.timestamp_ms(clock.now_ms())
+    }
+
+    pub fn payload(mut self, payload: Vec<u8>) -> Self {
+        self.event.payload = payload;
+        self
+    }
+
+    /// Serialize `value` as the JSON payload
+    pub fn payload_json<T: Serialize + ?Sized>(mut self, value: &T) -> Result<Self> {
+        self.event.payload = serde_json::to_vec(value)?;
+        Ok(self)
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.event.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Record the event that caused this one (`parent_id` metadata)
+    pub fn parent(self, parent_id: impl Into<String>) -> Self {
+        self.metadata(crate::envelope::keys::PARENT_ID, parent_id)
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.event.tags.push(tag.into());
+        self
+    }
+
+    pub fn confidence(mut self, confidence: f32) -> Self {
+        self.event.confidence = confidence;
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.event.priority = priority;
+        self
+    }
+
+    pub fn build(mut self) -> Event {
+        if self.event.id.is_empty() {
+            self.event.id = crate::ids::builder_id(&self.id_generator, self.id_version);
+        }
+        self.event
+    }
 }
 
 /// Event handler trait
@@ -125,10 +256,67 @@ pub struct EventBusStats {
     pub dropped_events: u64,
 }
 
+/// What happened to one published event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishReceipt {
+    /// Subscriptions on the topic whose event-type filter accepts the event
+    pub matched_subscribers: u64,
+    /// Subscriptions the event was queued for
+    pub delivered: u64,
+    /// Matched subscriptions that did not get it (backpressure, full or closed queue)
+    pub dropped: u64,
+}
+
+impl PublishReceipt {
+    /// Nobody received the event, because nobody matched or every delivery was dropped
+    pub fn is_unheard(&self) -> bool {
+        self.delivered == 0
+    }
+}
+
+/// How long `EventBus::shutdown` waits for in-flight publishes and persistence
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Publishes still running; `shutdown` waits for the count to reach zero
+#[derive(Default)]
+struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl InFlight {
+    fn enter(&self) -> InFlightGuard<'_> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self)
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            // Registered before the check, so a publish finishing in between still wakes us
+            let idle = self.idle.notified();
+            if self.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+struct InFlightGuard<'a>(&'a InFlight);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
 /// Event bus core implementation
 pub struct EventBus {
     // Topic -> Subscriber list
-    subscriptions: Arc<DashMap<String, Vec<Subscription>>>,
+    // copy-on-write per topic: publish snapshots the list with one Arc clone
+    subscriptions: Arc<DashMap<String, Arc<Vec<Subscription>>>>,
 
     // Broadcast channel for high priority events
     #[allow(dead_code)]
@@ -143,6 +331,13 @@ pub struct EventBus {
     // Dashboard event broadcaster (optional)
     dashboard_broadcaster: Option<crate::dashboard::EventBroadcaster>,
 
+    // Durable store written on publish (optional)
+    persister: Option<EventPersister>,
+
+    // Set by `shutdown`; publishes and subscribes are rejected afterwards
+    closed: AtomicBool,
+    in_flight: InFlight,
+
     // OpenTelemetry metrics
     published_counter: Counter<u64>,
     delivered_counter: Counter<u64>,
@@ -194,6 +389,9 @@ impl EventBus {
             stats: Arc::new(DashMap::new()),
             backpressure_threshold: 10_000,
             dashboard_broadcaster: None,
+            persister: None,
+            closed: AtomicBool::new(false),
+            in_flight: InFlight::default(),
             published_counter,
             delivered_counter,
             dropped_counter,
@@ -208,10 +406,34 @@ impl EventBus {
         Ok(())
     }
 
+    /// `shutdown_with_grace` with `DEFAULT_SHUTDOWN_GRACE`
     pub async fn shutdown(&self) -> Result<()> {
+        self.shutdown_with_grace(DEFAULT_SHUTDOWN_GRACE).await
+    }
+
+    /// Stop accepting publishes and subscriptions, let publishes already running deliver,
+    /// flush an `Async` event store, then close every subscriber channel. Receivers get the
+    /// events already queued, then `None`. The wait is bounded by `grace`: past it the
+    /// channels are closed anyway and `LoomError::Timeout` is returned.
+    pub async fn shutdown_with_grace(&self, grace: Duration) -> Result<()> {
         info!("Event Bus shutting down");
+        self.closed.store(true, Ordering::SeqCst);
+        let drain = async {
+            self.in_flight.wait_idle().await;
+            self.flush_event_store().await;
+        };
+        let drained = tokio::time::timeout(grace, drain).await;
+        // Dropping the senders ends every subscriber stream after its queued events
         self.subscriptions.clear();
-        Ok(())
+        drained.map_err(|_| {
+            warn!(target: "event_bus", grace = ?grace, "Shutdown grace elapsed before publishes and persistence drained");
+            LoomError::Timeout(format!("event bus drain exceeded {grace:?}"))
+        })
+    }
+
+    /// Whether `shutdown` was called
+    pub fn is_shut_down(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
     }
 
     /// Set dashboard broadcaster for real-time event streaming
@@ -219,13 +441,49 @@ impl EventBus {
         self.dashboard_broadcaster = Some(broadcaster);
     }
 
-    /// Publish event to topic
+    /// Persist every published event to `store`; `Async` mode must be set up inside a Tokio runtime
+    pub fn with_event_store(
+        mut self,
+        store: Arc<dyn EventStore>,
+        mode: PersistMode,
+        on_failure: PersistFailure,
+    ) -> Self {
+        self.persister = Some(EventPersister::new(store, mode, on_failure));
+        self
+    }
+
+    /// Wait until events queued for an `Async` store are written
+    pub async fn flush_event_store(&self) {
+        if let Some(persister) = &self.persister {
+            persister.flush().await;
+        }
+    }
+
+    /// Failed store writes so far, across all topics
+    pub fn persist_failures(&self) -> u64 {
+        self.persister.as_ref().map_or(0, EventPersister::failures)
+    }
+
+    /// Publish event to topic; the receipt says how many subscribers matched, got or missed it
     #[tracing::instrument(skip(self, event), fields(topic = %topic, event_id = %event.id, event_type = %event.r#type, qos_level = "unknown"))]
-    pub async fn publish(&self, topic: &str, event: Event) -> Result<u64> {
+    pub async fn publish(&self, topic: &str, event: Event) -> Result<PublishReceipt> {
         let start_time = Instant::now();
+        // Counted before the check, so shutdown either rejects this publish or waits for it
+        let _in_flight = self.in_flight.enter();
+        if self.is_shut_down() {
+            return Err(LoomError::EventBusError(format!(
+                "event bus is shut down; dropped event {} for topic {}",
+                event.id, topic
+            )));
+        }
 
         debug!("Publishing event {} to topic {}", event.id, topic);
 
+        // Persist first so a stored event is never missing one that subscribers saw
+        if let Some(ref persister) = self.persister {
+            persister.persist(topic, &event).await?;
+        }
+
         // Broadcast to Dashboard (if enabled)
         if let Some(ref broadcaster) = self.dashboard_broadcaster {
             let payload_preview = String::from_utf8_lossy(&event.payload)
@@ -281,16 +539,22 @@ impl EventBus {
             );
         }
 
-        // Get subscribers
-        if let Some(subs) = self.subscriptions.get(topic) {
+        // Get subscribers; a snapshot, so no map lock is held while sends await queue space
+        let subs = self
+            .subscriptions
+            .get(topic)
+            .map(|subs| Arc::clone(subs.value()));
+        if let Some(subs) = subs {
+            let mut matched = 0;
             let mut delivered = 0;
             let mut dropped = 0;
 
-            for sub in subs.value() {
+            for sub in subs.iter() {
                 // Check event type filtering
                 if !sub.event_types.is_empty() && !sub.event_types.contains(&event.r#type) {
                     continue;
                 }
+                matched += 1;
 
                 // Handle based on QoS level
                 match sub.qos {
@@ -359,7 +623,11 @@ impl EventBus {
             Span::current().record("dropped_count", dropped);
             Span::current().record("latency_ms", elapsed_ms);
 
-            Ok(delivered)
+            Ok(PublishReceipt {
+                matched_subscribers: matched,
+                delivered,
+                dropped,
+            })
         } else {
             warn!("No subscriptions for topic: {}", topic);
             // Decrement backlog for the publish that had no subscribers
@@ -376,7 +644,7 @@ impl EventBus {
             self.publish_latency
                 .record(elapsed_ms, &[KeyValue::new("topic", topic.to_string())]);
 
-            Ok(0)
+            Ok(PublishReceipt::default())
         }
     }
 
@@ -388,7 +656,13 @@ impl EventBus {
         event_types: Vec<String>,
         qos: QoSLevel,
     ) -> Result<(String, mpsc::Receiver<Event>)> {
-        let subscription_id = format!("sub_{}_{}", topic, uuid::Uuid::new_v4());
+        if self.is_shut_down() {
+            return Err(LoomError::EventBusError(format!(
+                "event bus is shut down; cannot subscribe to {}",
+                topic
+            )));
+        }
+        let subscription_id = format!("sub_{}_{}", topic, crate::ids::uuid_v4());
         Span::current().record("subscription_id", &subscription_id);
 
         let cap = match qos {
@@ -406,10 +680,9 @@ impl EventBus {
             sender: tx,
         };
 
-        self.subscriptions
-            .entry(topic.clone())
-            .or_default()
-            .push(subscription);
+        let mut subs = self.subscriptions.entry(topic.clone()).or_default();
+        Arc::make_mut(subs.value_mut()).push(subscription);
+        drop(subs);
 
         self.update_stats(&topic, |stats| {
             stats.active_subscriptions += 1;
@@ -431,11 +704,9 @@ impl EventBus {
     pub async fn unsubscribe(&self, subscription_id: &str) -> Result<()> {
         for mut entry in self.subscriptions.iter_mut() {
             let topic = entry.key().clone();
-            let before_count = entry.value().len();
-            entry.value_mut().retain(|sub| sub.id != subscription_id);
-            let after_count = entry.value().len();
+            if entry.value().iter().any(|sub| sub.id == subscription_id) {
+                Arc::make_mut(entry.value_mut()).retain(|sub| sub.id != subscription_id);
 
-            if before_count != after_count {
                 self.update_stats(&topic, |stats| {
                     stats.active_subscriptions = stats.active_subscriptions.saturating_sub(1);
                 });
@@ -493,18 +764,3 @@ impl<T> Apply for T {
         f(self)
     }
 }
-
-// UUID generation placeholder
-mod uuid {
-    pub struct Uuid;
-    impl Uuid {
-        pub fn new_v4() -> String {
-            use std::time::{SystemTime, UNIX_EPOCH};
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_nanos();
-            format!("{:x}", now)
-        }
-    }
-}
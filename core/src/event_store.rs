@@ -0,0 +1,456 @@
+//! Durable event storage hooked into `EventBus::publish`.
+//!
+//! Attach a store with `EventBus::with_event_store`. `PersistMode` picks whether `publish`
+//! waits for the write; `PersistFailure` picks whether a failed write fails the publish.
+//!
+//! JSONL records carry a `schema_version`. Records from older layouts are upgraded on read by
+//! chaining one migration step per version (see `migrate_event`), so logs written by earlier
+//! builds keep loading as `Event` evolves.
+
+use crate::proto::Event;
+use crate::{LoomError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+/// Sink for published events
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    async fn persist(&self, topic: &str, event: &Event) -> Result<()>;
+}
+
+/// When `publish` persists relative to delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PersistMode {
+    /// Persist before delivering; `publish` waits for the store
+    #[default]
+    Sync,
+    /// Queue for a background writer that persists in publish order; `publish` does not wait
+    Async,
+}
+
+/// What a failed write does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PersistFailure {
+    /// `publish` returns the error and the event is not delivered (`Sync` mode only)
+    #[default]
+    Error,
+    /// Log, count in `EventBus::persist_failures`, and deliver anyway
+    BestEffort,
+}
+
+/// Keeps every persisted event in memory, in persist order
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    events: Mutex<Vec<(String, Event)>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `(topic, event)` pairs in persist order
+    pub fn events(&self) -> Vec<(String, Event)> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn events_for(&self, topic: &str) -> Vec<Event> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(t, _)| t == topic)
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn persist(&self, topic: &str, event: &Event) -> Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .push((topic.to_string(), event.clone()));
+        Ok(())
+    }
+}
+
+/// Record layout `JsonlEventStore` writes. Bump it together with a new step in `MIGRATIONS`.
+pub const EVENT_SCHEMA_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n - 1]` upgrades a version `n` record to version `n + 1`
+const MIGRATIONS: [fn(&mut Map<String, Value>); 1] = [v1_to_v2];
+
+/// v1 records predate `schema_version` and kept binary payloads in a separate `payload_hex`
+/// field; v2 keeps every payload in `payload` and names its `payload_encoding`
+fn v1_to_v2(record: &mut Map<String, Value>) {
+    record.insert("schema_version".to_string(), Value::from(2));
+    if let Some(hex) = record.remove("payload_hex") {
+        record.insert("payload".to_string(), hex);
+        record.insert("payload_encoding".to_string(), Value::from("hex"));
+    }
+}
+
+/// Version of a stored record; records without the field are v1
+fn schema_version_of(record: &Map<String, Value>) -> Result<u32> {
+    match record.get("schema_version") {
+        None => Ok(1),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| LoomError::StorageError(format!("bad schema_version: {v}"))),
+    }
+}
+
+/// Upgrade a raw record written at `from_version` to the current layout
+fn migrate_record(raw: Value, from_version: u32) -> Result<StoredEvent> {
+    let Value::Object(mut record) = raw else {
+        return Err(LoomError::StorageError(
+            "stored event is not a JSON object".to_string(),
+        ));
+    };
+    if from_version == 0 || from_version > EVENT_SCHEMA_VERSION {
+        return Err(LoomError::StorageError(format!(
+            "unsupported event schema version {from_version} (current {EVENT_SCHEMA_VERSION})"
+        )));
+    }
+    for step in &MIGRATIONS[from_version as usize - 1..] {
+        step(&mut record);
+    }
+    Ok(serde_json::from_value(Value::Object(record))?)
+}
+
+/// Decode a stored event record written at `from_version`, upgrading it to the current `Event`.
+///
+/// `raw` is one JSONL line as written by `JsonlEventStore` (the `topic` field is ignored).
+/// Versions newer than `EVENT_SCHEMA_VERSION` are rejected with `StorageError` rather than
+/// read lossily.
+pub fn migrate_event(raw: Value, from_version: u32) -> Result<Event> {
+    Ok(migrate_record(raw, from_version)?.into_event()?.1)
+}
+
+/// How `StoredEvent::payload` encodes the payload bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PayloadEncoding {
+    /// The payload is UTF-8 text, stored as is
+    #[default]
+    Utf8,
+    /// Anything else, hex-encoded
+    Hex,
+}
+
+impl PayloadEncoding {
+    fn is_utf8(&self) -> bool {
+        *self == PayloadEncoding::Utf8
+    }
+}
+
+/// One JSON object per line. UTF-8 payloads are stored as text in `payload`, anything else
+/// hex-encoded with `payload_encoding: "hex"`.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredEvent {
+    schema_version: u32,
+    #[serde(default)]
+    topic: String,
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    timestamp_ms: i64,
+    source: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    payload: String,
+    #[serde(default, skip_serializing_if = "PayloadEncoding::is_utf8")]
+    payload_encoding: PayloadEncoding,
+    #[serde(default)]
+    confidence: f32,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    priority: i32,
+}
+
+impl StoredEvent {
+    fn new(topic: &str, event: &Event) -> Self {
+        let (payload, payload_encoding) = match std::str::from_utf8(&event.payload) {
+            Ok(text) => (text.to_string(), PayloadEncoding::Utf8),
+            Err(_) => (to_hex(&event.payload), PayloadEncoding::Hex),
+        };
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            topic: topic.to_string(),
+            id: event.id.clone(),
+            event_type: event.r#type.clone(),
+            timestamp_ms: event.timestamp_ms,
+            source: event.source.clone(),
+            metadata: event.metadata.clone(),
+            payload,
+            payload_encoding,
+            confidence: event.confidence,
+            tags: event.tags.clone(),
+            priority: event.priority,
+        }
+    }
+
+    fn into_event(self) -> Result<(String, Event)> {
+        let payload = match self.payload_encoding {
+            PayloadEncoding::Utf8 => self.payload.into_bytes(),
+            PayloadEncoding::Hex => from_hex(&self.payload).ok_or_else(|| {
+                LoomError::StorageError(format!("bad hex payload in {}", self.id))
+            })?,
+        };
+        let event = Event {
+            id: self.id,
+            r#type: self.event_type,
+            timestamp_ms: self.timestamp_ms,
+            source: self.source,
+            metadata: self.metadata,
+            payload,
+            confidence: self.confidence,
+            tags: self.tags,
+            priority: self.priority,
+        };
+        Ok((self.topic, event))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Appends events to a JSON Lines file; `load` reads them back for recovery
+pub struct JsonlEventStore {
+    path: PathBuf,
+    file: tokio::sync::Mutex<Option<tokio::fs::File>>,
+    fsync: bool,
+}
+
+impl JsonlEventStore {
+    /// The file is created on first write and always appended to. An existing file that ends
+    /// in a torn line is repaired first, so new events never land after the fragment.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: tokio::sync::Mutex::new(None),
+            fsync: false,
+        }
+    }
+
+    /// `fsync` after every line so events survive power loss, not just a process crash
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read `(topic, event)` pairs in write order, upgrading records from older schema versions;
+    /// a missing file is empty. A torn last line (crash mid-write) is skipped; malformed lines
+    /// elsewhere are errors.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Vec<(String, Event)>> {
+        let text = match tokio::fs::read_to_string(path.as_ref()).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        let mut events = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<Map<String, Value>>(line) {
+                Ok(record) => {
+                    let version = schema_version_of(&record)?;
+                    let stored = migrate_record(Value::Object(record), version)?;
+                    events.push(stored.into_event()?);
+                }
+                Err(_) if i + 1 == lines.len() && !text.ends_with('\n') => {
+                    warn!(target: "event_store", path = %path.as_ref().display(), "Skipping torn trailing line");
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(events)
+    }
+
+    /// Make the file end on a line boundary before appending: a complete record missing only
+    /// its newline gets one, and a torn fragment (which `load` skips) is cut off
+    async fn repair_tail(&self) -> Result<()> {
+        let bytes = match tokio::fs::read(&self.path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let keep = bytes.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let tail = &bytes[keep..];
+        if tail.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+        if serde_json::from_slice::<Map<String, Value>>(tail).is_ok() {
+            let mut file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&self.path)
+                .await?;
+            file.write_all(b"\n").await?;
+            file.flush().await?;
+            return Ok(());
+        }
+        warn!(target: "event_store", path = %self.path.display(), bytes = tail.len(), "Truncating torn trailing line before appending");
+        let file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .await?;
+        file.set_len(keep as u64).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventStore for JsonlEventStore {
+    async fn persist(&self, topic: &str, event: &Event) -> Result<()> {
+        let mut line = serde_json::to_vec(&StoredEvent::new(topic, event))?;
+        line.push(b'\n');
+        let mut guard = self.file.lock().await;
+        if guard.is_none() {
+            self.repair_tail().await?;
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            *guard = Some(file);
+        }
+        let file = guard.as_mut().expect("opened above");
+        file.write_all(&line).await?;
+        file.flush().await?;
+        if self.fsync {
+            file.sync_data().await?;
+        }
+        Ok(())
+    }
+}
+
+enum StoreCommand {
+    Persist(String, Event),
+    Flush(oneshot::Sender<()>),
+}
+
+/// The bus side of an attached store
+pub(crate) struct EventPersister {
+    store: Arc<dyn EventStore>,
+    on_failure: PersistFailure,
+    failures: Arc<AtomicU64>,
+    queue: Option<mpsc::UnboundedSender<StoreCommand>>,
+}
+
+impl EventPersister {
+    /// `Async` mode spawns the writer task, so this must run inside a Tokio runtime
+    pub(crate) fn new(
+        store: Arc<dyn EventStore>,
+        mode: PersistMode,
+        on_failure: PersistFailure,
+    ) -> Self {
+        let failures = Arc::new(AtomicU64::new(0));
+        let queue = match mode {
+            PersistMode::Sync => None,
+            PersistMode::Async => {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let store = Arc::clone(&store);
+                let failures = Arc::clone(&failures);
+                tokio::spawn(async move {
+                    while let Some(cmd) = rx.recv().await {
+                        match cmd {
+                            StoreCommand::Persist(topic, event) => {
+                                if let Err(e) = store.persist(&topic, &event).await {
+                                    failures.fetch_add(1, Ordering::Relaxed);
+                                    warn!(target: "event_store", topic = %topic, event_id = %event.id, error = %e, "Failed to persist event");
+                                }
+                            }
+                            StoreCommand::Flush(done) => {
+                                let _ = done.send(());
+                            }
+                        }
+                    }
+                });
+                Some(tx)
+            }
+        };
+        Self {
+            store,
+            on_failure,
+            failures,
+            queue,
+        }
+    }
+
+    /// `Err` only for a failed `Sync` write under `PersistFailure::Error`
+    pub(crate) async fn persist(&self, topic: &str, event: &Event) -> Result<()> {
+        if let Some(queue) = &self.queue {
+            if queue
+                .send(StoreCommand::Persist(topic.to_string(), event.clone()))
+                .is_err()
+            {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                warn!(target: "event_store", topic = %topic, "Event store writer stopped");
+            }
+            return Ok(());
+        }
+        match self.store.persist(topic, event).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                warn!(target: "event_store", topic = %topic, event_id = %event.id, error = %e, "Failed to persist event");
+                match self.on_failure {
+                    PersistFailure::Error => Err(LoomError::StorageError(format!(
+                        "failed to persist event {}: {}",
+                        event.id, e
+                    ))),
+                    PersistFailure::BestEffort => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Wait until every event queued so far has been written (no-op in `Sync` mode)
+    pub(crate) async fn flush(&self) {
+        if let Some(queue) = &self.queue {
+            let (tx, rx) = oneshot::channel();
+            if queue.send(StoreCommand::Flush(tx)).is_ok() {
+                let _ = rx.await;
+            }
+        }
+    }
+
+    pub(crate) fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
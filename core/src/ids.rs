@@ -0,0 +1,192 @@
+//! Unique identifier generation for calls, results and events.
+//!
+//! Components that assign ids on their own (broker, agent, orchestrator, collaborator, builders)
+//! take an [`IdGenerator`]; the default is UUID v7. Builders without one of their own use the
+//! process-wide default (`set_default_id_generator`).
+
+use rand::RngCore;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Source of auto-assigned `id` / thread / correlation values
+pub trait IdGenerator: Send + Sync + fmt::Debug {
+    fn new_id(&self) -> String;
+}
+
+// Installed by `set_default_id_generator`; `None` means UUID v7
+static DEFAULT_GENERATOR: RwLock<Option<Arc<dyn IdGenerator>>> = RwLock::new(None);
+
+/// Process-wide default generator: the one installed with `set_default_id_generator`, else
+/// UUID v7
+pub fn default_id_generator() -> Arc<dyn IdGenerator> {
+    DEFAULT_GENERATOR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(UuidVersion::V7))
+}
+
+/// Route every id not generated by an explicit generator through `generator`: builders
+/// without `.id_generator(..)` / `.id_version(..)`, and brokers and collaborators created
+/// afterwards
+pub fn set_default_id_generator(generator: Arc<dyn IdGenerator>) {
+    *DEFAULT_GENERATOR.write().unwrap_or_else(|e| e.into_inner()) = Some(generator);
+}
+
+/// Id for a builder: its own generator, else its UUID version, else the default generator
+pub(crate) fn builder_id(
+    generator: &Option<Arc<dyn IdGenerator>>,
+    version: Option<UuidVersion>,
+) -> String {
+    match (generator, version) {
+        (Some(g), _) => g.new_id(),
+        (None, Some(version)) => version.new_id(),
+        (None, None) => default_id_generator().new_id(),
+    }
+}
+
+/// UUID flavour used for auto-assigned ids
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UuidVersion {
+    /// Fully random
+    V4,
+    /// Unix-millisecond prefix; ids sort by creation time (monotonic within a process)
+    #[default]
+    V7,
+}
+
+impl IdGenerator for UuidVersion {
+    fn new_id(&self) -> String {
+        new_uuid(*self)
+    }
+}
+
+/// New id of the given version
+pub fn new_uuid(version: UuidVersion) -> String {
+    match version {
+        UuidVersion::V4 => uuid_v4(),
+        UuidVersion::V7 => uuid_v7(),
+    }
+}
+
+/// Random (version 4) UUID in canonical hyphenated form
+pub fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    format_uuid(&bytes)
+}
+
+// (last timestamp ms, 12-bit sequence) shared by v7 generation in this process
+static V7_STATE: Mutex<(u64, u16)> = Mutex::new((0, 0));
+
+/// Time-ordered (version 7) UUID.
+///
+/// The 12 `rand_a` bits hold a per-millisecond counter, so ids generated in the same
+/// millisecond still sort in creation order; on counter overflow the timestamp is advanced.
+pub fn uuid_v7() -> String {
+    let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    let (ms, seq) = {
+        let mut state = V7_STATE.lock().unwrap_or_else(|e| e.into_inner());
+        if now_ms > state.0 {
+            *state = (now_ms, 0);
+        } else if state.1 >= 0x0fff {
+            *state = (state.0 + 1, 0);
+        } else {
+            state.1 += 1;
+        }
+        *state
+    };
+
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&ms.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | ((seq >> 8) as u8 & 0x0f); // version 7 + high counter bits
+    bytes[7] = seq as u8;
+    rand::thread_rng().fill_bytes(&mut bytes[8..]);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    format_uuid(&bytes)
+}
+
+fn format_uuid(b: &[u8; 16]) -> String {
+    let hex: String = b.iter().map(|x| format!("{:02x}", x)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// ULIDs: 26 Crockford base32 characters, millisecond timestamp then 80 random bits.
+///
+/// Monotonic per generator: within one millisecond the random part is incremented, so ids
+/// sort in creation order.
+#[derive(Debug, Default)]
+pub struct UlidGenerator {
+    // (last timestamp ms, last 80-bit random part)
+    state: Mutex<(u64, u128)>,
+}
+
+impl UlidGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdGenerator for UlidGenerator {
+    fn new_id(&self) -> String {
+        const RAND_MASK: u128 = (1 << 80) - 1;
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let (ms, rand80) = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if now_ms > state.0 {
+                let mut bytes = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                *state = (now_ms, u128::from_be_bytes(bytes) & RAND_MASK);
+            } else if state.1 == RAND_MASK {
+                // Random part exhausted within this millisecond: borrow the next one
+                *state = (state.0 + 1, 0);
+            } else {
+                state.1 += 1;
+            }
+            *state
+        };
+        let value = ((ms as u128 & ((1 << 48) - 1)) << 80) | rand80;
+        (0..26)
+            .rev()
+            .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1f) as usize] as char)
+            .collect()
+    }
+}
+
+/// Deterministic `"{prefix}{n}"` ids with `n` counting up from 1 (tests, single-process tools)
+#[derive(Debug)]
+pub struct CounterIdGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl CounterIdGenerator {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdGenerator for CounterIdGenerator {
+    fn new_id(&self) -> String {
+        format!(
+            "{}{}",
+            self.prefix,
+            self.next.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+}
@@ -3,40 +3,63 @@
 
 pub mod action_broker;
 pub mod agent;
+pub mod clock; // Injectable time source (SystemClock / MockClock)
 pub mod collab; // Collaboration primitives built on EventBus + Envelope
+pub mod compression; // Optional gzip/zstd payload compression
 pub mod context;
 pub mod dashboard; // Real-time event flow visualization
 pub mod directory; // Agent & Capability directories
 pub mod envelope; // Unified metadata envelope for events/actions threads
 pub mod event;
+pub mod event_store; // Durable EventStore sinks written on publish
+pub mod ids; // UUID v4/v7 generation for auto-assigned ids
 pub mod llm;
 pub mod mcp; // Model Context Protocol client and adapters
+pub mod payload; // JSON payload codec with exact or lossy numbers
+pub mod plan; // Declarative multi-step plans run through the ActionBroker
 pub mod plugin;
 pub mod providers;
 pub mod router;
 pub mod storage;
 pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing; // Synthetic providers and a load harness for stress tests (feature "testing")
 
 // Export core types
-pub use action_broker::{ActionBroker, CapabilityProvider};
+pub use action_broker::{
+    ActionBroker, ActionCallBuilder, ActionCallExt, ActionResultBuilder, ActionResultExt,
+    AllowListAuthorizer, Authorizer, BrokerConfig, CapabilityProvider, JobProvider,
+};
 pub use agent::{Agent, AgentRuntime, AgentState};
+pub use clock::{Clock, MockClock, SystemClock};
 pub use collab::{types as collab_types, Collaborator};
 pub use context::{builder::ContextBuilder, PromptBundle, TokenBudget};
 pub use directory::{AgentDirectory, AgentInfo, CapabilityDirectory};
 pub use envelope::{agent_reply_topic, Envelope, ThreadTopicKind};
-pub use event::{Event, EventBus, EventExt, EventHandler, QoSLevel};
+pub use event::{Event, EventBuilder, EventBus, EventExt, EventHandler, PublishReceipt, QoSLevel};
+pub use event_store::{
+    migrate_event, EventStore, InMemoryEventStore, JsonlEventStore, PersistFailure, PersistMode,
+    EVENT_SCHEMA_VERSION,
+};
+pub use ids::{IdGenerator, UuidVersion};
 pub use llm::{LlmClient, LlmClientConfig, LlmResponse};
 pub use mcp::{McpClient, McpManager, McpToolAdapter};
+pub use payload::{NumberMode, PayloadCodec};
+pub use plan::{OnStepError, Plan, PlanExecutor, PlanOutcome};
 pub use plugin::{Plugin, PluginManager};
-pub use providers::{WeatherProvider, WebSearchProvider};
+pub use providers::{MockProvider, WeatherProvider, WebSearchProvider};
 pub use router::{
     ConfidenceEstimator, DummyConfidenceEstimator, ModelRouter, Route, RoutingDecision,
 };
+pub use telemetry::TurnSpan;
 
 // Generated proto code
 // Re-export proto types from the shared crate so existing paths `crate::proto::...` continue to work.
 pub use loom_proto as proto;
 
+// Cooperative cancellation handle accepted by long-running operations
+pub use tokio_util::sync::CancellationToken;
+
 // Error types
 use thiserror::Error;
 
@@ -61,7 +84,22 @@ pub enum LoomError {
     IoError(#[from] std::io::Error),
 
     #[error("Serialization error: {0}")]
-    SerializationError(#[from] serde_json::Error),
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Memory error: {0}")]
+    Memory(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+}
+
+impl From<tokio::time::error::Elapsed> for LoomError {
+    fn from(e: tokio::time::error::Elapsed) -> Self {
+        LoomError::Timeout(e.to_string())
+    }
 }
 pub type Result<T> = std::result::Result<T, LoomError>;
 
@@ -1,4 +1,4 @@
-use crate::context::{PromptBundle, TokenBudget};
+use crate::context::{HistoryEntry, PromptBundle, Role, TokenBudget};
 use serde_json::json;
 
 // Formatting overhead constants for fused text assembly
@@ -8,6 +8,19 @@ const HISTORY_LABEL_OVERHEAD: usize = "History:\n\n".len(); // "History:\n" + fi
 const HISTORY_ITEM_OVERHEAD: usize = "- \n".len(); // "- " + "\n" per history item
 const USER_LABEL_OVERHEAD: usize = "User:\n\n".len(); // "User:\n" + "\n"
 
+/// History line as rendered in fused text ("role: content")
+fn history_line(entry: &HistoryEntry) -> String {
+    format!("{}: {}", entry.role, entry.content)
+}
+
+/// Chat API role for a history entry; tool output without a tool_call_id is sent as user text
+fn chat_role(role: Role) -> &'static str {
+    match role {
+        Role::Tool => "user",
+        other => other.as_str(),
+    }
+}
+
 /// Helper function to calculate assembled character count including formatting overhead
 fn calculate_assembled_chars(system: &str, context_block: &str, instructions: &str) -> usize {
     let mut total =
@@ -39,11 +52,11 @@ pub fn promptbundle_to_messages_and_text(
         context_block.push_str("Context:\n");
         for d in &bundle.context_docs {
             context_block.push_str("- ");
-            context_block.push_str(d);
+            context_block.push_str(&d.text);
             context_block.push('\n');
         }
     }
-    let mut history_blocks: Vec<String> = bundle.history.clone();
+    let mut history_blocks: Vec<HistoryEntry> = bundle.history.clone();
     let mut instructions = bundle.instructions.clone();
 
     // Compute current assembled size in characters
@@ -52,7 +65,7 @@ pub fn promptbundle_to_messages_and_text(
         + instructions.chars().count()
         + history_blocks
             .iter()
-            .map(|s| s.chars().count())
+            .map(|h| history_line(h).chars().count())
             .sum::<usize>();
 
     // Add overhead for fused text formatting labels
@@ -76,8 +89,8 @@ pub fn promptbundle_to_messages_and_text(
     // Trim oldest history first until within char budget (character-accurate)
     while assembled_chars > char_budget && !history_blocks.is_empty() {
         let removed = history_blocks.remove(0);
-        assembled_chars =
-            assembled_chars.saturating_sub(removed.chars().count() + HISTORY_ITEM_OVERHEAD);
+        assembled_chars = assembled_chars
+            .saturating_sub(history_line(&removed).chars().count() + HISTORY_ITEM_OVERHEAD);
     }
     // Update formatting overhead if all history was removed
     if history_blocks.is_empty() {
@@ -129,7 +142,8 @@ pub fn promptbundle_to_messages_and_text(
                 bundle
                     .context_docs
                     .first()
-                    .unwrap_or(&String::new())
+                    .map(|d| d.text.as_str())
+                    .unwrap_or_default()
                     .chars()
                     .take(content_chars)
                     .collect::<String>()
@@ -159,7 +173,7 @@ pub fn promptbundle_to_messages_and_text(
         system = system.chars().take(allowed_chars).collect();
     }
 
-    // Build chat messages (system + optional context + history as user + user instructions)
+    // Build chat messages (system + optional context + role-tagged history + user instructions)
     let mut messages = Vec::new();
     if !system.is_empty() {
         messages.push(json!({"role": "system", "content": system}));
@@ -168,7 +182,11 @@ pub fn promptbundle_to_messages_and_text(
         messages.push(json!({"role": "system", "content": context_block.clone()}));
     }
     for h in &history_blocks {
-        messages.push(json!({"role": "user", "content": h}));
+        let content = match h.role {
+            Role::Tool => history_line(h),
+            _ => h.content.clone(),
+        };
+        messages.push(json!({"role": chat_role(h.role), "content": content}));
     }
     if !instructions.is_empty() {
         messages.push(json!({"role": "user", "content": instructions.clone()}));
@@ -189,7 +207,7 @@ pub fn promptbundle_to_messages_and_text(
         fused.push_str("History:\n");
         for h in &history_blocks {
             fused.push_str("- ");
-            fused.push_str(h);
+            fused.push_str(&history_line(h));
             fused.push('\n');
         }
         fused.push('\n');
@@ -4,17 +4,36 @@
 //! - `LlmClientConfig`, `LlmClient`, `LlmResponse` for talking to OpenAI-compatible backends
 //! - `promptbundle_to_messages_and_text` adapter for turning `PromptBundle` into payloads
 //! - `LlmGenerateProvider` capability provider registered as `llm.generate`
+//! - `generate_stream` plus the `llm.token` / `llm.complete` EventBus convention for streaming
+//! - `validate_arguments` for checking tool-call arguments against a JSON Schema subset
+//! - `TurnSummary`, published as `turn.completed` after each orchestrator turn
+//! - `AgentOutcome`, the step trace returned by the multi-step `ToolOrchestrator::run_agent`
 
 mod adapter;
 mod client;
+mod outcome;
 mod provider;
+mod schema;
+mod streaming;
 mod tool_orchestrator;
+mod turn;
 
 pub use adapter::promptbundle_to_messages_and_text;
 pub use client::{LlmClient, LlmClientConfig, LlmResponse};
+pub use outcome::{
+    bundle_hash, AgentOutcome, StepDecision, StepTrace, StopReason, DEFAULT_MAX_ITERATIONS,
+    DEFAULT_MAX_REPEATED_CALLS,
+};
 pub use provider::LlmGenerateProvider;
+pub use schema::validate_arguments;
+pub use streaming::{
+    AssembledStream, ChatStreamDecoder, StreamDelta, TokenStreamAssembler, TokenStreamPublisher,
+    LLM_COMPLETE_EVENT, LLM_TOKEN_EVENT,
+};
 pub use tool_orchestrator::{
-    build_action_call, make_refine_bundle, parse_tool_calls_from_chat,
-    parse_tool_calls_from_responses, FinalAnswer, NormalizedToolCall, OrchestratorOptions,
-    ToolChoice, ToolOrchestrator, ToolOrchestratorStats,
+    build_action_call, make_correction_bundle, make_refine_bundle, parse_tool_calls_from_chat,
+    parse_tool_calls_from_responses, validate_tool_calls, ArgumentError, FinalAnswer,
+    NormalizedToolCall, OrchestratorOptions, ToolChoice, ToolOrchestrator, ToolOrchestratorStats,
+    INVALID_ARGUMENTS,
 };
+pub use turn::{TokenUsage, ToolInvocation, TurnSummary, TURN_COMPLETED_EVENT};
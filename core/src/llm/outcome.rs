@@ -0,0 +1,113 @@
+//! Structured result of a multi-step `ToolOrchestrator::run_agent` loop.
+//!
+//! `AgentOutcome` serializes to JSON so runs can be stored and diffed for evaluation.
+
+use super::tool_orchestrator::NormalizedToolCall;
+use super::turn::ToolInvocation;
+use crate::context::PromptBundle;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Default `OrchestratorOptions::max_iterations`
+pub const DEFAULT_MAX_ITERATIONS: u32 = 8;
+/// Default `OrchestratorOptions::max_repeated_calls`
+pub const DEFAULT_MAX_REPEATED_CALLS: u32 = 3;
+
+/// Why the agent loop stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "message")]
+pub enum StopReason {
+    /// The model answered without calling tools
+    Completed,
+    /// The iteration cap was reached while the model kept calling tools
+    MaxIterations,
+    /// The model asked for the same tool with the same arguments more than
+    /// `max_repeated_calls` times
+    LoopDetected,
+    /// `OrchestratorOptions::deadline_ms` passed
+    DeadlineExceeded,
+    /// A model request or dispatch failed
+    Error(String),
+}
+
+/// What the model chose in one iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepDecision {
+    /// Call one or more tools
+    ToolCalls,
+    /// Answer in text
+    Answer,
+}
+
+/// One model request and the tool calls it produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepTrace {
+    /// 1-based iteration number
+    pub iteration: u32,
+    /// `bundle_hash` of the prompt sent in this iteration
+    pub bundle_hash: String,
+    pub decision: StepDecision,
+    /// Calls as parsed from the model, in order
+    pub tool_calls: Vec<NormalizedToolCall>,
+    /// Outcome of each entry in `tool_calls`, same order; empty when the loop detector
+    /// stopped the run before dispatch
+    pub results: Vec<ToolInvocation>,
+}
+
+/// Full trace of a `run_agent` call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentOutcome {
+    /// Completed iterations in order; an iteration cut short by an error or the deadline
+    /// is not included
+    pub steps: Vec<StepTrace>,
+    /// The model's text answer; only set when `stop_reason` is `Completed`
+    pub final_answer: Option<String>,
+    pub stop_reason: StopReason,
+}
+
+impl AgentOutcome {
+    pub fn is_completed(&self) -> bool {
+        self.stop_reason == StopReason::Completed
+    }
+
+    /// Tool calls made across all steps
+    pub fn tool_call_count(&self) -> usize {
+        self.steps.iter().map(|s| s.tool_calls.len()).sum()
+    }
+}
+
+/// Hex digest of a bundle's JSON form; equal bundles hash equally within a build
+pub fn bundle_hash(bundle: &PromptBundle) -> String {
+    let mut h = DefaultHasher::new();
+    serde_json::to_string(bundle)
+        .unwrap_or_default()
+        .hash(&mut h);
+    format!("{:016x}", h.finish())
+}
+
+/// Counts `(capability, arguments)` pairs across a run to catch a model stuck on one call
+#[derive(Debug, Default)]
+pub(super) struct RepeatedCalls {
+    seen: HashMap<(String, u64), u32>,
+}
+
+impl RepeatedCalls {
+    /// Count `calls`; true when any pair now occurs more than `limit` times (0 = never)
+    pub(super) fn record(&mut self, calls: &[NormalizedToolCall], limit: u32) -> bool {
+        let mut looping = false;
+        for call in calls {
+            let mut h = DefaultHasher::new();
+            call.arguments.to_string().hash(&mut h);
+            let count = self
+                .seen
+                .entry((call.name.clone(), h.finish()))
+                .or_default();
+            *count += 1;
+            looping |= limit > 0 && *count > limit;
+        }
+        looping
+    }
+}
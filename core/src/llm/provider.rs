@@ -1,4 +1,4 @@
-use crate::action_broker::CapabilityProvider;
+use crate::action_broker::{ActionResultExt, CapabilityProvider, CATEGORY_METADATA_KEY};
 use crate::context::PromptBundle;
 use crate::context::TokenBudget;
 use crate::proto::{
@@ -43,6 +43,7 @@ impl LlmGenerateProvider {
                         message: format!("Failed to deserialize payload: {}", e),
                         details: Default::default(),
                     }),
+                    metadata: Default::default(),
                 });
             }
         };
@@ -57,23 +58,21 @@ impl LlmGenerateProvider {
                 tools_json_schema: None,
                 context_docs: Vec::new(),
                 history: Vec::new(),
+                token_breakdown: None,
             }
         };
 
         let res = client.generate(&bundle, payload.budget).await;
         match res {
-            Ok(r) => Ok(ActionResult {
-                id: call.id.clone(),
-                status: ActionStatus::ActionOk as i32,
-                output: serde_json::to_vec(&serde_json::json!({
+            Ok(r) => Ok(ActionResult::try_ok_json(
+                call.id.clone(),
+                &serde_json::json!({
                     "text": r.text,
                     "model": r.model,
                     "provider": r.provider,
                     "usage": r.usage,
-                }))
-                .unwrap_or_default(),
-                error: None,
-            }),
+                }),
+            )),
             Err(e) => Ok(ActionResult {
                 id: call.id.clone(),
                 status: ActionStatus::ActionError as i32,
@@ -83,6 +82,7 @@ impl LlmGenerateProvider {
                     message: e.to_string(),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             }),
         }
     }
@@ -105,7 +105,11 @@ impl CapabilityProvider for LlmGenerateProvider {
             name: "llm.generate".to_string(),
             version: "0.1.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
-            metadata: Default::default(),
+            metadata: [(CATEGORY_METADATA_KEY.to_string(), "llm".to_string())]
+                .into_iter()
+                .collect(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -0,0 +1,129 @@
+//! Minimal JSON Schema checks for tool-call arguments.
+//!
+//! Covers the subset tool schemas actually use: `type`, `properties`, `required`,
+//! `additionalProperties: false`, `enum`, `items`, `minimum`/`maximum` and
+//! `minLength`/`maxLength`. Unknown keywords are ignored, so a schema this module does not
+//! understand never rejects arguments.
+
+use serde_json::Value;
+
+/// Check `args` against `schema`; the error names the offending path (e.g. `$.city`)
+pub fn validate_arguments(schema: &Value, args: &Value) -> std::result::Result<(), String> {
+    check(schema, args, "$")
+}
+
+fn check(schema: &Value, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`{}`-like schemas accept everything
+        return Ok(());
+    };
+
+    if let Some(ty) = schema.get("type") {
+        let allowed: Vec<&str> = match ty {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            return Err(format!(
+                "{} must be of type {}, got {}",
+                path,
+                allowed.join(" | "),
+                type_name(value)
+            ));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!(
+                "{} must be one of {}",
+                path,
+                Value::Array(options.clone())
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(obj) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !obj.contains_key(key) {
+                        return Err(format!("{} is missing required property '{}'", path, key));
+                    }
+                }
+            }
+            let props = schema.get("properties").and_then(Value::as_object);
+            for (key, v) in obj {
+                match props.and_then(|p| p.get(key)) {
+                    Some(sub) => check(sub, v, &format!("{}.{}", path, key))?,
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        return Err(format!("{} has unexpected property '{}'", path, key));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    return Err(format!("{} must be >= {}", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    return Err(format!("{} must be <= {}", path, max));
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    return Err(format!("{} must be at least {} characters", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    return Err(format!("{} must be at most {} characters", path, max));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn matches_type(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        // Unknown type names are not enforced
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,293 @@
+//! Streaming LLM output and the `llm.token` / `llm.complete` EventBus convention.
+//!
+//! Producer side: `LlmClient::generate_stream` yields text chunks; `TokenStreamPublisher`
+//! publishes each as an `llm.token` event and finishes with one `llm.complete` event.
+//! Consumer side: `TokenStreamAssembler` rebuilds the text per correlation id.
+//! `ToolOrchestrator::with_token_stream` publishes its answers through this convention.
+//!
+//! Event shape:
+//! - `llm.token`: payload = chunk (UTF-8); metadata `correlation_id`, `seq` (0-based)
+//! - `llm.complete`: payload = full text; metadata `correlation_id`, `chunks` (token event count)
+
+use super::adapter::promptbundle_to_messages_and_text;
+use super::client::LlmClient;
+use crate::context::{PromptBundle, TokenBudget};
+use crate::event::EventExt;
+use crate::proto::Event;
+use crate::{EventBus, LoomError, Result};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Event type of one streamed chunk
+pub const LLM_TOKEN_EVENT: &str = "llm.token";
+/// Event type closing a stream; its payload is the full text
+pub const LLM_COMPLETE_EVENT: &str = "llm.complete";
+
+/// One decoded server-sent event from a streaming completion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamDelta {
+    Text(String),
+    Done,
+}
+
+/// Incremental decoder for OpenAI-style SSE streams (Chat Completions and Responses).
+///
+/// Bytes may arrive split anywhere, including inside a UTF-8 sequence; only complete lines
+/// are decoded.
+#[derive(Debug, Default)]
+pub struct ChatStreamDecoder {
+    buf: Vec<u8>,
+}
+
+impl ChatStreamDecoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<StreamDelta> {
+        self.buf.extend_from_slice(bytes);
+        let mut out = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            if let Some(delta) = decode_line(&String::from_utf8_lossy(&line)) {
+                out.push(delta);
+            }
+        }
+        out
+    }
+
+    /// Decode a trailing line left without a newline at end of stream
+    pub fn finish(&mut self) -> Option<StreamDelta> {
+        let rest = std::mem::take(&mut self.buf);
+        decode_line(&String::from_utf8_lossy(&rest))
+    }
+}
+
+fn decode_line(line: &str) -> Option<StreamDelta> {
+    let data = line.trim().strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return Some(StreamDelta::Done);
+    }
+    let v: Value = serde_json::from_str(data).ok()?;
+    // Responses API: {"type":"response.output_text.delta","delta":"..."}
+    let text = if v.get("type").and_then(Value::as_str) == Some("response.output_text.delta") {
+        v.get("delta").and_then(Value::as_str)
+    } else {
+        // Chat Completions: {"choices":[{"delta":{"content":"..."}}]}
+        v.get("choices")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("delta"))
+            .and_then(|d| d.get("content"))
+            .and_then(Value::as_str)
+    };
+    text.filter(|t| !t.is_empty())
+        .map(|t| StreamDelta::Text(t.to_string()))
+}
+
+impl LlmClient {
+    /// Stream a Chat Completions answer as text chunks (`"stream": true`)
+    pub async fn generate_stream(
+        &self,
+        bundle: &PromptBundle,
+        budget: Option<TokenBudget>,
+    ) -> Result<BoxStream<'static, Result<String>>> {
+        let budget = budget.unwrap_or_default();
+        let (messages, _) = promptbundle_to_messages_and_text(bundle, budget);
+        let url = format!(
+            "{}/chat/completions",
+            self.cfg.base_url.trim_end_matches('/')
+        );
+        debug!(target = "llm_client", "POST {} (streaming)", url);
+
+        let mut req = self
+            .http
+            .post(&url)
+            .header("content-type", "application/json");
+        if let Some(key) = &self.cfg.api_key {
+            req = req.bearer_auth(key);
+        }
+        let body = json!({
+            "model": self.cfg.model,
+            "messages": messages,
+            "max_tokens": budget.max_output_tokens as u32,
+            "temperature": self.cfg.temperature,
+            "stream": true,
+        });
+        let resp = req
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| LoomError::AgentError(format!("Chat Completions HTTP error: {e}")))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(LoomError::AgentError(format!(
+                "Chat Completions error: status={} body={}",
+                status, text
+            )));
+        }
+
+        let state = (resp, ChatStreamDecoder::default(), VecDeque::new(), false);
+        let chunks = stream::unfold(
+            state,
+            |(mut resp, mut decoder, mut pending, mut done)| async move {
+                loop {
+                    if let Some(text) = pending.pop_front() {
+                        return Some((Ok(text), (resp, decoder, pending, done)));
+                    }
+                    if done {
+                        return None;
+                    }
+                    let deltas = match resp.chunk().await {
+                        Ok(Some(bytes)) => decoder.push(&bytes),
+                        Ok(None) => {
+                            done = true;
+                            decoder.finish().into_iter().collect()
+                        }
+                        Err(e) => {
+                            let err = LoomError::AgentError(format!("Stream read failed: {e}"));
+                            return Some((Err(err), (resp, decoder, pending, true)));
+                        }
+                    };
+                    for delta in deltas {
+                        match delta {
+                            StreamDelta::Text(t) => pending.push_back(t),
+                            StreamDelta::Done => done = true,
+                        }
+                    }
+                }
+            },
+        );
+        Ok(chunks.boxed())
+    }
+
+    /// Stream an answer and publish it on `publisher`'s topic; returns the full text
+    pub async fn generate_to_bus(
+        &self,
+        bundle: &PromptBundle,
+        budget: Option<TokenBudget>,
+        mut publisher: TokenStreamPublisher,
+    ) -> Result<String> {
+        let mut chunks = self.generate_stream(bundle, budget).await?;
+        while let Some(chunk) = chunks.next().await {
+            publisher.token(&chunk?).await?;
+        }
+        publisher.complete().await
+    }
+}
+
+/// Publishes one LLM stream as `llm.token` events followed by `llm.complete`
+pub struct TokenStreamPublisher {
+    bus: Arc<EventBus>,
+    topic: String,
+    correlation_id: String,
+    source: String,
+    seq: u64,
+    text: String,
+}
+
+impl TokenStreamPublisher {
+    /// `correlation_id` ties the stream to its turn; subscribers group chunks by it
+    pub fn new(
+        bus: Arc<EventBus>,
+        topic: impl Into<String>,
+        correlation_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            bus,
+            topic: topic.into(),
+            correlation_id: correlation_id.into(),
+            source: "llm".to_string(),
+            seq: 0,
+            text: String::new(),
+        }
+    }
+
+    /// Event source (default "llm")
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    /// Publish one chunk; empty chunks are skipped
+    pub async fn token(&mut self, chunk: &str) -> Result<()> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        let event = Event::builder(LLM_TOKEN_EVENT)
+            .id(format!("{}:token:{}", self.correlation_id, self.seq))
+            .source(self.source.clone())
+            .payload(chunk.as_bytes().to_vec())
+            .metadata("correlation_id", self.correlation_id.clone())
+            .metadata("seq", self.seq.to_string())
+            .build();
+        self.bus.publish(&self.topic, event).await?;
+        self.seq += 1;
+        self.text.push_str(chunk);
+        Ok(())
+    }
+
+    /// Publish `llm.complete` with the full text and return it
+    pub async fn complete(self) -> Result<String> {
+        let event = Event::builder(LLM_COMPLETE_EVENT)
+            .id(format!("{}:complete", self.correlation_id))
+            .source(self.source.clone())
+            .payload(self.text.as_bytes().to_vec())
+            .metadata("correlation_id", self.correlation_id.clone())
+            .metadata("chunks", self.seq.to_string())
+            .build();
+        self.bus.publish(&self.topic, event).await?;
+        Ok(self.text)
+    }
+}
+
+/// A finished stream rebuilt by `TokenStreamAssembler`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembledStream {
+    pub correlation_id: String,
+    pub text: String,
+}
+
+/// Subscriber-side reassembly of `llm.token` streams, keyed by correlation id.
+/// Chunks are ordered by `seq`, so delivery order does not matter.
+#[derive(Debug, Default)]
+pub struct TokenStreamAssembler {
+    streams: HashMap<String, BTreeMap<u64, String>>,
+}
+
+impl TokenStreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed an event; returns the finished stream when `event` is its `llm.complete`.
+    /// Unrelated events are ignored.
+    pub fn push(&mut self, event: &Event) -> Option<AssembledStream> {
+        let correlation_id = event.metadata.get("correlation_id")?.clone();
+        match event.r#type.as_str() {
+            LLM_TOKEN_EVENT => {
+                let seq = event.metadata.get("seq")?.parse().ok()?;
+                self.streams
+                    .entry(correlation_id)
+                    .or_default()
+                    .insert(seq, String::from_utf8_lossy(&event.payload).into_owned());
+                None
+            }
+            LLM_COMPLETE_EVENT => {
+                self.streams.remove(&correlation_id);
+                // The complete event carries the authoritative full text
+                Some(AssembledStream {
+                    correlation_id,
+                    text: String::from_utf8_lossy(&event.payload).into_owned(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Text received so far for an unfinished stream
+    pub fn partial(&self, correlation_id: &str) -> Option<String> {
+        self.streams
+            .get(correlation_id)
+            .map(|chunks| chunks.values().map(String::as_str).collect())
+    }
+}
@@ -1,17 +1,25 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tracing::{debug, info, warn, Span};
 
-use crate::action_broker::ActionBroker;
+use crate::action_broker::{ActionBroker, ActionResultBuilder};
 use crate::context::{PromptBundle, TokenBudget};
 use crate::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, QoSLevel};
-use crate::{LoomError, Result};
+use crate::telemetry::inject_trace_context;
+use crate::{EventBus, LoomError, Result};
 
 use super::adapter::promptbundle_to_messages_and_text;
 use super::client::LlmClient;
+use super::outcome::{
+    bundle_hash, AgentOutcome, RepeatedCalls, StepDecision, StepTrace, StopReason,
+    DEFAULT_MAX_ITERATIONS, DEFAULT_MAX_REPEATED_CALLS,
+};
+use super::schema::validate_arguments;
+use super::streaming::TokenStreamPublisher;
+use super::turn::{TokenUsage, ToolInvocation, TurnSummary};
 
 // OpenTelemetry imports
 use opentelemetry::{
@@ -39,6 +47,31 @@ pub struct OrchestratorOptions {
     pub per_tool_timeout_ms: u64,
     pub refine_on_tool_result: bool,
     pub max_tools_exposed: usize,
+    /// Model re-prompts allowed when tool arguments fail schema validation
+    #[serde(default = "default_argument_retries")]
+    pub max_argument_retries: u32,
+    /// Wall-clock limit for `run_agent` (None = no deadline)
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+    /// Model requests allowed in one `run_agent` call
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u32,
+    /// `run_agent` stops with `LoopDetected` once the same tool and arguments are requested
+    /// more than this many times (0 = no loop detection)
+    #[serde(default = "default_max_repeated_calls")]
+    pub max_repeated_calls: u32,
+}
+
+fn default_argument_retries() -> u32 {
+    2
+}
+
+fn default_max_iterations() -> u32 {
+    DEFAULT_MAX_ITERATIONS
+}
+
+fn default_max_repeated_calls() -> u32 {
+    DEFAULT_MAX_REPEATED_CALLS
 }
 
 impl Default for OrchestratorOptions {
@@ -48,12 +81,28 @@ impl Default for OrchestratorOptions {
             per_tool_timeout_ms: 30_000,
             refine_on_tool_result: true,
             max_tools_exposed: 64,
+            max_argument_retries: default_argument_retries(),
+            deadline_ms: None,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            max_repeated_calls: DEFAULT_MAX_REPEATED_CALLS,
         }
     }
 }
 
+/// Error code for tool calls whose arguments still fail validation after all retries
+pub const INVALID_ARGUMENTS: &str = "INVALID_ARGUMENTS";
+
+/// A tool call whose arguments failed schema validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentError {
+    /// Position of the call in the validated slice; the model may call one tool several times
+    pub index: usize,
+    pub tool: String,
+    pub message: String,
+}
+
 /// Normalized tool call parsed from model output
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NormalizedToolCall {
     pub id: Option<String>,
     pub name: String,
@@ -94,12 +143,17 @@ pub struct ToolOrchestrator {
     llm: Arc<LlmClient>,
     broker: Arc<ActionBroker>,
     pub stats: ToolOrchestratorStats,
+    // bus and topic for `turn.completed` events (None = not published)
+    turn_events: Option<(Arc<EventBus>, String)>,
+    // bus and topic for `llm.token` / `llm.complete` answer streams (None = not published)
+    token_events: Option<(Arc<EventBus>, String)>,
 
     // OpenTelemetry metrics
     runs_counter: Counter<u64>,
     tool_calls_counter: Counter<u64>,
     tool_errors_counter: Counter<u64>,
     refine_cycles_counter: Counter<u64>,
+    argument_retries_counter: Counter<u64>,
     tool_latency: Histogram<f64>,
     discovery_latency: Histogram<f64>,
     llm_latency: Histogram<f64>,
@@ -130,6 +184,11 @@ impl ToolOrchestrator {
             .with_description("Total number of refine cycles")
             .init();
 
+        let argument_retries_counter = meter
+            .u64_counter("loom.tool_orch.argument_retries_total")
+            .with_description("Total number of re-prompts for invalid tool arguments")
+            .init();
+
         let tool_latency = meter
             .f64_histogram("loom.tool_orch.tool_latency_ms")
             .with_description("Tool invocation latency in milliseconds")
@@ -149,16 +208,59 @@ impl ToolOrchestrator {
             llm,
             broker,
             stats: ToolOrchestratorStats::default(),
+            turn_events: None,
+            token_events: None,
             runs_counter,
             tool_calls_counter,
             tool_errors_counter,
             refine_cycles_counter,
+            argument_retries_counter,
             tool_latency,
             discovery_latency,
             llm_latency,
         }
     }
 
+    /// Publish a `turn.completed` event with a `TurnSummary` to `topic` after each successful run
+    pub fn with_turn_events(mut self, bus: Arc<EventBus>, topic: impl Into<String>) -> Self {
+        self.turn_events = Some((bus, topic.into()));
+        self
+    }
+
+    /// Publish final answers to `topic` as `llm.token` events and one `llm.complete` (see
+    /// `llm::streaming`), tagged with the run's correlation id (a generated one when it has
+    /// none). The refine answer of `run` is streamed as the model produces it; answers from
+    /// tool-enabled requests, which are not streamed, arrive as a single chunk.
+    pub fn with_token_stream(mut self, bus: Arc<EventBus>, topic: impl Into<String>) -> Self {
+        self.token_events = Some((bus, topic.into()));
+        self
+    }
+
+    fn token_publisher(&self, correlation_id: &Option<String>) -> Option<TokenStreamPublisher> {
+        let (bus, topic) = self.token_events.as_ref()?;
+        let correlation_id = correlation_id
+            .clone()
+            .unwrap_or_else(|| self.broker.id_generator().new_id());
+        Some(
+            TokenStreamPublisher::new(Arc::clone(bus), topic.clone(), correlation_id)
+                .with_source("tool_orchestrator"),
+        )
+    }
+
+    /// Best-effort publish of an already complete answer as a one-chunk stream
+    async fn publish_answer(&self, correlation_id: &Option<String>, text: &str) {
+        let Some(mut publisher) = self.token_publisher(correlation_id) else {
+            return;
+        };
+        let published = match publisher.token(text).await {
+            Ok(()) => publisher.complete().await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = published {
+            warn!(target="tool_orch", error=%e, "Failed to publish answer stream");
+        }
+    }
+
     /// Run the model with tools exposed; parse tool calls; invoke broker; optionally refine.
     /// Contract:
     /// - Input: PromptBundle + budget + options
@@ -170,6 +272,42 @@ impl ToolOrchestrator {
         budget: Option<TokenBudget>,
         options: OrchestratorOptions,
         correlation_id: Option<String>,
+    ) -> Result<FinalAnswer> {
+        let started = Instant::now();
+        let mut turn = TurnSummary {
+            goal: bundle.instructions.clone(),
+            correlation_id: correlation_id.clone(),
+            ..TurnSummary::default()
+        };
+        let answer = self
+            .run_turn(bundle, budget, options, correlation_id, &mut turn)
+            .await?;
+        turn.latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.publish_turn(&turn).await;
+        Ok(answer)
+    }
+
+    /// Best-effort `turn.completed` publish
+    async fn publish_turn(&self, turn: &TurnSummary) {
+        let Some((bus, topic)) = &self.turn_events else {
+            return;
+        };
+        let published = match turn.to_event("tool_orchestrator") {
+            Ok(event) => bus.publish(topic, event).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = published {
+            warn!(target="tool_orch", error=%e, "Failed to publish turn.completed");
+        }
+    }
+
+    async fn run_turn(
+        &mut self,
+        bundle: &PromptBundle,
+        budget: Option<TokenBudget>,
+        options: OrchestratorOptions,
+        correlation_id: Option<String>,
+        turn: &mut TurnSummary,
     ) -> Result<FinalAnswer> {
         let budget = budget.unwrap_or_default();
         self.stats.total_invocations += 1;
@@ -194,42 +332,33 @@ impl ToolOrchestrator {
         Span::current().record("tool_count", tools.len());
 
         debug!(target="tool_orch", count=%tools.len(), latency_ms=%discovery_elapsed_ms, "Tool discovery complete");
-        let (messages, input_text) = promptbundle_to_messages_and_text(bundle, budget);
-
-        // Prefer Responses API; fallback to Chat Completions
-        let use_tools = !tools.is_empty() && options.tool_choice != ToolChoice::None;
-        let resp_val = if use_tools {
-            match self
-                .post_responses_with_tools(&input_text, &tools, &options, budget)
-                .await
-            {
-                Ok(v) => Some(v),
-                Err(e) => {
-                    warn!(target="tool_orch", error=%e, "Responses API with tools failed; trying chat.completions");
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        let (mut raw, mut parsed_calls) = self
+            .request_tool_calls(bundle, &tools, &options, budget)
+            .await?;
+        turn.model_requests += 1;
+        turn.usage.add(TokenUsage::from_response(&raw));
 
-        let (raw, parsed_calls, provider_tag) = if let Some(v) = resp_val {
-            let calls = parse_tool_calls_from_responses(&v);
-            (v, calls, "responses")
-        } else {
-            let chat_val = self
-                .post_chat_with_tools(&messages, &tools, &options, budget)
+        // Catch bad arguments before dispatch and let the model correct them
+        let mut arg_errors = validate_tool_calls(&caps, &parsed_calls);
+        let mut retries = 0;
+        while !arg_errors.is_empty() && retries < options.max_argument_retries {
+            retries += 1;
+            self.argument_retries_counter.add(1, &[]);
+            warn!(target="tool_orch", attempt=%retries, errors=%arg_errors.len(), "Invalid tool arguments; asking model to correct them");
+            let correction = make_correction_bundle(bundle, &parsed_calls, &arg_errors);
+            (raw, parsed_calls) = self
+                .request_tool_calls(&correction, &tools, &options, budget)
                 .await?;
-            let calls = parse_tool_calls_from_chat(&chat_val);
-            (chat_val, calls, "chat.completions")
-        };
-
-        debug!(target="tool_orch", provider=%provider_tag, calls=%parsed_calls.len(), "Parsed tool calls");
+            turn.model_requests += 1;
+            turn.usage.add(TokenUsage::from_response(&raw));
+            arg_errors = validate_tool_calls(&caps, &parsed_calls);
+        }
 
         if parsed_calls.is_empty() {
             let text = extract_text_fallback(&raw).ok_or_else(|| {
                 LoomError::AgentError("No tool calls and no assistant text in model output".into())
             })?;
+            self.publish_answer(&correlation_id, &text).await;
             return Ok(FinalAnswer {
                 text,
                 tool_calls: vec![],
@@ -240,55 +369,13 @@ impl ToolOrchestrator {
 
         // Invoke tools sequentially for now
         let mut results: Vec<ActionResult> = Vec::new();
-        for call in &parsed_calls {
-            let started = Instant::now();
-            let action_call =
-                build_action_call(call, options.per_tool_timeout_ms, correlation_id.clone());
-            let res = self.broker.invoke(action_call).await?;
-            let elapsed = started.elapsed().as_secs_f64() * 1000.0;
-
-            // Update counters
-            self.stats.total_tool_calls += 1;
-            let status_str = if (res.status) == (ActionStatus::ActionOk as i32) {
-                "success"
-            } else {
-                self.stats.total_tool_errors += 1;
-                "error"
-            };
-
-            // Welford-like avg update
-            let n = self.stats.total_tool_calls as f64;
-            self.stats.avg_tool_latency_ms =
-                ((self.stats.avg_tool_latency_ms * (n - 1.0)) + elapsed) / n;
-
-            // Record metrics
-            self.tool_calls_counter.add(
-                1,
-                &[
-                    KeyValue::new("tool_name", call.name.clone()),
-                    KeyValue::new("status", status_str),
-                ],
-            );
-
-            self.tool_latency
-                .record(elapsed, &[KeyValue::new("tool_name", call.name.clone())]);
-
-            if status_str == "error" {
-                let error_code = res
-                    .error
-                    .as_ref()
-                    .map(|e| e.code.clone())
-                    .unwrap_or_else(|| "UNKNOWN".to_string());
-                self.tool_errors_counter.add(
-                    1,
-                    &[
-                        KeyValue::new("tool_name", call.name.clone()),
-                        KeyValue::new("error_code", error_code),
-                    ],
-                );
-            }
-
-            info!(target="tool_orch", tool=%call.name, status=%res.status, latency_ms=%elapsed, "Tool invocation finished");
+        for (index, call) in parsed_calls.iter().enumerate() {
+            let arg_error = arg_errors.iter().find(|e| e.index == index);
+            let (res, elapsed) = self
+                .invoke_tool(call, arg_error, &options, &correlation_id)
+                .await?;
+            turn.tools
+                .push(ToolInvocation::new(&call.name, &res, elapsed));
             results.push(res);
         }
 
@@ -296,8 +383,23 @@ impl ToolOrchestrator {
         if options.refine_on_tool_result {
             let refine_bundle = make_refine_bundle(bundle, &parsed_calls, &results);
             let refine_started = Instant::now();
-            let final_resp = self.llm.generate(&refine_bundle, Some(budget)).await?;
+            // Streamed answers carry no usage report
+            let text = match self.token_publisher(&correlation_id) {
+                Some(publisher) => {
+                    self.llm
+                        .generate_to_bus(&refine_bundle, Some(budget), publisher)
+                        .await?
+                }
+                None => {
+                    let final_resp = self.llm.generate(&refine_bundle, Some(budget)).await?;
+                    if let Some(usage) = &final_resp.usage {
+                        turn.usage.add(TokenUsage::from_usage(usage));
+                    }
+                    final_resp.text
+                }
+            };
             let refine_elapsed_ms = refine_started.elapsed().as_secs_f64() * 1000.0;
+            turn.model_requests += 1;
 
             // Record refine cycle metric
             self.refine_cycles_counter.add(1, &[]);
@@ -306,7 +408,7 @@ impl ToolOrchestrator {
 
             debug!(target="tool_orch", latency_ms=%refine_elapsed_ms, "Refine turn finished");
             return Ok(FinalAnswer {
-                text: final_resp.text,
+                text,
                 tool_calls: parsed_calls,
                 tool_results: results,
                 raw_model: Some(raw),
@@ -315,6 +417,7 @@ impl ToolOrchestrator {
 
         // No refine: summarize results into a concise answer
         let text = summarize_results_for_user(&parsed_calls, &results);
+        self.publish_answer(&correlation_id, &text).await;
         Ok(FinalAnswer {
             text,
             tool_calls: parsed_calls,
@@ -323,6 +426,237 @@ impl ToolOrchestrator {
         })
     }
 
+    /// Multi-step loop: ask the model, run the tools it picks, feed every result so far back
+    /// and repeat until it answers without tools, up to `options.max_iterations` requests.
+    /// Failures do not return `Err`: they end the run with `StopReason::Error` and the steps
+    /// completed so far.
+    #[tracing::instrument(name = "tool_orchestrator.run_agent", skip(self, bundle), fields(tool_choice = ?options.tool_choice, steps))]
+    pub async fn run_agent(
+        &mut self,
+        bundle: &PromptBundle,
+        budget: Option<TokenBudget>,
+        options: OrchestratorOptions,
+        correlation_id: Option<String>,
+    ) -> AgentOutcome {
+        let deadline = options
+            .deadline_ms
+            .and_then(|ms| Instant::now().checked_add(Duration::from_millis(ms)));
+        let mut steps = Vec::new();
+        let run = self.agent_steps(bundle, budget, &options, correlation_id, &mut steps);
+        let finished = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), run).await.ok(),
+            None => Some(run.await),
+        };
+        let (stop_reason, final_answer) = match finished {
+            Some(Ok(Ok(text))) => (StopReason::Completed, Some(text)),
+            Some(Ok(Err(reason))) => (reason, None),
+            Some(Err(e)) => (StopReason::Error(e.to_string()), None),
+            None => (StopReason::DeadlineExceeded, None),
+        };
+        Span::current().record("steps", steps.len());
+        info!(target="tool_orch", steps=%steps.len(), stop_reason=?stop_reason, "Agent loop finished");
+        AgentOutcome {
+            steps,
+            final_answer,
+            stop_reason,
+        }
+    }
+
+    /// Body of `run_agent`; `Ok(Err(reason))` when it stopped without an answer
+    async fn agent_steps(
+        &mut self,
+        bundle: &PromptBundle,
+        budget: Option<TokenBudget>,
+        options: &OrchestratorOptions,
+        correlation_id: Option<String>,
+        steps: &mut Vec<StepTrace>,
+    ) -> Result<std::result::Result<String, StopReason>> {
+        let budget = budget.unwrap_or_default();
+        self.stats.total_invocations += 1;
+        self.runs_counter.add(
+            1,
+            &[KeyValue::new(
+                "tool_choice",
+                format!("{:?}", options.tool_choice),
+            )],
+        );
+
+        let caps = self.broker.list_capabilities();
+        let tools = self.build_tools_for_llm(&caps, options.max_tools_exposed);
+        let mut all_calls: Vec<NormalizedToolCall> = Vec::new();
+        let mut all_results: Vec<ActionResult> = Vec::new();
+        let mut repeats = RepeatedCalls::default();
+        let mut prompt = bundle.clone();
+        for iteration in 1..=options.max_iterations {
+            let hash = bundle_hash(&prompt);
+            let (raw, calls) = self
+                .request_tool_calls(&prompt, &tools, options, budget)
+                .await?;
+            if calls.is_empty() {
+                let text = extract_text_fallback(&raw).ok_or_else(|| {
+                    LoomError::AgentError(
+                        "No tool calls and no assistant text in model output".into(),
+                    )
+                })?;
+                steps.push(StepTrace {
+                    iteration,
+                    bundle_hash: hash,
+                    decision: StepDecision::Answer,
+                    tool_calls: Vec::new(),
+                    results: Vec::new(),
+                });
+                self.publish_answer(&correlation_id, &text).await;
+                return Ok(Ok(text));
+            }
+
+            // Same tool, same arguments, again: the model is not making progress
+            if repeats.record(&calls, options.max_repeated_calls) {
+                warn!(target="tool_orch", iteration=%iteration, "Repeated tool call; stopping agent loop");
+                steps.push(StepTrace {
+                    iteration,
+                    bundle_hash: hash,
+                    decision: StepDecision::ToolCalls,
+                    tool_calls: calls,
+                    results: Vec::new(),
+                });
+                return Ok(Err(StopReason::LoopDetected));
+            }
+
+            // Invalid arguments are reported back to the model as INVALID_ARGUMENTS results
+            let arg_errors = validate_tool_calls(&caps, &calls);
+            let mut invocations = Vec::with_capacity(calls.len());
+            for (index, call) in calls.iter().enumerate() {
+                let arg_error = arg_errors.iter().find(|e| e.index == index);
+                let (res, elapsed) = self
+                    .invoke_tool(call, arg_error, options, &correlation_id)
+                    .await?;
+                invocations.push(ToolInvocation::new(&call.name, &res, elapsed));
+                all_results.push(res);
+            }
+            debug!(target="tool_orch", iteration=%iteration, calls=%calls.len(), "Agent step finished");
+            all_calls.extend(calls.iter().cloned());
+            steps.push(StepTrace {
+                iteration,
+                bundle_hash: hash,
+                decision: StepDecision::ToolCalls,
+                tool_calls: calls,
+                results: invocations,
+            });
+            prompt = make_refine_bundle(bundle, &all_calls, &all_results);
+        }
+        Ok(Err(StopReason::MaxIterations))
+    }
+
+    /// Dispatch one parsed call (or reject it with its `arg_error`) and record stats and
+    /// metrics; returns the result and its latency in ms
+    async fn invoke_tool(
+        &mut self,
+        call: &NormalizedToolCall,
+        arg_error: Option<&ArgumentError>,
+        options: &OrchestratorOptions,
+        correlation_id: &Option<String>,
+    ) -> Result<(ActionResult, f64)> {
+        let started = Instant::now();
+        let res = if let Some(err) = arg_error {
+            // Retries exhausted: never dispatch arguments known to be invalid
+            invalid_arguments_result(self.broker.id_generator().new_id(), &err.message)
+        } else {
+            let mut action_call =
+                build_action_call(call, options.per_tool_timeout_ms, correlation_id.clone());
+            action_call.id = self.broker.id_generator().new_id();
+            // Keep the tool call in this run's trace even if the provider is remote
+            inject_trace_context(&Span::current(), &mut action_call.headers);
+            self.broker.invoke(action_call).await?
+        };
+        let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+
+        // Update counters
+        self.stats.total_tool_calls += 1;
+        let status_str = if res.status_enum() == ActionStatus::ActionOk {
+            "success"
+        } else {
+            self.stats.total_tool_errors += 1;
+            "error"
+        };
+
+        // Welford-like avg update
+        let n = self.stats.total_tool_calls as f64;
+        self.stats.avg_tool_latency_ms =
+            ((self.stats.avg_tool_latency_ms * (n - 1.0)) + elapsed) / n;
+
+        // Record metrics
+        self.tool_calls_counter.add(
+            1,
+            &[
+                KeyValue::new("tool_name", call.name.clone()),
+                KeyValue::new("status", status_str),
+            ],
+        );
+
+        self.tool_latency
+            .record(elapsed, &[KeyValue::new("tool_name", call.name.clone())]);
+
+        if status_str == "error" {
+            let error_code = res
+                .error
+                .as_ref()
+                .map(|e| e.code.clone())
+                .unwrap_or_else(|| "UNKNOWN".to_string());
+            self.tool_errors_counter.add(
+                1,
+                &[
+                    KeyValue::new("tool_name", call.name.clone()),
+                    KeyValue::new("error_code", error_code),
+                ],
+            );
+        }
+
+        info!(target="tool_orch", tool=%call.name, status=%res.status, latency_ms=%elapsed, "Tool invocation finished");
+        Ok((res, elapsed))
+    }
+
+    /// One model turn with tools exposed; returns the raw response and its parsed tool calls
+    async fn request_tool_calls(
+        &self,
+        bundle: &PromptBundle,
+        tools: &[Value],
+        options: &OrchestratorOptions,
+        budget: TokenBudget,
+    ) -> Result<(Value, Vec<NormalizedToolCall>)> {
+        let (messages, input_text) = promptbundle_to_messages_and_text(bundle, budget);
+
+        // Prefer Responses API; fallback to Chat Completions
+        let use_tools = !tools.is_empty() && options.tool_choice != ToolChoice::None;
+        let resp_val = if use_tools {
+            match self
+                .post_responses_with_tools(&input_text, tools, options, budget)
+                .await
+            {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    warn!(target="tool_orch", error=%e, "Responses API with tools failed; trying chat.completions");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let (raw, parsed_calls, provider_tag) = if let Some(v) = resp_val {
+            let calls = parse_tool_calls_from_responses(&v);
+            (v, calls, "responses")
+        } else {
+            let chat_val = self
+                .post_chat_with_tools(&messages, tools, options, budget)
+                .await?;
+            let calls = parse_tool_calls_from_chat(&chat_val);
+            (chat_val, calls, "chat.completions")
+        };
+
+        debug!(target="tool_orch", provider=%provider_tag, calls=%parsed_calls.len(), "Parsed tool calls");
+        Ok((raw, parsed_calls))
+    }
+
     fn build_tools_for_llm(&self, caps: &[CapabilityDescriptor], limit: usize) -> Vec<Value> {
         let mut tools = Vec::new();
         for cap in caps.iter().take(limit) {
@@ -448,6 +782,7 @@ impl ToolOrchestrator {
     }
 }
 
+/// Map a parsed tool call onto an `ActionCall`; the id comes from the default generator (UUID v7)
 pub fn build_action_call(
     call: &NormalizedToolCall,
     timeout_ms: u64,
@@ -467,17 +802,72 @@ pub fn build_action_call(
         },
         timeout_ms: timeout_ms as i64,
         correlation_id: correlation_id.unwrap_or_default(),
-        qos: QoSLevel::QosBatched as i32,
+        qos: Some(QoSLevel::QosBatched as i32),
     }
 }
 
 fn new_call_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    format!("call_{:x}", now)
+    crate::ids::default_id_generator().new_id()
+}
+
+/// Validate each call's arguments against its capability's `metadata["schema"]`.
+/// Calls to unknown tools or tools without a (parseable) schema are not checked here.
+pub fn validate_tool_calls(
+    caps: &[CapabilityDescriptor],
+    calls: &[NormalizedToolCall],
+) -> Vec<ArgumentError> {
+    calls
+        .iter()
+        .enumerate()
+        .filter_map(|(index, call)| {
+            let schema = caps
+                .iter()
+                .find(|c| c.name == call.name)
+                .and_then(|c| c.metadata.get("schema"))
+                .and_then(|s| serde_json::from_str::<Value>(s).ok())?;
+            validate_arguments(&schema, &call.arguments)
+                .err()
+                .map(|message| ArgumentError {
+                    index,
+                    tool: call.name.clone(),
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Bundle for a correction turn: the original prompt plus the rejected calls and why
+pub fn make_correction_bundle(
+    base: &PromptBundle,
+    calls: &[NormalizedToolCall],
+    errors: &[ArgumentError],
+) -> PromptBundle {
+    let mut bundle = base.clone();
+    let mut block = String::from("Tool Argument Errors:\n");
+    for err in errors {
+        let args = calls
+            .get(err.index)
+            .map(|c| c.arguments.to_string())
+            .unwrap_or_default();
+        block.push_str(&format!("- {}({}): {}\n", err.tool, args, err.message));
+    }
+    block.push_str("Call the tools again with arguments that match their parameter schemas.");
+    // The system section grows, so the builder's accounting no longer applies
+    bundle.token_breakdown = None;
+    if bundle.system.is_empty() {
+        bundle.system = block;
+    } else {
+        bundle.system.push_str("\n\n");
+        bundle.system.push_str(&block);
+    }
+    bundle
+}
+
+fn invalid_arguments_result(id: String, message: &str) -> ActionResult {
+    ActionResultBuilder::new(ActionStatus::ActionError)
+        .id(id)
+        .error(INVALID_ARGUMENTS, message)
+        .build()
 }
 
 pub fn make_refine_bundle(
@@ -488,8 +878,7 @@ pub fn make_refine_bundle(
     let mut bundle = base.clone();
     let mut context_block = String::from("Tool Results:\n");
     for (i, (c, r)) in calls.iter().zip(results.iter()).enumerate() {
-        let status = r.status;
-        if status == (ActionStatus::ActionOk as i32) {
+        if r.status_enum() == ActionStatus::ActionOk {
             let preview = safe_snippet(&r.output);
             context_block.push_str(&format!("- {}: OK -> {}\n", c.name, preview));
         } else {
@@ -501,6 +890,7 @@ pub fn make_refine_bundle(
         }
     }
     // Prepend as context via system message (reusing existing mechanism)
+    bundle.token_breakdown = None;
     if bundle.system.is_empty() {
         bundle.system = context_block;
     } else {
@@ -514,7 +904,7 @@ fn summarize_results_for_user(calls: &[NormalizedToolCall], results: &[ActionRes
     // Simple human-readable summary
     let mut out = String::new();
     for (c, r) in calls.iter().zip(results.iter()) {
-        if r.status == (ActionStatus::ActionOk as i32) {
+        if r.status_enum() == ActionStatus::ActionOk {
             let snippet = safe_snippet(&r.output);
             out.push_str(&format!("{} → {}\n", c.name, snippet));
         } else {
@@ -0,0 +1,118 @@
+//! `turn.completed` events summarizing one `ToolOrchestrator::run`.
+//!
+//! Event shape: type `turn.completed`, payload = JSON `TurnSummary`, metadata `correlation_id`
+//! (when the run had one). Publish is opt-in via `ToolOrchestrator::with_turn_events`.
+
+use crate::event::EventExt;
+use crate::proto::{ActionResult, Event};
+use crate::{LoomError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Event type published after each orchestrator turn
+pub const TURN_COMPLETED_EVENT: &str = "turn.completed";
+
+/// Token counts reported by the model API, summed over a turn's requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Read a `usage` object from Responses (`input_tokens`/`output_tokens`) or
+    /// Chat Completions (`prompt_tokens`/`completion_tokens`); missing counts are 0
+    pub fn from_usage(usage: &Value) -> Self {
+        let count = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|k| usage.get(*k).and_then(Value::as_u64))
+                .unwrap_or(0)
+        };
+        let input_tokens = count(&["input_tokens", "prompt_tokens"]);
+        let output_tokens = count(&["output_tokens", "completion_tokens"]);
+        let total_tokens = match count(&["total_tokens"]) {
+            0 => input_tokens + output_tokens,
+            total => total,
+        };
+        Self {
+            input_tokens,
+            output_tokens,
+            total_tokens,
+        }
+    }
+
+    /// Usage of a raw model response; a response without `usage` counts as zero
+    pub fn from_response(raw: &Value) -> Self {
+        raw.get("usage").map(Self::from_usage).unwrap_or_default()
+    }
+
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// One tool call made during a turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub name: String,
+    /// `ok`, `error`, `timeout` or `retryable`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    pub latency_ms: f64,
+}
+
+impl ToolInvocation {
+    pub fn new(name: impl Into<String>, result: &ActionResult, latency_ms: f64) -> Self {
+        let status = result.status_enum().as_str();
+        Self {
+            name: name.into(),
+            status: status.to_string(),
+            error_code: result.error.as_ref().map(|e| e.code.clone()),
+            latency_ms,
+        }
+    }
+}
+
+/// Serializable summary of one orchestrator turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnSummary {
+    /// The bundle's `instructions` (the trigger goal when built by `ContextBuilder`)
+    pub goal: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Tool calls in invocation order
+    pub tools: Vec<ToolInvocation>,
+    /// Wall-clock time of the whole turn, model requests included
+    pub latency_ms: f64,
+    /// Model requests made (tool selection, argument corrections and refine)
+    pub model_requests: u32,
+    pub usage: TokenUsage,
+}
+
+impl TurnSummary {
+    /// Wrap as a `turn.completed` event
+    pub fn to_event(&self, source: &str) -> Result<Event> {
+        let mut builder = Event::builder(TURN_COMPLETED_EVENT)
+            .source(source)
+            .payload_json(self)?;
+        if let Some(corr) = &self.correlation_id {
+            builder = builder.metadata("correlation_id", corr.clone());
+        }
+        Ok(builder.build())
+    }
+
+    /// Decode a `turn.completed` event
+    pub fn from_event(event: &Event) -> Result<Self> {
+        if event.r#type != TURN_COMPLETED_EVENT {
+            return Err(LoomError::AgentError(format!(
+                "not a {} event: {}",
+                TURN_COMPLETED_EVENT, event.r#type
+            )));
+        }
+        Ok(serde_json::from_slice(&event.payload)?)
+    }
+}
@@ -4,7 +4,7 @@
 /// registered with the ActionBroker and invoked like native capabilities.
 use super::client::McpClient;
 use super::types::McpTool;
-use crate::action_broker::CapabilityProvider;
+use crate::action_broker::{ActionResultExt, CapabilityProvider};
 use crate::proto::{
     ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind,
 };
@@ -65,6 +65,8 @@ impl CapabilityProvider for McpToolAdapter {
             version: "0.1.0".to_string(),
             provider: ProviderKind::ProviderMcp as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -100,6 +102,7 @@ impl CapabilityProvider for McpToolAdapter {
                             message: format!("Invalid JSON payload: {}", e),
                             details: Default::default(),
                         }),
+                        metadata: Default::default(),
                     });
                 }
             }
@@ -119,6 +122,7 @@ impl CapabilityProvider for McpToolAdapter {
                             message: result.content,
                             details: Default::default(),
                         }),
+                        metadata: Default::default(),
                     })
                 } else {
                     // Tool succeeded
@@ -127,12 +131,7 @@ impl CapabilityProvider for McpToolAdapter {
                         "result": result.content
                     });
 
-                    Ok(ActionResult {
-                        id: call_id,
-                        status: ActionStatus::ActionOk as i32,
-                        output: serde_json::to_vec(&output)?,
-                        error: None,
-                    })
+                    Ok(ActionResult::try_ok_json(call_id, &output))
                 }
             }
             Err(e) => {
@@ -162,6 +161,7 @@ impl CapabilityProvider for McpToolAdapter {
                         message: error_message,
                         details: Default::default(),
                     }),
+                    metadata: Default::default(),
                 })
             }
         }
@@ -0,0 +1,92 @@
+//! JSON payload codec with explicit number handling.
+//!
+//! `1` and `1.0` always stay distinct. With the `exact-numbers` feature serde_json is built
+//! with `arbitrary_precision`, so parsed numbers keep their literal text and big integers or
+//! long decimals survive a round trip; without it they are read as `f64`.
+//! `NumberMode::Lossy` opts into the classic behavior (integers that fit `i64`/`u64`,
+//! everything else `f64`) for peers that expect it, whichever way serde_json is built.
+
+use crate::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// How numbers are treated when a payload is decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+    /// Keep numbers as parsed (exactly as written with the `exact-numbers` feature)
+    #[default]
+    Exact,
+    /// Integers that fit `i64`/`u64` stay integers; all other numbers are rounded to `f64`
+    Lossy,
+}
+
+/// Encodes and decodes JSON payloads (`ActionCall.payload`, `ActionResult.output`, ...)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PayloadCodec {
+    numbers: NumberMode,
+}
+
+impl PayloadCodec {
+    pub fn new(numbers: NumberMode) -> Self {
+        Self { numbers }
+    }
+
+    pub fn exact() -> Self {
+        Self::new(NumberMode::Exact)
+    }
+
+    pub fn lossy() -> Self {
+        Self::new(NumberMode::Lossy)
+    }
+
+    pub fn number_mode(&self) -> NumberMode {
+        self.numbers
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        match self.numbers {
+            NumberMode::Exact => Ok(serde_json::to_vec(value)?),
+            NumberMode::Lossy => {
+                let mut value = serde_json::to_value(value)?;
+                round_numbers(&mut value);
+                Ok(serde_json::to_vec(&value)?)
+            }
+        }
+    }
+
+    pub fn decode_value(&self, bytes: &[u8]) -> Result<Value> {
+        let mut value: Value = serde_json::from_slice(bytes)?;
+        if self.numbers == NumberMode::Lossy {
+            round_numbers(&mut value);
+        }
+        Ok(value)
+    }
+
+    /// Decode into `T`. A float is never coerced into an integer field, so a provider
+    /// expecting `u32` rejects `1.0` instead of guessing.
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match self.numbers {
+            NumberMode::Exact => Ok(serde_json::from_slice(bytes)?),
+            NumberMode::Lossy => Ok(serde_json::from_value(self.decode_value(bytes)?)?),
+        }
+    }
+}
+
+/// Whether `n` was written as an integer (no fraction or exponent)
+pub fn is_integer(n: &Number) -> bool {
+    !n.to_string().contains(['.', 'e', 'E'])
+}
+
+fn round_numbers(value: &mut Value) {
+    match value {
+        Value::Number(n) if n.as_i64().is_none() && n.as_u64().is_none() => {
+            if let Some(rounded) = n.as_f64().and_then(Number::from_f64) {
+                *n = rounded;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(round_numbers),
+        Value::Object(map) => map.values_mut().for_each(round_numbers),
+        _ => {}
+    }
+}
@@ -0,0 +1,417 @@
+//! Declarative multi-step plans run through the `ActionBroker`, with no LLM in the loop.
+//!
+//! A plan is a JSON array. Each entry is either a step or `{"parallel": [step, ...]}`;
+//! entries run in order and the steps of a parallel entry run concurrently:
+//!
+//! ```json
+//! [
+//!   {"id": "geo", "capability": "geo.lookup", "input": {"city": "Paris"}},
+//!   {"parallel": [
+//!     {"id": "weather", "capability": "weather.get", "input": {"lat": {"$ref": "$.geo.lat"}, "lon": {"$ref": "$.geo.lon"}}},
+//!     {"id": "news", "capability": "web.search", "input": {"query": {"$ref": "$.geo.name"}}}
+//!   ]}
+//! ]
+//! ```
+//!
+//! `{"$ref": "<path>"}` anywhere in an input is replaced by a value from an earlier step's
+//! output (parsed as JSON; non-JSON output is a string). Paths are a JSONPath subset:
+//! `$.<step>` followed by `.key`, `['key']` or `[index]` segments. References are checked
+//! before anything runs: they must name a step from an earlier entry.
+
+use crate::action_broker::{ActionBroker, ActionCallExt, ActionResultBuilder};
+use crate::proto::{ActionCall, ActionResult, ActionStatus};
+use crate::{LoomError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Error code for steps whose `$ref` could not be resolved (e.g. the source step failed)
+pub const UNRESOLVED_REFERENCE: &str = "UNRESOLVED_REFERENCE";
+/// Key of a reference object in step inputs
+pub const REF_KEY: &str = "$ref";
+
+/// One capability call in a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Unique within the plan; the root of references to this step's output
+    pub id: String,
+    pub capability: String,
+    /// Provider version; empty resolves by name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    /// JSON payload, possibly containing `{"$ref": ...}` objects
+    #[serde(default)]
+    pub input: Value,
+    /// Call timeout; `None` uses the broker default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<i64>,
+}
+
+/// A plan entry: one step, or steps run concurrently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PlanEntry {
+    Parallel { parallel: Vec<PlanStep> },
+    Step(PlanStep),
+}
+
+impl PlanEntry {
+    pub fn steps(&self) -> &[PlanStep] {
+        match self {
+            PlanEntry::Parallel { parallel } => parallel,
+            PlanEntry::Step(step) => std::slice::from_ref(step),
+        }
+    }
+}
+
+/// Ordered plan entries; serializes as the bare JSON array
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Plan {
+    pub entries: Vec<PlanEntry>,
+}
+
+impl Plan {
+    /// Parse and validate a JSON plan
+    pub fn from_json(json: &str) -> Result<Self> {
+        let plan: Plan = serde_json::from_str(json)?;
+        plan.validate()?;
+        Ok(plan)
+    }
+
+    /// Check ids are unique and non-empty, capabilities are set, and every `$ref` parses and
+    /// names a step from an earlier entry
+    pub fn validate(&self) -> Result<()> {
+        let mut earlier: HashSet<&str> = HashSet::new();
+        for entry in &self.entries {
+            if entry.steps().is_empty() {
+                return Err(invalid("empty parallel group"));
+            }
+            for step in entry.steps() {
+                if step.id.trim().is_empty() {
+                    return Err(invalid("step without an id"));
+                }
+                if step.capability.trim().is_empty() {
+                    return Err(invalid(&format!("step `{}` has no capability", step.id)));
+                }
+                let mut refs = Vec::new();
+                collect_refs(&step.input, &mut refs);
+                for path in refs {
+                    let path = RefPath::parse(path)?;
+                    if !earlier.contains(path.step.as_str()) {
+                        return Err(invalid(&format!(
+                            "step `{}` references `{}`, which is not an earlier step",
+                            step.id, path.step
+                        )));
+                    }
+                }
+            }
+            for step in entry.steps() {
+                if !earlier.insert(step.id.as_str()) {
+                    return Err(invalid(&format!("duplicate step id `{}`", step.id)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Step ids in execution order
+    pub fn step_ids(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .flat_map(|e| e.steps().iter().map(|s| s.id.as_str()))
+    }
+}
+
+/// What the executor does after a step fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnStepError {
+    /// Finish the failing entry (its parallel siblings included) and skip the rest
+    #[default]
+    Stop,
+    /// Keep going; steps referencing a failed step fail with `UNRESOLVED_REFERENCE`
+    Continue,
+}
+
+/// Result of one executed step
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub id: String,
+    pub result: ActionResult,
+}
+
+/// What a plan run produced
+#[derive(Debug, Clone, Default)]
+pub struct PlanOutcome {
+    /// Executed steps in plan order (parallel steps in their listed order)
+    pub steps: Vec<StepResult>,
+    /// Steps never run because the plan stopped
+    pub skipped: Vec<String>,
+    /// The first failed step when `OnStepError::Stop` ended the plan early
+    pub stopped_at: Option<String>,
+}
+
+impl PlanOutcome {
+    /// Every step ran and succeeded
+    pub fn is_success(&self) -> bool {
+        self.skipped.is_empty()
+            && self
+                .steps
+                .iter()
+                .all(|s| s.result.status_enum() == ActionStatus::ActionOk)
+    }
+
+    pub fn result(&self, id: &str) -> Option<&ActionResult> {
+        self.steps.iter().find(|s| s.id == id).map(|s| &s.result)
+    }
+
+    /// A successful step's output as JSON (a string when it is not valid JSON)
+    pub fn output(&self, id: &str) -> Option<Value> {
+        self.result(id)
+            .filter(|r| r.status_enum() == ActionStatus::ActionOk)
+            .map(|r| output_value(&r.output))
+    }
+}
+
+/// Runs plans through an `ActionBroker`
+pub struct PlanExecutor {
+    broker: Arc<ActionBroker>,
+    on_error: OnStepError,
+    correlation_id: Option<String>,
+}
+
+impl PlanExecutor {
+    pub fn new(broker: Arc<ActionBroker>) -> Self {
+        Self {
+            broker,
+            on_error: OnStepError::default(),
+            correlation_id: None,
+        }
+    }
+
+    /// Stop (default) or continue after a failed step
+    pub fn on_error(mut self, policy: OnStepError) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Correlation id stamped on every call (for cost budgets and tracing)
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Parse, validate and run a JSON plan
+    pub async fn run_json(&self, json: &str) -> Result<PlanOutcome> {
+        self.run(&Plan::from_json(json)?).await
+    }
+
+    /// Run `plan`. Only an invalid plan returns `Err`; step failures are reported as
+    /// results in the outcome.
+    pub async fn run(&self, plan: &Plan) -> Result<PlanOutcome> {
+        plan.validate()?;
+        let mut outputs: HashMap<String, Value> = HashMap::new();
+        let mut outcome = PlanOutcome::default();
+
+        for (index, entry) in plan.entries.iter().enumerate() {
+            let runs = entry
+                .steps()
+                .iter()
+                .map(|step| self.run_step(step, &outputs));
+            let results = futures_util::future::join_all(runs).await;
+
+            let mut first_failure = None;
+            for (step, result) in entry.steps().iter().zip(results) {
+                if result.status_enum() == ActionStatus::ActionOk {
+                    outputs.insert(step.id.clone(), output_value(&result.output));
+                } else if first_failure.is_none() {
+                    first_failure = Some(step.id.clone());
+                }
+                outcome.steps.push(StepResult {
+                    id: step.id.clone(),
+                    result,
+                });
+            }
+
+            if let Some(failed) = first_failure {
+                if self.on_error == OnStepError::Stop {
+                    warn!(target: "plan", step = %failed, "Plan step failed; stopping");
+                    outcome.skipped = plan.entries[index + 1..]
+                        .iter()
+                        .flat_map(|e| e.steps().iter().map(|s| s.id.clone()))
+                        .collect();
+                    outcome.stopped_at = Some(failed);
+                    break;
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    async fn run_step(&self, step: &PlanStep, outputs: &HashMap<String, Value>) -> ActionResult {
+        let call_id = self.broker.id_generator().new_id();
+        let input = match resolve_refs(&step.input, outputs) {
+            Ok(input) => input,
+            Err(message) => return error_result(call_id, UNRESOLVED_REFERENCE, message),
+        };
+        let call = match self.build_call(step, &call_id, &input) {
+            Ok(call) => call,
+            Err(e) => return error_result(call_id, "INVOKE_ERROR", e.to_string()),
+        };
+        debug!(target: "plan", step = %step.id, capability = %step.capability, "Running plan step");
+        match self.broker.invoke(call).await {
+            Ok(res) => res,
+            Err(e) => error_result(call_id, "INVOKE_ERROR", e.to_string()),
+        }
+    }
+
+    fn build_call(&self, step: &PlanStep, call_id: &str, input: &Value) -> Result<ActionCall> {
+        let mut builder = ActionCall::builder(step.capability.clone())
+            .id(call_id)
+            .version(step.version.clone())
+            .payload_json(input)?
+            .timeout_ms(step.timeout_ms.unwrap_or(0));
+        if let Some(correlation_id) = &self.correlation_id {
+            builder = builder.correlation_id(correlation_id.clone());
+        }
+        builder.build()
+    }
+}
+
+fn invalid(message: &str) -> LoomError {
+    LoomError::PluginError(format!("invalid plan: {}", message))
+}
+
+fn error_result(id: String, code: &str, message: String) -> ActionResult {
+    ActionResultBuilder::new(ActionStatus::ActionError)
+        .id(id)
+        .error(code, message)
+        .build()
+}
+
+fn output_value(output: &[u8]) -> Value {
+    serde_json::from_slice(output)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(output).into_owned()))
+}
+
+/// The `$ref` path when `value` is exactly `{"$ref": "<string>"}`
+fn as_ref(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.get(REF_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+fn collect_refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    if let Some(path) = as_ref(value) {
+        out.push(path);
+        return;
+    }
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| collect_refs(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_refs(v, out)),
+        _ => {}
+    }
+}
+
+fn resolve_refs(
+    value: &Value,
+    outputs: &HashMap<String, Value>,
+) -> std::result::Result<Value, String> {
+    if let Some(path) = as_ref(value) {
+        let path = RefPath::parse(path).map_err(|e| e.to_string())?;
+        return path.resolve(outputs);
+    }
+    Ok(match value {
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| resolve_refs(v, outputs))
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), resolve_refs(v, outputs)?)))
+                .collect::<std::result::Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parsed `$.<step>...` reference
+#[derive(Debug, Clone, PartialEq)]
+struct RefPath {
+    raw: String,
+    step: String,
+    segments: Vec<Segment>,
+}
+
+impl RefPath {
+    fn parse(raw: &str) -> Result<Self> {
+        let bad = |why: &str| invalid(&format!("bad reference `{}`: {}", raw, why));
+        let mut rest = raw
+            .strip_prefix('$')
+            .ok_or_else(|| bad("must start with `$`"))?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(bad("empty key"));
+                }
+                segments.push(Segment::Key(after[..end].to_string()));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| bad("unclosed `[`"))?;
+                let inner = &after[..end];
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                segments.push(match quoted {
+                    Some(key) => Segment::Key(key.to_string()),
+                    None => Segment::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| bad("index must be a number or a quoted key"))?,
+                    ),
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(bad("expected `.` or `[`"));
+            }
+        }
+        let mut segments = segments.into_iter();
+        let Some(Segment::Key(step)) = segments.next() else {
+            return Err(bad("must start with a step id"));
+        };
+        Ok(Self {
+            raw: raw.to_string(),
+            step,
+            segments: segments.collect(),
+        })
+    }
+
+    fn resolve(&self, outputs: &HashMap<String, Value>) -> std::result::Result<Value, String> {
+        let mut current = outputs
+            .get(&self.step)
+            .ok_or_else(|| format!("`{}`: step `{}` has no output", self.raw, self.step))?;
+        for segment in &self.segments {
+            let next = match segment {
+                Segment::Key(key) => current.get(key.as_str()),
+                Segment::Index(i) => current.get(*i),
+            };
+            current = next.ok_or_else(|| format!("`{}`: no value at this path", self.raw))?;
+        }
+        Ok(current.clone())
+    }
+}
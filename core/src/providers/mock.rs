@@ -0,0 +1,188 @@
+//! Scriptable in-process provider for tests.
+//!
+//! Register it like any other provider and keep a clone of the `Arc` to inspect the calls it
+//! received:
+//!
+//! ```ignore
+//! let mock = Arc::new(
+//!     MockProvider::named("tts.echo")
+//!         .on_json(json!({"text": "hi"}), json!({"audio": "..."}))
+//!         .returns(|call| ActionResult::builder(ActionStatus::ActionOk).output(call.payload.clone()).build()),
+//! );
+//! broker.register_provider(mock.clone());
+//! // ... exercise the agent ...
+//! assert_eq!(mock.call_count(), 1);
+//! ```
+use crate::action_broker::{ActionResultBuilder, CapabilityProvider};
+use crate::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind};
+use crate::{LoomError, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Responder = Arc<dyn Fn(&ActionCall) -> ActionResult + Send + Sync>;
+
+/// How the mock answers calls no scripted payload matched
+#[derive(Clone)]
+enum Fallback {
+    Respond(Responder),
+    /// `invoke` returns `Err`, which the broker reports as `CAPABILITY_ERROR`
+    Error(String),
+    /// Never returns, so the call runs into its timeout
+    Hang,
+}
+
+/// Provider answering from scripted responses or a closure, recording every call it gets.
+///
+/// Answers come from the first `on_json` entry matching the payload, else the fallback
+/// (`returns` / `fails` / `errors` / `hangs`; by default an empty `ActionOk`). Result ids are
+/// always set to the call id.
+pub struct MockProvider {
+    descriptor: CapabilityDescriptor,
+    scripted: Vec<(Value, ActionResult)>,
+    fallback: Fallback,
+    delay: Option<Duration>,
+    calls: Mutex<Vec<ActionCall>>,
+}
+
+impl MockProvider {
+    pub fn new(descriptor: CapabilityDescriptor) -> Self {
+        Self {
+            descriptor,
+            scripted: Vec::new(),
+            fallback: Fallback::Respond(Arc::new(|_| {
+                ActionResultBuilder::new(ActionStatus::ActionOk).build()
+            })),
+            delay: None,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Mock for `name` at version `1.0.0`
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::new(CapabilityDescriptor {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        })
+    }
+
+    /// Answer every unscripted call with `respond(call)`
+    pub fn returns<F>(mut self, respond: F) -> Self
+    where
+        F: Fn(&ActionCall) -> ActionResult + Send + Sync + 'static,
+    {
+        self.fallback = Fallback::Respond(Arc::new(respond));
+        self
+    }
+
+    /// Answer every unscripted call with `output` as a JSON `ActionOk` result
+    pub fn returns_json(self, output: Value) -> Self {
+        self.returns(move |_| json_result(&output))
+    }
+
+    /// Answer calls whose payload is JSON equal to `request` with `response` (JSON `ActionOk`).
+    /// Entries are tried in the order they were added.
+    pub fn on_json(self, request: Value, response: Value) -> Self {
+        let result = json_result(&response);
+        self.on_json_result(request, result)
+    }
+
+    /// Like `on_json`, answering with a full result (e.g. an error)
+    pub fn on_json_result(mut self, request: Value, result: ActionResult) -> Self {
+        self.scripted.push((request, result));
+        self
+    }
+
+    /// Answer every unscripted call with an `ActionError` result
+    pub fn fails(self, code: impl Into<String>, message: impl Into<String>) -> Self {
+        let (code, message) = (code.into(), message.into());
+        self.returns(move |_| {
+            ActionResultBuilder::new(ActionStatus::ActionError)
+                .error(code.clone(), message.clone())
+                .build()
+        })
+    }
+
+    /// Return `Err` from `invoke` for every unscripted call
+    pub fn errors(mut self, message: impl Into<String>) -> Self {
+        self.fallback = Fallback::Error(message.into());
+        self
+    }
+
+    /// Never answer unscripted calls, so they end in the broker's `TIMEOUT`
+    pub fn hangs(mut self) -> Self {
+        self.fallback = Fallback::Hang;
+        self
+    }
+
+    /// Wait `delay` before answering any call
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Calls received so far, oldest first (including ones that timed out)
+    pub fn calls(&self) -> Vec<ActionCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    pub fn last_call(&self) -> Option<ActionCall> {
+        self.calls.lock().unwrap().last().cloned()
+    }
+
+    /// Forget recorded calls
+    pub fn reset(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    fn scripted_for(&self, call: &ActionCall) -> Option<ActionResult> {
+        if self.scripted.is_empty() {
+            return None;
+        }
+        let payload: Value = serde_json::from_slice(&call.payload).ok()?;
+        self.scripted
+            .iter()
+            .find(|(request, _)| *request == payload)
+            .map(|(_, result)| result.clone())
+    }
+}
+
+fn json_result(output: &Value) -> ActionResult {
+    ActionResultBuilder::new(ActionStatus::ActionOk)
+        .output_json(output)
+        .expect("a JSON value always serializes")
+        .build()
+}
+
+#[async_trait]
+impl CapabilityProvider for MockProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        self.descriptor.clone()
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.calls.lock().unwrap().push(call.clone());
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        let mut result = match self.scripted_for(&call) {
+            Some(result) => result,
+            None => match &self.fallback {
+                Fallback::Respond(respond) => respond(&call),
+                Fallback::Error(message) => return Err(LoomError::PluginError(message.clone())),
+                Fallback::Hang => std::future::pending().await,
+            },
+        };
+        result.id = call.id;
+        Ok(result)
+    }
+}
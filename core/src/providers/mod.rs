@@ -1,6 +1,8 @@
 /// Capability providers for common tools and services
+pub mod mock;
 pub mod weather;
 pub mod web_search;
 
+pub use mock::MockProvider;
 pub use weather::WeatherProvider;
 pub use web_search::WebSearchProvider;
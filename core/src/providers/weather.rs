@@ -2,7 +2,7 @@
 ///
 /// Provides weather.get capability using Open-Meteo API (free, no API key required)
 /// Can be extended to support OpenWeatherMap or other services via configuration
-use crate::action_broker::CapabilityProvider;
+use crate::action_broker::{ActionResultExt, CapabilityProvider, CATEGORY_METADATA_KEY};
 use crate::proto::{
     ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind,
 };
@@ -226,6 +226,7 @@ impl Default for WeatherProvider {
 impl CapabilityProvider for WeatherProvider {
     fn descriptor(&self) -> CapabilityDescriptor {
         let mut metadata = HashMap::new();
+        metadata.insert(CATEGORY_METADATA_KEY.to_string(), "web".to_string());
         metadata.insert(
             "desc".to_string(),
             "Get current weather information for a location".to_string(),
@@ -256,6 +257,8 @@ impl CapabilityProvider for WeatherProvider {
             version: "0.1.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -296,6 +299,7 @@ impl CapabilityProvider for WeatherProvider {
                     message: "Location parameter cannot be empty".to_string(),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             });
         }
 
@@ -311,12 +315,7 @@ impl CapabilityProvider for WeatherProvider {
                     "units": weather.units,
                 });
 
-                Ok(ActionResult {
-                    id: call_id,
-                    status: ActionStatus::ActionOk as i32,
-                    output: serde_json::to_vec(&output)?,
-                    error: None,
-                })
+                Ok(ActionResult::try_ok_json(call_id, &output))
             }
             Err(e) => Ok(ActionResult {
                 id: call_id,
@@ -327,6 +326,7 @@ impl CapabilityProvider for WeatherProvider {
                     message: format!("Failed to get weather: {}", e),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             }),
         }
     }
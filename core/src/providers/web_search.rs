@@ -2,7 +2,7 @@
 ///
 /// Provides web.search capability using DuckDuckGo Instant Answer API
 /// Can be extended to support other search engines via configuration
-use crate::action_broker::CapabilityProvider;
+use crate::action_broker::{ActionResultExt, CapabilityProvider, CATEGORY_METADATA_KEY};
 use crate::proto::{
     ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind,
 };
@@ -185,6 +185,7 @@ impl Default for WebSearchProvider {
 impl CapabilityProvider for WebSearchProvider {
     fn descriptor(&self) -> CapabilityDescriptor {
         let mut metadata = HashMap::new();
+        metadata.insert(CATEGORY_METADATA_KEY.to_string(), "web".to_string());
         metadata.insert(
             "desc".to_string(),
             "Search the web for information using DuckDuckGo".to_string(),
@@ -216,6 +217,8 @@ impl CapabilityProvider for WebSearchProvider {
             version: "0.1.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -247,6 +250,7 @@ impl CapabilityProvider for WebSearchProvider {
                     message: "Query parameter cannot be empty".to_string(),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             });
         }
 
@@ -259,12 +263,7 @@ impl CapabilityProvider for WebSearchProvider {
                     "count": results.len(),
                 });
 
-                Ok(ActionResult {
-                    id: call_id,
-                    status: ActionStatus::ActionOk as i32,
-                    output: serde_json::to_vec(&output)?,
-                    error: None,
-                })
+                Ok(ActionResult::try_ok_json(call_id, &output))
             }
             Err(e) => Ok(ActionResult {
                 id: call_id,
@@ -275,6 +274,7 @@ impl CapabilityProvider for WebSearchProvider {
                     message: format!("Web search failed: {}", e),
                     details: Default::default(),
                 }),
+                metadata: Default::default(),
             }),
         }
     }
@@ -1,5 +1,6 @@
 // Telemetry and observability with OpenTelemetry support
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -7,6 +8,7 @@ use tracing::info;
 
 use opentelemetry::KeyValue;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
 use opentelemetry_sdk::Resource;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -250,3 +252,66 @@ pub fn shutdown_telemetry() {
 
     info!(target: "telemetry", "OpenTelemetry shutdown complete");
 }
+
+// ==============================================================================
+// Trace context propagation
+// ==============================================================================
+
+/// W3C trace context header carried in `ActionCall.headers`
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Write `span`'s W3C trace context (`traceparent`/`tracestate`) into `headers`.
+/// No-op unless an OpenTelemetry layer is installed and `span` is recorded.
+pub fn inject_trace_context(span: &tracing::Span, headers: &mut HashMap<String, String>) {
+    use opentelemetry::propagation::TextMapPropagator;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    TraceContextPropagator::new().inject_context(&span.context(), headers);
+}
+
+/// Read a W3C trace context from `headers`; `None` when absent or malformed
+pub fn extract_trace_context(headers: &HashMap<String, String>) -> Option<opentelemetry::Context> {
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    headers.get(TRACEPARENT_HEADER)?;
+    let cx = TraceContextPropagator::new().extract(headers);
+    cx.span().span_context().is_valid().then_some(cx)
+}
+
+/// Parent span for one agent turn.
+///
+/// Calls tagged with [`TurnSpan::apply_to`] (or built with `.headers(turn.headers().clone())`)
+/// carry the turn's trace context, and `ActionBroker::invoke` parents its span on it, so every
+/// tool call of the turn shows up as a child of `agent.turn` in Jaeger/Tempo.
+#[derive(Debug, Clone)]
+pub struct TurnSpan {
+    span: tracing::Span,
+    headers: HashMap<String, String>,
+}
+
+impl TurnSpan {
+    /// Open a turn span named `agent.turn` under the current span
+    pub fn start(goal: &str) -> Self {
+        let span = tracing::info_span!("agent.turn", goal = %goal);
+        let mut headers = HashMap::new();
+        inject_trace_context(&span, &mut headers);
+        Self { span, headers }
+    }
+
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Propagation headers for this turn (empty when tracing is not exported)
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// Attach this turn's trace context to `call`; existing headers are kept
+    pub fn apply_to(&self, call: &mut crate::proto::ActionCall) {
+        for (k, v) in &self.headers {
+            call.headers.insert(k.clone(), v.clone());
+        }
+    }
+}
@@ -0,0 +1,377 @@
+//! Load testing the broker with synthetic providers.
+//!
+//! `SyntheticProvider` answers after a latency drawn from a `LatencyDistribution` and fails or
+//! hangs at configurable rates. `LoadHarness` drives many concurrent `invoke`s against a
+//! capability for a duration (or a call count) and reports throughput and latency
+//! percentiles, e.g. to check concurrency limits and quarantine under realistic load:
+//!
+//! ```ignore
+//! let provider = Arc::new(
+//!     SyntheticProvider::named("search.web")
+//!         .latency(LatencyDistribution::Exponential { mean: Duration::from_millis(20) })
+//!         .error_rate(0.05),
+//! );
+//! broker.register_provider(provider.clone());
+//! broker.set_concurrency_limit("search.web", Some(8));
+//! let report = LoadHarness::new(broker, "search.web")
+//!     .concurrency(64)
+//!     .duration(Duration::from_secs(5))
+//!     .run()
+//!     .await;
+//! println!("{report}");
+//! assert!(provider.peak_in_flight() <= 8);
+//! ```
+
+use crate::action_broker::{ActionBroker, ActionResultBuilder, CapabilityProvider};
+use crate::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind};
+use crate::Result;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Error code of the failures a `SyntheticProvider` injects
+pub const SYNTHETIC_ERROR: &str = "SYNTHETIC_ERROR";
+/// `LoadReport::by_code` key for calls where `invoke` itself returned `Err`
+pub const INVOKE_ERROR: &str = "INVOKE_ERROR";
+
+/// How long a synthetic provider takes to answer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyDistribution {
+    Fixed(Duration),
+    /// Uniform between `min` and `max` (inclusive)
+    Uniform {
+        min: Duration,
+        max: Duration,
+    },
+    /// Exponential with the given mean: mostly fast, with a long tail
+    Exponential {
+        mean: Duration,
+    },
+}
+
+impl Default for LatencyDistribution {
+    fn default() -> Self {
+        LatencyDistribution::Fixed(Duration::ZERO)
+    }
+}
+
+impl LatencyDistribution {
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            LatencyDistribution::Fixed(d) => d,
+            LatencyDistribution::Uniform { min, max } if max > min => rng.gen_range(min..=max),
+            LatencyDistribution::Uniform { min, .. } => min,
+            LatencyDistribution::Exponential { mean } => {
+                // Inverse CDF; 1 - u is in (0, 1], so ln never sees 0
+                let u: f64 = rng.gen();
+                mean.mul_f64(-(1.0 - u).ln())
+            }
+        }
+    }
+}
+
+/// What one synthetic call does
+enum Behavior {
+    Answer,
+    Fail,
+    Hang,
+}
+
+/// Provider with configurable latency, error rate and hang rate, for load tests.
+///
+/// Failures are `ActionError` results with code `SYNTHETIC_ERROR`; hung calls never return,
+/// so they run into the broker's deadline. Rates are fractions in `0.0..=1.0`.
+pub struct SyntheticProvider {
+    descriptor: CapabilityDescriptor,
+    latency: LatencyDistribution,
+    error_rate: f64,
+    hang_rate: f64,
+    rng: Mutex<StdRng>,
+    calls: AtomicU64,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+impl SyntheticProvider {
+    pub fn new(descriptor: CapabilityDescriptor) -> Self {
+        Self {
+            descriptor,
+            latency: LatencyDistribution::default(),
+            error_rate: 0.0,
+            hang_rate: 0.0,
+            rng: Mutex::new(StdRng::from_entropy()),
+            calls: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Native provider for `name`, version `1.0.0`
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::new(CapabilityDescriptor {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        })
+    }
+
+    pub fn latency(mut self, latency: LatencyDistribution) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Share of calls answered with `SYNTHETIC_ERROR` (after their latency)
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Share of calls that never return
+    pub fn hang_rate(mut self, rate: f64) -> Self {
+        self.hang_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Fix the random seed so latencies and failures repeat from run to run
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Calls received so far
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Most calls that were running at once, to check concurrency limits
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::Relaxed)
+    }
+
+    fn draw(&self) -> (Duration, Behavior) {
+        let mut rng = self.rng.lock().unwrap();
+        let latency = self.latency.sample(&mut *rng);
+        let roll: f64 = rng.gen();
+        let behavior = if roll < self.hang_rate {
+            Behavior::Hang
+        } else if roll < self.hang_rate + self.error_rate {
+            Behavior::Fail
+        } else {
+            Behavior::Answer
+        };
+        (latency, behavior)
+    }
+}
+
+/// Decrements the in-flight count when a call finishes or is dropped by its deadline
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl CapabilityProvider for SyntheticProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        self.descriptor.clone()
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let running = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(running, Ordering::Relaxed);
+        let _guard = InFlightGuard(&self.in_flight);
+
+        let (latency, behavior) = self.draw();
+        if let Behavior::Hang = behavior {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(latency).await;
+        let builder = match behavior {
+            Behavior::Fail => ActionResultBuilder::new(ActionStatus::ActionError)
+                .error(SYNTHETIC_ERROR, "Injected failure"),
+            _ => ActionResultBuilder::new(ActionStatus::ActionOk),
+        };
+        Ok(builder.id(call.id).build())
+    }
+}
+
+/// Drives concurrent `invoke`s against one capability and measures them.
+///
+/// Each worker sends calls back to back until the duration is over or `max_calls` have been
+/// sent. Calls are clones of the template with fresh ids (`load-<worker>-<n>`), so the
+/// idempotency cache never answers them.
+pub struct LoadHarness {
+    broker: Arc<ActionBroker>,
+    template: ActionCall,
+    concurrency: usize,
+    duration: Duration,
+    max_calls: Option<u64>,
+}
+
+impl LoadHarness {
+    /// 8 workers for 1 s, calling `capability` with an empty payload
+    pub fn new(broker: Arc<ActionBroker>, capability: impl Into<String>) -> Self {
+        Self {
+            broker,
+            template: ActionCall {
+                capability: capability.into(),
+                ..Default::default()
+            },
+            concurrency: 8,
+            duration: Duration::from_secs(1),
+            max_calls: None,
+        }
+    }
+
+    /// Number of concurrent workers (at least 1)
+    pub fn concurrency(mut self, workers: usize) -> Self {
+        self.concurrency = workers.max(1);
+        self
+    }
+
+    /// How long workers keep sending calls
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Stop after this many calls in total, even before the duration is over
+    pub fn max_calls(mut self, calls: u64) -> Self {
+        self.max_calls = Some(calls);
+        self
+    }
+
+    /// Call to send (payload, timeout, QoS, headers, ...); its id is replaced per call
+    pub fn call(mut self, template: ActionCall) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub async fn run(&self) -> LoadReport {
+        let started = Instant::now();
+        let stop_at = started + self.duration;
+        let sent = Arc::new(AtomicU64::new(0));
+        let workers: Vec<_> = (0..self.concurrency)
+            .map(|worker| {
+                let broker = Arc::clone(&self.broker);
+                let template = self.template.clone();
+                let sent = Arc::clone(&sent);
+                let max_calls = self.max_calls;
+                tokio::spawn(async move {
+                    let mut samples = Vec::new();
+                    while Instant::now() < stop_at {
+                        let n = sent.fetch_add(1, Ordering::Relaxed);
+                        if max_calls.is_some_and(|max| n >= max) {
+                            break;
+                        }
+                        let mut call = template.clone();
+                        call.id = format!("load-{worker}-{n}");
+                        let call_started = Instant::now();
+                        let outcome = match broker.invoke(call).await {
+                            Ok(res) => match res.error {
+                                Some(error) if res.status != ActionStatus::ActionOk as i32 => {
+                                    Some(error.code)
+                                }
+                                _ => None,
+                            },
+                            Err(_) => Some(INVOKE_ERROR.to_string()),
+                        };
+                        samples.push((call_started.elapsed(), outcome));
+                    }
+                    samples
+                })
+            })
+            .collect();
+
+        let mut report = LoadReport::default();
+        for worker in workers {
+            // A panicking worker loses its samples; the broker catches provider panics
+            for (latency, outcome) in worker.await.unwrap_or_default() {
+                report.latencies.push(latency);
+                match outcome {
+                    None => report.ok += 1,
+                    Some(code) => *report.by_code.entry(code).or_default() += 1,
+                }
+            }
+        }
+        report.elapsed = started.elapsed();
+        report.latencies.sort_unstable();
+        report
+    }
+}
+
+/// Outcome of a `LoadHarness` run
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// Calls answered with `ActionOk`
+    pub ok: u64,
+    /// Failed calls by error code (`TIMEOUT`, `QUARANTINED`, `SYNTHETIC_ERROR`, ...)
+    pub by_code: BTreeMap<String, u64>,
+    /// Wall time of the run
+    pub elapsed: Duration,
+    /// Per-call latency as seen by the caller, sorted ascending
+    pub latencies: Vec<Duration>,
+}
+
+impl LoadReport {
+    pub fn total(&self) -> u64 {
+        self.latencies.len() as u64
+    }
+
+    pub fn failed(&self) -> u64 {
+        self.by_code.values().sum()
+    }
+
+    /// Completed calls per second
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.total() as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Latency at percentile `p` (`0.0..=100.0`, nearest rank); zero for an empty run
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} calls in {:.2?} ({:.1}/s): {} ok, {} failed",
+            self.total(),
+            self.elapsed,
+            self.throughput(),
+            self.ok,
+            self.failed()
+        )?;
+        for (code, count) in &self.by_code {
+            writeln!(f, "  {code}: {count}")?;
+        }
+        write!(
+            f,
+            "latency p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.percentile(100.0)
+        )
+    }
+}
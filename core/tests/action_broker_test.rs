@@ -1,10 +1,31 @@
 use async_trait::async_trait;
-use loom_core::action_broker::{ActionBroker, CapabilityProvider};
+use futures_util::FutureExt;
+use loom_core::action_broker::{
+    category_of, ActionBroker, ActionCallExt, ActionErrorExt, ActionResultBuilder, ActionResultExt,
+    AllowListAuthorizer, Authorizer, BatchOrdering, BrokerConfig, Candidate, CapabilityProvider,
+    ErrorClass, ExactVersion, FirstMatch, HttpErrorMapper, InvocationContext, InvocationScope,
+    JobProgress, JobProvider, OnBatchError, OnMissing, OnMissingKey, QuarantinePolicy, RateLimit,
+    RetryPolicy, RoundRobin, Timeout, TypedCapability, WarmUpOutcome, ANONYMOUS_KEY,
+    ATTEMPTS_METADATA_KEY, CANCELLED, CATEGORY_METADATA_KEY, COST_METADATA_KEY, DEBUG_DETAIL_KEY,
+    DRY_RUN_METADATA_KEY, DURATION_METADATA_KEY, HTTP_STATUS_DETAIL_KEY, JOB_ID_METADATA_KEY,
+    JOB_NOT_FOUND, JOB_PROGRESS_METADATA_KEY, NATIVE_CODE_DETAIL_KEY, OUTPUT_SCHEMA_INVALID,
+    PRINCIPAL_HEADER, PROVIDER_DEREGISTERED, PROVIDER_LIFECYCLE_TOPIC, PROVIDER_REGISTERED,
+    QUARANTINED, RATE_LIMITED, ROLE_HEADER, SCOPE_KEY_MISSING, SKIPPED, SOFT_TIMEOUT_METADATA_KEY,
+    TENANT_HEADER, TIMEOUT_MS_HEADER, TRANSFORM_ERROR, UNCATEGORIZED,
+};
+use loom_core::ids::CounterIdGenerator;
 use loom_core::proto::{
-    ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind, QoSLevel,
+    ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind,
+    QoSLevel, SCHEMA_METADATA_KEY,
+};
+use loom_core::{
+    define_capability, CancellationToken, EventBus, LoomError, MockClock, MockProvider, Result,
 };
-use loom_core::{LoomError, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 // Helper to create a test ActionCall
@@ -17,7 +38,7 @@ fn make_call(id: &str, capability: &str, version: &str, payload: Vec<u8>) -> Act
         headers: Default::default(),
         timeout_ms: 5000,
         correlation_id: String::new(),
-        qos: QoSLevel::QosRealtime as i32,
+        qos: Some(QoSLevel::QosRealtime as i32),
     }
 }
 
@@ -35,6 +56,8 @@ impl CapabilityProvider for EchoProvider {
             version: self.version.clone(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -44,6 +67,7 @@ impl CapabilityProvider for EchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -59,6 +83,8 @@ impl CapabilityProvider for ErrorProvider {
             version: "1.0.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -80,6 +106,8 @@ impl CapabilityProvider for SlowProvider {
             version: "1.0.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -90,6 +118,59 @@ impl CapabilityProvider for SlowProvider {
             status: ActionStatus::ActionOk as i32,
             output: vec![],
             error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+// Mock provider that panics inside invoke
+struct PanicProvider;
+
+#[async_trait]
+impl CapabilityProvider for PanicProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.panic".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, _call: ActionCall) -> Result<ActionResult> {
+        panic!("provider exploded");
+    }
+}
+
+// Mock provider that reports a fixed cost per call
+struct CostlyProvider {
+    cost: f64,
+}
+
+#[async_trait]
+impl CapabilityProvider for CostlyProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.costly".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert(COST_METADATA_KEY.to_string(), self.cost.to_string());
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: vec![],
+            error: None,
+            metadata,
         })
     }
 }
@@ -107,6 +188,8 @@ impl CapabilityProvider for CountingProvider {
             version: "1.0.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -118,6 +201,7 @@ impl CapabilityProvider for CountingProvider {
             status: ActionStatus::ActionOk as i32,
             output: vec![],
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -164,6 +248,38 @@ async fn invoke_unregistered_capability_errors() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn on_missing_result_returns_capability_not_found() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.set_on_missing(OnMissing::Result);
+
+    let res = broker
+        .invoke(make_call(
+            "call_missing",
+            "missing.capability",
+            "2.0",
+            vec![],
+        ))
+        .await?;
+    assert_eq!(res.status, ActionStatus::ActionError as i32);
+    let err = res.error.expect("error");
+    assert_eq!(err.code, "CAPABILITY_NOT_FOUND");
+    assert_eq!(err.details.get("version").map(String::as_str), Some("2.0"));
+    assert_eq!(broker.export_config().on_missing, OnMissing::Result);
+
+    broker.set_on_missing(OnMissing::Error);
+    assert!(broker
+        .invoke(make_call(
+            "call_missing_2",
+            "missing.capability",
+            "",
+            vec![]
+        ))
+        .await
+        .is_err());
+    Ok(())
+}
+
 #[tokio::test]
 async fn provider_error_returns_action_error_result() -> Result<()> {
     let broker = ActionBroker::new();
@@ -173,8 +289,11 @@ async fn provider_error_returns_action_error_result() -> Result<()> {
 
     let result = broker.invoke(call).await?;
     assert_eq!(result.status, ActionStatus::ActionError as i32);
-    assert!(result.error.is_some());
-    assert!(result.error.unwrap().message.contains("intentional error"));
+    let error = result.error.unwrap();
+    assert_eq!(error.code, "CAPABILITY_ERROR");
+    // The provider's own error text is internal: it stays out of the user-facing message
+    assert_eq!(error.message, "The action failed");
+    assert!(error.debug_detail().unwrap().contains("intentional error"));
     Ok(())
 }
 
@@ -193,6 +312,141 @@ async fn timeout_returns_action_timeout_result() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn error_messages_are_user_safe_and_debug_detail_can_be_redacted() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(ErrorProvider));
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 1000 }));
+    let mut slow = make_call("safe-timeout", "test.slow", "1.0.0", vec![]);
+    slow.timeout_ms = 50;
+
+    let timeout = broker.invoke(slow.clone()).await?.error.unwrap();
+    assert_eq!(timeout.message, "Action timed out");
+    let debug = timeout.debug_detail().unwrap();
+    assert!(
+        debug.contains("test.slow") && debug.contains("executing"),
+        "{debug}"
+    );
+
+    // Redaction drops only the debug detail; code, message and other details stay
+    broker.set_redact_debug_details(true);
+    slow.id = "safe-timeout-redacted".to_string();
+    let timeout = broker.invoke(slow).await?.error.unwrap();
+    assert_eq!(timeout.code, "TIMEOUT");
+    assert_eq!(timeout.debug_detail(), None);
+    assert_eq!(timeout.details["phase"], "executing");
+    let failed = broker
+        .invoke(make_call("safe-err", "test.error", "1.0.0", vec![]))
+        .await?
+        .error
+        .unwrap();
+    assert_eq!(failed.message, "The action failed");
+    assert!(!failed.details.contains_key(DEBUG_DETAIL_KEY));
+    assert!(broker.export_config().redact_debug_details);
+    Ok(())
+}
+
+#[tokio::test]
+async fn results_report_elapsed_time() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 1000 }));
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+
+    // Timeouts always carry how long the call ran
+    let mut call = make_call("elapsed-timeout", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 100;
+    let res = broker.invoke(call).await?;
+    let elapsed: u64 = res.error.unwrap().details["elapsed_ms"].parse().unwrap();
+    assert!((100..1000).contains(&elapsed), "elapsed_ms = {elapsed}");
+    assert!(!res.metadata.contains_key(DURATION_METADATA_KEY));
+
+    // Durations on other results are opt-in
+    let res = broker
+        .invoke(make_call(
+            "elapsed-off",
+            "test.echo",
+            "1.0.0",
+            b"x".to_vec(),
+        ))
+        .await?;
+    assert!(!res.metadata.contains_key(DURATION_METADATA_KEY));
+    broker.set_attach_duration(true);
+    assert!(broker.export_config().attach_duration);
+    let res = broker
+        .invoke(make_call("elapsed-on", "test.echo", "1.0.0", b"x".to_vec()))
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    let duration: u64 = res.metadata[DURATION_METADATA_KEY].parse().unwrap();
+    assert!(duration < 1000, "duration_ms = {duration}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn timeout_header_overrides_typed_timeout_and_is_clamped() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 300 }));
+
+    // Header shortens a generous typed timeout
+    let mut call = make_call("hdr-1", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 5_000;
+    call.headers.insert(TIMEOUT_MS_HEADER.into(), "50".into());
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    // Header values above the configured max are clamped
+    broker.apply_config(BrokerConfig {
+        max_header_timeout_ms: 50,
+        ..BrokerConfig::default()
+    });
+    let mut call = make_call("hdr-2", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = i64::MAX;
+    call.headers
+        .insert(TIMEOUT_MS_HEADER.into(), "60000".into());
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    // Malformed values fall back to the typed field
+    let mut call = make_call("hdr-3", "test.slow", "1.0.0", vec![]);
+    call.headers.insert(TIMEOUT_MS_HEADER.into(), "soon".into());
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    Ok(())
+}
+
+#[tokio::test]
+async fn registry_queries_and_provider_iteration() -> Result<()> {
+    let broker = ActionBroker::new();
+    assert!(broker.is_empty());
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "cap1".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "cap1".to_string(),
+        version: "2.0.0".to_string(),
+    }));
+    assert_eq!(broker.len(), 2);
+    assert!(broker.contains("cap1"));
+    assert!(!broker.contains("cap"));
+    assert!(broker.contains_version("cap1", "2.0.0"));
+    assert!(!broker.contains_version("cap1", "3.0.0"));
+
+    // The iterator holds no registry lock, so registering (and awaiting) mid-iteration is fine
+    for provider in broker.providers() {
+        let desc = provider.descriptor();
+        broker.register_provider(Arc::new(EchoProvider {
+            name: format!("{}-copy", desc.name),
+            version: desc.version.clone(),
+        }));
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(broker.len(), 4);
+    Ok(())
+}
+
 #[tokio::test]
 async fn list_capabilities_returns_registered_providers() -> Result<()> {
     let broker = ActionBroker::new();
@@ -265,7 +519,43 @@ async fn idempotent_call_returns_cached_result() -> Result<()> {
 }
 
 #[tokio::test]
-async fn zero_or_negative_timeout_defaults_to_30s() -> Result<()> {
+async fn idempotent_results_are_not_shared_across_tenants() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    let call_for = |tenant: &str, payload: &[u8]| {
+        let mut call = make_call("call_shared", "test.echo", "1.0.0", payload.to_vec());
+        call.headers.insert(TENANT_HEADER.into(), tenant.into());
+        call
+    };
+
+    let a = broker.invoke(call_for("a", b"from a")).await?;
+    let b = broker.invoke(call_for("b", b"from b")).await?;
+    assert_eq!(a.output, b"from a");
+    assert_eq!(b.output, b"from b");
+
+    // Each tenant replays its own result, and a supplied tenant counts like the header
+    let again = broker.invoke(call_for("a", b"ignored")).await?;
+    assert_eq!(again.output, b"from a");
+    let supplied = InvocationContext {
+        tenant_id: Some("b".to_string()),
+        ..Default::default()
+    };
+    let again = broker
+        .invoke_in_context(
+            make_call("call_shared", "test.echo", "1.0.0", b"ignored".to_vec()),
+            supplied,
+        )
+        .await?;
+    assert_eq!(again.output, b"from b");
+    assert_eq!(broker.diagnostics().cached_results, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn zero_or_negative_timeout_uses_the_broker_default() -> Result<()> {
     let broker = ActionBroker::new();
     broker.register_provider(Arc::new(EchoProvider {
         name: "test.echo".to_string(),
@@ -273,10 +563,2797 @@ async fn zero_or_negative_timeout_defaults_to_30s() -> Result<()> {
     }));
 
     let mut call = make_call("call_default_timeout", "test.echo", "1.0.0", vec![]);
-    call.timeout_ms = 0; // should default to 30000ms
+    call.timeout_ms = 0; // should default to the realtime 2000ms
 
     // This should not timeout even with 0 timeout_ms
     let result = broker.invoke(call).await?;
     assert_eq!(result.status, ActionStatus::ActionOk as i32);
     Ok(())
 }
+
+#[tokio::test]
+async fn idempotency_cache_expires_with_mock_clock() -> Result<()> {
+    let clock = MockClock::new(1_000);
+    let broker = ActionBroker::new()
+        .with_clock(clock.clone())
+        .with_cache_ttl(Duration::from_secs(60));
+    let count = Arc::new(Mutex::new(0));
+    broker.register_provider(Arc::new(CountingProvider {
+        count: Arc::clone(&count),
+    }));
+
+    let call = make_call("call_ttl", "test.count", "1.0.0", vec![]);
+    broker.invoke(call.clone()).await?;
+
+    // Still within TTL: served from cache
+    clock.advance(59_999);
+    broker.invoke(call.clone()).await?;
+    assert_eq!(*count.lock().await, 1);
+
+    // TTL elapsed: provider invoked again
+    clock.advance(1);
+    broker.invoke(call).await?;
+    assert_eq!(*count.lock().await, 2);
+    Ok(())
+}
+
+#[test]
+fn cache_trimming_past_the_bound_does_not_deadlock() {
+    let broker = Arc::new(ActionBroker::new());
+    broker.apply_config(BrokerConfig {
+        cache_max_entries: 1,
+        ..Default::default()
+    });
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+
+    // A deadlocked trim blocks its thread for good, so the calls get a thread of their own
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let worker = Arc::clone(&broker);
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let res = rt.block_on(async {
+            for i in 0..8 {
+                let call = make_call(&format!("call_trim_{i}"), "test.echo", "1.0.0", vec![]);
+                worker.invoke(call).await?;
+            }
+            Ok::<_, LoomError>(())
+        });
+        let _ = done_tx.send(res);
+    });
+    done_rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("trim_cache deadlocked")
+        .unwrap();
+    assert_eq!(broker.diagnostics().cached_results, 1);
+}
+
+fn schema_descriptor(version: &str, schema: &str) -> CapabilityDescriptor {
+    CapabilityDescriptor {
+        name: "test.schema".to_string(),
+        version: version.to_string(),
+        provider: ProviderKind::ProviderNative as i32,
+        metadata: [(SCHEMA_METADATA_KEY.to_string(), schema.to_string())]
+            .into_iter()
+            .collect(),
+        depends_on: Vec::new(),
+        default_qos: None,
+    }
+}
+
+#[tokio::test]
+async fn descriptor_checksum_invalidates_cached_results() -> Result<()> {
+    let base = schema_descriptor("1.0.0", r#"{"type":"object"}"#);
+    assert_eq!(base.checksum(), base.clone().checksum());
+
+    let mut renamed = base.clone();
+    renamed.name = "test.schema2".to_string();
+    let bumped = schema_descriptor("1.0.1", r#"{"type":"object"}"#);
+    let reshaped = schema_descriptor("1.0.0", r#"{"type":"string"}"#);
+    let mut unschemed = base.clone();
+    unschemed.metadata.clear();
+    for changed in [&renamed, &bumped, &reshaped, &unschemed] {
+        assert_ne!(changed.checksum(), base.checksum(), "{changed:?}");
+    }
+    // Metadata other than the schema does not count
+    let mut described = base.clone();
+    described
+        .metadata
+        .insert("desc".to_string(), "now with a description".to_string());
+    assert_eq!(described.checksum(), base.checksum());
+
+    // Re-registering an identical descriptor keeps the cached result
+    let broker = ActionBroker::new();
+    let first = Arc::new(MockProvider::new(base.clone()));
+    broker.register_provider(first.clone());
+    let call = make_call("checksum-1", "test.schema", "1.0.0", vec![]);
+    broker.invoke(call.clone()).await?;
+    let same = Arc::new(MockProvider::new(base));
+    broker.register_provider(same.clone());
+    broker.invoke(call.clone()).await?;
+    assert_eq!((first.call_count(), same.call_count()), (1, 0));
+
+    // A changed schema under the same name and version is a different provider
+    let changed = Arc::new(MockProvider::new(reshaped));
+    broker.register_provider(changed.clone());
+    broker.invoke(call.clone()).await?;
+    assert_eq!(changed.call_count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn provider_panic_returns_action_error_and_broker_stays_usable() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(PanicProvider));
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+
+    let result = broker
+        .invoke(make_call("call_panic", "test.panic", "1.0.0", vec![]))
+        .await?;
+    assert_eq!(result.status, ActionStatus::ActionError as i32);
+    let err = result.error.expect("panic should produce an error");
+    assert_eq!(err.code, "PROVIDER_PANIC");
+    assert_eq!(err.debug_detail(), Some("provider exploded"));
+
+    // Broker keeps serving other capabilities
+    let ok = broker
+        .invoke(make_call(
+            "call_after_panic",
+            "test.echo",
+            "1.0.0",
+            b"hi".to_vec(),
+        ))
+        .await?;
+    assert_eq!(ok.status, ActionStatus::ActionOk as i32);
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_list_authorizer_forbids_unlisted_callers() -> Result<()> {
+    let broker = ActionBroker::new();
+    let count = Arc::new(Mutex::new(0));
+    broker.register_provider(Arc::new(CountingProvider {
+        count: Arc::clone(&count),
+    }));
+    broker.set_authorizer(Arc::new(
+        AllowListAuthorizer::new()
+            .allow_role("worker", "test.*")
+            .allow_principal("agent.admin", "*"),
+    ));
+
+    // No identity headers: denied
+    let res = broker
+        .invoke(make_call("authz_anon", "test.count", "1.0.0", vec![]))
+        .await?;
+    assert_eq!(res.status, ActionStatus::ActionError as i32);
+    assert_eq!(res.error.unwrap().code, "FORBIDDEN");
+
+    // Wrong role: denied
+    let mut call = make_call("authz_guest", "test.count", "1.0.0", vec![]);
+    call.headers.insert(ROLE_HEADER.into(), "guest".into());
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.error.unwrap().code, "FORBIDDEN");
+    assert_eq!(
+        *count.lock().await,
+        0,
+        "denied calls never reach the provider"
+    );
+
+    // Role wildcard and principal wildcard: allowed
+    let mut call = make_call("authz_worker", "test.count", "1.0.0", vec![]);
+    call.headers.insert(ROLE_HEADER.into(), "worker".into());
+    assert_eq!(
+        broker.invoke(call).await?.status,
+        ActionStatus::ActionOk as i32
+    );
+
+    let mut call = make_call("authz_admin", "test.count", "1.0.0", vec![]);
+    call.headers
+        .insert(PRINCIPAL_HEADER.into(), "agent.admin".into());
+    assert_eq!(
+        broker.invoke(call).await?.status,
+        ActionStatus::ActionOk as i32
+    );
+    assert_eq!(*count.lock().await, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cost_accumulates_per_correlation_and_budget_rejects() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(CostlyProvider { cost: 0.4 }));
+    broker.set_budget("session-a", 1.0);
+
+    let call = |id: &str, cid: &str| {
+        let mut c = make_call(id, "test.costly", "1.0.0", vec![]);
+        c.correlation_id = cid.to_string();
+        c
+    };
+
+    for i in 0..3 {
+        let res = broker
+            .invoke(call(&format!("cost_a{i}"), "session-a"))
+            .await?;
+        assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    }
+    assert!((broker.cost_for("session-a") - 1.2).abs() < 1e-9);
+
+    // Budget spent: further calls are rejected without reaching the provider
+    let res = broker.invoke(call("cost_a3", "session-a")).await?;
+    assert_eq!(res.status, ActionStatus::ActionError as i32);
+    assert_eq!(res.error.unwrap().code, "BUDGET_EXCEEDED");
+    assert!((broker.cost_for("session-a") - 1.2).abs() < 1e-9);
+
+    // Other sessions are tracked independently and unbudgeted
+    broker.invoke(call("cost_b0", "session-b")).await?;
+    assert!((broker.cost_for("session-b") - 0.4).abs() < 1e-9);
+    assert_eq!(broker.cost_for("unknown"), 0.0);
+    Ok(())
+}
+
+fn tenant_call(id: &str, tenant: Option<&str>) -> ActionCall {
+    let mut call = make_call(id, "test.costly", "1.0.0", vec![]);
+    if let Some(tenant) = tenant {
+        call.headers.insert(TENANT_HEADER.into(), tenant.into());
+    }
+    call
+}
+
+#[tokio::test]
+async fn rate_limits_are_counted_per_tenant_and_window() -> Result<()> {
+    let clock = MockClock::new(0);
+    let broker = ActionBroker::new().with_clock(clock.clone());
+    broker.register_provider(Arc::new(CostlyProvider { cost: 0.0 }));
+    broker.set_rate_limit(
+        InvocationScope::Tenant,
+        Some(RateLimit {
+            max_calls: 2,
+            window_ms: 1_000,
+        }),
+    );
+
+    for i in 0..2 {
+        let res = broker
+            .invoke(tenant_call(&format!("rate_a{i}"), Some("acme")))
+            .await?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    clock.advance(400);
+    let res = broker.invoke(tenant_call("rate_a2", Some("acme"))).await?;
+    let error = res.error.unwrap();
+    assert_eq!(error.code, RATE_LIMITED);
+    assert_eq!(error.details["scope"], "tenant");
+    assert_eq!(error.details["key"], "acme");
+    assert_eq!(error.details["retry_after_ms"], "600");
+
+    // Another tenant has its own window; calls without a tenant share the anonymous one
+    for i in 0..2 {
+        let res = broker
+            .invoke(tenant_call(&format!("rate_b{i}"), Some("globex")))
+            .await?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    for i in 0..2 {
+        let res = broker
+            .invoke(tenant_call(&format!("rate_anon{i}"), None))
+            .await?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    let res = broker.invoke(tenant_call("rate_anon2", None)).await?;
+    assert_eq!(res.error.unwrap().details["key"], ANONYMOUS_KEY);
+
+    // Or are turned away outright
+    broker.set_on_missing_key(OnMissingKey::Reject);
+    let res = broker.invoke(tenant_call("rate_anon3", None)).await?;
+    let error = res.error.unwrap();
+    assert_eq!(error.code, SCOPE_KEY_MISSING);
+    assert_eq!(error.details["scope"], "tenant");
+    let diag = broker.diagnostics();
+    assert_eq!(diag.rate_limits["tenant"].max_calls, 2);
+    assert_eq!(diag.on_missing_key, OnMissingKey::Reject);
+    broker.set_on_missing_key(OnMissingKey::Shared);
+
+    // The next window admits the tenant again
+    clock.advance(600);
+    let res = broker.invoke(tenant_call("rate_a3", Some("acme"))).await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+
+    broker.set_rate_limit(InvocationScope::Tenant, None);
+    for i in 4..8 {
+        let res = broker
+            .invoke(tenant_call(&format!("rate_a{i}"), Some("acme")))
+            .await?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    Ok(())
+}
+
+// Denies every call made for the "suspended" tenant
+struct TenantAuthorizer;
+
+impl Authorizer for TenantAuthorizer {
+    fn authorize(&self, _call: &ActionCall) -> std::result::Result<(), ActionError> {
+        Ok(())
+    }
+
+    fn authorize_with_context(
+        &self,
+        call: &ActionCall,
+        ctx: &InvocationContext,
+    ) -> std::result::Result<(), ActionError> {
+        if ctx.tenant_id.as_deref() != Some("suspended") {
+            return self.authorize(call);
+        }
+        Err(ActionError {
+            code: "FORBIDDEN".to_string(),
+            message: "Tenant is suspended".to_string(),
+            details: Default::default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn tenant_scope_drives_authorization_and_budgets() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(CostlyProvider { cost: 0.5 }));
+    broker.set_authorizer(Arc::new(TenantAuthorizer));
+    broker.set_budget_scope(InvocationScope::Tenant);
+    broker.set_budget("acme", 1.0);
+
+    let res = broker
+        .invoke(tenant_call("tenant_suspended", Some("suspended")))
+        .await?;
+    assert_eq!(res.error.unwrap().code, "FORBIDDEN");
+
+    // Spend accrues per tenant across correlation ids
+    for (i, correlation) in ["turn-1", "turn-2"].into_iter().enumerate() {
+        let mut call = tenant_call(&format!("tenant_acme{i}"), Some("acme"));
+        call.correlation_id = correlation.to_string();
+        let res = broker.invoke(call).await?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    assert!((broker.cost_for("acme") - 1.0).abs() < 1e-9);
+    assert_eq!(broker.cost_for("turn-1"), 0.0);
+
+    let mut call = tenant_call("tenant_acme2", Some("acme"));
+    call.correlation_id = "turn-3".to_string();
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.error.unwrap().code, "BUDGET_EXCEEDED");
+
+    let res = broker
+        .invoke(tenant_call("tenant_globex", Some("globex")))
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    assert!((broker.cost_for("globex") - 0.5).abs() < 1e-9);
+    Ok(())
+}
+
+#[tokio::test]
+async fn provider_lifecycle_events_are_published_to_bus() -> Result<()> {
+    let bus = Arc::new(EventBus::new().await?);
+    let (_sub, mut rx) = bus
+        .subscribe(
+            PROVIDER_LIFECYCLE_TOPIC.to_string(),
+            vec![],
+            QoSLevel::QosBatched,
+        )
+        .await?;
+    let broker = ActionBroker::new()
+        .with_event_bus(Arc::clone(&bus))
+        .with_id_generator(Arc::new(CounterIdGenerator::new("lc-")));
+
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0".to_string(),
+    }));
+    assert!(broker.deregister_provider("test.echo", "1.0"));
+    assert!(!broker.deregister_provider("test.echo", "1.0"));
+
+    let registered = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+        .await
+        .expect("registered event")
+        .unwrap();
+    assert_eq!(registered.r#type, PROVIDER_REGISTERED);
+    assert_eq!(registered.id, "lc-1");
+    let desc: serde_json::Value = serde_json::from_slice(&registered.payload)?;
+    assert_eq!(desc["name"], "test.echo");
+    assert_eq!(desc["version"], "1.0");
+    assert_eq!(desc["provider"], "PROVIDER_NATIVE");
+
+    let deregistered = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+        .await
+        .expect("deregistered event")
+        .unwrap();
+    assert_eq!(deregistered.r#type, PROVIDER_DEREGISTERED);
+    assert_eq!(deregistered.id, "lc-2");
+    assert!(broker.list_capabilities().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn broker_config_round_trips_through_json_and_applies() -> Result<()> {
+    let source = ActionBroker::new().with_cache_ttl(Duration::from_secs(60));
+    source.set_default_budget(Some(5.0));
+    source.set_budget("corr-1", 1.5);
+    let mut cfg = source.export_config();
+    assert_eq!(cfg.cache_ttl_ms, Some(60_000));
+    assert_eq!(cfg.default_timeout_ms, 30_000);
+    cfg.default_timeout_ms = 50;
+    cfg.qos_timeouts_ms.clear();
+
+    let json = serde_json::to_string(&cfg)?;
+    let restored: BrokerConfig = serde_json::from_str(&json)?;
+    assert_eq!(restored, cfg);
+
+    // Providers are registered programmatically; only policy is restored
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 500 }));
+    broker.apply_config(restored);
+    assert_eq!(broker.export_config(), cfg);
+
+    // Restored default timeout applies to calls without their own timeout
+    let mut call = make_call("cfg-1", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 0;
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    // Partial snapshots fill in defaults
+    let partial: BrokerConfig = serde_json::from_str(r#"{"default_budget": 2.0}"#)?;
+    assert_eq!(partial.default_timeout_ms, 30_000);
+    assert_eq!(partial.qos_timeouts_ms["realtime"], 2_000);
+    assert_eq!(partial.default_budget, Some(2.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_capabilities_is_sorted_and_filterable() -> Result<()> {
+    let broker = ActionBroker::new();
+    for (name, version) in [
+        ("b.tool", "1.10.0"),
+        ("a.tool", "2.0.0"),
+        ("b.tool", "1.9.0"),
+        ("b.tool", "1.9"),
+    ] {
+        broker.register_provider(Arc::new(EchoProvider {
+            name: name.to_string(),
+            version: version.to_string(),
+        }));
+    }
+
+    let listed: Vec<(String, String)> = broker
+        .list_capabilities()
+        .into_iter()
+        .map(|d| (d.name, d.version))
+        .collect();
+    let expected = [
+        ("a.tool", "2.0.0"),
+        ("b.tool", "1.9"),
+        ("b.tool", "1.9.0"),
+        ("b.tool", "1.10.0"),
+    ];
+    assert_eq!(
+        listed,
+        expected
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    let b_only = broker.list_capabilities_filtered(|d| d.name.starts_with("b."));
+    assert_eq!(b_only.len(), 3);
+    assert_eq!(b_only[0].version, "1.9");
+
+    let wasm =
+        broker.list_capabilities_filtered(|d| d.provider == ProviderKind::ProviderWasm as i32);
+    assert!(wasm.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_call_times_out_while_waiting_for_permit() -> Result<()> {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 400 }));
+    broker.set_concurrency_limit("test.slow", Some(1));
+
+    let long = {
+        let broker = Arc::clone(&broker);
+        tokio::spawn(async move {
+            let mut call = make_call("long", "test.slow", "1.0.0", vec![]);
+            call.timeout_ms = 2_000;
+            broker.invoke(call).await
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    let started = std::time::Instant::now();
+    let mut queued = make_call("queued", "test.slow", "1.0.0", vec![]);
+    queued.timeout_ms = 100;
+    let res = broker.invoke(queued).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+    let err = res.error.unwrap();
+    assert_eq!(err.code, "TIMEOUT");
+    assert_eq!(err.details.get("phase").map(String::as_str), Some("queued"));
+    // Timed out on its own deadline, not after the long call released the permit
+    assert!(started.elapsed() < Duration::from_millis(350));
+
+    let long_res = long.await.unwrap()?;
+    assert_eq!(long_res.status, ActionStatus::ActionOk as i32);
+    assert_eq!(
+        broker.export_config().concurrency_limits.get("test.slow"),
+        Some(&1)
+    );
+    Ok(())
+}
+
+// Slow provider declaring background as its default QoS; reports the QoS it was called with
+struct BackgroundProvider;
+
+#[async_trait]
+impl CapabilityProvider for BackgroundProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.index".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: Some(QoSLevel::QosBackground as i32),
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("qos".to_string(), call.qos().as_str_name().to_string());
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: vec![],
+            error: None,
+            metadata,
+        })
+    }
+}
+
+#[tokio::test]
+async fn provider_default_qos_schedules_unset_calls_in_its_lane() -> Result<()> {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(BackgroundProvider));
+    broker.set_qos_concurrency_limit(QoSLevel::QosBackground, Some(1));
+
+    let unset_call = |id: &str, timeout_ms: i64| {
+        let mut call = make_call(id, "test.index", "", vec![]);
+        call.qos = None;
+        call.timeout_ms = timeout_ms;
+        call
+    };
+    let first = {
+        let broker = Arc::clone(&broker);
+        let call = unset_call("bg-1", 2_000);
+        tokio::spawn(async move { broker.invoke(call).await })
+    };
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    // Unset qos resolves to the provider's background default and queues behind bg-1
+    let res = broker.invoke(unset_call("bg-2", 100)).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+    let err = res.error.unwrap();
+    assert_eq!(err.details.get("phase").map(String::as_str), Some("queued"));
+
+    // An explicit call qos wins over the provider default and bypasses the background lane
+    let mut realtime = unset_call("rt-1", 1_000);
+    realtime.qos = Some(QoSLevel::QosRealtime as i32);
+    let res = broker.invoke(realtime).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    assert_eq!(
+        res.metadata.get("qos").map(String::as_str),
+        Some("QOS_REALTIME")
+    );
+
+    let res = first.await.unwrap()?;
+    assert_eq!(
+        res.metadata.get("qos").map(String::as_str),
+        Some("QOS_BACKGROUND")
+    );
+    assert_eq!(
+        broker
+            .export_config()
+            .qos_concurrency_limits
+            .get("background"),
+        Some(&1)
+    );
+    Ok(())
+}
+
+// Captures formatted log output for assertions
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogBuffer {
+    type Writer = LogBuffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn payload_sampling_logs_full_records_for_one_in_n_calls() -> Result<()> {
+    let logs = LogBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    broker.set_payload_sample_rate(3);
+    for i in 0..6 {
+        let call = make_call(&format!("s-{i}"), "test.echo", "1.0.0", b"hi".to_vec());
+        broker.invoke(call).await?;
+    }
+
+    let text = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let payload_lines: Vec<&str> = text
+        .lines()
+        .filter(|l| l.contains("action_broker::payload"))
+        .collect();
+    let full: Vec<&str> = payload_lines
+        .iter()
+        .copied()
+        .filter(|l| l.contains("sampled=true"))
+        .collect();
+    // Calls 0 and 3: one call record and one result record each, with the whole message
+    assert_eq!(full.len(), 4);
+    assert!(full.iter().all(|l| l.contains("s-0") || l.contains("s-3")));
+    assert!(full.iter().any(|l| l.contains("ActionCall {")));
+    assert!(full.iter().any(|l| l.contains("ActionResult {")));
+
+    let brief: Vec<&str> = payload_lines
+        .iter()
+        .copied()
+        .filter(|l| l.contains("sampled=false"))
+        .collect();
+    assert_eq!(brief.len(), 8);
+    assert!(brief.iter().all(|l| !l.contains("ActionCall {")));
+    assert!(brief
+        .iter()
+        .all(|l| l.contains("payload_bytes=2") || l.contains("output_bytes=2")));
+    assert_eq!(broker.export_config().payload_sample_every, 3);
+    Ok(())
+}
+
+// Echoes the payload as output and declares an output schema
+struct SchemaEchoProvider;
+
+#[async_trait]
+impl CapabilityProvider for SchemaEchoProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.weather".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: call.payload,
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["temp"],
+            "properties": {"temp": {"type": "number"}}
+        }))
+    }
+}
+
+#[tokio::test]
+async fn output_validation_flags_results_violating_the_schema() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SchemaEchoProvider));
+    let invoke = |id: &str, output: &[u8]| {
+        broker.invoke(make_call(id, "test.weather", "1.0.0", output.to_vec()))
+    };
+
+    // Off by default: malformed output passes through
+    let res = invoke("o-1", b"{}").await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+
+    broker.set_output_validation(true);
+    assert!(broker.export_config().validate_output);
+    let res = invoke("o-2", br#"{"temp": 21.5}"#).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+
+    for (id, output) in [("o-3", &br#"{"temp": "warm"}"#[..]), ("o-4", b"not json")] {
+        let res = invoke(id, output).await?;
+        assert_eq!(res.status, ActionStatus::ActionError as i32);
+        assert!(res.output.is_empty());
+        let err = res.error.unwrap();
+        assert_eq!(err.code, OUTPUT_SCHEMA_INVALID);
+        assert_eq!(
+            err.details.get("capability").map(String::as_str),
+            Some("test.weather")
+        );
+        assert!(err.details.contains_key("violation"));
+    }
+    Ok(())
+}
+
+// Produces one chunk every 20 ms; on soft cancel returns what it has so far
+struct ChunkingProvider;
+
+#[async_trait]
+impl CapabilityProvider for ChunkingProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.chunks".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.invoke_cancellable(call, CancellationToken::new())
+            .await
+    }
+
+    async fn invoke_cancellable(
+        &self,
+        call: ActionCall,
+        cancel: CancellationToken,
+    ) -> Result<ActionResult> {
+        let mut output = Vec::new();
+        for _ in 0..20 {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(Duration::from_millis(20)) => output.push(b'x'),
+            }
+        }
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output,
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn soft_deadline_lets_provider_return_partial_output() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(ChunkingProvider));
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 400 }));
+    broker.set_soft_timeout_ratio(0.5);
+    assert_eq!(broker.export_config().soft_timeout_ratio, Some(0.5));
+
+    let mut call = make_call("soft-1", "test.chunks", "1.0.0", vec![]);
+    call.timeout_ms = 200;
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    assert!(!res.output.is_empty() && res.output.len() < 20);
+    assert_eq!(
+        res.metadata
+            .get(SOFT_TIMEOUT_METADATA_KEY)
+            .map(String::as_str),
+        Some("true")
+    );
+
+    // Providers that ignore the token are still aborted at the hard deadline
+    let mut call = make_call("soft-2", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 100;
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    // Out-of-range ratios disable the soft phase
+    broker.set_soft_timeout_ratio(1.5);
+    let mut call = make_call("soft-3", "test.chunks", "1.0.0", vec![]);
+    call.timeout_ms = 2_000;
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.output.len(), 20);
+    assert!(!res.metadata.contains_key(SOFT_TIMEOUT_METADATA_KEY));
+
+    // Including negative or NaN ones arriving through a config instead of the setter
+    for ratio in [-0.5, f64::NAN] {
+        broker.apply_config(BrokerConfig {
+            soft_timeout_ratio: Some(ratio),
+            ..broker.export_config()
+        });
+        assert_eq!(broker.export_config().soft_timeout_ratio, None);
+        let mut call = make_call(&format!("soft-{ratio}"), "test.chunks", "1.0.0", vec![]);
+        call.timeout_ms = 2_000;
+        assert_eq!(broker.invoke(call).await?.output.len(), 20);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn result_transformers_rewrite_output_but_not_id() -> Result<()> {
+    let broker = ActionBroker::new();
+    for name in ["test.echo", "test.other"] {
+        broker.register_provider(Arc::new(EchoProvider {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+        }));
+    }
+    // Fahrenheit -> Celsius, then a tag; the id rewrite must be discarded
+    broker.add_result_transformer(
+        "test.echo",
+        Arc::new(|res: &mut ActionResult| {
+            let v: serde_json::Value = serde_json::from_slice(&res.output)?;
+            let f = v["temp_f"].as_f64().unwrap_or_default();
+            res.output = serde_json::to_vec(&serde_json::json!({"temp_c": (f - 32.0) / 1.8}))?;
+            res.id = "hijacked".to_string();
+            Ok(())
+        }),
+    );
+    broker.add_result_transformer(
+        "test.echo",
+        Arc::new(|res: &mut ActionResult| {
+            res.metadata
+                .insert("unit".to_string(), "celsius".to_string());
+            Ok(())
+        }),
+    );
+
+    let res = broker
+        .invoke(make_call(
+            "tr-1",
+            "test.echo",
+            "1.0.0",
+            br#"{"temp_f": 212}"#.to_vec(),
+        ))
+        .await?;
+    assert_eq!(res.id, "tr-1");
+    assert_eq!(res.output, br#"{"temp_c":100.0}"#);
+    assert_eq!(
+        res.metadata.get("unit").map(String::as_str),
+        Some("celsius")
+    );
+
+    // Other capabilities are untouched
+    let res = broker
+        .invoke(make_call("tr-2", "test.other", "1.0.0", b"raw".to_vec()))
+        .await?;
+    assert_eq!(res.output, b"raw");
+
+    // A failing transformer yields TRANSFORM_ERROR
+    let res = broker
+        .invoke(make_call(
+            "tr-3",
+            "test.echo",
+            "1.0.0",
+            b"not json".to_vec(),
+        ))
+        .await?;
+    assert_eq!(res.id, "tr-3");
+    assert_eq!(res.error.unwrap().code, TRANSFORM_ERROR);
+
+    broker.clear_result_transformers("test.echo");
+    let res = broker
+        .invoke(make_call(
+            "tr-4",
+            "test.echo",
+            "1.0.0",
+            b"not json".to_vec(),
+        ))
+        .await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    Ok(())
+}
+
+#[tokio::test]
+async fn action_call_builder_fills_defaults_and_validates() -> Result<()> {
+    let call = ActionCall::builder("test.echo")
+        .version("1.0")
+        .payload_json(&serde_json::json!({"text": "hi"}))?
+        .timeout_ms(3000)
+        .correlation_id("corr-7")
+        .header("sender", "agent.a")
+        .qos(QoSLevel::QosBatched)
+        .build()?;
+    assert_eq!(call.capability, "test.echo");
+    assert_eq!(call.timeout_ms, 3000);
+    assert_eq!(call.correlation_id, "corr-7");
+    assert_eq!(call.qos, Some(QoSLevel::QosBatched as i32));
+    assert_eq!(
+        call.headers.get("sender").map(String::as_str),
+        Some("agent.a")
+    );
+    assert_eq!(call.id.len(), 36, "generated UUID: {}", call.id);
+
+    let other = ActionCall::builder("test.echo").build()?;
+    assert_ne!(other.id, call.id);
+    assert!(other.headers.is_empty());
+    assert_eq!(ActionCall::builder("x").id("fixed").build()?.id, "fixed");
+
+    assert!(matches!(
+        ActionCall::builder("  ").build(),
+        Err(LoomError::PluginError(_))
+    ));
+
+    // Built calls dispatch like hand-written ones
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0".to_string(),
+    }));
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    Ok(())
+}
+
+// Provider with a configurable warm-up behaviour
+struct WarmingProvider {
+    name: &'static str,
+    delay_ms: u64,
+    fail: bool,
+    warmed: Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[async_trait]
+impl CapabilityProvider for WarmingProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: self.name.to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: vec![],
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+
+    async fn warm_up(&self) -> Result<()> {
+        tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+        if self.fail {
+            return Err(LoomError::PluginError("model file missing".into()));
+        }
+        self.warmed.store(true, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn warm_up_runs_providers_concurrently_and_reports_each() -> Result<()> {
+    let broker = ActionBroker::new();
+    let warmed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    for (name, delay_ms, fail) in [
+        ("a.ready", 100, false),
+        ("b.fail", 10, true),
+        ("c.slow", 5_000, false),
+    ] {
+        broker.register_provider(Arc::new(WarmingProvider {
+            name,
+            delay_ms,
+            fail,
+            warmed: Arc::clone(&warmed),
+        }));
+    }
+    // Default no-op warm-up
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "d.echo".to_string(),
+        version: "1.0".to_string(),
+    }));
+
+    let started = std::time::Instant::now();
+    let reports = broker
+        .warm_up_with_timeout(Duration::from_millis(300))
+        .await;
+    // Concurrent: bounded by the timeout, not the sum of delays
+    assert!(started.elapsed() < Duration::from_millis(1_000));
+
+    let outcomes: Vec<(&str, &WarmUpOutcome)> = reports
+        .iter()
+        .map(|r| (r.capability.as_str(), &r.outcome))
+        .collect();
+    assert_eq!(outcomes[0], ("a.ready", &WarmUpOutcome::Ready));
+    assert!(
+        matches!(outcomes[1], ("b.fail", WarmUpOutcome::Failed(m)) if m.contains("model file missing"))
+    );
+    assert_eq!(outcomes[2], ("c.slow", &WarmUpOutcome::TimedOut));
+    assert_eq!(outcomes[3], ("d.echo", &WarmUpOutcome::Ready));
+    assert!(warmed.load(std::sync::atomic::Ordering::SeqCst));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoke_batch_reports_mixed_outcome_in_input_order() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    broker.register_provider(Arc::new(ErrorProvider));
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 500 }));
+
+    let mut slow = make_call("b-slow", "test.slow", "1.0.0", vec![]);
+    slow.timeout_ms = 50;
+    let calls = vec![
+        make_call("b-ok-1", "test.echo", "1.0.0", b"x".to_vec()),
+        make_call("b-err", "test.error", "1.0.0", vec![]),
+        slow,
+        make_call("b-missing", "test.missing", "", vec![]),
+        make_call("b-ok-2", "test.echo", "1.0.0", b"y".to_vec()),
+    ];
+    let outcome = broker.invoke_batch(calls).await;
+
+    let ids: Vec<&str> = outcome.results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec!["b-ok-1", "b-err", "b-slow", "b-missing", "b-ok-2"]
+    );
+    assert_eq!(
+        (outcome.ok_count, outcome.error_count, outcome.timeout_count),
+        (2, 2, 1)
+    );
+    assert!(outcome.is_partial());
+    assert!(!outcome.is_all_ok());
+    assert_eq!(
+        outcome.results[3].error.as_ref().unwrap().code,
+        "INVOKE_ERROR"
+    );
+
+    let all_ok = broker
+        .invoke_batch(vec![make_call("b-ok-3", "test.echo", "1.0.0", vec![])])
+        .await;
+    assert!(all_ok.is_all_ok());
+
+    let failed = broker
+        .invoke_batch(vec![make_call("b-err-2", "test.error", "1.0.0", vec![])])
+        .await;
+    assert!(failed.is_total_failure());
+    Ok(())
+}
+
+/// `kv.put` stores its payload after a delay and `kv.get` fails until something is stored,
+/// so a get only succeeds when it runs after the put returned
+fn register_kv(broker: &ActionBroker) {
+    let stored = Arc::new(std::sync::Mutex::new(None));
+    let put_into = stored.clone();
+    broker.register_provider(Arc::new(
+        MockProvider::named("kv.put")
+            .with_delay(Duration::from_millis(30))
+            .returns(move |call| {
+                *put_into.lock().unwrap() = Some(call.payload.clone());
+                ActionResultBuilder::new(ActionStatus::ActionOk).build()
+            }),
+    ));
+    let get_from = stored;
+    broker.register_provider(Arc::new(MockProvider::named("kv.get").returns(move |_| {
+        match get_from.lock().unwrap().clone() {
+            Some(value) => ActionResultBuilder::new(ActionStatus::ActionOk)
+                .output(value)
+                .build(),
+            None => ActionResultBuilder::new(ActionStatus::ActionError)
+                .error("NOT_FOUND", "nothing stored")
+                .build(),
+        }
+    })));
+}
+
+#[tokio::test]
+async fn sequential_batch_runs_calls_after_their_predecessors() -> Result<()> {
+    let broker = ActionBroker::new();
+    register_kv(&broker);
+
+    let calls = || {
+        vec![
+            make_call("", "kv.put", "1.0.0", b"v1".to_vec()),
+            make_call("", "kv.get", "1.0.0", vec![]),
+        ]
+    };
+    // Concurrently, the get overtakes the delayed put
+    let concurrent = broker
+        .invoke_batch_with(calls(), BatchOrdering::Concurrent)
+        .await;
+    assert_eq!(
+        concurrent.results[1].error.as_ref().unwrap().code,
+        "NOT_FOUND"
+    );
+
+    let sequential = broker
+        .invoke_batch_with(calls(), BatchOrdering::Sequential(OnBatchError::Stop))
+        .await;
+    assert!(sequential.is_all_ok());
+    assert_eq!(sequential.results[1].output, b"v1");
+    assert_eq!(sequential.stopped_at, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sequential_batch_stops_or_continues_after_a_failure() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    broker.register_provider(Arc::new(ErrorProvider));
+    let calls = |tag: &str| {
+        vec![
+            make_call(&format!("{tag}-1"), "test.echo", "1.0.0", b"a".to_vec()),
+            make_call(&format!("{tag}-2"), "test.error", "1.0.0", vec![]),
+            make_call(&format!("{tag}-3"), "test.echo", "1.0.0", b"b".to_vec()),
+            make_call(&format!("{tag}-4"), "test.missing", "", vec![]),
+        ]
+    };
+
+    let stopped = broker
+        .invoke_batch_with(calls("stop"), BatchOrdering::Sequential(OnBatchError::Stop))
+        .await;
+    let ids: Vec<&str> = stopped.results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, vec!["stop-1", "stop-2", "stop-3", "stop-4"]);
+    assert_eq!(stopped.stopped_at, Some(1));
+    assert_eq!(
+        (stopped.ok_count, stopped.error_count, stopped.skipped_count),
+        (1, 1, 2)
+    );
+    for skipped in &stopped.results[2..] {
+        let error = skipped.error.as_ref().unwrap();
+        assert_eq!(error.code, SKIPPED);
+        assert!(error.message.contains("stop-2"), "{}", error.message);
+    }
+
+    let continued = broker
+        .invoke_batch_with(
+            calls("cont"),
+            BatchOrdering::Sequential(OnBatchError::Continue),
+        )
+        .await;
+    assert_eq!(continued.stopped_at, None);
+    assert_eq!(
+        (
+            continued.ok_count,
+            continued.error_count,
+            continued.skipped_count
+        ),
+        (2, 2, 0)
+    );
+    assert_eq!(continued.results[2].output, b"b");
+    assert_eq!(
+        continued.results[3].error.as_ref().unwrap().code,
+        "INVOKE_ERROR"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoke_hooks_observe_every_returned_result() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    broker.register_provider(Arc::new(ErrorProvider));
+
+    let started = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let ended = Arc::new(std::sync::Mutex::new(Vec::<(String, i32)>::new()));
+    let s = Arc::clone(&started);
+    broker.on_invoke_start(move |call| s.lock().unwrap().push(call.id.clone()));
+    let e = Arc::clone(&ended);
+    broker.on_invoke_end(move |call, res, _elapsed| {
+        e.lock().unwrap().push((call.id.clone(), res.status));
+    });
+
+    broker
+        .invoke(make_call("h-ok", "test.echo", "1.0.0", vec![]))
+        .await?;
+    broker
+        .invoke(make_call("h-err", "test.error", "1.0.0", vec![]))
+        .await?;
+    // Cache hits are results too
+    broker
+        .invoke(make_call("h-ok", "test.echo", "1.0.0", vec![]))
+        .await?;
+    // Err from invoke itself: start runs, end does not
+    assert!(broker
+        .invoke(make_call("h-missing", "test.missing", "", vec![]))
+        .await
+        .is_err());
+
+    assert_eq!(
+        *started.lock().unwrap(),
+        vec!["h-ok", "h-err", "h-ok", "h-missing"]
+    );
+    assert_eq!(
+        *ended.lock().unwrap(),
+        vec![
+            ("h-ok".to_string(), ActionStatus::ActionOk as i32),
+            ("h-err".to_string(), ActionStatus::ActionError as i32),
+            ("h-ok".to_string(), ActionStatus::ActionOk as i32),
+        ]
+    );
+
+    broker.clear_invoke_hooks();
+    broker
+        .invoke(make_call("h-after", "test.echo", "1.0.0", vec![]))
+        .await?;
+    assert_eq!(started.lock().unwrap().len(), 4);
+    Ok(())
+}
+
+#[test]
+fn timeout_maps_onto_the_wire_field_without_overflow() {
+    assert_eq!(Timeout::from_wire(0), Timeout::Default);
+    assert_eq!(Timeout::from_wire(-5), Timeout::Default);
+    assert_eq!(Timeout::from_wire(i64::MAX), Timeout::Unbounded);
+    assert_eq!(Timeout::from_millis(0), Timeout::Default);
+    assert_eq!(Timeout::from_secs(2).to_wire(), 2_000);
+    assert_eq!(Timeout::Unbounded.to_wire(), i64::MAX);
+    // Sub-millisecond rounds up instead of collapsing to "default"
+    assert_eq!(Timeout::from_micros(250).to_wire(), 1);
+    // Huge durations saturate just below the unbounded marker
+    assert_eq!(Timeout::from_secs(u64::MAX).to_wire(), i64::MAX - 1);
+
+    let mut call = ActionCall::builder("test.echo")
+        .timeout(Duration::from_micros(1_500))
+        .build()
+        .unwrap();
+    assert_eq!(call.timeout_ms, 2);
+    assert_eq!(Timeout::from_call(&call), Timeout::from_micros(1_500));
+    // Changing timeout_ms directly invalidates the exact header
+    call.timeout_ms = 50;
+    assert_eq!(Timeout::from_call(&call), Timeout::from_millis(50));
+}
+
+#[tokio::test]
+async fn unbounded_and_default_timeouts_resolve_in_the_broker() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 100 }));
+    broker.apply_config(BrokerConfig {
+        default_timeout_ms: 20,
+        qos_timeouts_ms: Default::default(),
+        ..BrokerConfig::default()
+    });
+
+    let mut defaulted = make_call("to-default", "test.slow", "1.0.0", vec![]);
+    defaulted.timeout_ms = 0;
+    let res = broker.invoke(defaulted).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    let mut unbounded = make_call("to-unbounded", "test.slow", "1.0.0", vec![]);
+    Timeout::Unbounded.apply_to(&mut unbounded);
+    let res = broker.invoke(unbounded).await?;
+    assert_eq!(res.status, ActionStatus::ActionOk as i32);
+    Ok(())
+}
+
+fn defaulted_timeout(broker: &ActionBroker, qos: QoSLevel) -> String {
+    let mut call = make_call("qos-timeout", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 0;
+    call.qos = Some(qos as i32);
+    broker.invoke_dry_run(call).metadata["timeout_ms"].clone()
+}
+
+#[tokio::test]
+async fn calls_without_a_timeout_use_their_qos_default() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 100 }));
+
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosRealtime), "2000");
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosBatched), "10000");
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosBackground), "60000");
+
+    // An explicit timeout still wins over the table
+    let mut explicit = make_call("qos-explicit", "test.slow", "1.0.0", vec![]);
+    explicit.qos = Some(QoSLevel::QosBackground as i32);
+    assert_eq!(
+        broker.invoke_dry_run(explicit).metadata["timeout_ms"],
+        "5000"
+    );
+
+    // Levels left out of the table fall back to default_timeout_ms
+    broker.set_qos_timeouts(HashMap::from([(
+        QoSLevel::QosRealtime,
+        Duration::from_millis(20),
+    )]));
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosRealtime), "20");
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosBatched), "30000");
+    assert_eq!(broker.export_config().qos_timeouts_ms["realtime"], 20);
+
+    let mut call = make_call("qos-realtime", "test.slow", "1.0.0", vec![]);
+    call.timeout_ms = 0;
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+
+    // Snapshots may spell levels like parse_qos does
+    broker.apply_config(serde_json::from_str(
+        r#"{"qos_timeouts_ms": {"QOS_BACKGROUND": 1500, "interactive": 1}}"#,
+    )?);
+    assert_eq!(defaulted_timeout(&broker, QoSLevel::QosBackground), "1500");
+    assert_eq!(
+        broker
+            .export_config()
+            .qos_timeouts_ms
+            .keys()
+            .collect::<Vec<_>>(),
+        vec!["background"]
+    );
+    Ok(())
+}
+
+// Provider whose descriptor declares dependencies
+struct DependentProvider {
+    name: &'static str,
+    depends_on: &'static [&'static str],
+}
+
+#[async_trait]
+impl CapabilityProvider for DependentProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: self.name.to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: self.depends_on.iter().map(|d| d.to_string()).collect(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: Vec::new(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[test]
+fn validate_dependencies_reports_missing_and_cycles() {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "tts.pipeline",
+        depends_on: &["tts.echo", "text.clean"],
+    }));
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "text.clean",
+        depends_on: &[],
+    }));
+    let problems = broker.validate_dependencies().unwrap_err();
+    assert_eq!(
+        problems,
+        vec!["tts.pipeline depends on missing capability 'tts.echo'"]
+    );
+
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "tts.echo",
+        depends_on: &[],
+    }));
+    assert!(broker.validate_dependencies().is_ok());
+
+    // text.clean -> a -> b -> text.clean
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "text.clean",
+        depends_on: &["a"],
+    }));
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "a",
+        depends_on: &["b"],
+    }));
+    broker.register_provider(Arc::new(DependentProvider {
+        name: "b",
+        depends_on: &["text.clean"],
+    }));
+    let problems = broker.validate_dependencies().unwrap_err();
+    assert_eq!(
+        problems,
+        vec!["dependency cycle: a -> b -> text.clean -> a"]
+    );
+}
+
+// Synchronous CPU-style work: sleeps the thread in 10 ms slices, stopping once cancelled
+struct BlockingProvider {
+    slices: usize,
+    stopped_early: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl CapabilityProvider for BlockingProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.blocking".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.invoke_cancellable(call, CancellationToken::new())
+            .await
+    }
+
+    async fn invoke_cancellable(
+        &self,
+        call: ActionCall,
+        cancel: CancellationToken,
+    ) -> Result<ActionResult> {
+        for _ in 0..self.slices {
+            if cancel.is_cancelled() {
+                self.stopped_early.store(true, Ordering::SeqCst);
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: b"done".to_vec(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+
+    fn runs_blocking(&self) -> bool {
+        true
+    }
+}
+
+// Single-threaded runtime: a provider blocking the worker would stall the timeout too
+#[tokio::test(flavor = "current_thread")]
+async fn blocking_providers_run_off_the_runtime_and_still_time_out() -> Result<()> {
+    let broker = ActionBroker::new();
+    let stopped_early = Arc::new(AtomicBool::new(false));
+    broker.register_provider(Arc::new(BlockingProvider {
+        slices: 50,
+        stopped_early: Arc::clone(&stopped_early),
+    }));
+
+    let mut call = make_call("blk-1", "test.blocking", "1.0.0", vec![]);
+    call.timeout_ms = 50;
+    let started = std::time::Instant::now();
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionTimeout);
+    assert!(started.elapsed() < Duration::from_millis(400));
+    // The hard timeout fires the token, so the pool thread stops at its next check
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(stopped_early.load(Ordering::SeqCst));
+
+    // Completing in time is a plain success without soft-timeout marking
+    let mut call = make_call("blk-2", "test.blocking", "1.0.0", vec![]);
+    call.timeout_ms = 5_000;
+    let ticker = tokio::spawn(async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        std::time::Instant::now()
+    });
+    let started = std::time::Instant::now();
+    let res = broker.invoke(call).await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    assert!(!res.metadata.contains_key(SOFT_TIMEOUT_METADATA_KEY));
+    // The runtime kept running other tasks while the provider slept
+    let ticked = ticker.await.unwrap();
+    assert!(ticked.duration_since(started) < Duration::from_millis(300));
+    Ok(())
+}
+
+struct CategorizedProvider {
+    name: &'static str,
+    category: Option<&'static str>,
+}
+
+#[async_trait]
+impl CapabilityProvider for CategorizedProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: self.name.to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: self
+                .category
+                .map(|c| (CATEGORY_METADATA_KEY.to_string(), c.to_string()))
+                .into_iter()
+                .collect(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: Vec::new(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[test]
+fn capabilities_group_by_category() {
+    let broker = ActionBroker::new();
+    for (name, category) in [
+        ("web.search", Some("web")),
+        ("audio.tts", Some("audio")),
+        ("web.fetch", Some(" Web ")),
+        ("misc.thing", None),
+        ("misc.blank", Some("  ")),
+    ] {
+        broker.register_provider(Arc::new(CategorizedProvider { name, category }));
+    }
+
+    let groups = broker.capabilities_by_category();
+    let names = |cat: &str| -> Vec<String> { groups[cat].iter().map(|d| d.name.clone()).collect() };
+    assert_eq!(groups.len(), 3);
+    // Categories are normalized; groups keep list_capabilities order
+    assert_eq!(names("web"), vec!["web.fetch", "web.search"]);
+    assert_eq!(names("audio"), vec!["audio.tts"]);
+    assert_eq!(names(UNCATEGORIZED), vec!["misc.blank", "misc.thing"]);
+
+    let web = broker.capabilities_in_category("WEB");
+    assert_eq!(web.len(), 2);
+    assert_eq!(category_of(&web[0]), "web");
+    assert!(broker.capabilities_in_category("code").is_empty());
+}
+
+// Declares a parameter schema and counts invocations
+struct ScheduledMeetingProvider {
+    invoked: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl CapabilityProvider for ScheduledMeetingProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"title": {"type": "string"}},
+            "required": ["title"]
+        });
+        CapabilityDescriptor {
+            name: "calendar.create".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: [("schema".to_string(), schema.to_string())]
+                .into_iter()
+                .collect(),
+            depends_on: Vec::new(),
+            default_qos: Some(QoSLevel::QosBatched as i32),
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.invoked.store(true, Ordering::SeqCst);
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: b"created".to_vec(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[test]
+fn dry_run_validates_without_invoking_the_provider() {
+    let broker = ActionBroker::new();
+    let invoked = Arc::new(AtomicBool::new(false));
+    broker.register_provider(Arc::new(ScheduledMeetingProvider {
+        invoked: Arc::clone(&invoked),
+    }));
+
+    let mut call = make_call(
+        "dry-ok",
+        "calendar.create",
+        "",
+        br#"{"title":"standup"}"#.to_vec(),
+    );
+    call.qos = None;
+    call.timeout_ms = 750;
+    let res = broker.invoke_dry_run(call);
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(res.id, "dry-ok");
+    assert!(res.output.is_empty());
+    assert_eq!(res.metadata[DRY_RUN_METADATA_KEY], "true");
+    assert_eq!(res.metadata["provider_version"], "1.0.0");
+    assert_eq!(res.metadata["qos"], "batched");
+    assert_eq!(res.metadata["timeout_ms"], "750");
+
+    // Payload violating the declared schema
+    let res = broker.invoke_dry_run(make_call(
+        "dry-bad",
+        "calendar.create",
+        "1.0.0",
+        br#"{"title":7}"#.to_vec(),
+    ));
+    assert_eq!(res.error.as_ref().unwrap().code, "INVALID_ARGUMENTS");
+    assert_eq!(res.metadata[DRY_RUN_METADATA_KEY], "true");
+
+    // Unknown capability is reported as a result even under OnMissing::Error
+    let res = broker.invoke_dry_run(make_call("dry-missing", "calendar.delete", "", vec![]));
+    assert_eq!(res.error.as_ref().unwrap().code, "CAPABILITY_NOT_FOUND");
+
+    // Authorization applies too
+    broker.set_authorizer(Arc::new(AllowListAuthorizer::new()));
+    let res = broker.invoke_dry_run(make_call(
+        "dry-denied",
+        "calendar.create",
+        "",
+        br#"{"title":"x"}"#.to_vec(),
+    ));
+    assert_eq!(res.error.as_ref().unwrap().code, "FORBIDDEN");
+
+    assert!(
+        !invoked.load(Ordering::SeqCst),
+        "dry runs never reach the provider"
+    );
+}
+
+#[tokio::test]
+async fn dry_run_applies_rate_limits_and_scoped_budgets() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider {
+        name: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+    }));
+    let call_for = |id: &str, tenant: &str| {
+        let mut call = make_call(id, "test.echo", "1.0.0", vec![]);
+        call.headers.insert(TENANT_HEADER.into(), tenant.into());
+        call
+    };
+
+    // Budgets are checked under the configured scope, not the correlation id
+    broker.set_budget_scope(InvocationScope::Tenant);
+    broker.set_budget("broke", 0.0);
+    let res = broker.invoke_dry_run(call_for("dry-budget", "broke"));
+    let error = res.error.unwrap();
+    assert_eq!(error.code, "BUDGET_EXCEEDED");
+    assert_eq!(error.message, "Budget exhausted for tenant broke");
+    assert_eq!(res.metadata[DRY_RUN_METADATA_KEY], "true");
+
+    // Dry runs peek at rate limits without using them up
+    broker.set_rate_limit(InvocationScope::Tenant, Some(RateLimit::per_minute(1)));
+    for i in 0..3 {
+        let res = broker.invoke_dry_run(call_for(&format!("dry-rate-{i}"), "acme"));
+        assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    }
+    let res = broker.invoke(call_for("real", "acme")).await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    let res = broker.invoke_dry_run(call_for("dry-limited", "acme"));
+    let error = res.error.unwrap();
+    assert_eq!(error.code, RATE_LIMITED);
+    assert_eq!(error.details["key"], "acme");
+    // ... and report what invoke would
+    let res = broker.invoke(call_for("real-2", "acme")).await?;
+    assert_eq!(res.error.unwrap().code, RATE_LIMITED);
+
+    // So do missing scope keys
+    broker.set_on_missing_key(OnMissingKey::Reject);
+    let res = broker.invoke_dry_run(make_call("dry-anon", "test.echo", "1.0.0", vec![]));
+    assert_eq!(res.error.unwrap().code, SCOPE_KEY_MISSING);
+    Ok(())
+}
+
+// Summarizer-style tool: reads the session named in the payload from the injected memory
+struct SessionSummaryProvider;
+
+#[async_trait]
+impl CapabilityProvider for SessionSummaryProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "memory.summarize".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.invoke_with_context(call, InvocationContext::default())
+            .await
+    }
+
+    async fn invoke_with_context(
+        &self,
+        call: ActionCall,
+        ctx: InvocationContext,
+    ) -> Result<ActionResult> {
+        let Some(reader) = ctx.reader.clone() else {
+            return Err(LoomError::PluginError("no memory".into()));
+        };
+        let session = String::from_utf8_lossy(&call.payload).into_owned();
+        let events = reader.recent_events(&session, 10).await?;
+        let summary = format!(
+            "{} events; correlation={}; bounded={}",
+            events.len(),
+            ctx.correlation_id,
+            ctx.remaining().is_some()
+        );
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: summary.into_bytes(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn providers_read_memory_through_the_invocation_context() -> Result<()> {
+    use loom_core::context::memory::InMemoryMemory;
+    use loom_core::context::MemoryWriter;
+    use loom_core::EventExt;
+
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SessionSummaryProvider));
+    let call = || {
+        let mut call = make_call("summ", "memory.summarize", "", b"s1".to_vec());
+        call.correlation_id = "turn-7".to_string();
+        call
+    };
+
+    // Without an installed reader the provider has no memory to read
+    let res = broker.invoke(call()).await?;
+    assert_eq!(res.error.unwrap().code, "CAPABILITY_ERROR");
+
+    let memory = InMemoryMemory::new();
+    for id in ["e1", "e2"] {
+        memory
+            .append_event(
+                "s1",
+                loom_core::proto::Event::builder("note").id(id).build(),
+            )
+            .await?;
+    }
+    broker.set_memory_reader(memory);
+    let mut second = call();
+    second.id = "summ-2".to_string();
+    let res = broker.invoke(second).await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(
+        String::from_utf8(res.output).unwrap(),
+        "2 events; correlation=turn-7; bounded=true"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn callers_can_supply_the_invocation_context() -> Result<()> {
+    use loom_core::action_broker::{InvocationScope, OnMissingKey, RateLimit};
+    use loom_core::context::memory::InMemoryMemory;
+    use loom_core::context::MemoryWriter;
+    use loom_core::{CancellationToken, EventExt, MockProvider};
+
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(SessionSummaryProvider));
+    broker.register_provider(Arc::new(MockProvider::named("slow.op").hangs()));
+    let shared = InMemoryMemory::new();
+    broker.set_memory_reader(shared);
+    let own = InMemoryMemory::new();
+    own.append_event("s1", loom_core::proto::Event::builder("note").build())
+        .await?;
+
+    // The caller's reader wins over the broker's
+    let ctx = InvocationContext {
+        reader: Some(own),
+        ..Default::default()
+    };
+    let res = broker
+        .invoke_in_context(
+            make_call("ctx-1", "memory.summarize", "", b"s1".to_vec()),
+            ctx,
+        )
+        .await?;
+    assert_eq!(
+        String::from_utf8(res.output).unwrap(),
+        "1 events; correlation=ctx-1; bounded=true"
+    );
+
+    // A supplied tenant counts like the tenant header
+    broker.set_on_missing_key(OnMissingKey::Reject);
+    broker.set_rate_limit(InvocationScope::Tenant, Some(RateLimit::per_second(10)));
+    let res = broker
+        .invoke(make_call("ctx-2", "memory.summarize", "", b"s1".to_vec()))
+        .await?;
+    assert_eq!(res.error.unwrap().code, "SCOPE_KEY_MISSING");
+    let ctx = InvocationContext {
+        tenant_id: Some("acme".to_string()),
+        ..Default::default()
+    };
+    let res = broker
+        .invoke_in_context(
+            make_call("ctx-3", "memory.summarize", "", b"s1".to_vec()),
+            ctx,
+        )
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    broker.set_rate_limit(InvocationScope::Tenant, None);
+
+    // Cancelling the caller's token cancels the call
+    let cancel = CancellationToken::new();
+    let ctx = InvocationContext {
+        cancel: cancel.clone(),
+        ..Default::default()
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+    });
+    let res = broker
+        .invoke_in_context(make_call("ctx-4", "slow.op", "", Vec::new()), ctx)
+        .await?;
+    let error = res.error.unwrap();
+    assert_eq!(error.code, "CANCELLED");
+    assert_eq!(error.details["phase"], "executing");
+    Ok(())
+}
+
+// Hangs while `stalled` is set, otherwise answers immediately; counts calls that reach it
+struct StallingProvider {
+    stalled: Arc<AtomicBool>,
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl CapabilityProvider for StallingProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "remote.flaky".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderGrpc as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.stalled.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: Vec::new(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn repeated_timeouts_quarantine_the_capability_until_probed() -> Result<()> {
+    let clock = MockClock::new(0);
+    let broker = ActionBroker::new().with_clock(clock.clone());
+    broker.set_quarantine_policy(Some(QuarantinePolicy {
+        max_consecutive_timeouts: 2,
+        cooldown_ms: 10_000,
+    }));
+    let stalled = Arc::new(AtomicBool::new(true));
+    let calls = Arc::new(AtomicUsize::new(0));
+    broker.register_provider(Arc::new(StallingProvider {
+        stalled: Arc::clone(&stalled),
+        calls: Arc::clone(&calls),
+    }));
+    let seq = AtomicUsize::new(0);
+    let call = || {
+        let mut call = make_call(
+            &format!("flaky-{}", seq.fetch_add(1, Ordering::SeqCst)),
+            "remote.flaky",
+            "",
+            vec![],
+        );
+        call.timeout_ms = 20;
+        call
+    };
+
+    for _ in 0..2 {
+        assert_eq!(
+            broker.invoke(call()).await?.status_enum(),
+            ActionStatus::ActionTimeout
+        );
+    }
+    assert_eq!(broker.quarantined(), vec!["remote.flaky".to_string()]);
+    let state = broker.quarantine_state("remote.flaky").unwrap();
+    assert_eq!(state.consecutive_timeouts, 2);
+    assert_eq!(state.until_ms, Some(10_000));
+
+    // Rejected without reaching the provider
+    clock.advance(4_000);
+    let res = broker.invoke(call()).await?;
+    let err = res.error.unwrap();
+    assert_eq!(err.code, QUARANTINED);
+    assert_eq!(err.details["retry_after_ms"], "6000");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // After the cooldown one probe goes through; it times out and restarts the cooldown
+    clock.advance(6_000);
+    assert_eq!(
+        broker.invoke(call()).await?.status_enum(),
+        ActionStatus::ActionTimeout
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        broker.quarantine_state("remote.flaky").unwrap().until_ms,
+        Some(20_000)
+    );
+
+    // A successful probe restores routing
+    stalled.store(false, Ordering::SeqCst);
+    clock.advance(10_000);
+    assert_eq!(
+        broker.invoke(call()).await?.status_enum(),
+        ActionStatus::ActionOk
+    );
+    assert!(broker.quarantined().is_empty());
+    assert!(broker.quarantine_state("remote.flaky").is_none());
+
+    // Manual unquarantine skips the cooldown
+    stalled.store(true, Ordering::SeqCst);
+    for _ in 0..2 {
+        broker.invoke(call()).await?;
+    }
+    assert!(broker
+        .quarantine_state("remote.flaky")
+        .unwrap()
+        .is_quarantined());
+    stalled.store(false, Ordering::SeqCst);
+    assert!(broker.unquarantine("remote.flaky"));
+    assert!(!broker.unquarantine("remote.flaky"));
+    assert_eq!(
+        broker.invoke(call()).await?.status_enum(),
+        ActionStatus::ActionOk
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn abandoned_probe_hands_the_probe_slot_back() -> Result<()> {
+    let clock = MockClock::new(0);
+    let broker = ActionBroker::new().with_clock(clock.clone());
+    broker.set_quarantine_policy(Some(QuarantinePolicy {
+        max_consecutive_timeouts: 1,
+        cooldown_ms: 1_000,
+    }));
+    let stalled = Arc::new(AtomicBool::new(true));
+    let calls = Arc::new(AtomicUsize::new(0));
+    broker.register_provider(Arc::new(StallingProvider {
+        stalled: Arc::clone(&stalled),
+        calls: Arc::clone(&calls),
+    }));
+    let call = |id: &str| {
+        let mut call = make_call(id, "remote.flaky", "", vec![]);
+        call.timeout_ms = 20;
+        call
+    };
+    assert_eq!(
+        broker.invoke(call("probe-0")).await?.status_enum(),
+        ActionStatus::ActionTimeout
+    );
+
+    // The probe's caller gives up while the provider is still working
+    clock.advance(1_000);
+    assert!(broker.invoke(call("probe-1")).now_or_never().is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert!(!broker.quarantine_state("remote.flaky").unwrap().probing);
+
+    // Or the probe's correlation is cancelled
+    let mut cancelled = call("probe-2");
+    cancelled.correlation_id = "sess-probe".to_string();
+    cancelled.timeout_ms = 1_000;
+    let (res, _) = tokio::join!(broker.invoke(cancelled), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        broker.cancel_correlation("sess-probe")
+    });
+    assert_eq!(res?.error.unwrap().code, CANCELLED);
+    assert!(!broker.quarantine_state("remote.flaky").unwrap().probing);
+
+    // So the next caller probes instead of being rejected
+    stalled.store(false, Ordering::SeqCst);
+    assert_eq!(
+        broker.invoke(call("probe-3")).await?.status_enum(),
+        ActionStatus::ActionOk
+    );
+    assert!(broker.quarantined().is_empty());
+    Ok(())
+}
+
+// Answers with its own version so tests can see which provider was routed to
+struct VersionedProvider(&'static str);
+
+#[async_trait]
+impl CapabilityProvider for VersionedProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "svc".to_string(),
+            version: self.0.to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: self.0.as_bytes().to_vec(),
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn route_selectors_choose_among_registered_versions() -> Result<()> {
+    let broker = ActionBroker::new();
+    for version in ["1.9.0", "1.10.0", "1.2.0"] {
+        broker.register_provider(Arc::new(VersionedProvider(version)));
+    }
+    let served = |res: ActionResult| String::from_utf8(res.output).unwrap();
+    let unversioned = || ActionCall::builder("svc").build().unwrap();
+    let pinned = |v: &str| ActionCall::builder("svc").version(v).build().unwrap();
+
+    let versions: Vec<String> = broker
+        .candidates("svc")
+        .into_iter()
+        .map(|c| c.version)
+        .collect();
+    assert_eq!(versions, ["1.2.0", "1.9.0", "1.10.0"]);
+
+    // Default: exact version when pinned (no fallback), else the highest
+    assert_eq!(served(broker.invoke(unversioned()).await?), "1.10.0");
+    assert_eq!(served(broker.invoke(pinned("1.2.0")).await?), "1.2.0");
+    assert!(broker.invoke(pinned("2.0.0")).await.is_err());
+
+    broker.set_route_selector(Arc::new(RoundRobin::new()));
+    let mut rotation = Vec::new();
+    for _ in 0..4 {
+        rotation.push(served(broker.invoke(unversioned()).await?));
+    }
+    assert_eq!(rotation, ["1.2.0", "1.9.0", "1.10.0", "1.2.0"]);
+
+    // Composed: honour a pinned version, otherwise a custom rule (lowest 1.x)
+    let lowest = |_: &str, _: &ActionCall, candidates: &[Candidate]| candidates.first().cloned();
+    broker.set_route_selector(Arc::new(FirstMatch::new().then(ExactVersion).then(lowest)));
+    assert_eq!(served(broker.invoke(pinned("1.9.0")).await?), "1.9.0");
+    assert_eq!(served(broker.invoke(unversioned()).await?), "1.2.0");
+    assert_eq!(served(broker.invoke(pinned("2.0.0")).await?), "1.2.0");
+
+    // A selector declining every candidate reads as a missing capability
+    broker.set_route_selector(Arc::new(|_: &str, _: &ActionCall, _: &[Candidate]| None));
+    assert!(broker.invoke(unversioned()).await.is_err());
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct SpeakRequest<'a> {
+    text: &'a str,
+    voice: &'a str,
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct SpeakReply {
+    text: String,
+    voice: String,
+    chars: usize,
+}
+
+// tts.echo answers JSON for "json", plain text for "text", audio bytes for "pcm"
+struct TtsEchoProvider;
+
+#[async_trait]
+impl CapabilityProvider for TtsEchoProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "tts.echo".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        let req: serde_json::Value = serde_json::from_slice(&call.payload)?;
+        let text = req["text"].as_str().unwrap_or_default();
+        let voice = req["voice"].as_str().unwrap_or_default();
+        let res = ActionResult::builder(ActionStatus::ActionOk).id(call.id);
+        Ok(match voice {
+            "" => ActionResult::builder(ActionStatus::ActionError)
+                .error("INVALID_ARGUMENTS", "voice is required")
+                .build(),
+            "text" => res.output(text.as_bytes().to_vec()).build(),
+            "pcm" => res
+                .output(vec![0, 159, 255])
+                .content_type("audio/pcm")
+                .build(),
+            _ => res
+                .output_json(&serde_json::json!({
+                    "text": text,
+                    "voice": voice,
+                    "chars": text.chars().count(),
+                }))?
+                .build(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn invoke_typed_round_trips_through_content_types() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(TtsEchoProvider));
+
+    let reply: SpeakReply = broker
+        .invoke_typed(
+            "tts.echo",
+            &SpeakRequest {
+                text: "héllo",
+                voice: "alloy",
+            },
+        )
+        .await?;
+    assert_eq!(
+        reply,
+        SpeakReply {
+            text: "héllo".to_string(),
+            voice: "alloy".to_string(),
+            chars: 5,
+        }
+    );
+
+    // Undeclared plain text decodes as a string, binary outputs as bytes
+    let text: String = broker
+        .invoke_typed("tts.echo", &json!({"text": "hi there", "voice": "text"}))
+        .await?;
+    assert_eq!(text, "hi there");
+    let pcm: Vec<u8> = broker
+        .invoke_typed("tts.echo", &json!({"text": "hi", "voice": "pcm"}))
+        .await?;
+    assert_eq!(pcm, vec![0, 159, 255]);
+
+    // A failed result is an Err carrying the code, not a decode attempt
+    let err = broker
+        .invoke_typed::<_, SpeakReply>("tts.echo", &json!({"text": "hi"}))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, LoomError::PluginError(m) if m.contains("INVALID_ARGUMENTS")),
+        "{err}"
+    );
+    Ok(())
+}
+
+define_capability!(
+    /// Handle for `TtsEchoProvider`
+    TtsEcho,
+    name = "tts.echo",
+    req = SpeakRequest<'static>,
+    resp = SpeakReply,
+);
+
+#[tokio::test]
+async fn define_capability_handles_bind_name_and_types() -> Result<()> {
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(TtsEchoProvider));
+    assert_eq!(TtsEcho::NAME, "tts.echo");
+
+    let reply = TtsEcho::invoke(
+        &broker,
+        &SpeakRequest {
+            text: "hi",
+            voice: "alloy",
+        },
+    )
+    .await?;
+    assert_eq!(reply.chars, 2);
+
+    // The call builder keeps the name and payload; the caller adds version, timeout, ...
+    let call = TtsEcho::call(&SpeakRequest {
+        text: "pinned",
+        voice: "alloy",
+    })?
+    .version("1.0.0")
+    .build()?;
+    assert_eq!(
+        (call.capability.as_str(), call.version.as_str()),
+        ("tts.echo", "1.0.0")
+    );
+    let reply = broker
+        .invoke_typed_call::<<TtsEcho as TypedCapability>::Response>(call)
+        .await?;
+    assert_eq!(reply.text, "pinned");
+
+    // Generic code can take any handle
+    async fn speak_twice<C: TypedCapability>(
+        broker: &ActionBroker,
+        req: &C::Request,
+    ) -> Result<(C::Response, C::Response)> {
+        Ok((
+            broker.invoke_capability::<C>(req).await?,
+            broker.invoke_capability::<C>(req).await?,
+        ))
+    }
+    let (a, b) = speak_twice::<TtsEcho>(
+        &broker,
+        &SpeakRequest {
+            text: "again",
+            voice: "alloy",
+        },
+    )
+    .await?;
+    assert_eq!(a, b);
+
+    // Failures surface like invoke_typed's
+    let err = TtsEcho::invoke(
+        &broker,
+        &SpeakRequest {
+            text: "hi",
+            voice: "",
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(&err, LoomError::PluginError(m) if m.contains("INVALID_ARGUMENTS")),
+        "{err}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn diagnostics_snapshot_reports_registry_limits_and_in_flight_calls() -> Result<()> {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 200 }));
+    broker.register_provider(Arc::new(VersionedProvider("1.10.0")));
+    broker.register_provider(Arc::new(VersionedProvider("1.9.0")));
+    broker.set_concurrency_limit("test.slow", Some(2));
+    broker.set_quarantine_policy(Some(QuarantinePolicy {
+        max_consecutive_timeouts: 3,
+        cooldown_ms: 1000,
+    }));
+
+    let slow = tokio::spawn({
+        let broker = Arc::clone(&broker);
+        async move {
+            broker
+                .invoke(make_call("diag-slow", "test.slow", "1.0.0", vec![]))
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let diag = broker.diagnostics();
+    assert_eq!(diag.in_flight, 1);
+    let names: Vec<&str> = diag.capabilities.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["svc", "test.slow"]);
+    let slow_diag = &diag.capabilities[1];
+    assert_eq!(slow_diag.concurrency_limit, Some(2));
+    assert_eq!(slow_diag.in_flight, 1);
+    assert_eq!(slow_diag.versions[0].provider, "PROVIDER_NATIVE");
+    let versions: Vec<&str> = diag.capabilities[0]
+        .versions
+        .iter()
+        .map(|v| v.version.as_str())
+        .collect();
+    assert_eq!(versions, vec!["1.9.0", "1.10.0"]);
+
+    // Serializes as-is for an HTTP endpoint
+    let json = serde_json::to_value(&diag)?;
+    assert_eq!(json["quarantine_policy"]["max_consecutive_timeouts"], 3);
+    assert_eq!(
+        json["capabilities"][1]["quarantine"],
+        serde_json::Value::Null
+    );
+
+    slow.await.unwrap()?;
+    assert_eq!(broker.diagnostics().in_flight, 0);
+    assert_eq!(broker.diagnostics().cached_results, 1);
+    Ok(())
+}
+
+// Job that reports half progress, then finishes once the test releases it
+struct ExportJob {
+    release: Arc<tokio::sync::Notify>,
+}
+
+#[async_trait]
+impl JobProvider for ExportJob {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "report.export".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: vec![],
+            default_qos: None,
+        }
+    }
+
+    async fn run(&self, call: ActionCall, progress: JobProgress) -> Result<ActionResult> {
+        progress.report(0.5);
+        self.release.notified().await;
+        if call.payload == b"boom" {
+            panic!("export crashed");
+        }
+        Ok(ActionResult::builder(ActionStatus::ActionOk)
+            .output(call.payload)
+            .build())
+    }
+}
+
+async fn poll_until_final(broker: &ActionBroker, job_id: &str) -> ActionResult {
+    for _ in 0..100 {
+        let res = broker.poll(job_id);
+        if res.status_enum().is_final() {
+            return res;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("job {job_id} never finished");
+}
+
+#[tokio::test]
+async fn jobs_are_submitted_polled_and_expire_after_their_ttl() -> Result<()> {
+    let clock = MockClock::new(0);
+    let broker = ActionBroker::new().with_clock(clock.clone());
+    broker.set_job_ttl(Duration::from_secs(60));
+    let release = Arc::new(tokio::sync::Notify::new());
+    broker.register_job_provider(Arc::new(ExportJob {
+        release: Arc::clone(&release),
+    }));
+
+    // Submission answers at once with a job id
+    let submitted = broker
+        .invoke(make_call(
+            "job-1",
+            "report.export",
+            "1.0.0",
+            b"rows".to_vec(),
+        ))
+        .await?;
+    assert_eq!(submitted.status_enum(), ActionStatus::ActionPending);
+    assert_eq!(submitted.id, "job-1");
+    let job_id = submitted.metadata[JOB_ID_METADATA_KEY].clone();
+
+    // Running: pending with the last reported progress
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let running = broker.poll(&job_id);
+    assert_eq!(running.status_enum(), ActionStatus::ActionPending);
+    assert_eq!(running.metadata[JOB_PROGRESS_METADATA_KEY], "0.5");
+
+    // Done: the final result, stable across polls until the TTL passes
+    release.notify_one();
+    let done = poll_until_final(&broker, &job_id).await;
+    assert_eq!(done.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(done.id, "job-1");
+    assert_eq!(done.output, b"rows");
+    clock.advance(60_000);
+    assert_eq!(broker.poll(&job_id), done);
+    clock.advance(1);
+    let expired = broker.poll(&job_id);
+    assert_eq!(expired.error.unwrap().code, JOB_NOT_FOUND);
+
+    // A panicking job ends in PROVIDER_PANIC; unknown ids are not found
+    let crashing = broker
+        .invoke(make_call(
+            "job-2",
+            "report.export",
+            "1.0.0",
+            b"boom".to_vec(),
+        ))
+        .await?;
+    release.notify_one();
+    let crashed = poll_until_final(&broker, &crashing.metadata[JOB_ID_METADATA_KEY]).await;
+    let crashed = crashed.error.unwrap();
+    assert_eq!(crashed.code, "PROVIDER_PANIC");
+    assert!(crashed.debug_detail().is_some());
+    // Polled results are redacted like invoked ones
+    broker.set_redact_debug_details(true);
+    let redacted = broker.poll(&crashing.metadata[JOB_ID_METADATA_KEY]);
+    assert_eq!(redacted.error.unwrap().debug_detail(), None);
+    assert_eq!(
+        broker.poll("no-such-job").error.unwrap().code,
+        JOB_NOT_FOUND
+    );
+    assert_eq!(broker.export_config().job_ttl_ms, 60_000);
+    Ok(())
+}
+
+#[tokio::test]
+async fn broker_service_composes_with_tower_layers() -> Result<()> {
+    use tower::{limit::ConcurrencyLimit, ServiceExt};
+
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 50 }));
+    let service = ConcurrencyLimit::new(broker.service(), 1);
+
+    // The limit serializes the calls: three 50 ms calls take at least 150 ms
+    let started = std::time::Instant::now();
+    let calls = (0..3).map(|i| {
+        let service = service.clone();
+        async move {
+            service
+                .oneshot(make_call(
+                    &format!("tower-{i}"),
+                    "test.slow",
+                    "1.0.0",
+                    vec![],
+                ))
+                .await
+        }
+    });
+    for res in futures_util::future::join_all(calls).await {
+        assert_eq!(res?.status_enum(), ActionStatus::ActionOk);
+    }
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    // Broker-level failures surface as the service error
+    let missing = broker
+        .service()
+        .oneshot(make_call("tower-missing", "no.such", "", vec![]))
+        .await;
+    assert!(matches!(missing, Err(LoomError::PluginError(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_correlation_cancels_every_in_flight_call_of_the_session() -> Result<()> {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(SlowProvider { delay_ms: 2_000 }));
+    broker.set_attach_duration(true);
+
+    let call = |id: &str, cid: &str| {
+        let mut c = make_call(id, "test.slow", "1.0.0", vec![]);
+        c.correlation_id = cid.to_string();
+        c
+    };
+    let spawn = |c: ActionCall| {
+        let broker = Arc::clone(&broker);
+        tokio::spawn(async move { broker.invoke(c).await })
+    };
+    let first = spawn(call("cancel-1", "sess-1"));
+    let second = spawn(call("cancel-2", "sess-1"));
+    let other = spawn(call("cancel-3", "sess-2"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let started = std::time::Instant::now();
+    assert_eq!(broker.cancel_correlation("sess-1"), 2);
+    for handle in [first, second] {
+        let res = handle.await.unwrap()?;
+        assert_eq!(res.status_enum(), ActionStatus::ActionError);
+        // Cancelled calls go through the same bookkeeping as any other outcome
+        assert!(res.metadata.contains_key(DURATION_METADATA_KEY));
+        let err = res.error.unwrap();
+        assert_eq!(err.code, CANCELLED);
+        assert_eq!(err.details["phase"], "executing");
+    }
+    assert_eq!(broker.diagnostics().cached_results, 0);
+    assert!(started.elapsed() < Duration::from_millis(500));
+
+    // Other sessions are untouched, and nothing is left to cancel
+    assert_eq!(broker.cancel_correlation("unknown"), 0);
+    assert_eq!(other.await.unwrap()?.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(broker.cancel_correlation("sess-1"), 0);
+    Ok(())
+}
+
+// Fails with the code given as payload, like an adapter passing through native errors
+struct NativeErrorProvider {
+    name: &'static str,
+    kind: ProviderKind,
+}
+
+#[async_trait]
+impl CapabilityProvider for NativeErrorProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: self.name.to_string(),
+            version: "1.0.0".to_string(),
+            provider: self.kind as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        let code = String::from_utf8(call.payload).unwrap();
+        if code.is_empty() {
+            return Err(LoomError::PluginError("boom".into()));
+        }
+        let mut res = ActionResult::builder(ActionStatus::ActionError)
+            .id(call.id)
+            .error(code, "request failed")
+            .build();
+        if let Some(status) = call.headers.get(HTTP_STATUS_DETAIL_KEY) {
+            let details = &mut res.error.as_mut().unwrap().details;
+            details.insert(HTTP_STATUS_DETAIL_KEY.to_string(), status.clone());
+        }
+        Ok(res)
+    }
+}
+
+#[tokio::test]
+async fn error_normalization_maps_adapter_failures_onto_the_taxonomy() -> Result<()> {
+    let broker = ActionBroker::new();
+    for (name, kind) in [
+        ("test.mcp", ProviderKind::ProviderMcp),
+        ("test.grpc", ProviderKind::ProviderGrpc),
+        ("test.native", ProviderKind::ProviderNative),
+        ("test.http", ProviderKind::ProviderNative),
+    ] {
+        broker.register_provider(Arc::new(NativeErrorProvider { name, kind }));
+    }
+    broker.set_capability_error_mapper("test.http", Arc::new(HttpErrorMapper));
+
+    let fail = |capability: &str, code: &str, http_status: Option<&str>| {
+        let mut call = make_call(
+            &format!("{capability}-{code}-{http_status:?}"),
+            capability,
+            "",
+            code.as_bytes().to_vec(),
+        );
+        if let Some(status) = http_status {
+            call.headers
+                .insert(HTTP_STATUS_DETAIL_KEY.to_string(), status.to_string());
+        }
+        call
+    };
+
+    // Off by default: native codes pass through
+    let res = broker.invoke(fail("test.mcp", "IO_ERROR", None)).await?;
+    assert_eq!(res.error.unwrap().code, "IO_ERROR");
+
+    broker.set_error_normalization(true);
+    assert!(broker.export_config().normalize_errors);
+    let cases = [
+        ("test.mcp", "TRANSPORT_ERROR", None, ErrorClass::Unavailable),
+        (
+            "test.mcp",
+            "INVALID_PARAMS",
+            None,
+            ErrorClass::InvalidArgument,
+        ),
+        ("test.mcp", "TOOL_ERROR", None, ErrorClass::Internal),
+        ("test.grpc", "DEADLINE_EXCEEDED", None, ErrorClass::Timeout),
+        ("test.grpc", "14", None, ErrorClass::Unavailable),
+        (
+            "test.native",
+            "INVALID_LOCATION",
+            None,
+            ErrorClass::InvalidArgument,
+        ),
+        (
+            "test.native",
+            "WEATHER_FETCH_FAILED",
+            None,
+            ErrorClass::Internal,
+        ),
+        (
+            "test.http",
+            "FETCH_FAILED",
+            Some("503"),
+            ErrorClass::Unavailable,
+        ),
+        (
+            "test.http",
+            "FETCH_FAILED",
+            Some("404"),
+            ErrorClass::InvalidArgument,
+        ),
+        (
+            "test.http",
+            "FETCH_FAILED",
+            Some("504"),
+            ErrorClass::Timeout,
+        ),
+    ];
+    for (capability, native, http_status, class) in cases {
+        let res = broker.invoke(fail(capability, native, http_status)).await?;
+        let err = res.error.unwrap();
+        assert_eq!(err.code, class.code(), "{capability} {native}");
+        assert_eq!(err.details[NATIVE_CODE_DETAIL_KEY], native);
+        assert_eq!(err.message, "request failed");
+    }
+
+    // Provider `Err`s are normalized too; taxonomy codes are left as they are
+    let res = broker.invoke(fail("test.grpc", "", None)).await?;
+    let err = res.error.unwrap();
+    assert_eq!(err.code, "INTERNAL");
+    assert_eq!(err.details[NATIVE_CODE_DETAIL_KEY], "CAPABILITY_ERROR");
+    let res = broker
+        .invoke(fail("test.native", "UNAVAILABLE", None))
+        .await?;
+    let err = res.error.unwrap();
+    assert_eq!(err.code, "UNAVAILABLE");
+    assert!(!err.details.contains_key(NATIVE_CODE_DETAIL_KEY));
+
+    // Custom mappers replace a kind's default
+    broker.set_error_mapper(
+        ProviderKind::ProviderMcp,
+        Arc::new(|_: ActionStatus, _: &loom_core::proto::ActionError| ErrorClass::Unavailable),
+    );
+    let res = broker
+        .invoke(fail("test.mcp", "PROTOCOL_ERROR", None))
+        .await?;
+    assert_eq!(res.error.unwrap().code, "UNAVAILABLE");
+    Ok(())
+}
+
+#[test]
+fn retry_jitter_spreads_backoff_and_timeouts() {
+    let fixed = RetryPolicy {
+        initial_backoff_ms: 100,
+        max_backoff_ms: 1_000,
+        ..Default::default()
+    };
+    assert_eq!(fixed.backoff(1), Duration::from_millis(100));
+    assert_eq!(fixed.backoff(3), Duration::from_millis(400));
+    assert_eq!(fixed.backoff(10), Duration::from_millis(1_000));
+    assert_eq!(
+        fixed.jittered_timeout(Duration::from_secs(1)),
+        Duration::from_secs(1)
+    );
+
+    let jittered = RetryPolicy {
+        jitter: 0.5,
+        timeout_jitter: 0.2,
+        ..fixed
+    };
+    let delays: Vec<Duration> = (0..200).map(|_| jittered.backoff(1)).collect();
+    let (min, max) = (delays.iter().min().unwrap(), delays.iter().max().unwrap());
+    assert!(*min >= Duration::from_millis(50) && *max <= Duration::from_millis(100));
+    assert!(
+        *max - *min >= Duration::from_millis(25),
+        "delays should spread over the jitter window, got {min:?}..{max:?}"
+    );
+
+    let timeouts: Vec<Duration> = (0..200)
+        .map(|_| jittered.jittered_timeout(Duration::from_secs(1)))
+        .collect();
+    let (min, max) = (
+        timeouts.iter().min().unwrap(),
+        timeouts.iter().max().unwrap(),
+    );
+    assert!(*min >= Duration::from_millis(800) && *max <= Duration::from_secs(1));
+    assert!(*max - *min >= Duration::from_millis(100));
+
+    // Non-finite jitter (e.g. from a hand-edited config) is treated as no jitter
+    for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let policy = RetryPolicy {
+            jitter: bad,
+            timeout_jitter: bad,
+            ..fixed
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(
+            policy.jittered_timeout(Duration::from_secs(1)),
+            Duration::from_secs(1)
+        );
+    }
+}
+
+// Answers `ActionRetryable` until `failures` calls have been made
+struct FlakyProvider {
+    failures: usize,
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl CapabilityProvider for FlakyProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.flaky".to_string(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        let status = match self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+            true => ActionStatus::ActionRetryable,
+            false => ActionStatus::ActionOk,
+        };
+        Ok(ActionResult::builder(status).id(call.id).build())
+    }
+}
+
+#[tokio::test]
+async fn retry_policy_reruns_retryable_calls_after_backoff() -> Result<()> {
+    let broker = ActionBroker::new();
+    let flaky = Arc::new(FlakyProvider {
+        failures: 2,
+        calls: AtomicUsize::new(0),
+    });
+    broker.register_provider(flaky.clone());
+
+    // Off by default: the first answer stands
+    let res = broker
+        .invoke(make_call("flaky-1", "test.flaky", "", vec![]))
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionRetryable);
+
+    broker.set_retry_policy(Some(RetryPolicy {
+        max_attempts: 3,
+        initial_backoff_ms: 5,
+        jitter: 0.5,
+        ..Default::default()
+    }));
+    let res = broker
+        .invoke(make_call("flaky-2", "test.flaky", "", vec![]))
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(res.metadata[ATTEMPTS_METADATA_KEY], "2");
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+
+    // Out of attempts: the last failure is returned (and cached) as is
+    broker.set_retry_policy(Some(RetryPolicy {
+        max_attempts: 2,
+        initial_backoff_ms: 5,
+        ..Default::default()
+    }));
+    flaky.calls.store(0, Ordering::SeqCst);
+    let res = broker
+        .invoke(make_call("flaky-3", "test.flaky", "", vec![]))
+        .await?;
+    assert_eq!(res.status_enum(), ActionStatus::ActionRetryable);
+    assert_eq!(res.metadata[ATTEMPTS_METADATA_KEY], "2");
+    assert_eq!(
+        broker.export_config().retry.map(|p| p.max_attempts),
+        Some(2)
+    );
+    Ok(())
+}
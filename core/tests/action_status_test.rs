@@ -0,0 +1,115 @@
+use loom_core::action_broker::{ActionErrorExt, SERIALIZATION_FAILED};
+use loom_core::proto::{
+    ActionResult, ActionStatus, OutputKind, CONTENT_TYPE_JSON, CONTENT_TYPE_TEXT,
+};
+use loom_core::ActionResultExt;
+use std::collections::HashMap;
+
+const ALL: [(i32, ActionStatus, &str); 5] = [
+    (0, ActionStatus::ActionOk, "ok"),
+    (1, ActionStatus::ActionError, "error"),
+    (2, ActionStatus::ActionTimeout, "timeout"),
+    (3, ActionStatus::ActionRetryable, "retryable"),
+    (4, ActionStatus::ActionPending, "pending"),
+];
+
+#[test]
+fn from_code_round_trips_every_variant() {
+    for (code, status, _) in ALL {
+        assert_eq!(ActionStatus::from_code(code), Some(status));
+        assert_eq!(status as i32, code);
+    }
+}
+
+#[test]
+fn from_code_rejects_unknown_values() {
+    for code in [-1, 5, 42, i32::MAX, i32::MIN] {
+        assert_eq!(ActionStatus::from_code(code), None, "code {code}");
+    }
+}
+
+#[test]
+fn display_uses_short_lowercase_names() {
+    for (_, status, name) in ALL {
+        assert_eq!(status.to_string(), name);
+        assert_eq!(status.as_str(), name);
+    }
+}
+
+#[test]
+fn status_enum_reads_known_codes_and_treats_unknown_as_error() {
+    for (code, status, _) in ALL {
+        let res = ActionResult {
+            status: code,
+            ..Default::default()
+        };
+        assert_eq!(res.status_enum(), status);
+    }
+    let unknown = ActionResult {
+        status: 99,
+        ..Default::default()
+    };
+    assert_eq!(unknown.status_enum(), ActionStatus::ActionError);
+}
+
+#[test]
+fn content_type_classifies_outputs_and_guards_binary_bytes() {
+    let audio = ActionResult::builder(ActionStatus::ActionOk)
+        .output(vec![0x52, 0x49, 0x46, 0x46, 0xff, 0xfe, 0x00])
+        .content_type("audio/wav")
+        .build();
+    assert_eq!(audio.content_type(), Some("audio/wav"));
+    assert_eq!(audio.output_kind(), OutputKind::Binary);
+    assert_eq!(audio.output_text(), None);
+
+    let json = ActionResult::builder(ActionStatus::ActionOk)
+        .output_json(&serde_json::json!({"ok": true}))
+        .unwrap()
+        .build();
+    assert_eq!(json.content_type(), Some(CONTENT_TYPE_JSON));
+    assert_eq!(json.output_kind(), OutputKind::Json);
+    assert_eq!(json.output_text(), Some(r#"{"ok":true}"#));
+
+    assert_eq!(
+        OutputKind::from_content_type("Application/Problem+JSON; charset=utf-8"),
+        OutputKind::Json
+    );
+    assert_eq!(
+        OutputKind::from_content_type(CONTENT_TYPE_TEXT),
+        OutputKind::Text
+    );
+}
+
+#[test]
+fn try_ok_json_reports_serialization_failures_instead_of_empty_output() {
+    let ok = ActionResult::try_ok_json("call-1", &serde_json::json!({"ok": true}));
+    assert_eq!(ok.id, "call-1");
+    assert_eq!(ok.status_enum(), ActionStatus::ActionOk);
+    assert_eq!(ok.content_type(), Some(CONTENT_TYPE_JSON));
+    assert_eq!(ok.output_text(), Some(r#"{"ok":true}"#));
+
+    // JSON object keys must be strings, so a tuple-keyed map cannot be serialized
+    let unserializable: HashMap<(u8, u8), u8> = [((1, 2), 3)].into_iter().collect();
+    let failed = ActionResult::try_ok_json("call-2", &unserializable);
+    assert_eq!(failed.id, "call-2");
+    assert_eq!(failed.status_enum(), ActionStatus::ActionError);
+    assert!(failed.output.is_empty());
+    let error = failed.error.unwrap();
+    assert_eq!(error.code, SERIALIZATION_FAILED);
+    assert!(error
+        .debug_detail()
+        .unwrap()
+        .contains("key must be a string"));
+}
+
+#[test]
+fn undeclared_outputs_are_sniffed() {
+    let undeclared = |output: &[u8]| ActionResult {
+        output: output.to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(undeclared(b"[1, 2]").output_kind(), OutputKind::Json);
+    assert_eq!(undeclared(b"hello").output_kind(), OutputKind::Text);
+    assert_eq!(undeclared(&[0xc3, 0x28]).output_kind(), OutputKind::Binary);
+    assert_eq!(undeclared(&[0xc3, 0x28]).output_text(), None);
+}
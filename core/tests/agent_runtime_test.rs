@@ -1,7 +1,13 @@
 use async_trait::async_trait;
-use loom_core::agent::{AgentBehavior, AgentRuntime};
-use loom_core::proto::{Action, AgentConfig, AgentState, Event};
-use loom_core::{ActionBroker, EventBus, ModelRouter, Result};
+use loom_core::agent::{
+    qos_for_priority, AgentBehavior, AgentRuntime, ResultFeedback, ACTION_DONE_EVENT_TYPE,
+    BATCHED_PRIORITY, REALTIME_PRIORITY,
+};
+use loom_core::context::memory::InMemoryMemory;
+use loom_core::context::MemoryReader;
+use loom_core::proto::{Action, ActionStatus, AgentConfig, AgentState, Event, QoSLevel};
+use loom_core::{ActionBroker, Envelope, EventBus, EventExt, MockProvider, ModelRouter, Result};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -388,3 +394,152 @@ async fn create_duplicate_agent_id_replaces_previous() -> Result<()> {
     assert_eq!(c2, 1, "second agent should receive");
     Ok(())
 }
+
+#[tokio::test]
+async fn result_feedback_stores_action_done_events_for_selected_statuses() -> Result<()> {
+    let bus = Arc::new(EventBus::new().await?);
+    bus.start().await?;
+
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(MockProvider::named("weather.get")));
+    broker.register_provider(Arc::new(
+        MockProvider::named("weather.fail").fails("UPSTREAM", "unavailable"),
+    ));
+    let memory = InMemoryMemory::new();
+    let router = ModelRouter::new().await?;
+    let runtime = AgentRuntime::new(Arc::clone(&bus), Arc::clone(&broker), router)
+        .await?
+        .with_result_feedback(
+            ResultFeedback::new(memory.clone()).statuses([ActionStatus::ActionOk]),
+        );
+
+    for (agent, capability) in [("agent_ok", "weather.get"), ("agent_fail", "weather.fail")] {
+        let cfg = AgentConfig {
+            agent_id: agent.to_string(),
+            agent_type: "test".to_string(),
+            subscribed_topics: vec![format!("topic.{agent}")],
+            capabilities: vec![],
+            parameters: Default::default(),
+        };
+        runtime
+            .create_agent(
+                cfg,
+                Box::new(ActionEmitBehavior {
+                    action_type: capability.to_string(),
+                }),
+            )
+            .await?;
+    }
+
+    // A threaded trigger stores under its thread; the other falls back to the agent id
+    let mut trigger = make_event("fb-trigger");
+    Envelope::new("thread-7", "test").attach_to_event(&mut trigger);
+    bus.publish("topic.agent_ok", trigger).await?;
+    bus.publish("topic.agent_fail", make_event("fb-trigger-2"))
+        .await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    let stored = memory.recent_events("thread-7", 10).await?;
+    assert_eq!(stored.len(), 1);
+    let done = &stored[0];
+    assert_eq!(done.r#type, ACTION_DONE_EVENT_TYPE);
+    assert_eq!(done.tags, vec!["action", "weather.get", "weather"]);
+    assert_eq!(done.metadata["status"], "ok");
+    assert_eq!(done.parent_id(), Some("fb-trigger"));
+    // The call's envelope correlates the stored result with the published action_result
+    assert_eq!(Envelope::from_event(done).correlation_id, done.id);
+
+    // Errors were not selected
+    assert!(memory.recent_events("agent_fail", 10).await?.is_empty());
+    Ok(())
+}
+
+// Emits one `tool.probe` action at a fixed priority for every event
+struct FixedPriorityBehavior {
+    priority: i32,
+}
+
+#[async_trait]
+impl AgentBehavior for FixedPriorityBehavior {
+    async fn on_event(&mut self, event: Event, _state: &mut AgentState) -> Result<Vec<Action>> {
+        Ok(vec![Action {
+            action_type: "tool.probe".to_string(),
+            parameters: Default::default(),
+            payload: event.id.into_bytes(),
+            priority: self.priority,
+        }])
+    }
+
+    async fn on_init(&mut self, _config: &AgentConfig) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn action_calls_inherit_the_trigger_priority_as_qos() -> Result<()> {
+    assert_eq!(qos_for_priority(REALTIME_PRIORITY), QoSLevel::QosRealtime);
+    assert_eq!(
+        qos_for_priority(REALTIME_PRIORITY - 1),
+        QoSLevel::QosBatched
+    );
+    assert_eq!(qos_for_priority(BATCHED_PRIORITY), QoSLevel::QosBatched);
+    assert_eq!(
+        qos_for_priority(BATCHED_PRIORITY - 1),
+        QoSLevel::QosBackground
+    );
+
+    let bus = Arc::new(EventBus::new().await?);
+    bus.start().await?;
+    let probe = Arc::new(MockProvider::named("tool.probe"));
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(probe.clone());
+    let router = ModelRouter::new().await?;
+    let runtime = AgentRuntime::new(Arc::clone(&bus), Arc::clone(&broker), router).await?;
+
+    // The behavior leaves priority unset (0), which alone would run in the background;
+    // a second agent raises its actions above any trigger
+    for (agent, priority) in [("agent_unset", 0), ("agent_urgent", 80)] {
+        let cfg = AgentConfig {
+            agent_id: agent.to_string(),
+            agent_type: "test".to_string(),
+            subscribed_topics: vec![format!("topic.{agent}")],
+            capabilities: vec![],
+            parameters: Default::default(),
+        };
+        runtime
+            .create_agent(cfg, Box::new(FixedPriorityBehavior { priority }))
+            .await?;
+    }
+
+    let prioritized = |id: &str, priority: i32| Event {
+        priority,
+        ..make_event(id)
+    };
+    bus.publish("topic.agent_unset", prioritized("urgent", 90))
+        .await?;
+    bus.publish("topic.agent_unset", prioritized("routine", 50))
+        .await?;
+    bus.publish("topic.agent_unset", prioritized("idle", 10))
+        .await?;
+    bus.publish("topic.agent_urgent", prioritized("idle-but-urgent", 10))
+        .await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    let qos_by_trigger: HashMap<String, Option<i32>> = probe
+        .calls()
+        .into_iter()
+        .map(|call| (String::from_utf8(call.payload).unwrap(), call.qos))
+        .collect();
+    let expect = |trigger: &str, qos: QoSLevel| {
+        assert_eq!(qos_by_trigger[trigger], Some(qos as i32), "{trigger}");
+    };
+    expect("urgent", QoSLevel::QosRealtime);
+    expect("routine", QoSLevel::QosBatched);
+    expect("idle", QoSLevel::QosBackground);
+    expect("idle-but-urgent", QoSLevel::QosRealtime);
+    Ok(())
+}
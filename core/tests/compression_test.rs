@@ -0,0 +1,196 @@
+use loom_core::compression::{
+    self, Codec, PayloadCompression, ACCEPT_ENCODING_KEY, CONTENT_ENCODING_KEY,
+};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use loom_core::proto::{ActionCall, ActionResult, Event};
+use std::collections::HashMap;
+
+fn large_payload() -> Vec<u8> {
+    "the quick brown fox jumps over the lazy dog. "
+        .repeat(100)
+        .into_bytes()
+}
+
+fn available() -> Vec<Codec> {
+    [Codec::Gzip, Codec::Zstd]
+        .into_iter()
+        .filter(|c| c.is_available())
+        .collect()
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn round_trip(codec: Codec) {
+    let compression = PayloadCompression::new(codec);
+    let original = large_payload();
+
+    let mut call = ActionCall {
+        id: "c1".into(),
+        payload: original.clone(),
+        ..Default::default()
+    };
+    assert!(compression.compress_call(&mut call).unwrap());
+    assert!(call.payload.len() < original.len());
+    assert_eq!(call.headers[CONTENT_ENCODING_KEY], codec.name());
+    assert!(compression::decompress_call(&mut call).unwrap());
+    assert_eq!(call.payload, original);
+    assert!(!call.headers.contains_key(CONTENT_ENCODING_KEY));
+
+    let mut result = ActionResult {
+        id: "c1".into(),
+        output: original.clone(),
+        ..Default::default()
+    };
+    assert!(compression.compress_result(&mut result).unwrap());
+    assert!(compression::decompress_result(&mut result).unwrap());
+    assert_eq!(result.output, original);
+
+    let mut event = Event {
+        id: "e1".into(),
+        payload: original.clone(),
+        ..Default::default()
+    };
+    assert!(compression.compress_event(&mut event).unwrap());
+    assert!(compression::decompress_event(&mut event).unwrap());
+    assert_eq!(event.payload, original);
+    assert!(event.metadata.is_empty());
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_round_trips_calls_results_and_events() {
+    round_trip(Codec::Gzip);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_round_trips_calls_results_and_events() {
+    round_trip(Codec::Zstd);
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[test]
+fn oversized_decoded_payloads_are_rejected() {
+    for codec in available() {
+        // 4 MiB of zeros compresses to a few KiB
+        let bomb = codec.compress(&vec![0u8; 4 << 20]).unwrap();
+        assert!(bomb.len() < 64 << 10);
+        assert!(codec.decompress(&bomb, 1 << 20).is_err());
+        assert_eq!(codec.decompress(&bomb, 4 << 20).unwrap().len(), 4 << 20);
+
+        let mut payload = bomb.clone();
+        let mut meta =
+            HashMap::from([(CONTENT_ENCODING_KEY.to_string(), codec.name().to_string())]);
+        assert!(compression::decompress_bounded(&mut payload, &mut meta, 1 << 20).is_err());
+        // The payload and its mark stay as they were
+        assert_eq!(payload, bomb);
+        assert!(meta.contains_key(CONTENT_ENCODING_KEY));
+    }
+}
+
+#[test]
+fn payloads_below_the_threshold_are_left_alone() {
+    for codec in available() {
+        let compression = PayloadCompression::new(codec).min_bytes(64);
+        let mut payload = b"short payload".to_vec();
+        let mut meta = HashMap::new();
+        assert!(!compression.compress(&mut payload, &mut meta).unwrap());
+        assert_eq!(payload, b"short payload");
+        assert!(meta.is_empty());
+        // Uncompressed payloads pass through decompress untouched
+        assert!(!compression::decompress(&mut payload, &mut meta).unwrap());
+        assert_eq!(payload, b"short payload");
+    }
+}
+
+#[test]
+fn payloads_already_encoded_are_not_compressed_twice() {
+    for codec in available() {
+        let compression = PayloadCompression::new(codec).min_bytes(1);
+        let mut payload = large_payload();
+        let mut meta = HashMap::from([(CONTENT_ENCODING_KEY.to_string(), "br".to_string())]);
+        assert!(!compression.compress(&mut payload, &mut meta).unwrap());
+        assert_eq!(payload, large_payload());
+    }
+}
+
+#[test]
+fn unknown_or_unavailable_codecs_are_errors() {
+    let mut payload = large_payload();
+    let mut meta = HashMap::from([(CONTENT_ENCODING_KEY.to_string(), "br".to_string())]);
+    assert!(compression::decompress(&mut payload, &mut meta).is_err());
+    // The payload and its mark stay as they were
+    assert_eq!(payload, large_payload());
+    assert_eq!(meta[CONTENT_ENCODING_KEY], "br");
+
+    for codec in [Codec::Gzip, Codec::Zstd] {
+        if !codec.is_available() {
+            assert!(codec.compress(b"data").is_err());
+            let mut meta = HashMap::new();
+            assert!(PayloadCompression::new(codec)
+                .min_bytes(1)
+                .compress(&mut large_payload(), &mut meta)
+                .is_err());
+            assert!(meta.is_empty());
+        }
+    }
+}
+
+#[test]
+fn accept_encoding_lists_are_matched_by_name() {
+    assert!(compression::accepts("zstd, gzip", Codec::Gzip));
+    assert!(compression::accepts(" GZIP ", Codec::Gzip));
+    assert!(!compression::accepts("br, deflate", Codec::Zstd));
+    assert!(!compression::accepts("", Codec::Zstd));
+    assert_eq!(Codec::parse("Zstd"), Some(Codec::Zstd));
+    let header = Codec::accept_header();
+    for codec in [Codec::Gzip, Codec::Zstd] {
+        assert_eq!(compression::accepts(&header, codec), codec.is_available());
+    }
+    // Used as the registration metadata key by the bridge
+    assert_eq!(ACCEPT_ENCODING_KEY, "accept-encoding");
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[tokio::test]
+async fn memory_store_compresses_at_rest_and_reads_back_originals() {
+    use loom_core::context::memory::InMemoryMemory;
+    use loom_core::context::MemoryWriter;
+
+    let codec = available()[0];
+    let mem = InMemoryMemory::new();
+    mem.set_payload_interning(true);
+    mem.set_payload_compression(Some(PayloadCompression::new(codec).min_bytes(64)));
+
+    let big = Event {
+        id: "big".into(),
+        r#type: "note".into(),
+        payload: large_payload(),
+        metadata: HashMap::from([("k".to_string(), "v".to_string())]),
+        ..Default::default()
+    };
+    let small = Event {
+        id: "small".into(),
+        r#type: "note".into(),
+        payload: b"tiny".to_vec(),
+        ..Default::default()
+    };
+    mem.append_event("s1", big.clone()).await.unwrap();
+    mem.append_event("s1", small.clone()).await.unwrap();
+
+    let stored = mem.find_event("big").unwrap();
+    assert_eq!(stored.payload, big.payload);
+    // The compression mark is kept by the store, not added to the event
+    assert_eq!(stored.metadata, big.metadata);
+    assert_eq!(mem.find_event("small").unwrap().payload, b"tiny");
+
+    let all = mem.query("s1", |_| true);
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].payload, big.payload);
+    let matched = mem.query("s1", |e| e.payload == big.payload);
+    assert_eq!(matched.len(), 1);
+    assert_eq!(matched[0].id, "big");
+
+    // Switching compression off leaves earlier events readable
+    mem.set_payload_compression(None);
+    assert_eq!(mem.find_event("big").unwrap().payload, big.payload);
+}
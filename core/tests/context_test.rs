@@ -0,0 +1,1944 @@
+use futures_util::stream::{BoxStream, StreamExt, TryStreamExt};
+use loom_core::action_broker::{ActionBroker, CapabilityProvider};
+use loom_core::context::builder::{
+    ContextBuilder, RetrievalPolicy, TriggerInput, DEFAULT_SYSTEM_PROMPT,
+};
+use loom_core::context::memory::{
+    InMemoryMemory, NullMemory, PayloadStats, QueryCacheStats, ReadOnlyMemory,
+};
+use loom_core::context::{
+    AssemblyContext, BundlePostprocessor, CachingEmbedder, ContextDoc, ContextStrategy,
+    Conversation, Embedder, EmbeddingIndex, HeuristicTokenCounter, HybridRetriever, LineSummarizer,
+    MemoryReader, MemoryWriter, MinimalStrategy, PromptBundle, Retention, Role, RoleMapping,
+    TokenBudget, TokenCounter, Turn, SNAPSHOT_EVENTS_METADATA_KEY, SNAPSHOT_EVENT_TYPE,
+};
+use loom_core::proto::{Event, SCHEMA_METADATA_KEY};
+use loom_core::{
+    CancellationToken, LoomError, MockClock, MockProvider, Result, WeatherProvider,
+    WebSearchProvider,
+};
+use std::sync::Arc;
+
+fn make_event(id: &str, ty: &str, ts: i64) -> Event {
+    Event {
+        id: id.to_string(),
+        r#type: ty.to_string(),
+        timestamp_ms: ts,
+        source: "test".to_string(),
+        metadata: Default::default(),
+        payload: vec![],
+        confidence: 1.0,
+        tags: vec![],
+        priority: 50,
+    }
+}
+
+#[tokio::test]
+async fn memory_stamps_untimed_events_with_injected_clock() -> Result<()> {
+    let clock = MockClock::new(42_000);
+    let mem = InMemoryMemory::with_clock(clock.clone());
+
+    mem.append_event("s1", make_event("e1", "intent", 0))
+        .await?;
+    clock.advance(500);
+    mem.append_event("s1", make_event("e2", "intent", 7))
+        .await?;
+
+    let hits = mem.retrieve("[42000]", 10, None).await?;
+    assert_eq!(hits.len(), 1, "first event stamped from clock");
+    let hits = mem.retrieve("[7]", 10, None).await?;
+    assert_eq!(hits.len(), 1, "explicit timestamps are kept");
+    Ok(())
+}
+
+fn trigger(session: &str, goal: &str) -> TriggerInput {
+    TriggerInput {
+        session_id: session.to_string(),
+        goal: Some(goal.to_string()),
+        tool_hints: vec![],
+        budget: TokenBudget::default(),
+        system_prompt: None,
+        retrieval_k: None,
+        min_score: None,
+    }
+}
+
+#[tokio::test]
+async fn identical_build_is_served_from_cache_until_memory_changes() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_cache(8);
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+
+    let first = builder.build(trigger("s1", "intent")).await?;
+    let second = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(first.context_docs, second.context_docs);
+    let stats = builder.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+    // A different trigger misses
+    builder.build(trigger("s1", "other")).await?;
+    assert_eq!(builder.cache_stats().misses, 2);
+
+    // Appending bumps the memory version and invalidates the cached bundle
+    mem.append_event("s1", make_event("e2", "intent", 2))
+        .await?;
+    let third = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(builder.cache_stats().misses, 3);
+    assert!(third.context_docs.len() > first.context_docs.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn cache_evicts_least_recently_used_bundle() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_cache(2);
+
+    builder.build(trigger("s1", "a")).await?;
+    builder.build(trigger("s1", "b")).await?;
+    builder.build(trigger("s1", "a")).await?; // hit, "b" becomes LRU
+    builder.build(trigger("s1", "c")).await?; // evicts "b"
+    builder.build(trigger("s1", "a")).await?; // still cached
+    let stats = builder.cache_stats();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.hits, 2);
+
+    builder.build(trigger("s1", "b")).await?;
+    assert_eq!(builder.cache_stats().misses, 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cache_keeps_lru_order_across_many_hits() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_cache(2);
+
+    builder.build(trigger("s1", "a")).await?;
+    builder.build(trigger("s1", "b")).await?;
+    // Far more hits than entries, alternating so each key is used again and again
+    for _ in 0..20 {
+        builder.build(trigger("s1", "b")).await?;
+        builder.build(trigger("s1", "a")).await?;
+    }
+    builder.build(trigger("s1", "c")).await?; // evicts "b", the older of the two
+    builder.build(trigger("s1", "a")).await?;
+    let stats = builder.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (41, 3, 2));
+
+    builder.build(trigger("s1", "b")).await?;
+    assert_eq!(builder.cache_stats().misses, 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn bundle_docs_carry_scores_and_sources() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    mem.append_event("s2", make_event("e2", "intent.intent", 2))
+        .await?;
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem));
+
+    let bundle = builder.build(trigger("s1", "intent")).await?;
+    // Summary first, then retrieved docs ordered by descending score
+    assert!(bundle.context_docs[0]
+        .text
+        .starts_with("Recent episode summary"));
+    let retrieved = &bundle.context_docs[1..];
+    assert_eq!(retrieved.len(), 2);
+    assert!(retrieved[0].score > retrieved[1].score);
+    assert_eq!(retrieved[0].source_id.as_deref(), Some("s2"));
+    assert!(retrieved.iter().all(|d| d.score > 0.0 && d.score <= 1.0));
+
+    let texts = bundle.context_texts();
+    assert_eq!(texts.len(), bundle.context_docs.len());
+    assert_eq!(texts[1], retrieved[0].text);
+    Ok(())
+}
+
+#[tokio::test]
+async fn history_entries_map_events_to_roles() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let mut user = make_event("u1", "intent", 1);
+    user.payload = b"turn on the lights".to_vec();
+    let mut reply = make_event("a1", "llm.response", 2);
+    reply.payload = b"Done.".to_vec();
+    let tool = make_event("t1", "action_result", 3);
+    let mut custom = make_event("c1", "note", 4);
+    custom.source = "ops.console".to_string();
+    for e in [user, reply, tool, custom] {
+        mem.append_event("s1", e).await?;
+    }
+
+    let roles = RoleMapping::new().map_source("ops.console", Role::System);
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_role_mapping(roles);
+    let bundle = builder.build(trigger("s1", "lights")).await?;
+
+    let got: Vec<(Role, &str, &str)> = bundle
+        .history
+        .iter()
+        .map(|h| (h.role, h.content.as_str(), h.event_id.as_str()))
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            (Role::User, "turn on the lights", "u1"),
+            (Role::Assistant, "Done.", "a1"),
+            (Role::Tool, "action_result", "t1"),
+            (Role::System, "note", "c1"),
+        ]
+    );
+    assert_eq!(bundle.history[3].timestamp_ms, 4);
+
+    // Only the most recent entries are kept
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_history_limit(2);
+    let bundle = builder.build(trigger("s1", "lights")).await?;
+    assert_eq!(bundle.history.len(), 2);
+    assert_eq!(bundle.history[0].event_id, "t1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn recency_decay_lets_a_newer_match_outrank_a_closer_old_one() -> Result<()> {
+    use std::time::Duration;
+
+    let clock = MockClock::new(9_000_000);
+    let mem = InMemoryMemory::with_clock(clock.clone());
+    // The old line is shorter, so "intent" covers more of it
+    let mut old = make_event("old", "intent", 1_000_000);
+    old.source = "a".to_string();
+    let mut new = make_event("new", "intent", 9_000_000);
+    new.source = "mic.primary".to_string();
+    mem.append_event("s1", old).await?;
+    mem.append_event("s1", new).await?;
+    let top = |docs: &[loom_core::context::ContextDoc]| docs[0].text.clone();
+
+    let plain = mem.retrieve_scored("intent", 2, None).await?;
+    assert!(top(&plain).ends_with("from a"));
+    assert!(plain[0].score > plain[1].score);
+
+    // Strong decay: the old doc is hours past a one-minute half-life
+    mem.set_recency_half_life(Some(Duration::from_secs(60)));
+    assert_eq!(mem.recency_half_life(), Some(Duration::from_secs(60)));
+    let decayed = mem.retrieve_scored("intent", 2, None).await?;
+    assert!(top(&decayed).ends_with("from mic.primary"));
+    let fresh_score = decayed[0].score;
+    assert_eq!(fresh_score, plain[1].score, "age 0 keeps the full score");
+
+    // One half-life later the fresh doc scores half as much
+    clock.advance(60_000);
+    let later = mem.retrieve_scored("intent", 2, None).await?;
+    assert!((later[0].score - fresh_score / 2.0).abs() < 1e-6);
+
+    // Weak decay keeps similarity in charge
+    mem.set_recency_half_life(Some(Duration::from_secs(365 * 24 * 3600)));
+    assert!(top(&mem.retrieve_scored("intent", 2, None).await?).ends_with("from a"));
+    mem.set_recency_half_life(None);
+    assert_eq!(mem.retrieve_scored("intent", 2, None).await?, plain);
+    Ok(())
+}
+
+/// Reader whose retrieval never completes, standing in for a slow embedding search
+struct StalledReader;
+
+#[async_trait::async_trait]
+impl MemoryReader for StalledReader {
+    async fn retrieve(
+        &self,
+        _query: &str,
+        _k: usize,
+        _filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn cancelled_build_abandons_retrieval() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(Arc::new(StalledReader), Arc::clone(&mem));
+    let cancel = CancellationToken::new();
+
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        canceller.cancel();
+    });
+    let started = std::time::Instant::now();
+    let res = builder.build_cancellable(trigger("s1", "x"), &cancel).await;
+    assert!(matches!(res, Err(LoomError::Cancelled)));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+    // An already-cancelled token short-circuits
+    let res = builder.build_cancellable(trigger("s1", "x"), &cancel).await;
+    assert!(matches!(res, Err(LoomError::Cancelled)));
+    Ok(())
+}
+
+fn tagged_event(id: &str, ty: &str, source: &str, tags: &[&str]) -> Event {
+    let mut e = make_event(id, ty, 1);
+    e.source = source.to_string();
+    e.tags = tags.iter().map(|t| t.to_string()).collect();
+    e
+}
+
+#[tokio::test]
+async fn filtered_retrieval_uses_type_source_and_tag_indexes() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", tagged_event("e1", "intent", "mic", &["voice"]))
+        .await?;
+    mem.append_event(
+        "s1",
+        tagged_event("e2", "intent", "ui", &["voice", "urgent"]),
+    )
+    .await?;
+    mem.append_event("s2", tagged_event("e3", "face", "camera", &["urgent"]))
+        .await?;
+
+    let by_type = mem
+        .retrieve("", 10, Some(serde_json::json!({"type": "intent"})))
+        .await?;
+    assert_eq!(by_type.len(), 2);
+
+    let by_tags = mem
+        .retrieve(
+            "",
+            10,
+            Some(serde_json::json!({"tags": ["voice", "urgent"]})),
+        )
+        .await?;
+    assert_eq!(by_tags, vec!["[1] intent from ui".to_string()]);
+
+    let combined = mem
+        .retrieve(
+            "",
+            10,
+            Some(serde_json::json!({"tag": "urgent", "source": "camera"})),
+        )
+        .await?;
+    assert_eq!(combined, vec!["[1] face from camera".to_string()]);
+
+    // The text query still applies on top of the filter
+    let none = mem
+        .retrieve("camera", 10, Some(serde_json::json!({"type": "intent"})))
+        .await?;
+    assert!(none.is_empty());
+
+    let bad = mem
+        .retrieve("", 10, Some(serde_json::json!({"colour": "red"})))
+        .await;
+    assert!(matches!(bad, Err(LoomError::Memory(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn bounded_store_evicts_oldest_and_keeps_indexes_consistent() -> Result<()> {
+    let mem = InMemoryMemory::bounded(2);
+    mem.append_event("s1", tagged_event("e1", "intent", "mic", &["old"]))
+        .await?;
+    mem.append_event("s1", tagged_event("e2", "intent", "mic", &[]))
+        .await?;
+    mem.append_event("s1", tagged_event("e3", "face", "camera", &[]))
+        .await?;
+
+    let recent = mem.recent_events("s1", 10).await?;
+    let ids: Vec<&str> = recent.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["e2", "e3"]);
+
+    // The evicted event no longer shows up through any index
+    let old = mem
+        .retrieve("", 10, Some(serde_json::json!({"tag": "old"})))
+        .await?;
+    assert!(old.is_empty());
+    let intents = mem
+        .retrieve("", 10, Some(serde_json::json!({"type": "intent"})))
+        .await?;
+    assert_eq!(intents.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn replayed_append_is_deduplicated_per_session() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    assert!(
+        mem.append_event("s1", make_event("e1", "intent", 1))
+            .await?
+    );
+    let version = mem.version("s1");
+    assert!(
+        !mem.append_event("s1", make_event("e1", "intent", 1))
+            .await?
+    );
+    // A duplicate is not a change, so cached bundles stay valid
+    assert_eq!(mem.version("s1"), version);
+    // The same id in another session is a distinct event
+    assert!(
+        mem.append_event("s2", make_event("e1", "intent", 1))
+            .await?
+    );
+
+    assert_eq!(mem.recent_events("s1", 10).await?.len(), 1);
+    assert_eq!(mem.retrieve("intent", 10, None).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn system_prompt_overrides_builder_then_trigger() -> Result<()> {
+    let mem = InMemoryMemory::new();
+
+    let default = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem));
+    let bundle = default.build(trigger("s1", "x")).await?;
+    assert_eq!(bundle.system, DEFAULT_SYSTEM_PROMPT);
+
+    let custom = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .with_system_prompt("You are a kitchen assistant.");
+    let bundle = custom.build(trigger("s1", "x")).await?;
+    assert_eq!(bundle.system, "You are a kitchen assistant.");
+
+    // Per-trigger value wins over the builder's
+    let mut t = trigger("s1", "x");
+    t.system_prompt = Some("Answer in French.".to_string());
+    assert_eq!(custom.build(t).await?.system, "Answer in French.");
+
+    // Empty overrides fall back instead of producing an empty system message
+    let mut t = trigger("s1", "x");
+    t.system_prompt = Some("  ".to_string());
+    assert_eq!(
+        custom.build(t).await?.system,
+        "You are a kitchen assistant."
+    );
+    let blank = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_system_prompt("");
+    assert_eq!(
+        blank.build(trigger("s1", "x")).await?.system,
+        DEFAULT_SYSTEM_PROMPT
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn raising_min_score_reduces_retrieved_docs() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    // Summary lines are "[ts] type from source"; a longer type means a lower coverage score
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    mem.append_event("s1", make_event("e2", "intent.followup.long", 2))
+        .await?;
+    mem.append_event("s1", make_event("e3", "intent.x", 3))
+        .await?;
+
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_retrieval_k(10);
+    let retrieved = |b: &PromptBundle| {
+        b.context_docs
+            .iter()
+            .filter(|d| !d.text.starts_with("Recent episode summary"))
+            .count()
+    };
+
+    let all = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(retrieved(&all), 3);
+
+    let scores: Vec<f32> = all.context_docs[1..].iter().map(|d| d.score).collect();
+    let mut strict = trigger("s1", "intent");
+    strict.min_score = Some(scores[1]);
+    let fewer = builder.build(strict).await?;
+    assert_eq!(retrieved(&fewer), 2);
+    assert!(fewer.context_docs.iter().all(|d| d.score >= scores[1]));
+
+    let mut strictest = trigger("s1", "intent");
+    strictest.min_score = Some(1.1);
+    assert_eq!(retrieved(&builder.build(strictest).await?), 0);
+
+    // Builder-level threshold and k apply when the trigger leaves them unset
+    let capped = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .with_retrieval_k(1)
+        .with_min_score(0.0);
+    assert_eq!(retrieved(&capped.build(trigger("s1", "intent")).await?), 1);
+    Ok(())
+}
+
+struct FailingReader;
+
+#[async_trait::async_trait]
+impl MemoryReader for FailingReader {
+    async fn retrieve(
+        &self,
+        _query: &str,
+        _k: usize,
+        _filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        Err(LoomError::Memory("index offline".into()))
+    }
+}
+
+/// Delegates to an InMemoryMemory, counting retrieval calls
+struct CountingReader {
+    mem: Arc<InMemoryMemory>,
+    retrievals: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl MemoryReader for CountingReader {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        self.retrievals
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.mem.retrieve(query, k, filters).await
+    }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        self.mem.recent_events(session, limit).await
+    }
+}
+
+#[tokio::test]
+async fn empty_goal_skips_retrieval_per_policy() -> Result<()> {
+    use loom_core::context::builder::EmptyGoalPolicy;
+    use std::sync::atomic::Ordering;
+
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let reader = Arc::new(CountingReader {
+        mem: Arc::clone(&mem),
+        retrievals: Default::default(),
+    });
+    let is_summary = |b: &PromptBundle| {
+        b.context_docs
+            .iter()
+            .any(|d| d.text.starts_with("Recent episode summary"))
+    };
+
+    let builder = ContextBuilder::new(Arc::clone(&reader), Arc::clone(&mem));
+    for goal in [None, Some(""), Some("   \n")] {
+        let mut input = trigger("s1", "");
+        input.goal = goal.map(String::from);
+        let bundle = builder.build(input).await?;
+        assert!(is_summary(&bundle), "SkipRetrieval keeps the summary");
+        assert_eq!(bundle.history.len(), 1);
+    }
+    assert_eq!(reader.retrievals.load(Ordering::SeqCst), 0);
+
+    let history_only = ContextBuilder::new(Arc::clone(&reader), Arc::clone(&mem))
+        .with_empty_goal_policy(EmptyGoalPolicy::HistoryOnly);
+    let bundle = history_only.build(trigger("s1", " ")).await?;
+    assert!(bundle.context_docs.is_empty());
+    assert_eq!(bundle.history.len(), 1);
+    assert_eq!(reader.retrievals.load(Ordering::SeqCst), 0);
+
+    // A real goal still retrieves
+    builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(reader.retrievals.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn retrieval_policy_controls_failed_retrieval() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let retrieved = |b: &PromptBundle| {
+        b.context_docs
+            .iter()
+            .filter(|d| !d.text.starts_with("Recent episode summary"))
+            .count()
+    };
+
+    // Degrade (default): build succeeds with no retrieved docs but keeps the summary
+    let degrade = ContextBuilder::new(Arc::new(FailingReader), Arc::clone(&mem));
+    let bundle = degrade.build(trigger("s1", "intent")).await?;
+    assert_eq!(retrieved(&bundle), 0);
+    assert_eq!(bundle.context_docs.len(), 1);
+
+    let strict = ContextBuilder::new(Arc::new(FailingReader), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Strict);
+    let err = strict.build(trigger("s1", "intent")).await;
+    assert!(matches!(err, Err(LoomError::Memory(msg)) if msg == "index offline"));
+
+    let fallback = ContextBuilder::new(Arc::new(FailingReader), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Fallback(mem.clone()));
+    let bundle = fallback.build(trigger("s1", "intent")).await?;
+    assert_eq!(retrieved(&bundle), 1);
+
+    // A failing fallback degrades instead of erroring
+    let both_down = ContextBuilder::new(Arc::new(FailingReader), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Fallback(Arc::new(FailingReader)));
+    assert_eq!(
+        retrieved(&both_down.build(trigger("s1", "intent")).await?),
+        0
+    );
+    Ok(())
+}
+
+/// Retrieves from an InMemoryMemory but cannot read session history
+struct HistoryDownReader {
+    mem: Arc<InMemoryMemory>,
+}
+
+#[async_trait::async_trait]
+impl MemoryReader for HistoryDownReader {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        self.mem.retrieve(query, k, filters).await
+    }
+
+    async fn recent_events(&self, _session: &str, _limit: usize) -> Result<Vec<Event>> {
+        Err(LoomError::Memory("history offline".into()))
+    }
+}
+
+#[tokio::test]
+async fn retrieval_policy_also_handles_failed_history_reads() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let reader = || Arc::new(HistoryDownReader { mem: mem.clone() });
+
+    // Degrade (default): no history, and the bundle is not cached
+    let degrade = ContextBuilder::new(reader(), Arc::clone(&mem)).with_cache(8);
+    let bundle = degrade.build(trigger("s1", "intent")).await?;
+    assert!(bundle.history.is_empty());
+    assert!(!bundle.context_docs.is_empty());
+    degrade.build(trigger("s1", "intent")).await?;
+    assert_eq!(degrade.cache_stats().hits, 0);
+
+    let strict = ContextBuilder::new(reader(), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Strict);
+    let err = strict.build(trigger("s1", "intent")).await;
+    assert!(matches!(err, Err(LoomError::Memory(msg)) if msg == "history offline"));
+
+    let fallback = ContextBuilder::new(reader(), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Fallback(mem.clone()));
+    assert_eq!(
+        fallback.build(trigger("s1", "intent")).await?.history.len(),
+        1
+    );
+
+    let both_down = ContextBuilder::new(reader(), Arc::clone(&mem))
+        .with_retrieval_policy(RetrievalPolicy::Fallback(reader()));
+    assert!(both_down
+        .build(trigger("s1", "intent"))
+        .await?
+        .history
+        .is_empty());
+    Ok(())
+}
+
+/// Delegates to an InMemoryMemory after sleeping, standing in for a slow vector backend
+struct SleepyReader {
+    mem: Arc<InMemoryMemory>,
+    delay: std::time::Duration,
+}
+
+#[async_trait::async_trait]
+impl MemoryReader for SleepyReader {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        tokio::time::sleep(self.delay).await;
+        self.mem.retrieve(query, k, filters).await
+    }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        self.mem.recent_events(session, limit).await
+    }
+}
+
+#[tokio::test]
+async fn retrieval_timeout_degrades_per_policy() -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let sleepy = || {
+        Arc::new(SleepyReader {
+            mem: Arc::clone(&mem),
+            delay: Duration::from_secs(5),
+        })
+    };
+    let retrieved = |b: &PromptBundle| {
+        b.context_docs
+            .iter()
+            .filter(|d| !d.text.starts_with("Recent episode summary"))
+            .count()
+    };
+
+    // Degrade (default): the build gives up on retrieval but keeps summary and history
+    let degrade = ContextBuilder::new(sleepy(), Arc::clone(&mem))
+        .with_retrieval_timeout(Duration::from_millis(50));
+    let started = Instant::now();
+    let bundle = degrade.build(trigger("s1", "intent")).await?;
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(retrieved(&bundle), 0);
+    assert_eq!(bundle.context_docs.len(), 1);
+    assert_eq!(bundle.history.len(), 1);
+
+    let strict = ContextBuilder::new(sleepy(), Arc::clone(&mem))
+        .with_retrieval_timeout(Duration::from_millis(50))
+        .with_retrieval_policy(RetrievalPolicy::Strict);
+    let err = strict.build(trigger("s1", "intent")).await;
+    assert!(matches!(err, Err(LoomError::Timeout(_))));
+
+    let fallback = ContextBuilder::new(sleepy(), Arc::clone(&mem))
+        .with_retrieval_timeout(Duration::from_millis(50))
+        .with_retrieval_policy(RetrievalPolicy::Fallback(mem.clone()));
+    assert_eq!(
+        retrieved(&fallback.build(trigger("s1", "intent")).await?),
+        1
+    );
+
+    // Fast enough readers are unaffected
+    let fast = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .with_retrieval_timeout(Duration::from_secs(5));
+    assert_eq!(retrieved(&fast.build(trigger("s1", "intent")).await?), 1);
+    Ok(())
+}
+
+/// Fails retrieval while `down` is set, otherwise delegates (version included) to an
+/// InMemoryMemory
+struct FlakyReader {
+    mem: Arc<InMemoryMemory>,
+    down: std::sync::atomic::AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl MemoryReader for FlakyReader {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(LoomError::Memory("index offline".into()));
+        }
+        self.mem.retrieve(query, k, filters).await
+    }
+
+    async fn recent_events(&self, session: &str, limit: usize) -> Result<Vec<Event>> {
+        self.mem.recent_events(session, limit).await
+    }
+
+    fn version(&self, session: &str) -> Option<u64> {
+        self.mem.version(session)
+    }
+}
+
+#[tokio::test]
+async fn degraded_bundles_are_not_cached() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let reader = Arc::new(FlakyReader {
+        mem: Arc::clone(&mem),
+        down: true.into(),
+    });
+    let builder = ContextBuilder::new(Arc::clone(&reader), Arc::clone(&mem)).with_cache(8);
+
+    let degraded = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(degraded.context_docs.len(), 1);
+    assert_eq!(builder.cache_stats().entries, 0);
+
+    // Once the index is back, the same trigger retrieves again and is cached
+    reader
+        .down
+        .store(false, std::sync::atomic::Ordering::SeqCst);
+    let recovered = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(recovered.context_docs.len(), 2);
+    builder.build(trigger("s1", "intent")).await?;
+    let stats = builder.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+    Ok(())
+}
+
+/// Streams from an InMemoryMemory, counting the docs the consumer pulled
+struct PullCountingReader {
+    mem: Arc<InMemoryMemory>,
+    pulled: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl MemoryReader for PullCountingReader {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        self.mem.retrieve(query, k, filters).await
+    }
+
+    fn retrieve_stream<'a>(
+        &'a self,
+        query: &'a str,
+        filters: Option<serde_json::Value>,
+    ) -> BoxStream<'a, Result<ContextDoc>> {
+        let pulled = Arc::clone(&self.pulled);
+        self.mem
+            .retrieve_stream(query, filters)
+            .inspect(move |_| {
+                pulled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            })
+            .boxed()
+    }
+}
+
+#[tokio::test]
+async fn streamed_retrieval_is_ranked_and_stops_once_the_token_budget_is_full() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    // Shorter timestamps make shorter lines, so the query covers more of them
+    for (i, ts) in [1_000, 7, 100_000, 42, 3].into_iter().enumerate() {
+        mem.append_event("docs", make_event(&format!("e{i}"), "intent", ts))
+            .await?;
+    }
+    let streamed: Vec<ContextDoc> = mem.retrieve_stream("intent", None).try_collect().await?;
+    assert_eq!(streamed, mem.retrieve_scored("intent", 10, None).await?);
+    assert!(streamed[0].text.starts_with("[7]"));
+    assert!(streamed[4].text.starts_with("[100000]"));
+
+    // Ten tokens per doc and a 25-token budget: two docs fit, the third ends retrieval
+    let pulled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let reader = Arc::new(PullCountingReader {
+        mem: Arc::clone(&mem),
+        pulled: Arc::clone(&pulled),
+    });
+    let builder = ContextBuilder::new(reader, Arc::clone(&mem))
+        .with_retrieval_k(5)
+        .with_retrieval_token_budget(25)
+        .with_token_counter(Arc::new(|_: &str| 10));
+    let bundle = builder.build(trigger("s1", "intent")).await?;
+    let texts: Vec<&str> = bundle
+        .context_docs
+        .iter()
+        .map(|d| d.text.as_str())
+        .collect();
+    assert_eq!(
+        texts,
+        vec![streamed[0].text.as_str(), streamed[1].text.as_str()]
+    );
+    assert_eq!(pulled.load(std::sync::atomic::Ordering::SeqCst), 3);
+    Ok(())
+}
+
+/// Fixed scored docs, returned best first regardless of the query
+struct ScoredCorpus(Vec<ContextDoc>);
+
+#[async_trait::async_trait]
+impl MemoryReader for ScoredCorpus {
+    async fn retrieve(
+        &self,
+        query: &str,
+        k: usize,
+        filters: Option<serde_json::Value>,
+    ) -> Result<Vec<String>> {
+        let docs = self.retrieve_scored(query, k, filters).await?;
+        Ok(docs.into_iter().map(|d| d.text).collect())
+    }
+
+    async fn retrieve_scored(
+        &self,
+        _query: &str,
+        k: usize,
+        _filters: Option<serde_json::Value>,
+    ) -> Result<Vec<ContextDoc>> {
+        Ok(self.0.iter().take(k).cloned().collect())
+    }
+}
+
+#[tokio::test]
+async fn mmr_retrieval_picks_across_clusters() -> Result<()> {
+    // Three clusters of near-duplicates; the source id names the cluster
+    let corpus = ScoredCorpus(
+        [
+            ("tokio runtime spawns async tasks", 0.95, "tokio"),
+            ("tokio runtime spawns async tasks quickly", 0.93, "tokio"),
+            ("the tokio runtime spawns async tasks", 0.92, "tokio"),
+            ("borrow checker enforces lifetimes", 0.80, "borrow"),
+            ("the borrow checker enforces lifetimes", 0.78, "borrow"),
+            ("cargo builds the workspace", 0.70, "cargo"),
+            ("cargo builds every workspace crate", 0.68, "cargo"),
+        ]
+        .into_iter()
+        .map(|(text, score, cluster)| ContextDoc::new(text, score, Some(cluster.to_string())))
+        .collect(),
+    );
+    let clusters = |docs: &[ContextDoc]| -> Vec<String> {
+        docs.iter()
+            .map(|d| d.source_id.clone().unwrap_or_default())
+            .collect()
+    };
+
+    let top = corpus.retrieve_scored("rust", 3, None).await?;
+    assert_eq!(clusters(&top), vec!["tokio", "tokio", "tokio"]);
+
+    let diverse = corpus.retrieve_mmr("rust", 3, 0.5, None).await?;
+    assert_eq!(clusters(&diverse), vec!["tokio", "borrow", "cargo"]);
+    // Relevance scores are kept, and each pick is its cluster's best doc
+    assert_eq!(diverse[0], corpus.0[0]);
+    assert_eq!(diverse[1], corpus.0[3]);
+    assert_eq!(diverse[2], corpus.0[5]);
+
+    // lambda = 1 ignores diversity and is plain top-k
+    assert_eq!(corpus.retrieve_mmr("rust", 3, 1.0, None).await?, top);
+    Ok(())
+}
+
+struct Prefix(&'static str);
+
+impl BundlePostprocessor for Prefix {
+    fn process(&self, bundle: &mut PromptBundle) -> Result<()> {
+        bundle.system = format!("{}{}", self.0, bundle.system);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn postprocessors_run_in_insertion_order_and_can_abort() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .with_system_prompt("base")
+        .with_postprocessor(Arc::new(Prefix("B:")))
+        .with_postprocessor(Arc::new(Prefix("A:")));
+    let bundle = builder.build(trigger("s1", "x")).await?;
+    assert_eq!(bundle.system, "A:B:base");
+
+    let failing = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem)).with_postprocessor(
+        Arc::new(|_: &mut PromptBundle| -> Result<()> {
+            Err(LoomError::AgentError("guardrail rejected bundle".into()))
+        }),
+    );
+    assert!(matches!(
+        failing.build(trigger("s1", "x")).await,
+        Err(LoomError::AgentError(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn null_memory_discards_writes_and_reads_empty() -> Result<()> {
+    let mem = NullMemory::new();
+    assert!(
+        !mem.append_event("s1", make_event("e1", "intent", 1))
+            .await?
+    );
+    assert!(mem.retrieve("intent", 10, None).await?.is_empty());
+    assert!(mem.recent_events("s1", 10).await?.is_empty());
+    assert_eq!(mem.summarize_episode("s1").await?, None);
+
+    let bundle = ContextBuilder::new(Arc::clone(&mem), Arc::clone(&mem))
+        .build(trigger("s1", "intent"))
+        .await?;
+    assert!(bundle.context_docs.is_empty());
+    assert!(bundle.history.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_only_memory_rejects_appends_and_delegates_reads() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    let ro = ReadOnlyMemory::new(Arc::clone(&mem));
+
+    let err = ro.append_event("s1", make_event("e2", "intent", 2)).await;
+    assert!(matches!(err, Err(LoomError::Memory(msg)) if msg == "read only"));
+    assert_eq!(mem.recent_events("s1", 10).await?.len(), 1);
+
+    assert_eq!(ro.retrieve("intent", 10, None).await?.len(), 1);
+    assert_eq!(ro.recent_events("s1", 10).await?.len(), 1);
+    assert!(ro.summarize_episode("s1").await?.is_some());
+    assert_eq!(ro.version("s1"), mem.version("s1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_filters_a_session_by_predicate_in_order() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    for (id, ty, ts) in [("e1", "intent", 3), ("e2", "face", 1), ("e3", "intent", 2)] {
+        mem.append_event("s1", make_event(id, ty, ts)).await?;
+    }
+    mem.append_event("s2", make_event("e4", "intent", 4))
+        .await?;
+
+    let intents = mem.query("s1", |e| e.r#type == "intent");
+    let ids: Vec<&str> = intents.iter().map(|e| e.id.as_str()).collect();
+    // Append order, not timestamp order
+    assert_eq!(ids, vec!["e1", "e3"]);
+    assert!(mem.query("missing", |_| true).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn append_events_matches_looped_appends() -> Result<()> {
+    let batched = InMemoryMemory::bounded(3);
+    let looped = InMemoryMemory::bounded(3);
+    let events = vec![
+        make_event("e1", "intent", 1),
+        make_event("e2", "face", 2),
+        make_event("e1", "intent", 3), // duplicate within the batch
+        make_event("e3", "intent", 4),
+        make_event("e4", "face", 5),
+    ];
+    batched.append_events("s1", events.clone()).await?;
+    for e in events {
+        looped.append_event("s1", e).await?;
+    }
+
+    let ids = |evts: Vec<Event>| evts.into_iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(
+        ids(batched.recent_events("s1", 10).await?),
+        vec!["e2", "e3", "e4"]
+    );
+    assert_eq!(
+        ids(batched.recent_events("s1", 10).await?),
+        ids(looped.recent_events("s1", 10).await?)
+    );
+    // Index reflects the batch and its evictions
+    let filter = serde_json::json!({"type": "intent"});
+    assert_eq!(batched.retrieve("", 10, Some(filter)).await?.len(), 1);
+    // A retained id is still deduplicated on later appends
+    assert!(
+        !batched
+            .append_event("s1", make_event("e3", "intent", 6))
+            .await?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeated_retrieval_hits_cache_until_next_append() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+
+    let first = mem.retrieve("intent", 5, None).await?;
+    let second = mem.retrieve("intent", 5, None).await?;
+    assert_eq!(first, second);
+    assert_eq!(
+        mem.query_cache_stats(),
+        QueryCacheStats { hits: 1, misses: 1 }
+    );
+
+    // A different k is a different key
+    mem.retrieve("intent", 1, None).await?;
+    assert_eq!(mem.query_cache_stats().misses, 2);
+
+    // Appending to any session invalidates cached results
+    mem.append_event("s2", make_event("e2", "intent", 2))
+        .await?;
+    assert_eq!(mem.retrieve("intent", 5, None).await?.len(), 2);
+    assert_eq!(
+        mem.query_cache_stats(),
+        QueryCacheStats { hits: 1, misses: 3 }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn interned_payloads_are_shared_and_read_back_intact() -> Result<()> {
+    let mem = InMemoryMemory::bounded(3);
+    mem.set_payload_interning(true);
+    let blob = vec![7u8; 1000];
+    for i in 0..3 {
+        let mut e = make_event(&format!("e{i}"), "context", i);
+        e.payload = blob.clone();
+        mem.append_event("s1", e).await?;
+    }
+    assert_eq!(
+        mem.payload_stats(),
+        PayloadStats {
+            payloads: 3,
+            logical_bytes: 3000,
+            unique_bytes: 1000
+        }
+    );
+    assert!((mem.payload_stats().dedup_ratio() - 3.0).abs() < 1e-9);
+    // Transparent to readers
+    let events = mem.recent_events("s1", 10).await?;
+    assert!(events.iter().all(|e| e.payload == blob));
+    // Including predicates that look at the payload
+    assert_eq!(mem.query("s1", |e| e.payload == blob).len(), 3);
+
+    // Evicted payloads stop counting
+    let mut other = make_event("e3", "context", 3);
+    other.payload = vec![1u8; 10];
+    mem.append_event("s1", other).await?;
+    let stats = mem.payload_stats();
+    assert_eq!((stats.payloads, stats.logical_bytes), (3, 2010));
+    assert_eq!(stats.unique_bytes, 1010);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tool_hints_are_deduplicated_and_capped() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let builder = ContextBuilder::new(mem.clone(), mem).with_max_tool_hints(3);
+
+    let mut input = trigger("s1", "plan");
+    input.tool_hints = [
+        "web.search",
+        "Web.Search",
+        " tts.speak ",
+        "",
+        "weather.get",
+        "WEB.SEARCH",
+        "calendar.add",
+        "maps.route",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let bundle = builder.build(input).await?;
+    let hints: Vec<String> = serde_json::from_str(bundle.tools_json_schema.as_deref().unwrap())?;
+    assert_eq!(hints, vec!["web.search", "tts.speak", "weather.get"]);
+
+    let no_hints = builder.build(trigger("s1", "plan")).await?;
+    assert!(no_hints.tools_json_schema.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn tool_hints_are_limited_to_registered_capabilities() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(WebSearchProvider::new()));
+    let builder = ContextBuilder::new(mem.clone(), mem)
+        .with_broker(Arc::clone(&broker))
+        .with_cache(8);
+
+    let mut input = trigger("s1", "plan");
+    input.tool_hints = vec!["web.search".to_string(), "weather.get".to_string()];
+    let bundle = builder.build(input.clone()).await?;
+    let hints: Vec<String> = serde_json::from_str(bundle.tools_json_schema.as_deref().unwrap())?;
+    assert_eq!(hints, vec!["web.search"], "phantom weather.get dropped");
+
+    // Registering the tool later is picked up despite the cache
+    broker.register_provider(Arc::new(WeatherProvider::new()));
+    let bundle = builder.build(input).await?;
+    let hints: Vec<String> = serde_json::from_str(bundle.tools_json_schema.as_deref().unwrap())?;
+    assert_eq!(hints, vec!["web.search", "weather.get"]);
+
+    let mut only_phantoms = trigger("s1", "plan");
+    only_phantoms.tool_hints = vec!["calendar.add".to_string()];
+    assert!(builder
+        .build(only_phantoms)
+        .await?
+        .tools_json_schema
+        .is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn changed_tool_schema_invalidates_cached_bundles() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let broker = Arc::new(ActionBroker::new());
+    let tool = |schema: &str| {
+        let mut mock = MockProvider::named("notes.add").descriptor();
+        mock.metadata
+            .insert(SCHEMA_METADATA_KEY.to_string(), schema.to_string());
+        Arc::new(MockProvider::new(mock))
+    };
+    broker.register_provider(tool(r#"{"required":["text"]}"#));
+    let builder = ContextBuilder::new(mem.clone(), mem)
+        .with_broker(Arc::clone(&broker))
+        .with_cache(8);
+    let mut input = trigger("s1", "take a note");
+    input.tool_hints = vec!["notes.add".to_string()];
+
+    builder.build(input.clone()).await?;
+    builder.build(input.clone()).await?;
+    assert_eq!(builder.cache_stats().hits, 1);
+
+    broker.register_provider(tool(r#"{"required":["text","tags"]}"#));
+    builder.build(input).await?;
+    assert_eq!(builder.cache_stats().misses, 2);
+    Ok(())
+}
+
+// Recency-only strategy: last two events, no summary or retrieval
+struct RecentOnlyStrategy;
+
+#[async_trait::async_trait]
+impl ContextStrategy for RecentOnlyStrategy {
+    async fn assemble(&self, ctx: AssemblyContext) -> Result<PromptBundle> {
+        Ok(PromptBundle {
+            system: ctx.system_prompt.clone(),
+            instructions: format!("{} ({} hints)", ctx.goal(), ctx.trigger.tool_hints.len()),
+            tools_json_schema: ctx.tools_json_schema()?,
+            context_docs: Vec::new(),
+            history: ctx.history(2).await?,
+            token_breakdown: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn builder_delegates_to_configured_strategy() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    for (i, ty) in ["a", "b", "c"].iter().enumerate() {
+        mem.append_event("s1", make_event(&format!("e{i}"), ty, i as i64))
+            .await?;
+    }
+
+    let minimal = ContextBuilder::new(mem.clone(), mem.clone())
+        .with_strategy(Arc::new(MinimalStrategy))
+        .build(trigger("s1", "a"))
+        .await?;
+    let default = ContextBuilder::new(mem.clone(), mem.clone())
+        .build(trigger("s1", "a"))
+        .await?;
+    assert_eq!(minimal.context_docs, default.context_docs);
+    assert_eq!(minimal.history, default.history);
+
+    let builder = ContextBuilder::new(mem.clone(), mem)
+        .with_strategy(Arc::new(RecentOnlyStrategy))
+        .with_postprocessor(Arc::new(|b: &mut PromptBundle| {
+            b.system.push_str(" [checked]");
+            Ok(())
+        }));
+    let mut input = trigger("s1", "a");
+    input.tool_hints = vec!["x".to_string(), "X".to_string()];
+    input.system_prompt = Some("custom".to_string());
+    let bundle = builder.build(input).await?;
+    assert!(bundle.context_docs.is_empty());
+    let ids: Vec<&str> = bundle.history.iter().map(|h| h.event_id.as_str()).collect();
+    assert_eq!(ids, vec!["e1", "e2"]);
+    // Strategies see normalized hints and the resolved prompt; postprocessors still run
+    assert_eq!(bundle.instructions, "a (1 hints)");
+    assert_eq!(bundle.system, "custom [checked]");
+    Ok(())
+}
+
+#[tokio::test]
+async fn token_breakdown_attributes_every_section_and_sums_to_total() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let mut greeting = make_event("e1", "user_message", 1);
+    greeting.payload = b"what is the weather in paris today".to_vec();
+    mem.append_event("s1", greeting).await?;
+    let words = |text: &str| text.split_whitespace().count();
+    let builder = ContextBuilder::new(mem.clone(), mem)
+        .with_system_prompt("You are terse.")
+        .with_token_counter(Arc::new(words));
+
+    let mut input = trigger("s1", "weather in paris");
+    input.tool_hints = vec!["weather.get".to_string(), "web.search".to_string()];
+    let bundle = builder.build(input).await?;
+    let breakdown = bundle.token_breakdown.expect("builder fills the breakdown");
+
+    assert_eq!(breakdown.system, 3);
+    assert_eq!(breakdown.instructions, 3);
+    assert_eq!(breakdown.tools, 1, "tool schema is one JSON word");
+    assert!(breakdown.context_docs > 0, "retrieval hit counted");
+    assert!(breakdown.history > 0, "history counted");
+    let sections: usize = breakdown.sections().iter().map(|(_, n)| n).sum();
+    assert_eq!(sections, breakdown.total());
+    let independent = words(&bundle.system)
+        + words(&bundle.instructions)
+        + words(bundle.tools_json_schema.as_deref().unwrap())
+        + bundle
+            .context_docs
+            .iter()
+            .map(|d| words(&d.text))
+            .sum::<usize>()
+        + bundle
+            .history
+            .iter()
+            .map(|h| words(&h.content))
+            .sum::<usize>();
+    assert_eq!(breakdown.total(), independent);
+    assert_eq!(bundle.count_tokens(&words), breakdown);
+
+    // Default counter: ~4 chars per token
+    assert_eq!(HeuristicTokenCounter.count("abcdefghi"), 3);
+    Ok(())
+}
+
+// Counts assemblies; each takes `delay` unless the build is cancelled first
+#[derive(Default)]
+struct SlowCountingStrategy {
+    delay: std::time::Duration,
+    started: std::sync::atomic::AtomicUsize,
+    cancelled: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl ContextStrategy for SlowCountingStrategy {
+    async fn assemble(&self, ctx: AssemblyContext) -> Result<PromptBundle> {
+        use std::sync::atomic::Ordering;
+        self.started.fetch_add(1, Ordering::SeqCst);
+        if ctx
+            .or_cancelled(tokio::time::sleep(self.delay))
+            .await
+            .is_err()
+        {
+            self.cancelled.fetch_add(1, Ordering::SeqCst);
+            return Err(LoomError::Cancelled);
+        }
+        MinimalStrategy.assemble(ctx).await
+    }
+}
+
+#[tokio::test]
+async fn debounced_builds_coalesce_into_the_latest_trigger() -> Result<()> {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    let mem = InMemoryMemory::new();
+    let strategy = Arc::new(SlowCountingStrategy::default());
+    let builder = Arc::new(
+        ContextBuilder::new(mem.clone(), mem)
+            .with_strategy(strategy.clone())
+            .with_debounce(Duration::from_millis(50)),
+    );
+
+    let mut handles = Vec::new();
+    for goal in ["first", "second", "third"] {
+        let builder = Arc::clone(&builder);
+        handles.push(tokio::spawn(async move {
+            builder.build(trigger("s1", goal)).await
+        }));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    for handle in handles {
+        let bundle = handle.await.unwrap()?;
+        assert!(
+            bundle.instructions.contains("third"),
+            "{}",
+            bundle.instructions
+        );
+    }
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 1);
+
+    // Other sessions are debounced independently
+    builder.build(trigger("s2", "solo")).await?;
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn debounce_cancels_a_superseded_build_in_flight() -> Result<()> {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    let mem = InMemoryMemory::new();
+    let strategy = Arc::new(SlowCountingStrategy {
+        delay: Duration::from_millis(300),
+        ..Default::default()
+    });
+    let builder = Arc::new(
+        ContextBuilder::new(mem.clone(), mem)
+            .with_strategy(strategy.clone())
+            .with_debounce(Duration::from_millis(20)),
+    );
+
+    let early = {
+        let builder = Arc::clone(&builder);
+        tokio::spawn(async move { builder.build(trigger("s1", "old")).await })
+    };
+    // Past the window: the first build is running
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 1);
+
+    let late = builder.build(trigger("s1", "new")).await?;
+    let early = early.await.unwrap()?;
+    assert!(late.instructions.contains("new"));
+    assert!(
+        early.instructions.contains("new"),
+        "superseded caller gets the newer bundle"
+    );
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 2);
+    assert_eq!(strategy.cancelled.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn debounce_survives_the_building_caller_leaving() -> Result<()> {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    let mem = InMemoryMemory::new();
+    let strategy = Arc::new(SlowCountingStrategy {
+        delay: Duration::from_millis(100),
+        ..Default::default()
+    });
+    let builder = Arc::new(
+        ContextBuilder::new(mem.clone(), mem)
+            .with_strategy(strategy.clone())
+            .with_debounce(Duration::from_millis(20)),
+    );
+    let spawn_build = |goal: &'static str, cancel: CancellationToken| {
+        let builder = Arc::clone(&builder);
+        tokio::spawn(async move {
+            builder
+                .build_cancellable(trigger("s1", goal), &cancel)
+                .await
+        })
+    };
+
+    // The latest caller is cancelled while it builds for the group
+    let early = spawn_build("early", CancellationToken::new());
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let cancel = CancellationToken::new();
+    let late = spawn_build("late", cancel.clone());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 1);
+    cancel.cancel();
+    assert!(matches!(late.await.unwrap(), Err(LoomError::Cancelled)));
+    // The remaining caller takes over with its own trigger
+    let early = early.await.unwrap()?;
+    assert!(
+        early.instructions.contains("early"),
+        "{}",
+        early.instructions
+    );
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 2);
+
+    // Same when the building caller's future is dropped
+    let early = spawn_build("kept", CancellationToken::new());
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let late = spawn_build("dropped", CancellationToken::new());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    late.abort();
+    let early = early.await.unwrap()?;
+    assert!(
+        early.instructions.contains("kept"),
+        "{}",
+        early.instructions
+    );
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 4);
+
+    // A lone caller that leaves abandons its build
+    let cancel = CancellationToken::new();
+    let alone = spawn_build("alone", cancel.clone());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cancel.cancel();
+    assert!(matches!(alone.await.unwrap(), Err(LoomError::Cancelled)));
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(strategy.started.load(Ordering::SeqCst), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn conversation_persists_turns_and_renders_history() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    let mut convo = Conversation::new("chat");
+    convo
+        .append(mem.as_ref(), Turn::user("weather in Paris?"))
+        .await?;
+    convo
+        .append(mem.as_ref(), Turn::tool("weather.get", r#"{"temp":18}"#))
+        .await?;
+    convo.append(mem.as_ref(), Turn::assistant("18°C")).await?;
+    // Unrelated events in the session are skipped on load
+    mem.append_event("chat", make_event("noise", "sensor.tick", 1))
+        .await?;
+
+    let loaded = Conversation::load(mem.as_ref(), "chat", 10).await?;
+    assert_eq!(loaded, convo);
+    assert_eq!(loaded.turns[1].tool_name.as_deref(), Some("weather.get"));
+
+    let json = serde_json::to_string(&convo)?;
+    assert_eq!(serde_json::from_str::<Conversation>(&json)?, convo);
+
+    let roles: Vec<Role> = convo.to_history().iter().map(|h| h.role).collect();
+    assert_eq!(roles, vec![Role::User, Role::Tool, Role::Assistant]);
+    let mut bundle = PromptBundle::default();
+    convo.render_into(&mut bundle);
+    assert_eq!(bundle.history.last().unwrap().content, "18°C");
+
+    // The builder's default role mapping agrees with the typed turns
+    let built = ContextBuilder::new(mem.clone(), mem)
+        .build(trigger("chat", "weather"))
+        .await?;
+    let built_roles: Vec<Role> = built.history.iter().take(3).map(|h| h.role).collect();
+    assert_eq!(built_roles, roles);
+    Ok(())
+}
+
+/// Embeds text as counts over three concepts: vehicles, fruit, repair
+struct ConceptEmbedder;
+
+#[async_trait::async_trait]
+impl Embedder for ConceptEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut v = vec![0.0; 3];
+        for word in loom_core::context::keyword::tokenize(text) {
+            match word.as_str() {
+                "car" | "automobile" | "vehicle" => v[0] += 1.0,
+                "apple" | "banana" | "fruit" => v[1] += 1.0,
+                "repair" | "fix" | "mechanic" => v[2] += 1.0,
+                _ => {}
+            }
+        }
+        Ok(v)
+    }
+}
+
+#[tokio::test]
+async fn hybrid_retrieval_beats_vector_or_keyword_alone() {
+    let retriever = HybridRetriever::new(Arc::new(ConceptEmbedder));
+    for (id, text) in [
+        // The answer: about car repair and names the exact model
+        (
+            "target",
+            "mechanic notes for the car model zx81 with an apple sticker",
+        ),
+        // Semantically closest, but a different model
+        ("semantic", "vehicle fix guide: car mechanic basics"),
+        // Lexically closest, but about fruit
+        ("lexical", "zx81 zx81 zx81 banana price list"),
+        ("filler1", "garden tools and seeds"),
+        ("filler2", "weather report for the weekend"),
+    ] {
+        retriever.add_document(id, text).await.unwrap();
+    }
+    let query = "automobile repair zx81";
+    let top = |docs: Vec<loom_core::context::ContextDoc>| docs[0].source_id.clone().unwrap();
+
+    let vector_only = retriever.with_alpha(1.0);
+    assert_eq!(top(vector_only.search(query, 3).await.unwrap()), "semantic");
+    vector_only.set_alpha(0.0);
+    assert_eq!(top(vector_only.search(query, 3).await.unwrap()), "lexical");
+
+    vector_only.set_alpha(0.5);
+    assert_eq!(vector_only.alpha(), 0.5);
+    let hybrid = vector_only.retrieve_scored(query, 3, None).await.unwrap();
+    assert_eq!(top(hybrid.clone()), "target");
+    assert!(hybrid.windows(2).all(|w| w[0].score >= w[1].score));
+    assert!(hybrid.iter().all(|d| (0.0..=1.0).contains(&d.score)));
+    // Filters are rejected, not ignored
+    assert!(vector_only
+        .retrieve_scored(query, 3, Some(serde_json::json!({"type": "x"})))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn embedding_index_persists_and_reloads_without_re_embedding() {
+    let path = std::env::temp_dir().join(format!("loom-vectors-{}.bin", std::process::id()));
+    let index = EmbeddingIndex::new(Arc::new(ConceptEmbedder));
+    for (id, text) in [
+        ("cars", "car repair at the mechanic"),
+        ("fruit", "apple and banana"),
+        ("mixed", "fruit stand next to the car wash"),
+    ] {
+        index.add(id, text).await.unwrap();
+    }
+    index.save(&path).unwrap();
+
+    let reloaded = EmbeddingIndex::new(Arc::new(ConceptEmbedder));
+    assert_eq!(reloaded.load(&path).unwrap(), 3);
+    assert_eq!(reloaded.dimensions(), Some(3));
+    assert_eq!(
+        reloaded.search("fix my vehicle", 3).await.unwrap(),
+        index.search("fix my vehicle", 3).await.unwrap()
+    );
+
+    // Loaded vectors mix with new ones, and saving over the loaded file keeps them readable
+    reloaded.add("snack", "a banana").await.unwrap();
+    reloaded.remove("mixed");
+    reloaded.save(&path).unwrap();
+    let mut found = reloaded.search("apple", 3).await.unwrap();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        found,
+        vec![
+            ("cars".to_string(), 0.0),
+            ("fruit".to_string(), 1.0),
+            ("snack".to_string(), 1.0)
+        ]
+    );
+    let again = EmbeddingIndex::new(Arc::new(ConceptEmbedder));
+    assert_eq!(again.load(&path).unwrap(), 3);
+    let mut found_again = again.search("apple", 3).await.unwrap();
+    found_again.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(found_again, found);
+
+    // A different embedding size is rejected and nothing is loaded
+    let wider = EmbeddingIndex::new(Arc::new(ConceptEmbedder)).with_dimensions(4);
+    let err = wider.load(&path).unwrap_err();
+    assert!(err.to_string().contains("3 dimensions"));
+    assert!(wider.is_empty());
+
+    // So is a file from an unknown format version. Loaded files are never written in place.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[8..12].copy_from_slice(&99u32.to_le_bytes());
+    let bad = path.with_extension("v99");
+    std::fs::write(&bad, &bytes).unwrap();
+    let err = reloaded.load(&bad).unwrap_err();
+    assert!(err.to_string().contains("format version 99"));
+    std::fs::remove_file(&bad).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn causal_chain_follows_parent_ids_across_sessions() {
+    use loom_core::EventExt;
+    let memory = InMemoryMemory::new();
+    let intent = Event::builder("intent").id("intent-1").build();
+    let call = Event::builder("tool.call")
+        .id("call-1")
+        .parent("intent-1")
+        .build();
+    let result = Event::builder("tool.result")
+        .id("result-1")
+        .parent("call-1")
+        .build();
+    memory.append_event("user", intent).await.unwrap();
+    memory.append_event("tools", call).await.unwrap();
+    memory.append_event("tools", result).await.unwrap();
+
+    let chain: Vec<String> = memory
+        .causal_chain("result-1")
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(chain, vec!["intent-1", "call-1", "result-1"]);
+    assert_eq!(
+        memory.find_event("call-1").unwrap().parent_id(),
+        Some("intent-1")
+    );
+    assert!(memory.causal_chain("missing").is_empty());
+
+    // A cycle ends the walk instead of looping forever
+    let a = Event::builder("a").id("a").parent("b").build();
+    let b = Event::builder("b").id("b").parent("a").build();
+    memory.append_events("loop", vec![a, b]).await.unwrap();
+    assert_eq!(memory.causal_chain("a").len(), 2);
+}
+
+// ConceptEmbedder that counts single embeds and texts sent through `embed_batch`
+#[derive(Default)]
+struct CountingEmbedder {
+    embedded: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl Embedder for CountingEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedded
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        ConceptEmbedder.embed(text).await
+    }
+}
+
+#[tokio::test]
+async fn caching_embedder_reuses_vectors_and_reports_stats() -> Result<()> {
+    use std::sync::atomic::Ordering;
+
+    let inner = Arc::new(CountingEmbedder::default());
+    let cached = Arc::new(CachingEmbedder::new(inner.clone(), 2));
+
+    let first = cached.embed("fix my car").await?;
+    assert_eq!(cached.embed("fix my car").await?, first);
+    assert_eq!(inner.embedded.load(Ordering::SeqCst), 1);
+
+    // Batches only forward the misses, and results keep input order
+    let texts = vec!["fix my car".to_string(), "apple pie".to_string()];
+    let batch = cached.embed_batch(&texts).await?;
+    assert_eq!(
+        batch,
+        vec![first.clone(), ConceptEmbedder.embed("apple pie").await?]
+    );
+    assert_eq!(inner.embedded.load(Ordering::SeqCst), 2);
+
+    let stats = cached.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 2));
+
+    // Capacity 2: a third text evicts the least recently used ("fix my car")
+    cached.embed("banana").await?;
+    cached.embed("fix my car").await?;
+    assert_eq!(inner.embedded.load(Ordering::SeqCst), 4);
+
+    // Shared across tasks behind an Arc<dyn Embedder>
+    let shared: Arc<dyn Embedder> = cached.clone();
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            tokio::spawn(async move { shared.embed("fix my car").await })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap()?, first);
+    }
+    assert_eq!(inner.embedded.load(Ordering::SeqCst), 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshot_compacts_old_events_and_keeps_them_retrievable() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.set_retention(Retention {
+        keep_recent: 5,
+        max_age: None,
+    });
+    for i in 0..50 {
+        let ty = if i == 7 { "order.placed" } else { "speech" };
+        mem.append_event("s1", make_event(&format!("e{i}"), ty, 1_000 + i))
+            .await?;
+    }
+    let before = mem.retrieve("order.placed", 5, None).await?;
+    assert_eq!(before, vec!["[1007] order.placed from test".to_string()]);
+
+    let snapshot = mem.snapshot("s1", &LineSummarizer).await?.unwrap();
+    assert_eq!(snapshot.r#type, SNAPSHOT_EVENT_TYPE);
+    assert_eq!(snapshot.metadata[SNAPSHOT_EVENTS_METADATA_KEY], "45");
+
+    // One snapshot plus the five retained events, oldest first
+    let events = mem.recent_events("s1", usize::MAX).await?;
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0].id, snapshot.id);
+    assert_eq!(events[1].id, "e45");
+
+    // The old event's gist is found through the snapshot, also by type filter
+    let hits = mem.retrieve_scored("order.placed", 5, None).await?;
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0].text.contains("[1007] order.placed from test"));
+    let filtered = mem
+        .retrieve(
+            "order",
+            5,
+            Some(serde_json::json!({"type": SNAPSHOT_EVENT_TYPE})),
+        )
+        .await?;
+    assert_eq!(filtered.len(), 1);
+
+    // Nothing new outside the window: no snapshot
+    assert!(mem.snapshot("s1", &LineSummarizer).await?.is_none());
+
+    // A later snapshot folds in the earlier one
+    for i in 50..53 {
+        mem.append_event("s1", make_event(&format!("e{i}"), "speech", 1_000 + i))
+            .await?;
+    }
+    let second = mem.snapshot("s1", &LineSummarizer).await?.unwrap();
+    assert_eq!(second.metadata[SNAPSHOT_EVENTS_METADATA_KEY], "48");
+    assert_eq!(mem.recent_events("s1", usize::MAX).await?.len(), 6);
+    assert!(mem.find_event(&snapshot.id).is_none());
+    assert_eq!(mem.retrieve("order.placed", 5, None).await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshot_retention_keeps_events_younger_than_max_age() -> Result<()> {
+    let clock = MockClock::new(10_000);
+    let mem = InMemoryMemory::with_clock(clock.clone());
+    mem.set_retention(Retention {
+        keep_recent: 0,
+        max_age: Some(std::time::Duration::from_secs(5)),
+    });
+    for (i, ts) in [1_000, 2_000, 8_000, 9_000].into_iter().enumerate() {
+        mem.append_event("s1", make_event(&format!("e{i}"), "speech", ts))
+            .await?;
+    }
+    let snapshot = mem.snapshot("s1", &LineSummarizer).await?.unwrap();
+    assert_eq!(snapshot.metadata[SNAPSHOT_EVENTS_METADATA_KEY], "2");
+    let ids: Vec<_> = mem
+        .recent_events("s1", usize::MAX)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, vec![snapshot.id.clone(), "e2".into(), "e3".into()]);
+    assert!(mem.snapshot("unknown", &LineSummarizer).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn read_consistent_never_sees_half_of_a_concurrent_batch() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    // Each batch is a question and its answer; a consistent view always holds whole pairs
+    let writer = {
+        let mem = Arc::clone(&mem);
+        tokio::spawn(async move {
+            for i in 0..500 {
+                let pair = vec![
+                    make_event(&format!("q{i}"), "question", i + 1),
+                    make_event(&format!("a{i}"), "answer", i + 1),
+                ];
+                mem.append_events("s1", pair).await?;
+                tokio::task::yield_now().await;
+            }
+            Result::Ok(())
+        })
+    };
+    let readers: Vec<_> = (0..3)
+        .map(|_| {
+            let mem = Arc::clone(&mem);
+            tokio::spawn(async move {
+                let mut seen = 0;
+                for _ in 0..200 {
+                    let view = mem.read_consistent("s1");
+                    let events = view.events();
+                    assert_eq!(events.len() % 2, 0, "torn batch in snapshot");
+                    for (i, pair) in events.chunks(2).enumerate() {
+                        assert_eq!(pair[0].id, format!("q{i}"));
+                        assert_eq!(pair[1].id, format!("a{i}"));
+                    }
+                    assert!(events.len() >= seen, "snapshots went backwards");
+                    seen = events.len();
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    for reader in readers {
+        reader.await.unwrap();
+    }
+    writer.await.unwrap()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn context_builder_over_a_session_snapshot_ignores_later_appends() -> Result<()> {
+    let mem = InMemoryMemory::new();
+    mem.append_event("s1", make_event("e1", "intent", 1))
+        .await?;
+    mem.append_event("s2", make_event("x1", "intent", 1))
+        .await?;
+    let view = Arc::new(mem.read_consistent("s1"));
+    assert_eq!(view.session(), "s1");
+
+    mem.append_event("s1", make_event("e2", "intent", 2))
+        .await?;
+    let builder = ContextBuilder::new(Arc::clone(&view), Arc::clone(&view));
+    let bundle = builder.build(trigger("s1", "intent")).await?;
+    assert_eq!(bundle.history.len(), 1);
+    // Summary plus the one retrievable event of s1; s2 and the later append are out of view
+    assert!(bundle.context_docs[0]
+        .text
+        .starts_with("Recent episode summary"));
+    let retrieved = &bundle.context_docs[1..];
+    assert_eq!(retrieved.len(), 1);
+    assert_eq!(retrieved[0].source_id.as_deref(), Some("s1"));
+    assert_eq!(view.version("s1"), None);
+
+    assert!(view
+        .append_event("s1", make_event("e3", "intent", 3))
+        .await
+        .is_err());
+    assert!(mem.read_consistent("unknown").events().is_empty());
+    assert_eq!(mem.read_consistent("s1").events().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn session_iteration_replays_from_a_cursor_and_tails_new_appends() -> Result<()> {
+    use loom_core::context::memory::{SessionIterOptions, SEQ_METADATA_KEY};
+    use std::time::Duration;
+
+    let mem = InMemoryMemory::new();
+    for (i, id) in ["e0", "e1", "e2"].into_iter().enumerate() {
+        mem.append_event("s1", make_event(id, "intent", i as i64 + 1))
+            .await?;
+    }
+    mem.append_event("s2", make_event("other", "intent", 1))
+        .await?;
+
+    let ids: Vec<String> = mem
+        .iter_session("s1")
+        .map_ok(|e| e.event.id)
+        .try_collect()
+        .await?;
+    assert_eq!(ids, ["e0", "e1", "e2"]);
+
+    // Following needs a bus to be woken through
+    let follow_from_e1 = SessionIterOptions {
+        from_seq: 1,
+        follow: true,
+    };
+    let first = mem
+        .iter_session_with("s1", follow_from_e1)
+        .next()
+        .await
+        .unwrap();
+    assert!(matches!(first, Err(LoomError::Memory(_))));
+
+    let bus = Arc::new(loom_core::EventBus::new().await?);
+    mem.set_event_bus(Arc::clone(&bus));
+    let (_, mut published) = bus
+        .subscribe(
+            loom_core::context::memory::session_topic("s1"),
+            Vec::new(),
+            loom_core::proto::QoSLevel::QosBatched,
+        )
+        .await?;
+
+    let mut tail = mem.iter_session_with("s1", follow_from_e1);
+    for (seq, id) in [(1, "e1"), (2, "e2")] {
+        let next = tail.next().await.unwrap()?;
+        assert_eq!((next.seq, next.event.id.as_str()), (seq, id));
+    }
+
+    let writer = Arc::clone(&mem);
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        writer
+            .append_event("s2", make_event("other-2", "intent", 2))
+            .await?;
+        writer
+            .append_event("s1", make_event("e3", "intent", 4))
+            .await?;
+        writer
+            .append_events(
+                "s1",
+                vec![make_event("e4", "intent", 5), make_event("e5", "intent", 6)],
+            )
+            .await
+    });
+    for (seq, id) in [(3, "e3"), (4, "e4"), (5, "e5")] {
+        let next = tokio::time::timeout(Duration::from_secs(2), tail.next())
+            .await
+            .expect("appended event reaches the tailing stream")
+            .unwrap()?;
+        assert_eq!((next.seq, next.event.id.as_str()), (seq, id));
+    }
+
+    // Other bus subscribers see the appends with their sequence numbers
+    let event = published.recv().await.unwrap();
+    assert_eq!(event.id, "e3");
+    assert_eq!(event.metadata[SEQ_METADATA_KEY], "3");
+
+    // The tail ends once the bus shuts down
+    bus.shutdown().await?;
+    assert!(tail.next().await.is_none());
+    Ok(())
+}
+
+#[cfg(feature = "tiktoken")]
+#[tokio::test]
+async fn tiktoken_counter_counts_real_bpe_tokens() -> Result<()> {
+    use loom_core::context::TiktokenCounter;
+
+    fn send_sync<T: Send + Sync>(_: &T) {}
+
+    let text = "The quick brown fox jumps over the lazy dog.";
+    let counter = TiktokenCounter::for_model("gpt-4")?;
+    send_sync(&counter);
+    assert_eq!(counter.count(text), 10);
+    assert_eq!(HeuristicTokenCounter.count(text), 11);
+    // Loaded once, so a second counter is cheap and agrees
+    assert_eq!(TiktokenCounter::cl100k()?.count(text), 10);
+    assert!(TiktokenCounter::for_model("no-such-model").is_err());
+
+    // Selected per builder; the breakdown then uses the model's encoding
+    let mem = InMemoryMemory::new();
+    let counter = TiktokenCounter::o200k()?;
+    let builder =
+        ContextBuilder::new(mem.clone(), mem).with_token_counter(Arc::new(counter.clone()));
+    let bundle = builder.build(trigger("s1", "weather in paris")).await?;
+    let breakdown = bundle.token_breakdown.expect("builder fills the breakdown");
+    assert_eq!(breakdown, bundle.count_tokens(&counter));
+    Ok(())
+}
@@ -15,6 +15,8 @@ impl CapabilityProvider for EchoProvider {
             version: "v1".into(),
             provider: loom_core::proto::ProviderKind::ProviderNative as i32,
             metadata: std::collections::HashMap::new(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -24,6 +26,7 @@ impl CapabilityProvider for EchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
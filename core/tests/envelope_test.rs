@@ -61,7 +61,7 @@ fn envelope_apply_to_action_call_sets_headers_and_correlation() {
         payload: vec![],
         headers: HashMap::new(),
         correlation_id: "".into(),
-        qos: QoSLevel::QosRealtime as i32,
+        qos: Some(QoSLevel::QosRealtime as i32),
         timeout_ms: 0,
     };
     env.apply_to_action_call(&mut call);
@@ -0,0 +1,41 @@
+use loom_core::LoomError;
+use std::time::Duration;
+
+#[test]
+fn serde_errors_convert_to_serialization_variant() {
+    let err: LoomError = serde_json::from_str::<serde_json::Value>("{oops")
+        .unwrap_err()
+        .into();
+    assert!(matches!(err, LoomError::Serialization(_)));
+    assert!(err.to_string().starts_with("Serialization error:"));
+}
+
+#[tokio::test]
+async fn elapsed_deadline_converts_to_timeout_variant() {
+    let elapsed = tokio::time::timeout(Duration::from_millis(1), std::future::pending::<()>())
+        .await
+        .unwrap_err();
+    let err: LoomError = elapsed.into();
+    assert!(matches!(err, LoomError::Timeout(_)));
+    assert!(err.to_string().starts_with("Timed out:"));
+}
+
+#[test]
+fn kind_variants_display_and_act_as_std_errors() {
+    let cases = [
+        (LoomError::Cancelled, "Operation cancelled"),
+        (
+            LoomError::Memory("store closed".into()),
+            "Memory error: store closed",
+        ),
+        (
+            LoomError::Timeout("retrieval".into()),
+            "Timed out: retrieval",
+        ),
+    ];
+    for (err, expected) in cases {
+        assert_eq!(err.to_string(), expected);
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(err);
+        assert_eq!(boxed.to_string(), expected);
+    }
+}
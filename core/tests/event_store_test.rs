@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use loom_core::event::EventBus;
+use loom_core::event_store::{
+    migrate_event, EventStore, InMemoryEventStore, JsonlEventStore, PersistFailure, PersistMode,
+    EVENT_SCHEMA_VERSION,
+};
+use loom_core::proto::{Event, QoSLevel};
+use loom_core::{LoomError, Result};
+use serde_json::json;
+use std::sync::Arc;
+
+fn make_event(id: &str, payload: &[u8]) -> Event {
+    Event {
+        id: id.to_string(),
+        r#type: "unit".to_string(),
+        timestamp_ms: 42,
+        source: "test".to_string(),
+        metadata: [("k".to_string(), "v".to_string())].into_iter().collect(),
+        payload: payload.to_vec(),
+        confidence: 0.5,
+        tags: vec!["t".to_string()],
+        priority: 3,
+    }
+}
+
+struct FailingStore;
+
+#[async_trait]
+impl EventStore for FailingStore {
+    async fn persist(&self, _topic: &str, _event: &Event) -> Result<()> {
+        Err(LoomError::StorageError("disk full".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn sync_store_sees_every_published_event_in_order() -> Result<()> {
+    let store = Arc::new(InMemoryEventStore::new());
+    let bus = EventBus::new().await?.with_event_store(
+        store.clone(),
+        PersistMode::Sync,
+        PersistFailure::Error,
+    );
+
+    // Persisted even without subscribers
+    bus.publish("a", make_event("e1", b"one")).await?;
+    bus.publish("b", make_event("e2", b"two")).await?;
+    bus.publish("a", make_event("e3", b"three")).await?;
+
+    let ids: Vec<(String, String)> = store.events().into_iter().map(|(t, e)| (t, e.id)).collect();
+    assert_eq!(
+        ids,
+        vec![
+            ("a".to_string(), "e1".to_string()),
+            ("b".to_string(), "e2".to_string()),
+            ("a".to_string(), "e3".to_string()),
+        ]
+    );
+    assert_eq!(store.events_for("a").len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn jsonl_store_round_trips_events_written_asynchronously() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("loom-events-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let bus = EventBus::new().await?.with_event_store(
+        Arc::new(JsonlEventStore::new(&path)),
+        PersistMode::Async,
+        PersistFailure::BestEffort,
+    );
+
+    let text = make_event("e1", br#"{"x":1}"#);
+    let binary = make_event("e2", &[0, 159, 255]);
+    bus.publish("t", text.clone()).await?;
+    bus.publish("t", binary.clone()).await?;
+    bus.flush_event_store().await;
+
+    let loaded = JsonlEventStore::load(&path).await?;
+    assert_eq!(
+        loaded,
+        vec![("t".to_string(), text), ("t".to_string(), binary)]
+    );
+    assert_eq!(bus.persist_failures(), 0);
+
+    // A torn trailing line from a crash mid-write is skipped on recovery
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, br#"{"topic":"t","id":"#))?;
+    assert_eq!(JsonlEventStore::load(&path).await?.len(), 2);
+
+    // A store reopened after the crash cuts the fragment off before appending
+    let reopened = JsonlEventStore::new(&path);
+    let after = make_event("e3", b"after");
+    reopened.persist("t", &after).await?;
+    let loaded = JsonlEventStore::load(&path).await?;
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded[2], ("t".to_string(), after));
+
+    // A complete last record that only lost its newline is kept
+    let text = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, text.trim_end())?;
+    let reopened = JsonlEventStore::new(&path);
+    reopened.persist("t", &make_event("e4", b"")).await?;
+    assert_eq!(JsonlEventStore::load(&path).await?.len(), 4);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn persist_failure_policy_decides_whether_publish_fails() -> Result<()> {
+    let strict = EventBus::new().await?.with_event_store(
+        Arc::new(FailingStore),
+        PersistMode::Sync,
+        PersistFailure::Error,
+    );
+    let (_sub, mut rx) = strict
+        .subscribe("t".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+    let err = strict
+        .publish("t", make_event("e1", b""))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LoomError::StorageError(_)));
+    // Nothing is delivered that was not stored
+    assert!(rx.try_recv().is_err());
+    assert_eq!(strict.persist_failures(), 1);
+
+    let lenient = EventBus::new().await?.with_event_store(
+        Arc::new(FailingStore),
+        PersistMode::Sync,
+        PersistFailure::BestEffort,
+    );
+    let (_sub, mut rx) = lenient
+        .subscribe("t".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+    assert_eq!(
+        lenient.publish("t", make_event("e2", b"")).await?.delivered,
+        1
+    );
+    assert_eq!(rx.recv().await.map(|e| e.id), Some("e2".to_string()));
+    assert_eq!(lenient.persist_failures(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn v1_records_migrate_to_the_current_event() -> Result<()> {
+    // Layout written before records carried a schema_version
+    let v1 = json!({
+        "topic": "t",
+        "id": "e1",
+        "type": "unit",
+        "timestamp_ms": 42,
+        "source": "test",
+        "metadata": {"k": "v"},
+        "payload": "one",
+        "confidence": 0.5,
+        "tags": ["t"],
+        "priority": 3
+    });
+    assert_eq!(migrate_event(v1.clone(), 1)?, make_event("e1", b"one"));
+
+    // v1 kept binary payloads in `payload_hex`; v2 folds them into `payload`
+    let mut v1_binary = v1.clone();
+    let fields = v1_binary.as_object_mut().unwrap();
+    fields.remove("payload");
+    fields.insert("payload_hex".to_string(), json!("00ff10"));
+    assert_eq!(
+        migrate_event(v1_binary.clone(), 1)?,
+        make_event("e1", &[0x00, 0xff, 0x10])
+    );
+
+    // Versions this build does not know are rejected, not read lossily
+    let err = migrate_event(v1.clone(), EVENT_SCHEMA_VERSION + 1).unwrap_err();
+    assert!(matches!(err, LoomError::StorageError(_)));
+
+    // load upgrades old lines in a log that mixes versions
+    let path = std::env::temp_dir().join(format!("loom-events-v1-{}.jsonl", std::process::id()));
+    std::fs::write(&path, format!("{v1}\n{v1_binary}\n"))?;
+    let store = JsonlEventStore::new(&path);
+    store.persist("t", &make_event("e2", b"two")).await?;
+    store.persist("t", &make_event("e3", &[0xfe])).await?;
+    let written = std::fs::read_to_string(&path)?;
+    let current: serde_json::Value = serde_json::from_str(written.lines().nth(2).unwrap())?;
+    assert_eq!(current["schema_version"], EVENT_SCHEMA_VERSION);
+    let binary: serde_json::Value = serde_json::from_str(written.lines().nth(3).unwrap())?;
+    assert_eq!(binary["payload"], "fe");
+    assert_eq!(binary["payload_encoding"], "hex");
+    assert!(binary.get("payload_hex").is_none());
+
+    let loaded = JsonlEventStore::load(&path).await?;
+    assert_eq!(
+        loaded,
+        vec![
+            ("t".to_string(), make_event("e1", b"one")),
+            ("t".to_string(), make_event("e1", &[0x00, 0xff, 0x10])),
+            ("t".to_string(), make_event("e2", b"two")),
+            ("t".to_string(), make_event("e3", &[0xfe])),
+        ]
+    );
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
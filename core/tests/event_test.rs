@@ -1,6 +1,8 @@
-use loom_core::event::EventBus;
+use loom_core::event::{EventBus, PublishReceipt};
 use loom_core::proto::{Event, QoSLevel};
-use loom_core::Result;
+use loom_core::{InMemoryEventStore, LoomError, PersistFailure, PersistMode, Result};
+use std::sync::Arc;
+use std::time::Duration;
 
 // Helper to create a test event
 fn make_event(id: &str, event_type: &str) -> Event {
@@ -161,8 +163,13 @@ async fn qos_batched_queues_without_drop_within_capacity() -> Result<()> {
 async fn publish_to_empty_topic_returns_zero() -> Result<()> {
     let bus = EventBus::new().await?;
     let evt = make_event("orphan", "unit");
-    let delivered = bus.publish("topic.empty", evt).await?;
-    assert_eq!(delivered, 0, "no subscribers should mean zero delivery");
+    let receipt = bus.publish("topic.empty", evt).await?;
+    assert_eq!(
+        receipt,
+        PublishReceipt::default(),
+        "no subscribers should mean zero delivery"
+    );
+    assert!(receipt.is_unheard());
     Ok(())
 }
 
@@ -177,8 +184,16 @@ async fn multiple_subscribers_on_same_topic() -> Result<()> {
         .await?;
 
     let evt = make_event("multi", "unit");
-    let delivered = bus.publish("topic.multi", evt).await?;
-    assert_eq!(delivered, 2, "both subscribers should receive");
+    let receipt = bus.publish("topic.multi", evt).await?;
+    assert_eq!(
+        receipt,
+        PublishReceipt {
+            matched_subscribers: 2,
+            delivered: 2,
+            dropped: 0,
+        },
+        "both subscribers should receive"
+    );
 
     let r1 = rx1.recv().await.expect("rx1 closed");
     let r2 = rx2.recv().await.expect("rx2 closed");
@@ -241,14 +256,169 @@ async fn shutdown_clears_subscriptions() -> Result<()> {
         .await?;
 
     bus.shutdown().await?;
+    assert!(bus.is_shut_down());
 
-    // After shutdown, publish should not crash but also no delivery
+    // After shutdown, publishes and subscribes are rejected
     let evt = make_event("post_shut", "unit");
-    let delivered = bus.publish("topic.shut", evt).await?;
-    assert_eq!(delivered, 0, "subscriptions cleared after shutdown");
+    assert!(matches!(
+        bus.publish("topic.shut", evt).await,
+        Err(LoomError::EventBusError(_))
+    ));
+    assert!(bus
+        .subscribe("topic.shut".to_string(), vec![], QoSLevel::QosBatched)
+        .await
+        .is_err());
 
     // Channel should be closed or empty
     let r = tokio::time::timeout(std::time::Duration::from_millis(200), rx.recv()).await;
     assert!(r.is_err() || r.unwrap().is_none());
     Ok(())
 }
+
+#[tokio::test]
+async fn shutdown_delivers_events_published_just_before_it() -> Result<()> {
+    let store = Arc::new(InMemoryEventStore::new());
+    let bus = Arc::new(EventBus::new().await?.with_event_store(
+        store.clone(),
+        PersistMode::Async,
+        PersistFailure::BestEffort,
+    ));
+    let (_sub_id, mut rx) = bus
+        .subscribe("topic.drain".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+
+    // Fill the batched queue (1024), so the last publish is still waiting for space
+    let total = 1_030;
+    let publisher = {
+        let bus = Arc::clone(&bus);
+        tokio::spawn(async move {
+            for i in 0..total {
+                bus.publish("topic.drain", make_event(&format!("d{i}"), "unit"))
+                    .await?;
+            }
+            Result::Ok(())
+        })
+    };
+    while store.len() < 1_025 {
+        tokio::task::yield_now().await;
+    }
+    let shutdown = {
+        let bus = Arc::clone(&bus);
+        tokio::spawn(async move { bus.shutdown_with_grace(Duration::from_secs(5)).await })
+    };
+
+    // The subscriber sees every event in order, then a clean end of stream
+    let mut received = Vec::new();
+    while let Some(event) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("stream ends after shutdown")
+    {
+        received.push(event.id);
+    }
+    assert_eq!(received.len(), total);
+    assert_eq!(received.last().map(String::as_str), Some("d1029"));
+    publisher.await.unwrap()?;
+    shutdown.await.unwrap()?;
+    assert_eq!(
+        store.len(),
+        total,
+        "async store flushed before shutdown returned"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn shutdown_closes_channels_when_grace_runs_out() -> Result<()> {
+    let bus = Arc::new(EventBus::new().await?);
+    let (_sub_id, mut rx) = bus
+        .subscribe("topic.stuck".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+    let publisher = {
+        let bus = Arc::clone(&bus);
+        tokio::spawn(async move {
+            for i in 0..1_025 {
+                bus.publish("topic.stuck", make_event(&format!("s{i}"), "unit"))
+                    .await?;
+            }
+            Result::Ok(())
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Nobody reads, so the last publish never gets queue space
+    let res = bus.shutdown_with_grace(Duration::from_millis(50)).await;
+    assert!(matches!(res, Err(LoomError::Timeout(_))));
+    // Reading unblocks the stuck publish; the stream then ends
+    let mut count = 0;
+    while tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("stream ends")
+        .is_some()
+    {
+        count += 1;
+    }
+    assert_eq!(count, 1_025);
+    publisher.await.unwrap()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn publish_receipt_with_only_filtered_subscribers_is_unheard() -> Result<()> {
+    let bus = EventBus::new().await?;
+    let (_sub_id, _rx) = bus
+        .subscribe(
+            "topic.nobody".to_string(),
+            vec!["other".to_string()],
+            QoSLevel::QosBatched,
+        )
+        .await?;
+    let receipt = bus
+        .publish("topic.nobody", make_event("lonely", "unit"))
+        .await?;
+    assert_eq!(receipt.matched_subscribers, 0);
+    assert!(receipt.is_unheard());
+    Ok(())
+}
+
+#[tokio::test]
+async fn publish_receipt_counts_matched_delivered_and_dropped() -> Result<()> {
+    let bus = EventBus::new().await?;
+    let (_a, mut rx_a) = bus
+        .subscribe("topic.receipt".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+    let (_b, mut rx_b) = bus
+        .subscribe(
+            "topic.receipt".to_string(),
+            vec!["unit".to_string()],
+            QoSLevel::QosRealtime,
+        )
+        .await?;
+    let (_filtered, _rx_filtered) = bus
+        .subscribe(
+            "topic.receipt".to_string(),
+            vec!["other".to_string()],
+            QoSLevel::QosBatched,
+        )
+        .await?;
+    // A subscriber that went away without unsubscribing misses the event
+    let (_gone, rx_gone) = bus
+        .subscribe("topic.receipt".to_string(), vec![], QoSLevel::QosBatched)
+        .await?;
+    drop(rx_gone);
+
+    let receipt = bus
+        .publish("topic.receipt", make_event("r1", "unit"))
+        .await?;
+    assert_eq!(
+        receipt,
+        PublishReceipt {
+            matched_subscribers: 3,
+            delivered: 2,
+            dropped: 1,
+        }
+    );
+    assert!(!receipt.is_unheard());
+    assert_eq!(rx_a.recv().await.expect("channel closed").id, "r1");
+    assert_eq!(rx_b.recv().await.expect("channel closed").id, "r1");
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use loom_core::action_broker::{ActionBroker, CapabilityProvider};
+use loom_core::ids::{
+    new_uuid, set_default_id_generator, uuid_v7, CounterIdGenerator, IdGenerator, UlidGenerator,
+    UuidVersion,
+};
+use loom_core::proto::{
+    ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, Event, ProviderKind,
+};
+use loom_core::{ActionCallExt, ActionResultExt, EventExt, MockClock, Result};
+use std::sync::Arc;
+
+fn version_nibble(id: &str) -> char {
+    id.chars().nth(14).unwrap()
+}
+
+#[test]
+fn builders_generate_distinct_sortable_v7_ids() -> Result<()> {
+    let ids: Vec<String> = (0..200)
+        .map(|i| {
+            ActionCall::builder("test.echo")
+                .id_version(UuidVersion::V7)
+                .payload(vec![i as u8])
+                .build()
+                .unwrap()
+                .id
+        })
+        .collect();
+
+    let mut sorted = ids.clone();
+    sorted.sort();
+    assert_eq!(sorted, ids, "v7 ids sort in creation order");
+    sorted.dedup();
+    assert_eq!(sorted.len(), ids.len(), "ids are unique");
+    assert!(ids.iter().all(|id| version_nibble(id) == '7'));
+
+    let first = Event::builder("intent").id_version(UuidVersion::V7).build();
+    let second = ActionResult::builder(ActionStatus::ActionOk)
+        .id_version(UuidVersion::V7)
+        .build();
+    assert!(first.id < second.id);
+    Ok(())
+}
+
+#[test]
+fn v7_is_default_and_explicit_ids_are_kept() -> Result<()> {
+    let a = ActionCall::builder("x").build()?;
+    let b = ActionCall::builder("x").build()?;
+    assert_ne!(a.id, b.id);
+    assert_eq!(version_nibble(&a.id), '7');
+    assert_eq!(version_nibble(&new_uuid(UuidVersion::V4)), '4');
+    assert_eq!(UuidVersion::default(), UuidVersion::V7);
+
+    // Builders without a generator of their own go through the process-wide default
+    set_default_id_generator(Arc::new(CounterIdGenerator::new("g-")));
+    let call = ActionCall::builder("x").build()?;
+    let evt = Event::builder("intent").build();
+    let res = ActionResult::builder(ActionStatus::ActionOk).build();
+    assert_eq!(
+        (call.id, evt.id, res.id),
+        ("g-1".into(), "g-2".into(), "g-3".into())
+    );
+    let explicit = ActionCall::builder("x")
+        .id_version(UuidVersion::V4)
+        .build()?;
+    assert_eq!(version_nibble(&explicit.id), '4');
+    set_default_id_generator(Arc::new(UuidVersion::V7));
+
+    let evt = Event::builder("intent").id("evt-1").source("mic").build();
+    assert_eq!(evt.id, "evt-1");
+    assert_eq!((evt.confidence, evt.priority), (1.0, 50));
+    assert!(evt.timestamp_ms > 0);
+    // An injected clock stamps the event instead of the wall clock
+    let clock = MockClock::new(1_000);
+    assert_eq!(
+        Event::builder("intent")
+            .clock(clock.as_ref())
+            .build()
+            .timestamp_ms,
+        1_000
+    );
+
+    let res = ActionResult::builder(ActionStatus::ActionError)
+        .id("call-1")
+        .error("BAD_INPUT", "missing text")
+        .detail("field", "text")
+        .build();
+    assert_eq!(res.id, "call-1");
+    let error = res.error.unwrap();
+    assert_eq!(error.code, "BAD_INPUT");
+    assert_eq!(error.details["field"], "text");
+    Ok(())
+}
+
+#[test]
+fn v7_ids_are_canonical_uuids() {
+    let id = uuid_v7();
+    let parts: Vec<&str> = id.split('-').collect();
+    assert_eq!(
+        parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+        vec![8, 4, 4, 4, 12]
+    );
+    // RFC 4122 variant bits
+    assert!(matches!(
+        parts[3].chars().next(),
+        Some('8' | '9' | 'a' | 'b')
+    ));
+}
+
+struct EchoIdProvider;
+
+#[async_trait]
+impl CapabilityProvider for EchoIdProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.echo".into(),
+            version: "1.0.0".into(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult::builder(ActionStatus::ActionOk)
+            .id(call.id)
+            .build())
+    }
+}
+
+#[tokio::test]
+async fn custom_generator_is_used_for_auto_assigned_ids() -> Result<()> {
+    let ids: Arc<dyn IdGenerator> = Arc::new(CounterIdGenerator::new("t-"));
+    let call = ActionCall::builder("test.echo")
+        .id_generator(ids.clone())
+        .build()?;
+    assert_eq!(call.id, "t-1");
+    let evt = Event::builder("intent").id_generator(ids.clone()).build();
+    assert_eq!(evt.id, "t-2");
+
+    // The broker fills in ids of calls that arrive without one
+    let broker = ActionBroker::new().with_id_generator(ids.clone());
+    broker.register_provider(Arc::new(EchoIdProvider));
+    let mut anonymous = call.clone();
+    anonymous.id.clear();
+    let res = broker.invoke(anonymous).await?;
+    assert_eq!(res.id, "t-3");
+    assert_eq!(broker.id_generator().new_id(), "t-4");
+    Ok(())
+}
+
+#[test]
+fn ulids_are_crockford_and_monotonic() {
+    let gen = UlidGenerator::new();
+    let ids: Vec<String> = (0..500).map(|_| gen.new_id()).collect();
+    assert!(ids.iter().all(|id| id.len() == 26));
+    assert!(ids.iter().all(|id| id
+        .chars()
+        .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+        && !id.contains(['I', 'L', 'O', 'U'])));
+    let mut sorted = ids.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted, ids, "ULIDs are unique and sort in creation order");
+}
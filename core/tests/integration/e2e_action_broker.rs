@@ -10,7 +10,7 @@ use super::*;
 ///
 /// Setup:
 /// - Mock slow provider with 2-second delay
-/// - ActionBroker default timeout: 30 seconds
+/// - ActionBroker default timeout for the provider's batched QoS: 10 seconds
 ///
 /// Flow:
 /// 1. Agent invokes "slow_process" capability
@@ -86,7 +86,7 @@ async fn test_action_timeout_handling() {
         .await
         .unwrap();
 
-    // Publish event (ActionBroker will use default 30s timeout)
+    // Publish event (ActionBroker will use the batched default 10s timeout)
     let event = Event {
         id: "evt_slow".to_string(),
         r#type: "trigger_slow".to_string(),
@@ -108,7 +108,7 @@ async fn test_action_timeout_handling() {
         .expect("Channel should not be closed");
 
     assert_eq!(result.r#type, "action_result");
-    // The slow action should complete successfully (not timeout with default 30s)
+    // The slow action should complete successfully (not timeout with default 10s)
     assert_eq!(
         result.metadata.get("status").map(|s| s.as_str()),
         Some("ok")
@@ -142,7 +142,7 @@ async fn test_idempotent_action_invocation() {
         headers: HashMap::new(),
         timeout_ms: 5000,
         correlation_id: "test".to_string(),
-        qos: QoSLevel::QosBatched as i32,
+        qos: Some(QoSLevel::QosBatched as i32),
     };
 
     // First invocation
@@ -161,3 +161,117 @@ async fn test_idempotent_action_invocation() {
 
     println!("✓ Idempotent action test passed: Cache hit on duplicate call ID");
 }
+
+/// Test: Binary Output Through The Event Bus
+///
+/// Validates that a non-UTF-8 output (synthesized audio) reaches `action_result`
+/// subscribers byte-for-byte, with its content type in the event metadata.
+#[tokio::test]
+async fn test_binary_output_keeps_bytes_and_content_type() {
+    use loom_core::proto::OutputKind;
+    use loom_core::ActionResultExt;
+
+    const WAV: [u8; 8] = [0x52, 0x49, 0x46, 0x46, 0xff, 0xfe, 0x00, 0x80];
+
+    struct MockAudioProvider;
+
+    #[async_trait::async_trait]
+    impl CapabilityProvider for MockAudioProvider {
+        fn descriptor(&self) -> CapabilityDescriptor {
+            CapabilityDescriptor {
+                name: "tts.synthesize".to_string(),
+                version: "1.0.0".to_string(),
+                provider: ProviderKind::ProviderNative as i32,
+                metadata: HashMap::new(),
+                depends_on: Vec::new(),
+                default_qos: None,
+            }
+        }
+
+        async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+            Ok(ActionResult::builder(ActionStatus::ActionOk)
+                .id(call.id)
+                .output(WAV.to_vec())
+                .content_type("audio/wav")
+                .build())
+        }
+    }
+
+    struct SpeakBehavior;
+
+    #[async_trait::async_trait]
+    impl loom_core::agent::AgentBehavior for SpeakBehavior {
+        async fn on_init(&mut self, _config: &AgentConfig) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_event(
+            &mut self,
+            _event: Event,
+            _state: &mut AgentState,
+        ) -> Result<Vec<Action>> {
+            Ok(vec![Action {
+                action_type: "tts.synthesize".to_string(),
+                parameters: HashMap::new(),
+                payload: b"hello".to_vec(),
+                priority: 50,
+            }])
+        }
+
+        async fn on_shutdown(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    let event_bus = Arc::new(EventBus::new().await.unwrap());
+    let action_broker = Arc::new(ActionBroker::new());
+    action_broker.register_provider(Arc::new(MockAudioProvider));
+    let agent_runtime = AgentRuntime::new(
+        Arc::clone(&event_bus),
+        Arc::clone(&action_broker),
+        ModelRouter::new().await.unwrap(),
+    )
+    .await
+    .unwrap();
+    let config = AgentConfig {
+        agent_id: "speaker".to_string(),
+        agent_type: "test".to_string(),
+        subscribed_topics: vec!["speak_topic".to_string()],
+        capabilities: vec!["tts.synthesize".to_string()],
+        parameters: HashMap::new(),
+    };
+    agent_runtime
+        .create_agent(config, Box::new(SpeakBehavior))
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let (_sub, mut result_rx) = event_bus
+        .subscribe(
+            "agent.speaker".to_string(),
+            vec!["action_result".to_string()],
+            QoSLevel::QosBatched,
+        )
+        .await
+        .unwrap();
+    let trigger = Event {
+        id: "evt_speak".to_string(),
+        r#type: "speak".to_string(),
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        source: "test".to_string(),
+        metadata: HashMap::new(),
+        payload: vec![],
+        confidence: 1.0,
+        tags: vec![],
+        priority: 50,
+    };
+    event_bus.publish("speak_topic", trigger).await.unwrap();
+
+    let result = tokio::time::timeout(Duration::from_secs(2), result_rx.recv())
+        .await
+        .expect("Should receive result")
+        .expect("Channel should not be closed");
+    assert_eq!(result.payload, WAV);
+    assert_eq!(result.content_type(), Some("audio/wav"));
+    assert_eq!(result.payload_kind(), OutputKind::Binary);
+}
@@ -77,11 +77,14 @@ async fn test_e2e_event_to_action_to_result() {
         priority: 50,
     };
 
-    let delivered = event_bus
+    let receipt = event_bus
         .publish("test_topic", test_event.clone())
         .await
         .unwrap();
-    assert!(delivered > 0, "Event should be delivered to subscribers");
+    assert!(
+        receipt.delivered > 0,
+        "Event should be delivered to subscribers"
+    );
 
     // 5. Wait for and verify routing_decision event
     let mut routing_decision_received = false;
@@ -95,6 +98,7 @@ async fn test_e2e_event_to_action_to_result() {
                     assert_eq!(evt.source, "agent.test_agent");
                     assert!(evt.metadata.contains_key("route"));
                     assert!(evt.metadata.contains_key("reason"));
+                    assert_eq!(evt.metadata.get("parent_id").map(|s| s.as_str()), Some("evt_test_001"));
                     println!("✓ Routing decision: {:?}", evt.metadata.get("route"));
                 }
 
@@ -103,6 +107,8 @@ async fn test_e2e_event_to_action_to_result() {
                     assert_eq!(evt.source, "agent.test_agent");
                     assert_eq!(evt.metadata.get("action_type").map(|s| s.as_str()), Some("echo"));
                     assert_eq!(evt.metadata.get("status").map(|s| s.as_str()), Some("ok"));
+                    // The result points back at the triggering event
+                    assert_eq!(evt.metadata.get("parent_id").map(|s| s.as_str()), Some("evt_test_001"));
 
                     let output = String::from_utf8_lossy(&evt.payload);
                     assert!(output.contains("ECHO:"), "Output should contain echo prefix");
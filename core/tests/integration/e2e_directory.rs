@@ -19,6 +19,8 @@ impl CapabilityProvider for EchoProvider {
             version: self.version.clone(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
     async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
@@ -27,6 +29,7 @@ impl CapabilityProvider for EchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
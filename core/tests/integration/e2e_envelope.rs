@@ -145,6 +145,8 @@ impl CapabilityProvider for HeaderCaptureProvider {
             version: "1.0.0".into(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -156,6 +158,7 @@ impl CapabilityProvider for HeaderCaptureProvider {
             status: ActionStatus::ActionOk as i32,
             output: vec![],
             error: None,
+            metadata: Default::default(),
         })
     }
 }
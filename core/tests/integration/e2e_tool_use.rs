@@ -45,6 +45,8 @@ impl CapabilityProvider for MockWebSearchProvider {
             version: "0.1.0".into(),
             provider: loom_core::proto::ProviderKind::ProviderNative as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -67,6 +69,7 @@ impl CapabilityProvider for MockWebSearchProvider {
             status: ActionStatus::ActionOk as i32,
             output: serde_json::to_vec(&results)?,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -97,6 +100,8 @@ impl CapabilityProvider for MockWeatherProvider {
             version: "0.1.0".into(),
             provider: loom_core::proto::ProviderKind::ProviderNative as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -119,6 +124,7 @@ impl CapabilityProvider for MockWeatherProvider {
             status: ActionStatus::ActionOk as i32,
             output: serde_json::to_vec(&weather)?,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -141,6 +147,8 @@ impl CapabilityProvider for FailingProvider {
             version: "0.1.0".into(),
             provider: loom_core::proto::ProviderKind::ProviderNative as i32,
             metadata,
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -154,6 +162,7 @@ impl CapabilityProvider for FailingProvider {
                 message: "This tool always fails for testing".into(),
                 details: Default::default(),
             }),
+            metadata: Default::default(),
         })
     }
 }
@@ -199,7 +208,7 @@ async fn test_broker_invokes_web_search() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 5000,
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
@@ -228,7 +237,7 @@ async fn test_broker_invokes_weather_get() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 5000,
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
@@ -255,7 +264,7 @@ async fn test_broker_handles_failing_tool() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 1000,
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
@@ -281,7 +290,7 @@ async fn test_multiple_tools_sequential_invocation() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 5000,
         correlation_id: "session_123".into(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let weather_call = ActionCall {
@@ -292,7 +301,7 @@ async fn test_multiple_tools_sequential_invocation() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 5000,
         correlation_id: "session_123".into(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let search_result = broker.invoke(search_call).await?;
@@ -323,7 +332,7 @@ async fn test_tool_timeout_handling() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 1, // 1ms timeout - very tight but provider is fast
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
@@ -359,6 +368,7 @@ fn test_prompt_bundle_for_refine() {
         tools_json_schema: None,
         context_docs: vec![],
         history: vec![],
+        token_breakdown: None,
     };
 
     assert!(!bundle.system.is_empty());
@@ -383,7 +393,7 @@ async fn test_real_web_search_provider() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 15000,
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
@@ -417,7 +427,7 @@ async fn test_real_weather_provider() -> Result<()> {
         headers: Default::default(),
         timeout_ms: 15000,
         correlation_id: String::new(),
-        qos: loom_core::proto::QoSLevel::QosBatched as i32,
+        qos: Some(loom_core::proto::QoSLevel::QosBatched as i32),
     };
 
     let result = broker.invoke(call).await?;
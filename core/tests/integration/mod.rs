@@ -57,6 +57,8 @@ impl CapabilityProvider for MockEchoProvider {
                 );
                 m
             },
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -70,6 +72,7 @@ impl CapabilityProvider for MockEchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: output.into_bytes(),
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -87,6 +90,9 @@ impl CapabilityProvider for MockSlowProvider {
             version: "1.0.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: HashMap::new(),
+            depends_on: Vec::new(),
+            // Slow work runs as batched, under its 10 s default timeout
+            default_qos: Some(QoSLevel::QosBatched as i32),
         }
     }
 
@@ -98,6 +104,7 @@ impl CapabilityProvider for MockSlowProvider {
             status: ActionStatus::ActionOk as i32,
             output: b"SLOW_DONE".to_vec(),
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -113,6 +120,8 @@ impl CapabilityProvider for MockFailingProvider {
             version: "1.0.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: HashMap::new(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -1,5 +1,8 @@
-use loom_core::context::{PromptBundle, TokenBudget};
-use loom_core::llm::{LlmClient, LlmClientConfig};
+use loom_core::context::{HistoryEntry, PromptBundle, Role, TokenBudget};
+use loom_core::llm::{
+    ChatStreamDecoder, LlmClient, LlmClientConfig, StreamDelta, TokenStreamAssembler,
+    TokenStreamPublisher, LLM_COMPLETE_EVENT, LLM_TOKEN_EVENT,
+};
 use loom_core::Result;
 use serde_json::json;
 use serial_test::serial;
@@ -64,6 +67,15 @@ fn client_creation_succeeds() -> Result<()> {
     Ok(())
 }
 
+fn history_entry(role: Role, content: &str) -> HistoryEntry {
+    HistoryEntry {
+        role,
+        content: content.to_string(),
+        timestamp_ms: 0,
+        event_id: String::new(),
+    }
+}
+
 // Note: Full HTTP request/response testing requires a mock server or integration test.
 // Here we test the adapter logic that prepares payloads.
 
@@ -73,8 +85,12 @@ fn adapter_builds_messages_from_bundle() {
         system: "You are helpful".to_string(),
         instructions: "Answer concisely".to_string(),
         tools_json_schema: None,
-        context_docs: vec!["Doc1".to_string(), "Doc2".to_string()],
-        history: vec!["User: hi".to_string(), "Assistant: hello".to_string()],
+        context_docs: vec!["Doc1".into(), "Doc2".into()],
+        history: vec![
+            history_entry(Role::User, "hi"),
+            history_entry(Role::Assistant, "hello"),
+        ],
+        token_breakdown: None,
     };
     let budget = TokenBudget {
         max_input_tokens: 512,
@@ -92,9 +108,15 @@ fn adapter_builds_messages_from_bundle() {
         .iter()
         .any(|m| m.get("role").and_then(|r| r.as_str()) == Some("user")));
 
+    // History keeps its roles in both representations
+    assert!(messages
+        .iter()
+        .any(|m| m["role"] == "assistant" && m["content"] == "hello"));
+
     // Fused text should contain system and context
     assert!(fused.contains("System:"));
     assert!(fused.contains("Context:"));
+    assert!(fused.contains("- assistant: hello"));
 }
 
 #[test]
@@ -103,8 +125,9 @@ fn adapter_respects_token_budget() {
         system: "S".repeat(1000),
         instructions: "I".repeat(1000),
         tools_json_schema: None,
-        context_docs: vec!["C".repeat(1000)],
-        history: vec!["H".repeat(1000)],
+        context_docs: vec!["C".repeat(1000).into()],
+        history: vec![history_entry(Role::User, &"H".repeat(1000))],
+        token_breakdown: None,
     };
     let budget = TokenBudget {
         max_input_tokens: 64, // ~256 chars
@@ -133,6 +156,7 @@ fn adapter_handles_empty_bundle() {
         tools_json_schema: None,
         context_docs: vec![],
         history: vec![],
+        token_breakdown: None,
     };
     let budget = TokenBudget::default();
 
@@ -162,6 +186,7 @@ fn adapter_includes_tools_schema_when_provided() {
         tools_json_schema: Some(tools_schema.to_string()),
         context_docs: vec![],
         history: vec![],
+        token_breakdown: None,
     };
     let budget = TokenBudget::default();
 
@@ -229,3 +254,59 @@ fn token_budget_default_values() {
     assert_eq!(budget.max_input_tokens, 2048);
     assert_eq!(budget.max_output_tokens, 512);
 }
+
+#[test]
+fn stream_decoder_handles_split_lines_and_both_apis() {
+    let mut dec = ChatStreamDecoder::default();
+    let chat = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n";
+    let (a, b) = chat.as_bytes().split_at(20);
+    assert!(dec.push(a).is_empty());
+    assert_eq!(dec.push(b), vec![StreamDelta::Text("Hel".into())]);
+
+    let rest = concat!(
+        ": keep-alive\n",
+        "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"lo\"}\n",
+        "data: [DONE]\n",
+    );
+    assert_eq!(
+        dec.push(rest.as_bytes()),
+        vec![StreamDelta::Text("lo".into()), StreamDelta::Done]
+    );
+}
+
+#[tokio::test]
+async fn token_stream_roundtrips_through_the_event_bus() -> Result<()> {
+    let bus = std::sync::Arc::new(loom_core::EventBus::new().await?);
+    let (_sub, mut rx) = bus
+        .subscribe(
+            "turn.out".to_string(),
+            vec![LLM_TOKEN_EVENT.to_string(), LLM_COMPLETE_EVENT.to_string()],
+            loom_core::QoSLevel::QosRealtime,
+        )
+        .await?;
+
+    let mut publisher = TokenStreamPublisher::new(bus.clone(), "turn.out", "turn-1");
+    for chunk in ["The ", "", "answer ", "is 42"] {
+        publisher.token(chunk).await?;
+    }
+    assert_eq!(publisher.complete().await?, "The answer is 42");
+
+    let mut assembler = TokenStreamAssembler::new();
+    let mut partials = Vec::new();
+    let done = loop {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv())
+            .await
+            .expect("stream event")
+            .expect("bus open");
+        if let Some(done) = assembler.push(&event) {
+            break done;
+        }
+        partials.push(assembler.partial("turn-1").unwrap());
+    };
+    assert_eq!(partials, vec!["The ", "The answer ", "The answer is 42"]);
+    assert_eq!(done.correlation_id, "turn-1");
+    assert_eq!(done.text, "The answer is 42");
+    assert!(assembler.partial("turn-1").is_none());
+    Ok(())
+}
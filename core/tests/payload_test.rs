@@ -0,0 +1,70 @@
+use loom_core::payload::is_integer;
+use loom_core::{NumberMode, PayloadCodec};
+use serde::Deserialize;
+use serde_json::Value;
+
+const BIG_INT: &str = "123456789012345678901234567890";
+const LONG_DECIMAL: &str = "3.14159265358979323846264338327950288";
+
+#[cfg(feature = "exact-numbers")]
+#[test]
+fn exact_mode_round_trips_big_integers_and_long_decimals() {
+    let codec = PayloadCodec::default();
+    let input = format!(r#"{{"id":{BIG_INT},"max":18446744073709551615,"pi":{LONG_DECIMAL}}}"#);
+
+    let value = codec.decode_value(input.as_bytes()).unwrap();
+    assert_eq!(value["id"].to_string(), BIG_INT);
+    assert_eq!(value["pi"].to_string(), LONG_DECIMAL);
+    assert_eq!(value["max"].as_u64(), Some(u64::MAX));
+
+    let encoded = codec.encode(&value).unwrap();
+    assert_eq!(String::from_utf8(encoded).unwrap(), input);
+}
+
+#[test]
+fn integers_and_floats_stay_distinct() {
+    let codec = PayloadCodec::default();
+    assert_eq!(codec.number_mode(), NumberMode::Exact);
+    let value = codec.decode_value(br#"[1, 1.0, 2.5, -7]"#).unwrap();
+    let kinds: Vec<bool> = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| match v {
+            Value::Number(n) => is_integer(n),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(kinds, vec![true, false, false, true]);
+    assert_eq!(
+        String::from_utf8(codec.encode(&value).unwrap()).unwrap(),
+        "[1,1.0,2.5,-7]"
+    );
+
+    // An integer field rejects a float instead of truncating it
+    #[derive(Debug, Deserialize)]
+    struct Args {
+        #[allow(dead_code)]
+        count: u32,
+    }
+    assert!(codec.decode::<Args>(br#"{"count": 3}"#).is_ok());
+    assert!(codec.decode::<Args>(br#"{"count": 3.0}"#).is_err());
+}
+
+#[test]
+fn lossy_mode_rounds_what_does_not_fit_native_types() {
+    let codec = PayloadCodec::lossy();
+    let input = format!(r#"{{"id":{BIG_INT},"pi":{LONG_DECIMAL},"n":42}}"#);
+    let value = codec.decode_value(input.as_bytes()).unwrap();
+    assert_eq!(value["n"].as_i64(), Some(42));
+    assert_ne!(value["id"].to_string(), BIG_INT);
+    assert_eq!(value["id"].as_f64(), Some(1.2345678901234568e29));
+    assert_eq!(value["pi"].as_f64(), Some(std::f64::consts::PI));
+
+    let encoded = String::from_utf8(codec.encode(&value).unwrap()).unwrap();
+    assert!(!encoded.contains(LONG_DECIMAL));
+    let pi: f64 = codec.decode::<Value>(encoded.as_bytes()).unwrap()["pi"]
+        .as_f64()
+        .unwrap();
+    assert_eq!(pi, std::f64::consts::PI);
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use loom_core::action_broker::{ActionBroker, CapabilityProvider};
+use loom_core::plan::{OnStepError, Plan, PlanExecutor, UNRESOLVED_REFERENCE};
+use loom_core::proto::{
+    ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, ProviderKind,
+};
+use loom_core::{LoomError, Result};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Barrier;
+
+type Handler = Box<dyn Fn(Value) -> std::result::Result<Value, String> + Send + Sync>;
+
+// Provider backed by a closure over the JSON payload; `Err` becomes an error result
+struct FnProvider {
+    name: String,
+    handler: Handler,
+    calls: AtomicUsize,
+    barrier: Option<Arc<Barrier>>,
+}
+
+impl FnProvider {
+    fn new(
+        name: &str,
+        handler: impl Fn(Value) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            handler: Box::new(handler),
+            calls: AtomicUsize::new(0),
+            barrier: None,
+        })
+    }
+
+    // Waits for every other provider on `barrier` before answering
+    fn gated(name: &str, barrier: Arc<Barrier>) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            handler: Box::new(Ok),
+            calls: AtomicUsize::new(0),
+            barrier: Some(barrier),
+        })
+    }
+}
+
+#[async_trait]
+impl CapabilityProvider for FnProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: self.name.clone(),
+            version: "1.0.0".to_string(),
+            provider: ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(barrier) = &self.barrier {
+            barrier.wait().await;
+        }
+        let input: Value = serde_json::from_slice(&call.payload)?;
+        Ok(match (self.handler)(input) {
+            Ok(out) => ActionResult {
+                id: call.id,
+                status: ActionStatus::ActionOk as i32,
+                output: serde_json::to_vec(&out)?,
+                error: None,
+                metadata: Default::default(),
+            },
+            Err(message) => ActionResult {
+                id: call.id,
+                status: ActionStatus::ActionError as i32,
+                output: Vec::new(),
+                error: Some(ActionError {
+                    code: "FAILED".to_string(),
+                    message,
+                    details: Default::default(),
+                }),
+                metadata: Default::default(),
+            },
+        })
+    }
+}
+
+fn geo() -> Arc<FnProvider> {
+    FnProvider::new("geo.lookup", |input| {
+        Ok(json!({"name": input["city"], "coords": [48.85, 2.35]}))
+    })
+}
+
+fn echo(name: &str) -> Arc<FnProvider> {
+    FnProvider::new(name, Ok)
+}
+
+fn failing(name: &str) -> Arc<FnProvider> {
+    FnProvider::new(name, |_| Err("boom".to_string()))
+}
+
+#[tokio::test]
+async fn outputs_flow_into_later_inputs_through_references() {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(geo());
+    broker.register_provider(echo("weather.get"));
+
+    let outcome = PlanExecutor::new(broker)
+        .run_json(
+            r#"[
+                {"id": "geo", "capability": "geo.lookup", "input": {"city": "Paris"}},
+                {"id": "weather", "capability": "weather.get", "input": {
+                    "place": {"$ref": "$.geo.name"},
+                    "lat": {"$ref": "$.geo.coords[0]"},
+                    "lon": {"$ref": "$['geo']['coords'][1]"},
+                    "units": "metric"
+                }}
+            ]"#,
+        )
+        .await
+        .unwrap();
+
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.output("weather").unwrap(),
+        json!({"place": "Paris", "lat": 48.85, "lon": 2.35, "units": "metric"})
+    );
+}
+
+#[tokio::test]
+async fn parallel_steps_run_concurrently() {
+    let broker = Arc::new(ActionBroker::new());
+    let barrier = Arc::new(Barrier::new(2));
+    broker.register_provider(FnProvider::gated("a.run", Arc::clone(&barrier)));
+    broker.register_provider(FnProvider::gated("b.run", barrier));
+    broker.register_provider(echo("merge.run"));
+
+    let plan = Plan::from_json(
+        r#"[
+            {"parallel": [
+                {"id": "a", "capability": "a.run", "input": {"v": 1}},
+                {"id": "b", "capability": "b.run", "input": {"v": 2}}
+            ]},
+            {"id": "merge", "capability": "merge.run", "input": [{"$ref": "$.a.v"}, {"$ref": "$.b.v"}]}
+        ]"#,
+    )
+    .unwrap();
+
+    // Sequential dispatch would leave each gated provider waiting for the other
+    let outcome =
+        tokio::time::timeout(Duration::from_secs(2), PlanExecutor::new(broker).run(&plan))
+            .await
+            .expect("parallel steps were not run concurrently")
+            .unwrap();
+
+    assert!(outcome.is_success());
+    let ids: Vec<&str> = outcome.steps.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["a", "b", "merge"]);
+    assert_eq!(outcome.output("merge").unwrap(), json!([1, 2]));
+}
+
+#[tokio::test]
+async fn a_failed_step_stops_the_plan_by_default() {
+    let broker = Arc::new(ActionBroker::new());
+    let after = echo("after.run");
+    broker.register_provider(failing("bad.run"));
+    broker.register_provider(echo("ok.run"));
+    broker.register_provider(after.clone());
+
+    let outcome = PlanExecutor::new(broker)
+        .run_json(
+            r#"[
+                {"parallel": [
+                    {"id": "bad", "capability": "bad.run"},
+                    {"id": "sibling", "capability": "ok.run", "input": {}}
+                ]},
+                {"id": "after", "capability": "after.run", "input": {}},
+                {"id": "last", "capability": "after.run", "input": {}}
+            ]"#,
+        )
+        .await
+        .unwrap();
+
+    assert!(!outcome.is_success());
+    assert_eq!(outcome.stopped_at.as_deref(), Some("bad"));
+    assert_eq!(outcome.skipped, ["after", "last"]);
+    // Parallel siblings of the failing step still complete
+    assert_eq!(
+        outcome.result("sibling").unwrap().status_enum(),
+        ActionStatus::ActionOk
+    );
+    assert_eq!(after.calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn continue_policy_fails_only_dependent_steps() {
+    let broker = Arc::new(ActionBroker::new());
+    let dependent = echo("dependent.run");
+    broker.register_provider(failing("bad.run"));
+    broker.register_provider(dependent.clone());
+    broker.register_provider(echo("ok.run"));
+
+    let outcome = PlanExecutor::new(broker)
+        .on_error(OnStepError::Continue)
+        .run_json(
+            r#"[
+                {"id": "bad", "capability": "bad.run", "input": {}},
+                {"id": "uses_bad", "capability": "dependent.run", "input": {"x": {"$ref": "$.bad.x"}}},
+                {"id": "independent", "capability": "ok.run", "input": {"y": 1}}
+            ]"#,
+        )
+        .await
+        .unwrap();
+
+    assert!(outcome.skipped.is_empty());
+    assert!(outcome.stopped_at.is_none());
+    let unresolved = outcome.result("uses_bad").unwrap();
+    assert_eq!(
+        unresolved.error.as_ref().unwrap().code,
+        UNRESOLVED_REFERENCE
+    );
+    assert_eq!(dependent.calls.load(Ordering::SeqCst), 0);
+    assert_eq!(outcome.output("independent").unwrap(), json!({"y": 1}));
+}
+
+#[tokio::test]
+async fn missing_paths_in_an_output_are_unresolved() {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(geo());
+    broker.register_provider(echo("weather.get"));
+
+    let outcome = PlanExecutor::new(broker)
+        .run_json(
+            r#"[
+                {"id": "geo", "capability": "geo.lookup", "input": {"city": "Oslo"}},
+                {"id": "weather", "capability": "weather.get", "input": {"lat": {"$ref": "$.geo.coords[5]"}}}
+            ]"#,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(outcome.stopped_at.as_deref(), Some("weather"));
+    let err = outcome.result("weather").unwrap().error.clone().unwrap();
+    assert_eq!(err.code, UNRESOLVED_REFERENCE);
+    assert!(err.message.contains("$.geo.coords[5]"));
+}
+
+#[tokio::test]
+async fn unknown_capabilities_become_invoke_errors() {
+    let broker = Arc::new(ActionBroker::new());
+    let outcome = PlanExecutor::new(broker)
+        .run_json(r#"[{"id": "x", "capability": "nope.run", "input": {}}]"#)
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome.result("x").unwrap().error.as_ref().unwrap().code,
+        "INVOKE_ERROR"
+    );
+}
+
+#[test]
+fn invalid_plans_are_rejected_before_running() {
+    let cases = [
+        // duplicate id
+        r#"[{"id": "a", "capability": "x"}, {"id": "a", "capability": "y"}]"#,
+        // forward reference
+        r#"[{"id": "a", "capability": "x", "input": {"v": {"$ref": "$.b"}}}, {"id": "b", "capability": "y"}]"#,
+        // reference to a sibling in the same parallel group
+        r#"[{"parallel": [{"id": "a", "capability": "x"}, {"id": "b", "capability": "y", "input": {"$ref": "$.a"}}]}]"#,
+        // malformed path
+        r#"[{"id": "a", "capability": "x"}, {"id": "b", "capability": "y", "input": {"$ref": "a.v"}}]"#,
+        // empty capability
+        r#"[{"id": "a", "capability": ""}]"#,
+        // empty parallel group
+        r#"[{"parallel": []}]"#,
+    ];
+    for json in cases {
+        assert!(
+            matches!(Plan::from_json(json), Err(LoomError::PluginError(_))),
+            "accepted invalid plan: {}",
+            json
+        );
+    }
+}
+
+#[test]
+fn plans_round_trip_as_a_bare_array() {
+    let json = json!([
+        {"id": "a", "capability": "x", "input": {"k": 1}},
+        {"parallel": [
+            {"id": "b", "capability": "y", "input": {"$ref": "$.a.k"}, "timeout_ms": 500},
+            {"id": "c", "capability": "z", "version": "2.0.0", "input": null}
+        ]}
+    ]);
+    let plan: Plan = serde_json::from_value(json.clone()).unwrap();
+    plan.validate().unwrap();
+    assert_eq!(plan.step_ids().collect::<Vec<_>>(), ["a", "b", "c"]);
+    assert_eq!(serde_json::to_value(&plan).unwrap(), json);
+}
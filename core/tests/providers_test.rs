@@ -35,7 +35,7 @@ mod web_search {
             headers: Default::default(),
             timeout_ms: 5000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         // Should return an error in the result, not Err
@@ -61,7 +61,7 @@ mod web_search {
             headers: Default::default(),
             timeout_ms: 5000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         let result = provider.invoke(call).await.unwrap();
@@ -81,7 +81,7 @@ mod web_search {
             headers: Default::default(),
             timeout_ms: 15000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         let result = provider.invoke(call).await.unwrap();
@@ -137,7 +137,7 @@ mod weather {
             headers: Default::default(),
             timeout_ms: 5000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         // Should return an error in the result, not Err
@@ -163,7 +163,7 @@ mod weather {
             headers: Default::default(),
             timeout_ms: 5000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         let result = provider.invoke(call).await.unwrap();
@@ -184,7 +184,7 @@ mod weather {
             headers: Default::default(),
             timeout_ms: 15000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         let result = provider.invoke(call).await.unwrap();
@@ -210,7 +210,7 @@ mod weather {
             headers: Default::default(),
             timeout_ms: 15000,
             correlation_id: String::new(),
-            qos: 0,
+            qos: None,
         };
 
         let result = provider.invoke(call).await.unwrap();
@@ -221,3 +221,85 @@ mod weather {
         }
     }
 }
+
+mod mock {
+    use super::*;
+    use loom_core::action_broker::{ActionBroker, ActionCallExt, ActionResultExt};
+    use loom_core::proto::ActionResult;
+    use loom_core::providers::MockProvider;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn call(capability: &str, payload: serde_json::Value) -> ActionCall {
+        ActionCall::builder(capability)
+            .payload_json(&payload)
+            .unwrap()
+            .timeout_ms(200)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scripted_and_closure_responses_are_recorded() {
+        let mock = Arc::new(
+            MockProvider::named("tts.echo")
+                .on_json(json!({"text": "hi"}), json!({"audio": "scripted"}))
+                .returns(|call| {
+                    ActionResult::builder(ActionStatus::ActionOk)
+                        .output(call.payload.clone())
+                        .build()
+                }),
+        );
+        let broker = ActionBroker::new();
+        broker.register_provider(mock.clone());
+
+        let scripted = broker
+            .invoke(call("tts.echo", json!({"text": "hi"})))
+            .await
+            .unwrap();
+        let output: serde_json::Value = serde_json::from_slice(&scripted.output).unwrap();
+        assert_eq!(output["audio"], "scripted");
+
+        let echoed = call("tts.echo", json!({"text": "other"}));
+        let id = echoed.id.clone();
+        let res = broker.invoke(echoed).await.unwrap();
+        assert_eq!(res.id, id, "results carry the call id");
+        assert_eq!(res.output, br#"{"text":"other"}"#);
+
+        assert_eq!(mock.call_count(), 2);
+        assert_eq!(mock.last_call().unwrap().payload, res.output);
+        mock.reset();
+        assert!(mock.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failure_modes() {
+        let broker = ActionBroker::new();
+        broker.register_provider(Arc::new(
+            MockProvider::named("svc.fail").fails("UPSTREAM_DOWN", "no backend"),
+        ));
+        broker.register_provider(Arc::new(MockProvider::named("svc.error").errors("boom")));
+        let hanging = Arc::new(MockProvider::named("svc.hang").hangs());
+        broker.register_provider(hanging.clone());
+        broker.register_provider(Arc::new(
+            MockProvider::named("svc.slow")
+                .returns_json(json!({"ok": true}))
+                .with_delay(Duration::from_millis(20)),
+        ));
+
+        let res = broker.invoke(call("svc.fail", json!({}))).await.unwrap();
+        assert_eq!(res.error.unwrap().code, "UPSTREAM_DOWN");
+        let res = broker.invoke(call("svc.error", json!({}))).await.unwrap();
+        assert_eq!(res.error.unwrap().code, "CAPABILITY_ERROR");
+        let res = broker.invoke(call("svc.hang", json!({}))).await.unwrap();
+        assert_eq!(res.status, ActionStatus::ActionTimeout as i32);
+        assert_eq!(
+            hanging.call_count(),
+            1,
+            "timed-out calls are still recorded"
+        );
+        let res = broker.invoke(call("svc.slow", json!({}))).await.unwrap();
+        assert_eq!(res.status, ActionStatus::ActionOk as i32);
+        assert_eq!(res.output, br#"{"ok":true}"#);
+    }
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use loom_core::action_broker::{ActionBroker, CapabilityProvider};
+use loom_core::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor, QoSLevel};
+use loom_core::telemetry::{extract_trace_context, TRACEPARENT_HEADER};
+use loom_core::{Result, TurnSpan};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::trace::TracerProvider;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Collects finished spans in memory
+#[derive(Debug, Clone, Default)]
+struct CapturingExporter {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+}
+
+impl SpanExporter for CapturingExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.spans.lock().unwrap().extend(batch);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+struct EchoProvider;
+
+#[async_trait]
+impl CapabilityProvider for EchoProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        CapabilityDescriptor {
+            name: "test.echo".into(),
+            version: "1.0.0".into(),
+            provider: loom_core::proto::ProviderKind::ProviderNative as i32,
+            metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
+        }
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        Ok(ActionResult {
+            id: call.id,
+            status: ActionStatus::ActionOk as i32,
+            output: call.payload,
+            error: None,
+            metadata: Default::default(),
+        })
+    }
+}
+
+fn make_call(id: &str) -> ActionCall {
+    ActionCall {
+        id: id.to_string(),
+        capability: "test.echo".to_string(),
+        version: "1.0.0".to_string(),
+        payload: vec![],
+        headers: Default::default(),
+        timeout_ms: 1000,
+        correlation_id: String::new(),
+        qos: Some(QoSLevel::QosRealtime as i32),
+    }
+}
+
+#[test]
+fn extract_ignores_missing_or_malformed_traceparent() {
+    let mut headers = std::collections::HashMap::new();
+    assert!(extract_trace_context(&headers).is_none());
+    headers.insert(TRACEPARENT_HEADER.to_string(), "not-a-trace".to_string());
+    assert!(extract_trace_context(&headers).is_none());
+}
+
+#[test]
+fn turn_without_otel_layer_has_no_headers() {
+    let turn = TurnSpan::start("idle");
+    let mut call = make_call("plain");
+    turn.apply_to(&mut call);
+    assert!(call.headers.is_empty());
+}
+
+#[test]
+fn invokes_tagged_with_a_turn_are_children_of_the_turn_span() {
+    let exporter = CapturingExporter::default();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("telemetry_test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let broker = ActionBroker::new();
+    broker.register_provider(Arc::new(EchoProvider));
+
+    let turn = TurnSpan::start("weather in Paris");
+    assert!(turn.headers().contains_key(TRACEPARENT_HEADER));
+    for id in ["t-1", "t-2"] {
+        let mut call = make_call(id);
+        turn.apply_to(&mut call);
+        // Invoked outside the turn's scope: only the header links them
+        rt.block_on(broker.invoke(call)).unwrap();
+    }
+    drop(turn);
+    let _ = provider.force_flush();
+
+    let spans = exporter.spans.lock().unwrap();
+    let turn_span = spans.iter().find(|s| s.name == "agent.turn").unwrap();
+    let invokes: Vec<&SpanData> = spans.iter().filter(|s| s.name == "invoke").collect();
+    assert_eq!(invokes.len(), 2);
+    for span in invokes {
+        assert_eq!(
+            span.span_context.trace_id(),
+            turn_span.span_context.trace_id()
+        );
+        assert_eq!(span.parent_span_id, turn_span.span_context.span_id());
+    }
+}
@@ -0,0 +1,91 @@
+use loom_core::action_broker::{ActionBroker, BrokerConfig, QuarantinePolicy, QUARANTINED};
+use loom_core::proto::ActionCall;
+use loom_core::testing::{LatencyDistribution, LoadHarness, SyntheticProvider, SYNTHETIC_ERROR};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn load_harness_reports_percentiles_and_respects_concurrency_limits() {
+    let broker = Arc::new(ActionBroker::new());
+    let provider = Arc::new(
+        SyntheticProvider::named("load.search")
+            .latency(LatencyDistribution::Uniform {
+                min: Duration::from_millis(2),
+                max: Duration::from_millis(8),
+            })
+            .error_rate(0.2)
+            .seed(7),
+    );
+    broker.register_provider(provider.clone());
+    broker.set_concurrency_limit("load.search", Some(4));
+
+    let report = LoadHarness::new(Arc::clone(&broker), "load.search")
+        .concurrency(16)
+        .duration(Duration::from_secs(10))
+        .max_calls(200)
+        .run()
+        .await;
+
+    assert_eq!(report.total(), 200);
+    assert_eq!(provider.calls(), 200);
+    assert_eq!(report.ok + report.failed(), 200);
+    let injected = report.by_code[SYNTHETIC_ERROR];
+    assert!(
+        (20..=60).contains(&injected),
+        "{injected} injected failures"
+    );
+    assert!(report.percentile(50.0) >= Duration::from_millis(2));
+    assert!(report.percentile(50.0) <= report.percentile(99.0));
+    assert_eq!(report.percentile(100.0), *report.latencies.last().unwrap());
+    assert!(report.throughput() > 0.0);
+
+    // 16 workers, but the broker lets only 4 calls reach the provider at once
+    assert_eq!(provider.peak_in_flight(), 4);
+}
+
+#[tokio::test]
+async fn load_harness_shows_quarantine_shedding_a_hanging_provider() {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(
+        SyntheticProvider::named("load.flaky").hang_rate(1.0),
+    ));
+    broker.set_quarantine_policy(Some(QuarantinePolicy {
+        max_consecutive_timeouts: 3,
+        cooldown_ms: 60_000,
+    }));
+
+    let report = LoadHarness::new(Arc::clone(&broker), "load.flaky")
+        .call(ActionCall {
+            capability: "load.flaky".to_string(),
+            timeout_ms: 20,
+            ..Default::default()
+        })
+        .concurrency(1)
+        .duration(Duration::from_millis(300))
+        .run()
+        .await;
+
+    // Three timeouts trip the quarantine; later calls are rejected without waiting
+    assert_eq!(report.ok, 0);
+    assert_eq!(report.by_code["TIMEOUT"], 3);
+    assert!(report.by_code[QUARANTINED] > 10);
+    assert!(report.to_string().contains("QUARANTINED"));
+}
+
+#[tokio::test]
+async fn load_past_the_idempotency_cache_bound_keeps_flowing() {
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(SyntheticProvider::named("load.echo")));
+    broker.apply_config(BrokerConfig {
+        cache_max_entries: 32,
+        ..Default::default()
+    });
+
+    // Trimming the overflowing cache used to deadlock the runtime
+    let report = LoadHarness::new(Arc::clone(&broker), "load.echo")
+        .concurrency(4)
+        .max_calls(500)
+        .run()
+        .await;
+    assert_eq!(report.ok, 500);
+}
@@ -2,13 +2,20 @@ use async_trait::async_trait;
 use loom_core::action_broker::{ActionBroker, CapabilityProvider};
 use loom_core::context::PromptBundle;
 use loom_core::llm::{
-    build_action_call, make_refine_bundle, parse_tool_calls_from_chat,
-    parse_tool_calls_from_responses, NormalizedToolCall,
+    build_action_call, bundle_hash, make_correction_bundle, make_refine_bundle,
+    parse_tool_calls_from_chat, parse_tool_calls_from_responses, validate_arguments,
+    validate_tool_calls, AgentOutcome, LlmClient, LlmClientConfig, NormalizedToolCall,
+    OrchestratorOptions, StepDecision, StopReason, TokenStreamAssembler, TokenUsage,
+    ToolInvocation, ToolOrchestrator, TurnSummary, DEFAULT_MAX_ITERATIONS,
+    DEFAULT_MAX_REPEATED_CALLS, INVALID_ARGUMENTS, LLM_COMPLETE_EVENT, LLM_TOKEN_EVENT,
+    TURN_COMPLETED_EVENT,
 };
 use loom_core::proto::{ActionCall, ActionResult, ActionStatus, CapabilityDescriptor};
 use loom_core::Result;
-use serde_json::json;
-use std::sync::Arc;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 struct EchoProvider;
 
@@ -20,6 +27,8 @@ impl CapabilityProvider for EchoProvider {
             version: "0.1.0".into(),
             provider: loom_core::proto::ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -29,6 +38,7 @@ impl CapabilityProvider for EchoProvider {
             status: ActionStatus::ActionOk as i32,
             output: call.payload,
             error: None,
+            metadata: Default::default(),
         })
     }
 }
@@ -198,6 +208,7 @@ fn test_refine_bundle_contains_results() {
         status: ActionStatus::ActionOk as i32,
         output: b"result".to_vec(),
         error: None,
+        metadata: Default::default(),
     };
     let bundle = make_refine_bundle(&base, &calls, &[ok]);
 
@@ -213,6 +224,7 @@ fn test_refine_bundle_with_existing_system() {
         tools_json_schema: None,
         context_docs: vec![],
         history: vec![],
+        token_breakdown: None,
     };
     let calls = vec![NormalizedToolCall {
         id: None,
@@ -224,6 +236,7 @@ fn test_refine_bundle_with_existing_system() {
         status: ActionStatus::ActionOk as i32,
         output: b"search results".to_vec(),
         error: None,
+        metadata: Default::default(),
     };
     let bundle = make_refine_bundle(&base, &calls, &[ok]);
 
@@ -250,6 +263,7 @@ fn test_refine_bundle_with_error_result() {
             message: "Request timed out".into(),
             details: Default::default(),
         }),
+        metadata: Default::default(),
     };
     let bundle = make_refine_bundle(&base, &calls, &[error]);
 
@@ -276,6 +290,7 @@ fn test_refine_bundle_truncates_many_results() {
             status: ActionStatus::ActionOk as i32,
             output: b"ok".to_vec(),
             error: None,
+            metadata: Default::default(),
         });
     }
 
@@ -288,3 +303,420 @@ fn test_refine_bundle_truncates_many_results() {
     // But not all 20
     assert!(!bundle.system.contains("tool_15"));
 }
+
+fn weather_descriptor() -> CapabilityDescriptor {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "city": {"type": "string", "minLength": 1},
+            "days": {"type": "integer", "minimum": 1, "maximum": 7},
+            "units": {"enum": ["metric", "imperial"]}
+        },
+        "required": ["city"],
+        "additionalProperties": false
+    });
+    CapabilityDescriptor {
+        name: "weather.get".into(),
+        version: "0.1.0".into(),
+        provider: loom_core::proto::ProviderKind::ProviderNative as i32,
+        metadata: [("schema".to_string(), schema.to_string())]
+            .into_iter()
+            .collect(),
+        depends_on: Vec::new(),
+        default_qos: None,
+    }
+}
+
+#[test]
+fn test_validate_arguments_reports_offending_path() {
+    let schema: serde_json::Value =
+        serde_json::from_str(&weather_descriptor().metadata["schema"]).unwrap();
+
+    assert!(validate_arguments(&schema, &json!({"city": "Paris", "days": 3})).is_ok());
+
+    let missing = validate_arguments(&schema, &json!({"days": 3})).unwrap_err();
+    assert!(missing.contains("required property 'city'"), "{missing}");
+    let wrong_type =
+        validate_arguments(&schema, &json!({"city": "Paris", "days": "3"})).unwrap_err();
+    assert!(wrong_type.contains("$.days"), "{wrong_type}");
+    assert!(validate_arguments(&schema, &json!({"city": "Paris", "days": 9})).is_err());
+    assert!(validate_arguments(&schema, &json!({"city": "Paris", "units": "kelvin"})).is_err());
+    let extra = validate_arguments(&schema, &json!({"city": "Paris", "zip": "75001"})).unwrap_err();
+    assert!(extra.contains("unexpected property 'zip'"), "{extra}");
+}
+
+#[test]
+fn test_validate_tool_calls_skips_tools_without_schema() {
+    let caps = vec![weather_descriptor(), EchoProvider.descriptor()];
+    let calls = vec![
+        NormalizedToolCall {
+            id: None,
+            name: "weather.get".into(),
+            arguments: json!({"location": "Paris"}),
+        },
+        NormalizedToolCall {
+            id: None,
+            name: "unit.echo".into(),
+            arguments: json!("anything"),
+        },
+    ];
+    let errors = validate_tool_calls(&caps, &calls);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].tool, "weather.get");
+}
+
+#[test]
+fn test_correction_bundle_surfaces_validation_errors() {
+    let base = PromptBundle {
+        system: "You are a helpful assistant".into(),
+        ..Default::default()
+    };
+    let calls = vec![NormalizedToolCall {
+        id: None,
+        name: "weather.get".into(),
+        arguments: json!({"location": "Paris"}),
+    }];
+    let errors = validate_tool_calls(&[weather_descriptor()], &calls);
+    let bundle = make_correction_bundle(&base, &calls, &errors);
+
+    assert!(bundle.system.starts_with("You are a helpful assistant"));
+    assert!(bundle.system.contains("Tool Argument Errors:"));
+    assert!(bundle.system.contains(&errors[0].message));
+    assert!(bundle.system.contains(r#"{"location":"Paris"}"#));
+}
+
+#[test]
+fn test_correction_bundle_echoes_each_rejected_call() {
+    // Two calls to the same tool; only the second is invalid
+    let calls = vec![
+        NormalizedToolCall {
+            id: None,
+            name: "weather.get".into(),
+            arguments: json!({"city": "Paris"}),
+        },
+        NormalizedToolCall {
+            id: None,
+            name: "weather.get".into(),
+            arguments: json!({"location": "Rome"}),
+        },
+    ];
+    let errors = validate_tool_calls(&[weather_descriptor()], &calls);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].index, 1);
+
+    let bundle = make_correction_bundle(&PromptBundle::default(), &calls, &errors);
+    assert!(bundle.system.contains(r#"{"location":"Rome"}"#));
+    assert!(!bundle.system.contains(r#"{"city":"Paris"}"#));
+}
+
+#[test]
+fn test_turn_summary_round_trips_through_event() -> Result<()> {
+    let mut usage = TokenUsage::from_response(&json!({
+        "usage": {"input_tokens": 12, "output_tokens": 3, "total_tokens": 15}
+    }));
+    // Chat Completions naming, without a total
+    usage.add(TokenUsage::from_usage(
+        &json!({"prompt_tokens": 20, "completion_tokens": 5}),
+    ));
+    assert_eq!(
+        usage,
+        TokenUsage {
+            input_tokens: 32,
+            output_tokens: 8,
+            total_tokens: 40
+        }
+    );
+
+    let failed = ActionResult {
+        id: "r1".into(),
+        status: ActionStatus::ActionTimeout as i32,
+        output: vec![],
+        error: Some(loom_core::proto::ActionError {
+            code: "TIMEOUT".into(),
+            message: "slow".into(),
+            details: Default::default(),
+        }),
+        metadata: Default::default(),
+    };
+    let summary = TurnSummary {
+        goal: "weather in Paris".into(),
+        correlation_id: Some("corr-1".into()),
+        tools: vec![ToolInvocation::new("weather.get", &failed, 12.5)],
+        latency_ms: 40.0,
+        model_requests: 2,
+        usage,
+    };
+    assert_eq!(summary.tools[0].status, "timeout");
+    assert_eq!(summary.tools[0].error_code.as_deref(), Some("TIMEOUT"));
+
+    let event = summary.to_event("tool_orchestrator")?;
+    assert_eq!(event.r#type, TURN_COMPLETED_EVENT);
+    assert_eq!(event.metadata["correlation_id"], "corr-1");
+    assert_eq!(TurnSummary::from_event(&event)?, summary);
+    Ok(())
+}
+
+// OpenAI-compatible stand-in: serves `replies` in order on /responses, then a plain answer.
+// Every request waits `delay` first; an empty reply list with `fail` set answers HTTP 500.
+async fn spawn_fake_model(replies: Vec<Value>, delay: Duration, fail: bool) -> String {
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+
+    type Replies = Arc<Mutex<VecDeque<Value>>>;
+    async fn reply(
+        State((replies, delay, fail)): State<(Replies, Duration, bool)>,
+    ) -> std::result::Result<Json<Value>, StatusCode> {
+        tokio::time::sleep(delay).await;
+        if fail {
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        let next = replies.lock().unwrap().pop_front();
+        Ok(Json(next.unwrap_or_else(
+            || json!({"output": [], "output_text": "done"}),
+        )))
+    }
+
+    let state: (Replies, Duration, bool) = (Arc::new(Mutex::new(replies.into())), delay, fail);
+    let app = Router::new()
+        .route("/responses", post(reply))
+        .route("/chat/completions", post(reply))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}", addr)
+}
+
+fn tool_use(name: &str, input: Value) -> Value {
+    json!({"output": [{"content": [{"type": "tool_use", "name": name, "id": "c", "input": input}]}]})
+}
+
+fn orchestrator_for(base_url: String) -> ToolOrchestrator {
+    let llm = LlmClient::new(LlmClientConfig {
+        base_url,
+        model: "fake".into(),
+        api_key: None,
+        request_timeout_ms: 5_000,
+        temperature: 0.0,
+    })
+    .unwrap();
+    let broker = Arc::new(ActionBroker::new());
+    broker.register_provider(Arc::new(EchoProvider));
+    broker.register_provider(Arc::new(WeatherProvider));
+    ToolOrchestrator::new(Arc::new(llm), broker)
+}
+
+// Echoes its arguments under the weather schema
+struct WeatherProvider;
+
+#[async_trait]
+impl CapabilityProvider for WeatherProvider {
+    fn descriptor(&self) -> CapabilityDescriptor {
+        weather_descriptor()
+    }
+
+    async fn invoke(&self, call: ActionCall) -> Result<ActionResult> {
+        EchoProvider.invoke(call).await
+    }
+}
+
+#[tokio::test]
+async fn run_rejects_only_the_calls_whose_arguments_stay_invalid() -> Result<()> {
+    // The model repeats one valid and one invalid call to the same tool through its correction
+    let both = json!({"output": [{"content": [
+        {"type": "tool_use", "name": "weather.get", "id": "a", "input": {"city": "Paris"}},
+        {"type": "tool_use", "name": "weather.get", "id": "b", "input": {"location": "Rome"}}
+    ]}]});
+    let url = spawn_fake_model(vec![both.clone(), both], Duration::ZERO, false).await;
+    let mut orch = orchestrator_for(url);
+    let options = OrchestratorOptions {
+        refine_on_tool_result: false,
+        max_argument_retries: 1,
+        ..Default::default()
+    };
+
+    let answer = orch
+        .run(&PromptBundle::default(), None, options, None)
+        .await?;
+    assert_eq!(answer.tool_results.len(), 2);
+    assert_eq!(answer.tool_results[0].status_enum(), ActionStatus::ActionOk);
+    assert_eq!(
+        serde_json::from_slice::<Value>(&answer.tool_results[0].output)?,
+        json!({"city": "Paris"})
+    );
+    let rejected = answer.tool_results[1].error.as_ref().unwrap();
+    assert_eq!(rejected.code, INVALID_ARGUMENTS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_agent_traces_each_step_until_the_model_answers() {
+    let url = spawn_fake_model(
+        vec![
+            tool_use("unit.echo", json!({"n": 1})),
+            tool_use("unit.echo", json!({"n": 2})),
+        ],
+        Duration::ZERO,
+        false,
+    )
+    .await;
+    let mut orch = orchestrator_for(url);
+    let bundle = PromptBundle {
+        instructions: "count to two".into(),
+        ..Default::default()
+    };
+
+    let outcome = orch
+        .run_agent(&bundle, None, OrchestratorOptions::default(), None)
+        .await;
+    assert_eq!(outcome.stop_reason, StopReason::Completed);
+    assert_eq!(outcome.final_answer.as_deref(), Some("done"));
+    assert_eq!(outcome.steps.len(), 3);
+    assert_eq!(outcome.tool_call_count(), 2);
+
+    let first = &outcome.steps[0];
+    assert_eq!(first.iteration, 1);
+    assert_eq!(first.bundle_hash, bundle_hash(&bundle));
+    assert_eq!(first.decision, StepDecision::ToolCalls);
+    assert_eq!(first.tool_calls[0].arguments, json!({"n": 1}));
+    assert_eq!(first.results[0].status, "ok");
+    // Later prompts carry the tool results, so they hash differently
+    assert_ne!(outcome.steps[1].bundle_hash, first.bundle_hash);
+    assert_eq!(outcome.steps[2].decision, StepDecision::Answer);
+    assert!(outcome.steps[2].tool_calls.is_empty());
+
+    // Measured latencies read back exactly, including ones serde_json's fast float
+    // parser would be off by one ulp on
+    let mut stored = outcome.clone();
+    stored.steps[0].results[0].latency_ms = 395.32430340349924;
+    for outcome in [outcome, stored] {
+        let json = serde_json::to_string(&outcome).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AgentOutcome>(&json).unwrap(),
+            outcome
+        );
+    }
+}
+
+#[tokio::test]
+async fn run_agent_publishes_its_answer_as_a_token_stream() -> Result<()> {
+    let url = spawn_fake_model(
+        vec![tool_use("unit.echo", json!({"n": 1}))],
+        Duration::ZERO,
+        false,
+    )
+    .await;
+    let bus = Arc::new(loom_core::EventBus::new().await?);
+    let (_sub, mut rx) = bus
+        .subscribe(
+            "turn.out".to_string(),
+            vec![LLM_TOKEN_EVENT.to_string(), LLM_COMPLETE_EVENT.to_string()],
+            loom_core::QoSLevel::QosRealtime,
+        )
+        .await?;
+    let mut orch = orchestrator_for(url).with_token_stream(bus, "turn.out");
+
+    let outcome = orch
+        .run_agent(
+            &PromptBundle::default(),
+            None,
+            OrchestratorOptions::default(),
+            Some("corr-7".into()),
+        )
+        .await;
+    assert_eq!(outcome.final_answer.as_deref(), Some("done"));
+
+    let mut assembler = TokenStreamAssembler::new();
+    let done = loop {
+        let event = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .expect("stream event")
+            .expect("bus open");
+        if let Some(done) = assembler.push(&event) {
+            break done;
+        }
+    };
+    assert_eq!(done.correlation_id, "corr-7");
+    assert_eq!(done.text, "done");
+    // Tool steps are not streamed, only the answer
+    assert!(rx.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_agent_reports_deadline_and_errors_as_stop_reasons() {
+    let slow = spawn_fake_model(vec![], Duration::from_millis(500), false).await;
+    let options = OrchestratorOptions {
+        deadline_ms: Some(50),
+        ..Default::default()
+    };
+    let outcome = orchestrator_for(slow)
+        .run_agent(&PromptBundle::default(), None, options, None)
+        .await;
+    assert_eq!(outcome.stop_reason, StopReason::DeadlineExceeded);
+    assert!(outcome.steps.is_empty());
+    assert!(outcome.final_answer.is_none());
+
+    let broken = spawn_fake_model(vec![], Duration::ZERO, true).await;
+    let outcome = orchestrator_for(broken)
+        .run_agent(
+            &PromptBundle::default(),
+            None,
+            OrchestratorOptions::default(),
+            None,
+        )
+        .await;
+    assert!(matches!(outcome.stop_reason, StopReason::Error(ref m) if m.contains("500")));
+    assert!(!outcome.is_completed());
+}
+
+#[tokio::test]
+async fn run_agent_stops_when_the_model_repeats_the_same_call() {
+    // A model that picks the same tool with the same arguments forever
+    let url = spawn_fake_model(
+        vec![tool_use("unit.echo", json!({"q": "again"})); 10],
+        Duration::ZERO,
+        false,
+    )
+    .await;
+    let options = OrchestratorOptions {
+        max_repeated_calls: 2,
+        ..Default::default()
+    };
+    let outcome = orchestrator_for(url)
+        .run_agent(&PromptBundle::default(), None, options, None)
+        .await;
+    assert_eq!(outcome.stop_reason, StopReason::LoopDetected);
+    assert_eq!(outcome.steps.len(), 3);
+    // The third request is recorded but never dispatched
+    let last = outcome.steps.last().unwrap();
+    assert_eq!(last.tool_calls.len(), 1);
+    assert!(last.results.is_empty());
+    assert!(outcome.final_answer.is_none());
+}
+
+#[tokio::test]
+async fn run_agent_honors_the_iteration_cap() {
+    let replies = (0..5).map(|n| tool_use("unit.echo", json!({ "n": n })));
+    let url = spawn_fake_model(replies.collect(), Duration::ZERO, false).await;
+    let options = OrchestratorOptions {
+        max_iterations: 2,
+        ..Default::default()
+    };
+    let outcome = orchestrator_for(url)
+        .run_agent(&PromptBundle::default(), None, options, None)
+        .await;
+    assert_eq!(outcome.stop_reason, StopReason::MaxIterations);
+    assert_eq!(outcome.steps.len(), 2);
+    assert_eq!(outcome.tool_call_count(), 2);
+
+    // Defaults survive older serialized options
+    let parsed: OrchestratorOptions = serde_json::from_value(json!({
+        "tool_choice": "Auto",
+        "per_tool_timeout_ms": 1000,
+        "refine_on_tool_result": false,
+        "max_tools_exposed": 8
+    }))
+    .unwrap();
+    assert_eq!(parsed.max_iterations, DEFAULT_MAX_ITERATIONS);
+    assert_eq!(parsed.max_repeated_calls, DEFAULT_MAX_REPEATED_CALLS);
+}
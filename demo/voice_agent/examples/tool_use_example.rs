@@ -9,6 +9,7 @@ use loom_core::context::{PromptBundle, TokenBudget};
 use loom_core::llm::{
     LlmClient, LlmClientConfig, OrchestratorOptions, ToolChoice, ToolOrchestrator,
 };
+use loom_core::proto::ActionStatus;
 use loom_core::{Result, WeatherProvider, WebSearchProvider};
 use std::sync::Arc;
 use tracing::info;
@@ -76,6 +77,7 @@ async fn main() -> Result<()> {
             tools_json_schema: None,
             context_docs: vec![],
             history: vec![],
+            token_breakdown: None,
         };
 
         let budget = TokenBudget {
@@ -88,6 +90,10 @@ async fn main() -> Result<()> {
             per_tool_timeout_ms: 30_000,
             refine_on_tool_result: true,
             max_tools_exposed: 64,
+            max_argument_retries: 2,
+            deadline_ms: None,
+            max_iterations: 8,
+            max_repeated_calls: 3,
         };
 
         match orchestrator
@@ -125,7 +131,9 @@ async fn main() -> Result<()> {
                         };
                         info!("   {}. Status: {}", idx + 1, status_str);
 
-                        if result.status == 0 && !result.output.is_empty() {
+                        if result.status_enum() == ActionStatus::ActionOk
+                            && !result.output.is_empty()
+                        {
                             if let Ok(output) =
                                 serde_json::from_slice::<serde_json::Value>(&result.output)
                             {
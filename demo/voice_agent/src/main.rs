@@ -88,6 +88,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 tools_json_schema: None,
                 context_docs: vec![],
                 history: vec![],
+                token_breakdown: None,
             };
             let budget = TokenBudget {
                 max_input_tokens: 2048,
@@ -113,12 +114,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 headers,
                 timeout_ms: 60_000, // generous budget
                 correlation_id: ev.id.clone(),
-                qos: QoSLevel::QosBatched as i32,
+                qos: Some(QoSLevel::QosBatched as i32),
             };
 
             let res = broker.invoke(call).await;
             let reply_text = match res {
-                Ok(r) if r.status == (loom_core::proto::ActionStatus::ActionOk as i32) => {
+                Ok(r) if r.status_enum() == loom_core::proto::ActionStatus::ActionOk => {
                     let v: Result<serde_json::Value, _> = serde_json::from_slice(&r.output);
                     match v {
                         Ok(val) => val
@@ -167,11 +168,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 headers: cfg.tts_headers(),
                 timeout_ms: 30_000,
                 correlation_id: ev.id.clone(),
-                qos: QoSLevel::QosRealtime as i32,
+                qos: Some(QoSLevel::QosRealtime as i32),
             };
             match broker.invoke(tts_call).await {
                 Ok(result) => {
-                    if result.status != (loom_core::proto::ActionStatus::ActionOk as i32) {
+                    if result.status_enum() != loom_core::proto::ActionStatus::ActionOk {
                         warn!(
                             target = "voice_agent",
                             status = result.status,
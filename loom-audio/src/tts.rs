@@ -23,7 +23,7 @@
 
 use crate::utils::{gen_id, now_ms};
 use async_trait::async_trait;
-use loom_core::action_broker::CapabilityProvider;
+use loom_core::action_broker::{ActionResultExt, CapabilityProvider};
 use loom_core::event::EventBus;
 use loom_core::proto::{
     ActionCall, ActionError, ActionResult, ActionStatus, CapabilityDescriptor, Event, ProviderKind,
@@ -144,6 +144,8 @@ impl CapabilityProvider for TtsSpeakProvider {
             version: "0.1.0".to_string(),
             provider: ProviderKind::ProviderNative as i32,
             metadata: Default::default(),
+            depends_on: Vec::new(),
+            default_qos: None,
         }
     }
 
@@ -165,6 +167,7 @@ impl CapabilityProvider for TtsSpeakProvider {
                 status: ActionStatus::ActionOk as i32,
                 output: Vec::new(),
                 error: None,
+                metadata: Default::default(),
             });
         }
 
@@ -235,10 +238,9 @@ impl CapabilityProvider for TtsSpeakProvider {
             };
             let _ = self.bus.publish(&self.cfg.topic, ev).await;
 
-            return Ok(ActionResult {
-                id: call.id,
-                status: ActionStatus::ActionOk as i32,
-                output: serde_json::to_vec(&serde_json::json!({
+            return Ok(ActionResult::try_ok_json(
+                call.id,
+                &serde_json::json!({
                     "engine": "none",
                     "printed": true,
                     "voice": voice,
@@ -246,10 +248,8 @@ impl CapabilityProvider for TtsSpeakProvider {
                     "volume": volume,
                     "sample_rate": sample_rate,
                     "player": player,
-                }))
-                .unwrap_or_default(),
-                error: None,
-            });
+                }),
+            ));
         }
 
         // Execute synthesis + playback in blocking task
@@ -301,6 +301,7 @@ impl CapabilityProvider for TtsSpeakProvider {
                         message: err.to_string(),
                         details: Default::default(),
                     }),
+                    metadata: Default::default(),
                 };
             }
 
@@ -352,10 +353,9 @@ impl CapabilityProvider for TtsSpeakProvider {
             };
             let _ = tokio::runtime::Handle::current().block_on(bus.publish(&topic, ev));
 
-            ActionResult {
-                id: call_id,
-                status: ActionStatus::ActionOk as i32,
-                output: serde_json::to_vec(&serde_json::json!({
+            ActionResult::try_ok_json(
+                call_id,
+                &serde_json::json!({
                     "engine": engine,
                     "voice": voice,
                     "rate": rate,
@@ -363,10 +363,8 @@ impl CapabilityProvider for TtsSpeakProvider {
                     "sample_rate": sample_rate,
                     "player": player,
                     "wav_path": wav_path,
-                }))
-                .unwrap_or_default(),
-                error: None,
-            }
+                }),
+            )
         });
 
         // Apply internal timeout to the blocking task
@@ -401,6 +399,7 @@ impl CapabilityProvider for TtsSpeakProvider {
                         message: "TTS synthesis/playback timed out".into(),
                         details: Default::default(),
                     }),
+                    metadata: Default::default(),
                 })
             }
         }
@@ -4,3 +4,158 @@
 #![allow(warnings)]
 
 include!(concat!(env!("OUT_DIR"), "/loom.v1.rs"));
+
+impl ActionStatus {
+    /// Decode a wire status; `None` for values this build does not know.
+    /// (prost's own `from_i32` is deprecated in favour of `TryFrom<i32>`.)
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::try_from(code).ok()
+    }
+
+    /// Short lowercase name: `ok`, `error`, `timeout`, `retryable` or `pending`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionStatus::ActionOk => "ok",
+            ActionStatus::ActionError => "error",
+            ActionStatus::ActionTimeout => "timeout",
+            ActionStatus::ActionRetryable => "retryable",
+            ActionStatus::ActionPending => "pending",
+        }
+    }
+
+    /// Every status except `ActionPending` is final
+    pub fn is_final(&self) -> bool {
+        *self != ActionStatus::ActionPending
+    }
+}
+
+impl std::fmt::Display for ActionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `CapabilityDescriptor.metadata` key holding the JSON Schema of the call payload
+pub const SCHEMA_METADATA_KEY: &str = "schema";
+
+impl CapabilityDescriptor {
+    /// Fingerprint of the name, version and `metadata["schema"]`, for caches that must drop
+    /// entries when a provider changes. FNV-1a, so it is stable across processes and builds.
+    /// Other metadata, the provider kind and the default QoS do not affect it.
+    pub fn checksum(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let mut hash = OFFSET;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(PRIME);
+            }
+        };
+        let schema = self.metadata.get(SCHEMA_METADATA_KEY);
+        for field in [Some(&self.name), Some(&self.version), schema] {
+            // Length prefixes keep ("ab", "c") apart from ("a", "bc"); no schema is not ""
+            match field {
+                Some(text) => {
+                    feed(&[1]);
+                    feed(&(text.len() as u64).to_le_bytes());
+                    feed(text.as_bytes());
+                }
+                None => feed(&[0]),
+            }
+        }
+        hash
+    }
+}
+
+/// `ActionResult.metadata` / `Event.metadata` key holding the MIME type of the output/payload
+pub const CONTENT_TYPE_METADATA_KEY: &str = "content_type";
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+pub const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
+pub const CONTENT_TYPE_BINARY: &str = "application/octet-stream";
+
+/// How to read an output's bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    Json,
+    Text,
+    Binary,
+}
+
+impl OutputKind {
+    /// Classify a MIME type: `*/json` and `*+json` are JSON, `text/*` is text, anything else
+    /// (audio, images, octet-stream, ...) is binary
+    pub fn from_content_type(content_type: &str) -> Self {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if essence.ends_with("/json") || essence.ends_with("+json") {
+            OutputKind::Json
+        } else if essence.starts_with("text/") {
+            OutputKind::Text
+        } else {
+            OutputKind::Binary
+        }
+    }
+
+    /// Best guess for undeclared bytes: valid JSON, then UTF-8 text, else binary
+    pub fn sniff(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Err(_) => OutputKind::Binary,
+            Ok(text) if serde_json::from_str::<serde_json::Value>(text).is_ok() => OutputKind::Json,
+            Ok(_) => OutputKind::Text,
+        }
+    }
+}
+
+fn kind_of(content_type: Option<&str>, bytes: &[u8]) -> OutputKind {
+    content_type
+        .map(OutputKind::from_content_type)
+        .unwrap_or_else(|| OutputKind::sniff(bytes))
+}
+
+impl ActionResult {
+    /// `status` as an enum; unknown values read as `ActionError`. Unlike the generated
+    /// `status()`, an unrecognized code never reads as `ActionOk`.
+    pub fn status_enum(&self) -> ActionStatus {
+        ActionStatus::from_code(self.status).unwrap_or(ActionStatus::ActionError)
+    }
+
+    /// MIME type declared by the provider, if any. `output` is always raw bytes; decode it
+    /// as text only when this (or `output_kind`) says so.
+    pub fn content_type(&self) -> Option<&str> {
+        self.metadata
+            .get(CONTENT_TYPE_METADATA_KEY)
+            .map(String::as_str)
+    }
+
+    /// Declared kind of `output`, or a sniffed one when no content type was set
+    pub fn output_kind(&self) -> OutputKind {
+        kind_of(self.content_type(), &self.output)
+    }
+
+    /// `output` as text for JSON/text outputs that are valid UTF-8; `None` for binary
+    /// outputs, which must not go through `String::from_utf8_lossy`
+    pub fn output_text(&self) -> Option<&str> {
+        match self.output_kind() {
+            OutputKind::Binary => None,
+            _ => std::str::from_utf8(&self.output).ok(),
+        }
+    }
+}
+
+impl Event {
+    /// MIME type of `payload`, if the publisher declared one
+    pub fn content_type(&self) -> Option<&str> {
+        self.metadata
+            .get(CONTENT_TYPE_METADATA_KEY)
+            .map(String::as_str)
+    }
+
+    /// Declared kind of `payload`, or a sniffed one when no content type was set
+    pub fn payload_kind(&self) -> OutputKind {
+        kind_of(self.content_type(), &self.payload)
+    }
+}
@@ -25,7 +25,7 @@ _sym_db = _symbol_database.Default()
 from . import event_pb2 as event__pb2
 
 
-DESCRIPTOR = _descriptor_pool.Default().AddSerializedFile(b'\n\x0c\x61\x63tion.proto\x12\x07loom.v1\x1a\x0b\x65vent.proto\"\xce\x01\n\x14\x43\x61pabilityDescriptor\x12\x0c\n\x04name\x18\x01 \x01(\t\x12\x0f\n\x07version\x18\x02 \x01(\t\x12\'\n\x08provider\x18\x03 \x01(\x0e\x32\x15.loom.v1.ProviderKind\x12=\n\x08metadata\x18\x04 \x03(\x0b\x32+.loom.v1.CapabilityDescriptor.MetadataEntry\x1a/\n\rMetadataEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\"\xfd\x01\n\nActionCall\x12\n\n\x02id\x18\x01 \x01(\t\x12\x12\n\ncapability\x18\x02 \x01(\t\x12\x0f\n\x07version\x18\x03 \x01(\t\x12\x0f\n\x07payload\x18\x04 \x01(\x0c\x12\x31\n\x07headers\x18\x05 \x03(\x0b\x32 .loom.v1.ActionCall.HeadersEntry\x12\x12\n\ntimeout_ms\x18\x06 \x01(\x03\x12\x16\n\x0e\x63orrelation_id\x18\x07 \x01(\t\x12\x1e\n\x03qos\x18\x08 \x01(\x0e\x32\x11.loom.v1.QoSLevel\x1a.\n\x0cHeadersEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\"\x90\x01\n\x0b\x41\x63tionError\x12\x0c\n\x04\x63ode\x18\x01 \x01(\t\x12\x0f\n\x07message\x18\x02 \x01(\t\x12\x32\n\x07\x64\x65tails\x18\x03 \x03(\x0b\x32!.loom.v1.ActionError.DetailsEntry\x1a.\n\x0c\x44\x65tailsEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\"v\n\x0c\x41\x63tionResult\x12\n\n\x02id\x18\x01 \x01(\t\x12%\n\x06status\x18\x02 \x01(\x0e\x32\x15.loom.v1.ActionStatus\x12\x0e\n\x06output\x18\x03 \x01(\x0c\x12#\n\x05\x65rror\x18\x04 \x01(\x0b\x32\x14.loom.v1.ActionError\"\x19\n\x17ListCapabilitiesRequest\"O\n\x18ListCapabilitiesResponse\x12\x33\n\x0c\x63\x61pabilities\x18\x01 \x03(\x0b\x32\x1d.loom.v1.CapabilityDescriptor*[\n\x0cProviderKind\x12\x13\n\x0fPROVIDER_NATIVE\x10\x00\x12\x11\n\rPROVIDER_WASM\x10\x01\x12\x11\n\rPROVIDER_GRPC\x10\x02\x12\x10\n\x0cPROVIDER_MCP\x10\x03*Y\n\x0c\x41\x63tionStatus\x12\r\n\tACTION_OK\x10\x00\x12\x10\n\x0c\x41\x43TION_ERROR\x10\x01\x12\x12\n\x0e\x41\x43TION_TIMEOUT\x10\x02\x12\x14\n\x10\x41\x43TION_RETRYABLE\x10\x03\x32\x9d\x01\n\x0c\x41\x63tionBroker\x12W\n\x10ListCapabilities\x12 .loom.v1.ListCapabilitiesRequest\x1a!.loom.v1.ListCapabilitiesResponse\x12\x34\n\x06Invoke\x12\x13.loom.v1.ActionCall\x1a\x15.loom.v1.ActionResultb\x06proto3')
+DESCRIPTOR = _descriptor_pool.Default().AddSerializedFile(b'\n\x0c\x61\x63tion.proto\x12\x07loom.v1\x1a\x0b\x65vent.proto\"\x9f\x02\n\x14\x43\x61pabilityDescriptor\x12\x0c\n\x04name\x18\x01 \x01(\t\x12\x0f\n\x07version\x18\x02 \x01(\t\x12\'\n\x08provider\x18\x03 \x01(\x0e\x32\x15.loom.v1.ProviderKind\x12=\n\x08metadata\x18\x04 \x03(\x0b\x32+.loom.v1.CapabilityDescriptor.MetadataEntry\x12\x12\n\ndepends_on\x18\x05 \x03(\t\x12+\n\x0b\x64\x65\x66\x61ult_qos\x18\x06 \x01(\x0e\x32\x11.loom.v1.QoSLevelH\x00\x88\x01\x01\x1a/\n\rMetadataEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\x42\x0e\n\x0c_default_qos\"\x8a\x02\n\nActionCall\x12\n\n\x02id\x18\x01 \x01(\t\x12\x12\n\ncapability\x18\x02 \x01(\t\x12\x0f\n\x07version\x18\x03 \x01(\t\x12\x0f\n\x07payload\x18\x04 \x01(\x0c\x12\x31\n\x07headers\x18\x05 \x03(\x0b\x32 .loom.v1.ActionCall.HeadersEntry\x12\x12\n\ntimeout_ms\x18\x06 \x01(\x03\x12\x16\n\x0e\x63orrelation_id\x18\x07 \x01(\t\x12#\n\x03qos\x18\x08 \x01(\x0e\x32\x11.loom.v1.QoSLevelH\x00\x88\x01\x01\x1a.\n\x0cHeadersEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\x42\x06\n\x04_qos\"\x90\x01\n\x0b\x41\x63tionError\x12\x0c\n\x04\x63ode\x18\x01 \x01(\t\x12\x0f\n\x07message\x18\x02 \x01(\t\x12\x32\n\x07\x64\x65tails\x18\x03 \x03(\x0b\x32!.loom.v1.ActionError.DetailsEntry\x1a.\n\x0c\x44\x65tailsEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\"\xde\x01\n\x0c\x41\x63tionResult\x12\n\n\x02id\x18\x01 \x01(\t\x12%\n\x06status\x18\x02 \x01(\x0e\x32\x15.loom.v1.ActionStatus\x12\x0e\n\x06output\x18\x03 \x01(\x0c\x12#\n\x05\x65rror\x18\x04 \x01(\x0b\x32\x14.loom.v1.ActionError\x12\x35\n\x08metadata\x18\x05 \x03(\x0b\x32#.loom.v1.ActionResult.MetadataEntry\x1a/\n\rMetadataEntry\x12\x0b\n\x03key\x18\x01 \x01(\t\x12\r\n\x05value\x18\x02 \x01(\t:\x02\x38\x01\"\x19\n\x17ListCapabilitiesRequest\"O\n\x18ListCapabilitiesResponse\x12\x33\n\x0c\x63\x61pabilities\x18\x01 \x03(\x0b\x32\x1d.loom.v1.CapabilityDescriptor*[\n\x0cProviderKind\x12\x13\n\x0fPROVIDER_NATIVE\x10\x00\x12\x11\n\rPROVIDER_WASM\x10\x01\x12\x11\n\rPROVIDER_GRPC\x10\x02\x12\x10\n\x0cPROVIDER_MCP\x10\x03*m\n\x0c\x41\x63tionStatus\x12\r\n\tACTION_OK\x10\x00\x12\x10\n\x0c\x41\x43TION_ERROR\x10\x01\x12\x12\n\x0e\x41\x43TION_TIMEOUT\x10\x02\x12\x14\n\x10\x41\x43TION_RETRYABLE\x10\x03\x12\x12\n\x0e\x41\x43TION_PENDING\x10\x04\x32\x9d\x01\n\x0c\x41\x63tionBroker\x12W\n\x10ListCapabilities\x12 .loom.v1.ListCapabilitiesRequest\x1a!.loom.v1.ListCapabilitiesResponse\x12\x34\n\x06Invoke\x12\x13.loom.v1.ActionCall\x1a\x15.loom.v1.ActionResultb\x06proto3')
 
 _globals = globals()
 _builder.BuildMessageAndEnumDescriptors(DESCRIPTOR, _globals)
@@ -38,28 +38,32 @@ if not _descriptor._USE_C_DESCRIPTORS:
   _globals['_ACTIONCALL_HEADERSENTRY']._serialized_options = b'8\001'
   _globals['_ACTIONERROR_DETAILSENTRY']._loaded_options = None
   _globals['_ACTIONERROR_DETAILSENTRY']._serialized_options = b'8\001'
-  _globals['_PROVIDERKIND']._serialized_start=878
-  _globals['_PROVIDERKIND']._serialized_end=969
-  _globals['_ACTIONSTATUS']._serialized_start=971
-  _globals['_ACTIONSTATUS']._serialized_end=1060
+  _globals['_ACTIONRESULT_METADATAENTRY']._loaded_options = None
+  _globals['_ACTIONRESULT_METADATAENTRY']._serialized_options = b'8\001'
+  _globals['_PROVIDERKIND']._serialized_start=1077
+  _globals['_PROVIDERKIND']._serialized_end=1168
+  _globals['_ACTIONSTATUS']._serialized_start=1170
+  _globals['_ACTIONSTATUS']._serialized_end=1279
   _globals['_CAPABILITYDESCRIPTOR']._serialized_start=39
-  _globals['_CAPABILITYDESCRIPTOR']._serialized_end=245
-  _globals['_CAPABILITYDESCRIPTOR_METADATAENTRY']._serialized_start=198
-  _globals['_CAPABILITYDESCRIPTOR_METADATAENTRY']._serialized_end=245
-  _globals['_ACTIONCALL']._serialized_start=248
-  _globals['_ACTIONCALL']._serialized_end=501
-  _globals['_ACTIONCALL_HEADERSENTRY']._serialized_start=455
-  _globals['_ACTIONCALL_HEADERSENTRY']._serialized_end=501
-  _globals['_ACTIONERROR']._serialized_start=504
-  _globals['_ACTIONERROR']._serialized_end=648
-  _globals['_ACTIONERROR_DETAILSENTRY']._serialized_start=602
-  _globals['_ACTIONERROR_DETAILSENTRY']._serialized_end=648
-  _globals['_ACTIONRESULT']._serialized_start=650
-  _globals['_ACTIONRESULT']._serialized_end=768
-  _globals['_LISTCAPABILITIESREQUEST']._serialized_start=770
-  _globals['_LISTCAPABILITIESREQUEST']._serialized_end=795
-  _globals['_LISTCAPABILITIESRESPONSE']._serialized_start=797
-  _globals['_LISTCAPABILITIESRESPONSE']._serialized_end=876
-  _globals['_ACTIONBROKER']._serialized_start=1063
-  _globals['_ACTIONBROKER']._serialized_end=1220
+  _globals['_CAPABILITYDESCRIPTOR']._serialized_end=326
+  _globals['_CAPABILITYDESCRIPTOR_METADATAENTRY']._serialized_start=263
+  _globals['_CAPABILITYDESCRIPTOR_METADATAENTRY']._serialized_end=310
+  _globals['_ACTIONCALL']._serialized_start=329
+  _globals['_ACTIONCALL']._serialized_end=595
+  _globals['_ACTIONCALL_HEADERSENTRY']._serialized_start=541
+  _globals['_ACTIONCALL_HEADERSENTRY']._serialized_end=587
+  _globals['_ACTIONERROR']._serialized_start=598
+  _globals['_ACTIONERROR']._serialized_end=742
+  _globals['_ACTIONERROR_DETAILSENTRY']._serialized_start=696
+  _globals['_ACTIONERROR_DETAILSENTRY']._serialized_end=742
+  _globals['_ACTIONRESULT']._serialized_start=745
+  _globals['_ACTIONRESULT']._serialized_end=967
+  _globals['_ACTIONRESULT_METADATAENTRY']._serialized_start=263
+  _globals['_ACTIONRESULT_METADATAENTRY']._serialized_end=310
+  _globals['_LISTCAPABILITIESREQUEST']._serialized_start=969
+  _globals['_LISTCAPABILITIESREQUEST']._serialized_end=994
+  _globals['_LISTCAPABILITIESRESPONSE']._serialized_start=996
+  _globals['_LISTCAPABILITIESRESPONSE']._serialized_end=1075
+  _globals['_ACTIONBROKER']._serialized_start=1282
+  _globals['_ACTIONBROKER']._serialized_end=1439
 # @@protoc_insertion_point(module_scope)